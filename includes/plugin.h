@@ -15,38 +15,63 @@
 #define ANALOGSDK_API
 #endif
 
-const uint32_t ANALOG_SDK_PLUGIN_ABI_VERSION = 0;
+/// Version of the `WootingAnalog_PluginDescriptor` layout
+#define WOOTING_ANALOG_PLUGIN_ABI_VERSION 1
 
+/// This is only read by SDKs which don't know about `wooting_analog_plugin_descriptor`, so that they refuse the plugin rather
+/// than looking for the functions of the old ABI
+const uint32_t ANALOG_SDK_PLUGIN_ABI_VERSION = WOOTING_ANALOG_PLUGIN_ABI_VERSION;
 
-typedef void(*device_event)(void*, WootingAnalog_DeviceEventType, WootingAnalog_DeviceInfo*);
+/// Called by the plugin for every device event, with the `callback_data` given to `initialise`. The device only needs to be valid
+/// for the duration of the call
+typedef void(*device_event)(void* callback_data, WootingAnalog_DeviceEventType event, const WootingAnalog_DeviceInfoEx_FFI* device);
 
-/// Get a name describing the `Plugin`.
-ANALOGSDK_API const char* _name();
+/// The functions of the plugin, which the SDK gets through `wooting_analog_plugin_descriptor`. Functions marked as optional can
+/// be left as NULL.
+///
+/// Device info is given as `WootingAnalog_DeviceInfoEx_FFI`, which the plugin fills in and keeps the memory of (including its
+/// strings). Its `size` must be set to `sizeof(WootingAnalog_DeviceInfoEx_FFI)`, fields which aren't known can be left as 0/NULL.
+///
+/// Functions which can fail return a `WootingAnalogResult` in place of their return value when they do, cast to its type (e.g.
+/// `(float)WootingAnalogResult_NoDevices` for `read_analog`)
+typedef struct WootingAnalog_PluginDescriptor {
+    /// Must be `WOOTING_ANALOG_PLUGIN_ABI_VERSION`
+    uint32_t abi_version;
+    /// Must be `sizeof(WootingAnalog_PluginDescriptor)`
+    uint32_t size;
 
-/// A callback fired immediately after the plugin is loaded. Usually used
-/// for initialization.
-ANALOGSDK_API int _initialise(void* callback_data, device_event callback);
+    /// Get a name describing the `Plugin`. The string must stay valid for as long as the plugin is loaded
+    const char* (*name)(void);
 
-/// A function fired to check if the plugin is currently initialised
-ANALOGSDK_API bool is_initialised();
+    /// Called once after the plugin is loaded, giving the function to call on device events along with the data to call it with.
+    /// Returns the number of connected devices
+    int (*initialise)(void* callback_data, device_event callback);
 
-/// A callback fired immediately before the plugin is unloaded. Use this if
-/// you need to do any cleanup.
-ANALOGSDK_API void unload();
+    /// A function fired to check if the plugin is currently initialised
+    bool (*is_initialised)(void);
 
-/// Function called to get the full analog read buffer for a particular device with ID `device`. `len` is the maximum amount
-/// of keys that can be accepted, any more beyond this will be ignored by the SDK.
-/// If `device` is 0 then no specific device is specified and the data should be read from all devices and combined
-ANALOGSDK_API int _read_full_buffer(uint16_t code_buffer[], float analog_buffer[], int len, WootingAnalog_DeviceID device);
+    /// (Optional) A callback fired immediately before the plugin is unloaded. Use this if you need to do any cleanup.
+    void (*unload)(void);
 
-/// This function is fired by the SDK to collect up all Device Info structs. The memory for the struct should be retained and only dropped
-/// when the device is disconnected or the plugin is unloaded. This ensures that the Device Info is not garbled when it's being accessed by the client.
-///
-/// # Notes
-///
-/// Although, the client should be copying any data they want to use for a prolonged time as there is no lifetime guarantee on the data.
-ANALOGSDK_API int _device_info(WootingAnalog_DeviceInfo* buffer[], int len);
+    /// Function called to get the full analog read buffer for a particular device with ID `device`. `len` is the length of both
+    /// buffers, any keys beyond this should be left out. Returns the number of keys written.
+    /// If `device` is 0 then no specific device is specified and the data should be read from all devices and combined.
+    /// Only keys which are currently pressed should be given
+    int (*read_full_buffer)(uint16_t code_buffer[], float analog_buffer[], unsigned int len, WootingAnalog_DeviceID device);
+
+    /// Fills `buffer` with pointers to the info of the connected devices, giving the number filled in, which is at most `len`.
+    /// If `buffer` is NULL the number of connected devices is given instead, which the SDK uses to size the buffer.
+    ///
+    /// # Notes
+    ///
+    /// The structs must stay valid until the next call, the SDK copies anything it wants to keep.
+    int (*device_info)(const WootingAnalog_DeviceInfoEx_FFI* buffer[], unsigned int len);
+
+    /// (Optional) Function called to get the analog value for a particular HID key `code` from the device with ID `device`.
+    /// If `device` is 0 then no specific device is specified and the value should be read from all devices and combined.
+    /// If this is NULL the value is read through `read_full_buffer` instead
+    float (*read_analog)(uint16_t code, WootingAnalog_DeviceID device);
+} WootingAnalog_PluginDescriptor;
 
-/// Function called to get the analog value for a particular HID key `code` from the device with ID `device`.
-/// If `device` is 0 then no specific device is specified and the value should be read from all devices and combined
-ANALOGSDK_API float read_analog(uint16_t code, WootingAnalog_DeviceID device);
\ No newline at end of file
+/// The only function the plugin needs to export, giving its descriptor, which must stay valid for as long as the plugin is loaded
+ANALOGSDK_API const WootingAnalog_PluginDescriptor* wooting_analog_plugin_descriptor(void);
@@ -7,6 +7,18 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Length of a dense analog buffer which covers every code on the HID Keyboard/Keypad page. Dense buffers are indexed by HID code,
+ * so a buffer of this length can hold the value of every standard key
+ */
+#define WootingAnalog_DENSE_BUFFER_LEN 256
+
+/**
+ * Keys on the HID Consumer page (0x0C), e.g. media keys, are given as `CONSUMER_PAGE | usage`. These are their own set of codes
+ * which is the same in every `KeycodeType`, so they won't be mistaken for the Keyboard/Keypad page keys of the same usage
+ */
+#define WootingAnalog_CONSUMER_PAGE 49152
+
 typedef enum WootingAnalog_DeviceEventType {
   /**
    * Device has been connected
@@ -50,8 +62,111 @@ typedef enum WootingAnalog_KeycodeType {
    * Windows Virtual Keys which are translated to the current keyboard locale
    */
   WootingAnalog_KeycodeType_VirtualKeyTranslate = 3,
+  /**
+   * Linux input event codes (`KEY_*` in `linux/input-event-codes.h`), as given by evdev
+   */
+  WootingAnalog_KeycodeType_Evdev = 4,
+  /**
+   * X keysyms which are translated using the XKB keymap of the current layout (Linux only). Keysyms up to 0xFFFF can be used
+   * as codes (e.g. 'q' is 0x71, Escape is 0xFF1B), but not the Unicode keysyms above that. Keysyms overlap the range of custom
+   * keys, so those can't be read in this mode
+   */
+  WootingAnalog_KeycodeType_XkbKeysym = 5,
 } WootingAnalog_KeycodeType;
 
+/**
+ * The physical layouts the SDK knows the geometry of
+ */
+typedef enum WootingAnalog_KeyboardLayout {
+  /**
+   * The layout of the device isn't known
+   */
+  WootingAnalog_KeyboardLayout_Unknown = 0,
+  /**
+   * Full size ANSI layout, with function row, navigation cluster & numpad
+   */
+  WootingAnalog_KeyboardLayout_ANSI = 1,
+  /**
+   * Full size ISO layout, with function row, navigation cluster & numpad
+   */
+  WootingAnalog_KeyboardLayout_ISO = 2,
+  /**
+   * 60% ANSI layout, which is the alphanumeric block with Escape in place of the backquote
+   */
+  WootingAnalog_KeyboardLayout_ANSI60 = 3,
+  /**
+   * 80% (tenkeyless) ANSI layout, which is the full size layout without the numpad
+   */
+  WootingAnalog_KeyboardLayout_ANSI80 = 4,
+  /**
+   * A standalone numpad
+   */
+  WootingAnalog_KeyboardLayout_Keypad = 5,
+} WootingAnalog_KeyboardLayout;
+
+/**
+ * How a plugin has been behaving. Every call into a plugin has a time budget, plugins which keep overrunning it or panicking are
+ * quarantined
+ */
+typedef enum WootingAnalog_PluginStatus {
+  /**
+   * The plugin is responding normally
+   */
+  WootingAnalog_PluginStatus_Healthy = 0,
+  /**
+   * The last calls into the plugin overran their time budget or panicked, it will be quarantined if this keeps happening
+   */
+  WootingAnalog_PluginStatus_Degraded = 1,
+  /**
+   * The plugin is no longer called and its devices have been reported as disconnected
+   */
+  WootingAnalog_PluginStatus_Quarantined = 2,
+} WootingAnalog_PluginStatus;
+
+/**
+ * How the two sides of a virtual axis are combined when keys on both sides are pressed
+ */
+typedef enum WootingAnalog_AxisCombine {
+  /**
+   * The negative side is taken off the positive side, so pressing both equally gives 0
+   */
+  WootingAnalog_AxisCombine_Difference = 0,
+  /**
+   * The side which is pressed further wins
+   */
+  WootingAnalog_AxisCombine_MaxWins = 1,
+  /**
+   * The side which was pressed last wins
+   */
+  WootingAnalog_AxisCombine_LastPressed = 2,
+} WootingAnalog_AxisCombine;
+
+/**
+ * What a key does when it's bound for mouse emulation
+ */
+typedef enum WootingAnalog_MouseAction {
+  /**
+   * Moves the pointer, the further the key is pressed the faster it moves
+   */
+  WootingAnalog_MouseAction_MoveUp = 0,
+  WootingAnalog_MouseAction_MoveDown = 1,
+  WootingAnalog_MouseAction_MoveLeft = 2,
+  WootingAnalog_MouseAction_MoveRight = 3,
+  /**
+   * Scrolls, the further the key is pressed the faster it scrolls
+   */
+  WootingAnalog_MouseAction_ScrollUp = 4,
+  WootingAnalog_MouseAction_ScrollDown = 5,
+  WootingAnalog_MouseAction_ScrollLeft = 6,
+  WootingAnalog_MouseAction_ScrollRight = 7,
+  /**
+   * Holds a mouse button down while the key is pressed past the halfway point
+   */
+  WootingAnalog_MouseAction_LeftButton = 8,
+  WootingAnalog_MouseAction_RightButton = 9,
+  WootingAnalog_MouseAction_MiddleButton = 10,
+} WootingAnalog_MouseAction;
+
 typedef enum WootingAnalogResult {
   WootingAnalogResult_Ok = 1,
   /**
@@ -98,6 +213,14 @@ typedef enum WootingAnalogResult {
    * Indicates that the Analog SDK could not be found on the system
    */
   WootingAnalogResult_DLLNotFound = -1990,
+  /**
+   * A plugin didn't respond within its time budget
+   */
+  WootingAnalogResult_Timeout = -1989,
+  /**
+   * Sending raw data to devices hasn't been enabled
+   */
+  WootingAnalogResult_PermissionDenied = -1988,
 } WootingAnalogResult;
 
 /**
@@ -109,6 +232,84 @@ typedef struct WootingAnalog_DeviceInfo WootingAnalog_DeviceInfo;
 
 typedef uint64_t WootingAnalog_DeviceID;
 
+/**
+ * Identifies a reader of the SDK, see `wooting_analog_create_session`
+ */
+typedef uint32_t WootingAnalog_SessionID;
+
+/**
+ * Identifies a registered device event listener, see `wooting_analog_add_device_event_listener`
+ */
+typedef uint32_t WootingAnalog_ListenerID;
+
+/**
+ * The position & size of a single key, in key units
+ */
+typedef struct WootingAnalog_KeyGeometry {
+  /**
+   * HID code of the key
+   */
+  uint16_t code;
+  /**
+   * Distance of the left edge from the left of the keyboard
+   */
+  float x;
+  /**
+   * Distance of the top edge from the top of the keyboard
+   */
+  float y;
+  float width;
+  float height;
+} WootingAnalog_KeyGeometry;
+
+/**
+ * How a key is moving, worked out from every report of the device it's on. Times are in microseconds on the clock given by
+ * `wooting_analog_kinematics_time`, where 0 means it hasn't happened
+ */
+typedef struct WootingAnalog_KeyKinematics {
+  /**
+   * How far the key is pressed, from 0.0 to 1.0
+   */
+  float depth;
+  /**
+   * How fast the key is moving in depth per second, positive while it's going down
+   */
+  float velocity;
+  /**
+   * How fast the velocity is changing in depth per second squared
+   */
+  float acceleration;
+  /**
+   * The furthest the key has been pressed during the current press, or the last one if it has been released
+   */
+  float peak_depth;
+  /**
+   * When the key was last pressed
+   */
+  uint64_t pressed_at;
+  /**
+   * When the key was released after it was last pressed, 0 while it's held down
+   */
+  uint64_t released_at;
+} WootingAnalog_KeyKinematics;
+
+/**
+ * How fast mouse emulation moves the pointer or scrolls for how far a key is pressed. The speed is
+ * `max_speed * ((depth - deadzone) / (1 - deadzone)) ^ exponent`, so an exponent above 1 gives finer control near the top of the
+ * key travel and an exponent of 1 is linear
+ */
+typedef struct WootingAnalog_MouseCurve {
+  /**
+   * How far a key has to be pressed before it moves anything, from 0 up to (but not including) 1
+   */
+  float deadzone;
+  float exponent;
+  /**
+   * The speed with the key fully pressed, in pixels (or lines when scrolling) per second
+   */
+  float max_speed;
+} WootingAnalog_MouseCurve;
+
 /**
  * The core `DeviceInfo` struct which contains all the interesting information
  * for a particular device. This is the version which the consumer of the SDK will receive
@@ -142,6 +343,78 @@ typedef struct WootingAnalog_DeviceInfo_FFI {
   enum WootingAnalog_DeviceType device_type;
 } WootingAnalog_DeviceInfo_FFI;
 
+/**
+ * The extended version of `DeviceInfo_FFI`, which also carries the `DeviceDetails` of the device. Unknown strings are null
+ * and unknown numbers are 0
+ *
+ * # Notes
+ * * `size` is set by the caller to the size of the struct they were built with, and only the fields which fit within it are
+ * filled in. This allows fields to be added to the end of the struct without breaking older callers
+ * * The strings are owned by the SDK, see `wooting_analog_get_device_info_ex` for how long they're kept
+ */
+typedef struct WootingAnalog_DeviceInfoEx_FFI {
+  /**
+   * Size of the struct in bytes, set by the caller to `sizeof(WootingAnalog_DeviceInfoEx_FFI)` & by the SDK to how much it filled in
+   */
+  uint32_t size;
+  /**
+   * Device Vendor ID `vid`
+   */
+  uint16_t vendor_id;
+  /**
+   * Device Product ID `pid`
+   */
+  uint16_t product_id;
+  /**
+   * Device Manufacturer name
+   */
+  const char *manufacturer_name;
+  /**
+   * Device name
+   */
+  const char *device_name;
+  /**
+   * Unique device ID, which should be generated using `generate_device_id`
+   */
+  WootingAnalog_DeviceID device_id;
+  /**
+   * Hardware type of the Device
+   */
+  enum WootingAnalog_DeviceType device_type;
+  /**
+   * Serial number of the device
+   */
+  const char *serial_number;
+  /**
+   * Version of the firmware running on the device
+   */
+  const char *firmware_version;
+  /**
+   * Path of the interface the device is read through, as given by the OS
+   */
+  const char *interface_path;
+  /**
+   * Number of keys on the device which give analog values
+   */
+  uint32_t analog_key_count;
+  /**
+   * Number of distinct analog values the device reports
+   */
+  uint32_t value_resolution;
+  /**
+   * Rate the device nominally reports at, in Hz
+   */
+  uint32_t report_rate;
+  /**
+   * Name of the plugin the device is read through, which is filled in by the SDK
+   */
+  const char *plugin_name;
+  /**
+   * Whether the keys of the device are only ever fully pressed or released
+   */
+  bool digital;
+} WootingAnalog_DeviceInfoEx_FFI;
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -16,6 +16,10 @@ WootingAnalog_DeviceID generate_device_id(const char* serial_number,
                                           uint16_t vendor_id,
                                           uint16_t product_id);
 
+WootingAnalog_DeviceID generate_device_id_from_path(const char* interface_path,
+                                                    uint16_t vendor_id,
+                                                    uint16_t product_id);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -0,0 +1,189 @@
+//! Conversion between HID codes & Linux input event codes (`KEY_*` from linux/input-event-codes.h), which is needed both by the
+//! SDK for `KeycodeType::Evdev` and by plugins reading keyboards through evdev.
+use bimap::BiMap;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    //<HID code, Linux input event code (KEY_*)>
+    static ref EVDEV_MAP: BiMap<u8, u16> = {
+        let mut bimap: BiMap<u8, u16> = BiMap::new();
+        bimap.insert(0x04, 0x001e); //KEY_A
+        bimap.insert(0x05, 0x0030); //KEY_B
+        bimap.insert(0x06, 0x002e); //KEY_C
+        bimap.insert(0x07, 0x0020); //KEY_D
+
+        bimap.insert(0x08, 0x0012); //KEY_E
+        bimap.insert(0x09, 0x0021); //KEY_F
+        bimap.insert(0x0a, 0x0022); //KEY_G
+        bimap.insert(0x0b, 0x0023); //KEY_H
+        bimap.insert(0x0c, 0x0017); //KEY_I
+        bimap.insert(0x0d, 0x0024); //KEY_J
+        bimap.insert(0x0e, 0x0025); //KEY_K
+        bimap.insert(0x0f, 0x0026); //KEY_L
+
+        bimap.insert(0x10, 0x0032); //KEY_M
+        bimap.insert(0x11, 0x0031); //KEY_N
+        bimap.insert(0x12, 0x0018); //KEY_O
+        bimap.insert(0x13, 0x0019); //KEY_P
+        bimap.insert(0x14, 0x0010); //KEY_Q
+        bimap.insert(0x15, 0x0013); //KEY_R
+        bimap.insert(0x16, 0x001f); //KEY_S
+        bimap.insert(0x17, 0x0014); //KEY_T
+
+        bimap.insert(0x18, 0x0016); //KEY_U
+        bimap.insert(0x19, 0x002f); //KEY_V
+        bimap.insert(0x1a, 0x0011); //KEY_W
+        bimap.insert(0x1b, 0x002d); //KEY_X
+        bimap.insert(0x1c, 0x0015); //KEY_Y
+        bimap.insert(0x1d, 0x002c); //KEY_Z
+        bimap.insert(0x1e, 0x0002); //KEY_1
+        bimap.insert(0x1f, 0x0003); //KEY_2
+
+        bimap.insert(0x20, 0x0004); //KEY_3
+        bimap.insert(0x21, 0x0005); //KEY_4
+        bimap.insert(0x22, 0x0006); //KEY_5
+        bimap.insert(0x23, 0x0007); //KEY_6
+        bimap.insert(0x24, 0x0008); //KEY_7
+        bimap.insert(0x25, 0x0009); //KEY_8
+        bimap.insert(0x26, 0x000a); //KEY_9
+        bimap.insert(0x27, 0x000b); //KEY_0
+
+        bimap.insert(0x28, 0x001c); //KEY_ENTER
+        bimap.insert(0x29, 0x0001); //KEY_ESC
+        bimap.insert(0x2a, 0x000e); //KEY_BACKSPACE
+        bimap.insert(0x2b, 0x000f); //KEY_TAB
+        bimap.insert(0x2c, 0x0039); //KEY_SPACE
+        bimap.insert(0x2d, 0x000c); //KEY_MINUS
+        bimap.insert(0x2e, 0x000d); //KEY_EQUAL
+        bimap.insert(0x2f, 0x001a); //KEY_LEFTBRACE
+
+        bimap.insert(0x30, 0x001b); //KEY_RIGHTBRACE
+        bimap.insert(0x31, 0x002b); //KEY_BACKSLASH
+        bimap.insert(0x33, 0x0027); //KEY_SEMICOLON
+        bimap.insert(0x34, 0x0028); //KEY_APOSTROPHE
+        bimap.insert(0x35, 0x0029); //KEY_GRAVE
+        bimap.insert(0x36, 0x0033); //KEY_COMMA
+        bimap.insert(0x37, 0x0034); //KEY_DOT
+
+        bimap.insert(0x38, 0x0035); //KEY_SLASH
+        bimap.insert(0x39, 0x003a); //KEY_CAPSLOCK
+        bimap.insert(0x3a, 0x003b); //KEY_F1
+        bimap.insert(0x3b, 0x003c); //KEY_F2
+        bimap.insert(0x3c, 0x003d); //KEY_F3
+        bimap.insert(0x3d, 0x003e); //KEY_F4
+        bimap.insert(0x3e, 0x003f); //KEY_F5
+        bimap.insert(0x3f, 0x0040); //KEY_F6
+
+        bimap.insert(0x40, 0x0041); //KEY_F7
+        bimap.insert(0x41, 0x0042); //KEY_F8
+        bimap.insert(0x42, 0x0043); //KEY_F9
+        bimap.insert(0x43, 0x0044); //KEY_F10
+        bimap.insert(0x44, 0x0057); //KEY_F11
+        bimap.insert(0x45, 0x0058); //KEY_F12
+        bimap.insert(0x46, 0x0063); //KEY_SYSRQ
+        bimap.insert(0x47, 0x0046); //KEY_SCROLLLOCK
+
+        bimap.insert(0x48, 0x0077); //KEY_PAUSE
+        bimap.insert(0x49, 0x006e); //KEY_INSERT
+        bimap.insert(0x4a, 0x0066); //KEY_HOME
+        bimap.insert(0x4b, 0x0068); //KEY_PAGEUP
+        bimap.insert(0x4c, 0x006f); //KEY_DELETE
+        bimap.insert(0x4d, 0x006b); //KEY_END
+        bimap.insert(0x4e, 0x006d); //KEY_PAGEDOWN
+        bimap.insert(0x4f, 0x006a); //KEY_RIGHT
+
+        bimap.insert(0x50, 0x0069); //KEY_LEFT
+        bimap.insert(0x51, 0x006c); //KEY_DOWN
+        bimap.insert(0x52, 0x0067); //KEY_UP
+        bimap.insert(0x53, 0x0045); //KEY_NUMLOCK
+        bimap.insert(0x54, 0x0062); //KEY_KPSLASH
+        bimap.insert(0x55, 0x0037); //KEY_KPASTERISK
+        bimap.insert(0x56, 0x004a); //KEY_KPMINUS
+        bimap.insert(0x57, 0x004e); //KEY_KPPLUS
+
+        bimap.insert(0x58, 0x0060); //KEY_KPENTER
+        bimap.insert(0x59, 0x004f); //KEY_KP1
+        bimap.insert(0x5a, 0x0050); //KEY_KP2
+        bimap.insert(0x5b, 0x0051); //KEY_KP3
+        bimap.insert(0x5c, 0x004b); //KEY_KP4
+        bimap.insert(0x5d, 0x004c); //KEY_KP5
+        bimap.insert(0x5e, 0x004d); //KEY_KP6
+        bimap.insert(0x5f, 0x0047); //KEY_KP7
+
+        bimap.insert(0x60, 0x0048); //KEY_KP8
+        bimap.insert(0x61, 0x0049); //KEY_KP9
+        bimap.insert(0x62, 0x0052); //KEY_KP0
+        bimap.insert(0x63, 0x0053); //KEY_KPDOT
+        bimap.insert(0x64, 0x0056); //KEY_102ND
+        bimap.insert(0x65, 0x007f); //KEY_COMPOSE
+        bimap.insert(0x66, 0x0074); //KEY_POWER
+        bimap.insert(0x67, 0x0075); //KEY_KPEQUAL
+
+        bimap.insert(0x68, 0x00b7); //KEY_F13
+        bimap.insert(0x69, 0x00b8); //KEY_F14
+        bimap.insert(0x6a, 0x00b9); //KEY_F15
+        bimap.insert(0x6b, 0x00ba); //KEY_F16
+        bimap.insert(0x6c, 0x00bb); //KEY_F17
+        bimap.insert(0x6d, 0x00bc); //KEY_F18
+        bimap.insert(0x6e, 0x00bd); //KEY_F19
+        bimap.insert(0x6f, 0x00be); //KEY_F20
+
+        bimap.insert(0x70, 0x00bf); //KEY_F21
+        bimap.insert(0x71, 0x00c0); //KEY_F22
+        bimap.insert(0x72, 0x00c1); //KEY_F23
+        bimap.insert(0x73, 0x00c2); //KEY_F24
+        bimap.insert(0x74, 0x0086); //KEY_OPEN
+        bimap.insert(0x75, 0x008a); //KEY_HELP
+        bimap.insert(0x76, 0x0082); //KEY_PROPS
+        bimap.insert(0x77, 0x0084); //KEY_FRONT
+
+        bimap.insert(0x78, 0x0080); //KEY_STOP
+        bimap.insert(0x79, 0x0081); //KEY_AGAIN
+        bimap.insert(0x7a, 0x0083); //KEY_UNDO
+        bimap.insert(0x7b, 0x0089); //KEY_CUT
+        bimap.insert(0x7c, 0x0085); //KEY_COPY
+        bimap.insert(0x7d, 0x0087); //KEY_PASTE
+        bimap.insert(0x7e, 0x0088); //KEY_FIND
+        bimap.insert(0x7f, 0x0071); //KEY_MUTE
+
+        bimap.insert(0x80, 0x0073); //KEY_VOLUMEUP
+        bimap.insert(0x81, 0x0072); //KEY_VOLUMEDOWN
+        bimap.insert(0x85, 0x0079); //KEY_KPCOMMA
+        bimap.insert(0x87, 0x0059); //KEY_RO
+
+        bimap.insert(0x88, 0x005d); //KEY_KATAKANAHIRAGANA
+        bimap.insert(0x89, 0x007c); //KEY_YEN
+        bimap.insert(0x8a, 0x005c); //KEY_HENKAN
+        bimap.insert(0x8b, 0x005e); //KEY_MUHENKAN
+        bimap.insert(0x8c, 0x005f); //KEY_KPJPCOMMA
+
+        bimap.insert(0x90, 0x007a); //KEY_HANGEUL
+        bimap.insert(0x91, 0x007b); //KEY_HANJA
+        bimap.insert(0x92, 0x005a); //KEY_KATAKANA
+        bimap.insert(0x93, 0x005b); //KEY_HIRAGANA
+        bimap.insert(0x94, 0x0055); //KEY_ZENKAKUHANKAKU
+
+        bimap.insert(0xb6, 0x00b3); //KEY_KPLEFTPAREN
+        bimap.insert(0xb7, 0x00b4); //KEY_KPRIGHTPAREN
+
+        bimap.insert(0xe0, 0x001d); //KEY_LEFTCTRL
+        bimap.insert(0xe1, 0x002a); //KEY_LEFTSHIFT
+        bimap.insert(0xe2, 0x0038); //KEY_LEFTALT
+        bimap.insert(0xe3, 0x007d); //KEY_LEFTMETA
+        bimap.insert(0xe4, 0x0061); //KEY_RIGHTCTRL
+        bimap.insert(0xe5, 0x0036); //KEY_RIGHTSHIFT
+        bimap.insert(0xe6, 0x0064); //KEY_RIGHTALT
+        bimap.insert(0xe7, 0x007e); //KEY_RIGHTMETA
+        bimap
+    };
+}
+
+/// Gives the Linux input event code of the key with the given HID code
+pub fn hid_to_evdev(code: u16) -> Option<u16> {
+    EVDEV_MAP.get_by_left(&(code as u8)).copied()
+}
+
+/// Gives the HID code of the key with the given Linux input event code
+pub fn evdev_to_hid(code: u16) -> Option<u16> {
+    EVDEV_MAP.get_by_right(&code).map(|&c| u16::from(c))
+}
@@ -0,0 +1,280 @@
+//! Names for keys, so that e.g. bindings can be configured as "LeftShift" rather than 0xe1.
+//!
+//! The canonical name of a key is the name of its `HIDCodes`/`ConsumerCodes` variant, which doesn't depend on the locale or
+//! layout. Parsing is case insensitive, ignores spaces, underscores & dashes and also accepts a set of common aliases.
+use crate::{ConsumerCodes, FromPrimitive, HIDCodes, WootingAnalogResult, CONSUMER_PAGE};
+use std::fmt;
+use std::str::FromStr;
+
+/// Names which are taken as-is, before any normalisation, as they're made of the characters that normalisation removes
+const SYMBOL_ALIASES: &[(&str, &str)] = &[
+    ("-", "Minus"),
+    ("=", "Equal"),
+    ("[", "BracketLeft"),
+    ("]", "BracketRight"),
+    ("\\", "Backslash"),
+    (";", "Semicolon"),
+    ("'", "Quote"),
+    ("`", "Backquote"),
+    (",", "Comma"),
+    (".", "Period"),
+    ("/", "Slash"),
+    (" ", "Space"),
+];
+
+/// <normalised alias, canonical name> of the Keyboard/Keypad page keys
+const KEYBOARD_ALIASES: &[(&str, &str)] = &[
+    ("esc", "Escape"),
+    ("bksp", "Backspace"),
+    ("spacebar", "Space"),
+    ("del", "Delete"),
+    ("ins", "Insert"),
+    ("pgup", "PageUp"),
+    ("pgdn", "PageDown"),
+    ("pagedn", "PageDown"),
+    ("up", "ArrowUp"),
+    ("down", "ArrowDown"),
+    ("left", "ArrowLeft"),
+    ("right", "ArrowRight"),
+    ("caps", "CapsLock"),
+    ("prtsc", "PrintScreen"),
+    ("printscr", "PrintScreen"),
+    ("print", "PrintScreen"),
+    ("pause", "PauseBreak"),
+    ("break", "PauseBreak"),
+    ("scrlk", "ScrollLock"),
+    ("apps", "ContextMenu"),
+    ("application", "ContextMenu"),
+    ("grave", "Backquote"),
+    ("tilde", "Backquote"),
+    ("apostrophe", "Quote"),
+    ("leftbracket", "BracketLeft"),
+    ("rightbracket", "BracketRight"),
+    ("dot", "Period"),
+    ("ctrl", "LeftCtrl"),
+    ("control", "LeftCtrl"),
+    ("lctrl", "LeftCtrl"),
+    ("leftcontrol", "LeftCtrl"),
+    ("rctrl", "RightCtrl"),
+    ("rightcontrol", "RightCtrl"),
+    ("shift", "LeftShift"),
+    ("lshift", "LeftShift"),
+    ("rshift", "RightShift"),
+    ("alt", "LeftAlt"),
+    ("lalt", "LeftAlt"),
+    ("ralt", "RightAlt"),
+    ("altgr", "RightAlt"),
+    ("meta", "LeftMeta"),
+    ("lmeta", "LeftMeta"),
+    ("win", "LeftMeta"),
+    ("lwin", "LeftMeta"),
+    ("leftwin", "LeftMeta"),
+    ("super", "LeftMeta"),
+    ("leftsuper", "LeftMeta"),
+    ("leftgui", "LeftMeta"),
+    ("rmeta", "RightMeta"),
+    ("rwin", "RightMeta"),
+    ("rightwin", "RightMeta"),
+    ("rightsuper", "RightMeta"),
+    ("rightgui", "RightMeta"),
+];
+
+/// <normalised alias, canonical name> of the Consumer page keys
+const CONSUMER_ALIASES: &[(&str, &str)] = &[
+    ("nexttrack", "ScanNextTrack"),
+    ("previoustrack", "ScanPreviousTrack"),
+    ("prevtrack", "ScanPreviousTrack"),
+    ("mediaplaypause", "PlayPause"),
+    ("mediastop", "Stop"),
+    ("calculator", "ALCalculator"),
+    ("mail", "ALEmailReader"),
+    ("mycomputer", "ALLocalBrowser"),
+    ("browserhome", "ACHome"),
+    ("browserback", "ACBack"),
+    ("browserforward", "ACForward"),
+    ("browserrefresh", "ACRefresh"),
+    ("browsersearch", "ACSearch"),
+    ("browserstop", "ACStop"),
+    ("browserfavorites", "ACBookmarks"),
+];
+
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Gives the canonical form of the normalised `name` if it's one of the `aliases` or alternate spellings
+fn resolve_alias(name: &str, aliases: &[(&str, &str)]) -> Option<String> {
+    if let Some((_, canonical)) = aliases.iter().find(|(alias, _)| *alias == name) {
+        return Some(normalise(canonical));
+    }
+
+    //Digits are "N1", but "1" & "Digit1" are the more obvious names
+    let digit = name.strip_prefix("digit").unwrap_or(name);
+    if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) {
+        return Some(format!("n{}", digit));
+    }
+
+    //Accept the various names in use for the numpad, e.g. "Keypad7", "KP7" or "Num7"
+    for prefix in ["keypad", "kp"].iter() {
+        if let Some(rest) = name.strip_prefix(prefix) {
+            return Some(format!("numpad{}", rest));
+        }
+    }
+    match name.strip_prefix("num") {
+        Some(rest) if rest.len() == 1 && rest.chars().all(|c| c.is_ascii_digit()) => {
+            Some(format!("numpad{}", rest))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the code from `codes` which has the given name or alias, using `name_of` to get the name of each code
+fn find_code<F>(
+    name: &str,
+    aliases: &[(&str, &str)],
+    codes: std::ops::Range<u16>,
+    name_of: F,
+) -> Option<u16>
+where
+    F: Fn(u16) -> Option<String>,
+{
+    let name = match SYMBOL_ALIASES.iter().find(|(symbol, _)| *symbol == name) {
+        Some((_, canonical)) => normalise(canonical),
+        None => normalise(name),
+    };
+    let matches = |target: &str| {
+        codes
+            .clone()
+            .find(|&code| name_of(code).map_or(false, |n| normalise(&n) == target))
+    };
+    matches(&name).or_else(|| resolve_alias(&name, aliases).and_then(|alias| matches(&alias)))
+}
+
+impl fmt::Display for HIDCodes {
+    /// Writes the canonical name of the key, e.g. "LeftShift"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for HIDCodes {
+    type Err = WootingAnalogResult;
+
+    /// Finds the key with the given canonical name or alias, giving `NoMapping` if there isn't one
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        find_code(name, KEYBOARD_ALIASES, 0..0x100, |code| {
+            HIDCodes::from_u16(code).map(|key| key.to_string())
+        })
+        .and_then(HIDCodes::from_u16)
+        .ok_or(WootingAnalogResult::NoMapping)
+    }
+}
+
+impl fmt::Display for ConsumerCodes {
+    /// Writes the canonical name of the key, e.g. "PlayPause"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for ConsumerCodes {
+    type Err = WootingAnalogResult;
+
+    /// Finds the key with the given canonical name or alias, giving `NoMapping` if there isn't one
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        find_code(
+            name,
+            CONSUMER_ALIASES,
+            CONSUMER_PAGE..CONSUMER_PAGE + 0x1000,
+            |code| ConsumerCodes::from_u16(code).map(|key| key.to_string()),
+        )
+        .and_then(ConsumerCodes::from_u16)
+        .ok_or(WootingAnalogResult::NoMapping)
+    }
+}
+
+/// Names of Consumer page keys can be given this prefix, to tell them apart from Keyboard/Keypad page keys of the same name
+const CONSUMER_PREFIX: &str = "Consumer";
+
+/// Gives the canonical name of the key with the given HID code, which can be on the Keyboard/Keypad or the Consumer page.
+/// Consumer page keys which share their name with a Keyboard/Keypad page key (e.g. `Power`) are given the "Consumer" prefix
+pub fn key_name(code: u16) -> Option<String> {
+    if let Some(key) = HIDCodes::from_u16(code) {
+        return Some(key.to_string());
+    }
+
+    let name = ConsumerCodes::from_u16(code)?.to_string();
+    if name.parse::<HIDCodes>().is_ok() {
+        Some(format!("{}{}", CONSUMER_PREFIX, name))
+    } else {
+        Some(name)
+    }
+}
+
+/// Gives the HID code of the key with the given name or alias. Keyboard/Keypad page keys take priority over the Consumer page,
+/// unless the name has the "Consumer" prefix
+pub fn key_from_name(name: &str) -> Option<u16> {
+    let consumer = normalise(name)
+        .strip_prefix(&normalise(CONSUMER_PREFIX))
+        .and_then(|rest| rest.parse::<ConsumerCodes>().ok());
+    if let Some(key) = consumer {
+        return Some(key as u16);
+    }
+
+    name.parse::<HIDCodes>()
+        .map(|key| key as u16)
+        .or_else(|_| name.parse::<ConsumerCodes>().map(|key| key as u16))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_names() {
+        for code in (0..0x100).chain(CONSUMER_PAGE..CONSUMER_PAGE + 0x1000) {
+            if let Some(name) = key_name(code) {
+                assert_eq!(key_from_name(&name), Some(code), "{}", name);
+            }
+        }
+        assert_eq!(HIDCodes::LeftShift.to_string(), "LeftShift");
+        assert_eq!(ConsumerCodes::PlayPause.to_string(), "PlayPause");
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!("w".parse(), Ok(HIDCodes::W));
+        assert_eq!("left_shift".parse(), Ok(HIDCodes::LeftShift));
+        assert_eq!("LShift".parse(), Ok(HIDCodes::LeftShift));
+        assert_eq!("Esc".parse(), Ok(HIDCodes::Escape));
+        assert_eq!("1".parse(), Ok(HIDCodes::N1));
+        assert_eq!("Digit0".parse(), Ok(HIDCodes::N0));
+        assert_eq!("Keypad 7".parse(), Ok(HIDCodes::Numpad7));
+        assert_eq!("KP_Enter".parse(), Ok(HIDCodes::NumpadEnter));
+        assert_eq!("Num7".parse(), Ok(HIDCodes::Numpad7));
+        assert_eq!("NumLock".parse(), Ok(HIDCodes::NumLock));
+        assert_eq!("-".parse(), Ok(HIDCodes::Minus));
+        assert_eq!("Next Track".parse(), Ok(ConsumerCodes::ScanNextTrack));
+        assert_eq!(key_from_name("Mute"), Some(ConsumerCodes::Mute as u16));
+        //Aliases shouldn't cross over to the other page
+        assert_eq!(key_from_name("MediaStop"), Some(ConsumerCodes::Stop as u16));
+        assert_eq!(key_from_name("Stop"), Some(HIDCodes::Stop as u16));
+        assert_eq!(
+            key_from_name("Consumer Stop"),
+            Some(ConsumerCodes::Stop as u16)
+        );
+        assert_eq!(
+            key_name(ConsumerCodes::Power as u16),
+            Some("ConsumerPower".to_owned())
+        );
+        assert_eq!(
+            "NotAKey".parse::<HIDCodes>(),
+            Err(WootingAnalogResult::NoMapping)
+        );
+        assert_eq!(key_from_name(""), None);
+    }
+}
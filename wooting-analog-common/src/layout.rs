@@ -0,0 +1,272 @@
+//! Physical layouts of keyboards, so that apps can draw a device's keys (e.g. for a heatmap) without keeping their own copy
+//! of every product's layout.
+//!
+//! Positions & sizes are in key units, where 1.0 is the size of a standard alphanumeric key, measured from the top left of the
+//! keyboard.
+use crate::HIDCodes;
+#[cfg(feature = "serdes")]
+use serde::{Deserialize, Serialize};
+
+/// The physical layouts the SDK knows the geometry of
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy, Hash, Eq, Primitive)]
+#[repr(C)]
+pub enum KeyboardLayout {
+    /// The layout of the device isn't known
+    Unknown = 0,
+    /// Full size ANSI layout, with function row, navigation cluster & numpad
+    ANSI = 1,
+    /// Full size ISO layout, with function row, navigation cluster & numpad
+    ISO = 2,
+    /// 60% ANSI layout, which is the alphanumeric block with Escape in place of the backquote
+    ANSI60 = 3,
+    /// 80% (tenkeyless) ANSI layout, which is the full size layout without the numpad
+    ANSI80 = 4,
+    /// A standalone numpad
+    Keypad = 5,
+}
+
+/// The position & size of a single key, in key units
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[repr(C)]
+pub struct KeyGeometry {
+    /// HID code of the key
+    pub code: u16,
+    /// Distance of the left edge from the left of the keyboard
+    pub x: f32,
+    /// Distance of the top edge from the top of the keyboard
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl KeyboardLayout {
+    /// Gives the geometry of every key in the layout, ordered by row and then from left to right. This is empty for `Unknown`
+    ///
+    /// # Notes
+    ///
+    /// The ISO Enter isn't a rectangle, so it's given as its lower part, which is 1.25 wide and spans both rows
+    pub fn keys(&self) -> Vec<KeyGeometry> {
+        let mut layout = LayoutBuilder::default();
+        match self {
+            KeyboardLayout::Unknown => {}
+            KeyboardLayout::ANSI | KeyboardLayout::ISO => {
+                layout.function_row();
+                layout.alphanumeric(1.5, *self == KeyboardLayout::ISO, false);
+                layout.navigation(15.25, 1.5);
+                layout.numpad(18.5, 1.5);
+            }
+            KeyboardLayout::ANSI80 => {
+                layout.function_row();
+                layout.alphanumeric(1.5, false, false);
+                layout.navigation(15.25, 1.5);
+            }
+            KeyboardLayout::ANSI60 => layout.alphanumeric(0.0, false, true),
+            KeyboardLayout::Keypad => layout.numpad(0.0, 0.0),
+        }
+        layout.finish()
+    }
+}
+
+/// Places keys left to right along a row, which keeps the layout definitions close to what they look like
+#[derive(Default)]
+struct LayoutBuilder {
+    keys: Vec<KeyGeometry>,
+    x: f32,
+    y: f32,
+}
+
+impl LayoutBuilder {
+    /// Moves to the given position, which the next key will be placed at
+    fn at(&mut self, x: f32, y: f32) -> &mut Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    fn gap(&mut self, width: f32) -> &mut Self {
+        self.x += width;
+        self
+    }
+
+    fn sized(&mut self, code: HIDCodes, width: f32, height: f32) -> &mut Self {
+        self.keys.push(KeyGeometry {
+            code: code as u16,
+            x: self.x,
+            y: self.y,
+            width,
+            height,
+        });
+        self.x += width;
+        self
+    }
+
+    fn wide(&mut self, code: HIDCodes, width: f32) -> &mut Self {
+        self.sized(code, width, 1.0)
+    }
+
+    /// Places a run of standard 1x1 keys
+    fn keys(&mut self, codes: &[HIDCodes]) -> &mut Self {
+        for code in codes.iter().cloned() {
+            self.wide(code, 1.0);
+        }
+        self
+    }
+
+    fn function_row(&mut self) {
+        use HIDCodes::*;
+        self.at(0.0, 0.0)
+            .keys(&[Escape])
+            .gap(1.0)
+            .keys(&[F1, F2, F3, F4])
+            .gap(0.5)
+            .keys(&[F5, F6, F7, F8])
+            .gap(0.5)
+            .keys(&[F9, F10, F11, F12])
+            .gap(0.25)
+            .keys(&[PrintScreen, ScrollLock, PauseBreak]);
+    }
+
+    /// The block of 15 key units wide from the number row to the space bar, starting at `y`
+    fn alphanumeric(&mut self, y: f32, iso: bool, compact: bool) {
+        use HIDCodes::*;
+        //60% boards put Escape where the backquote normally is
+        let corner = if compact { Escape } else { Backquote };
+        self.at(0.0, y)
+            .keys(&[corner, N1, N2, N3, N4, N5, N6, N7, N8, N9, N0, Minus, Equal])
+            .wide(Backspace, 2.0);
+
+        self.at(0.0, y + 1.0)
+            .wide(Tab, 1.5)
+            .keys(&[Q, W, E, R, T, Y, U, I, O, P])
+            .keys(&[BracketLeft, BracketRight]);
+        if iso {
+            self.gap(0.25).sized(Enter, 1.25, 2.0);
+        } else {
+            self.wide(Backslash, 1.5);
+        }
+
+        self.at(0.0, y + 2.0)
+            .wide(CapsLock, 1.75)
+            .keys(&[A, S, D, F, G, H, J, K, L, Semicolon, Quote]);
+        if iso {
+            self.keys(&[InternationalHash]);
+        } else {
+            self.wide(Enter, 2.25);
+        }
+
+        self.at(0.0, y + 3.0);
+        if iso {
+            self.wide(LeftShift, 1.25).keys(&[InternationalBackslash]);
+        } else {
+            self.wide(LeftShift, 2.25);
+        }
+        self.keys(&[Z, X, C, V, B, N, M, Comma, Period, Slash])
+            .wide(RightShift, 2.75);
+
+        self.at(0.0, y + 4.0)
+            .wide(LeftCtrl, 1.25)
+            .wide(LeftMeta, 1.25)
+            .wide(LeftAlt, 1.25)
+            .wide(Space, 6.25)
+            .wide(RightAlt, 1.25)
+            .wide(RightMeta, 1.25)
+            .wide(ContextMenu, 1.25)
+            .wide(RightCtrl, 1.25);
+    }
+
+    /// The Insert to Page Down block with the arrows below, starting at the number row `y`
+    fn navigation(&mut self, x: f32, y: f32) {
+        use HIDCodes::*;
+        self.at(x, y).keys(&[Insert, Home, PageUp]);
+        self.at(x, y + 1.0).keys(&[Delete, End, PageDown]);
+        self.at(x + 1.0, y + 3.0).keys(&[ArrowUp]);
+        self.at(x, y + 4.0)
+            .keys(&[ArrowLeft, ArrowDown, ArrowRight]);
+    }
+
+    fn numpad(&mut self, x: f32, y: f32) {
+        use HIDCodes::*;
+        self.at(x, y)
+            .keys(&[NumLock, NumpadDivide, NumpadMultiply, NumpadSubtract]);
+        self.at(x, y + 1.0)
+            .keys(&[Numpad7, Numpad8, Numpad9])
+            .sized(NumpadAdd, 1.0, 2.0);
+        self.at(x, y + 2.0).keys(&[Numpad4, Numpad5, Numpad6]);
+        self.at(x, y + 3.0)
+            .keys(&[Numpad1, Numpad2, Numpad3])
+            .sized(NumpadEnter, 1.0, 2.0);
+        self.at(x, y + 4.0)
+            .wide(Numpad0, 2.0)
+            .keys(&[NumpadDecimal]);
+    }
+
+    fn finish(mut self) -> Vec<KeyGeometry> {
+        self.keys.sort_by(|a, b| {
+            a.y.partial_cmp(&b.y)
+                .unwrap()
+                .then(a.x.partial_cmp(&b.x).unwrap())
+        });
+        self.keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromPrimitive;
+
+    const LAYOUTS: [KeyboardLayout; 5] = [
+        KeyboardLayout::ANSI,
+        KeyboardLayout::ISO,
+        KeyboardLayout::ANSI60,
+        KeyboardLayout::ANSI80,
+        KeyboardLayout::Keypad,
+    ];
+
+    fn overlaps(a: &KeyGeometry, b: &KeyGeometry) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    #[test]
+    fn key_counts() {
+        assert_eq!(KeyboardLayout::ANSI.keys().len(), 104);
+        assert_eq!(KeyboardLayout::ISO.keys().len(), 105);
+        assert_eq!(KeyboardLayout::ANSI80.keys().len(), 87);
+        assert_eq!(KeyboardLayout::ANSI60.keys().len(), 61);
+        assert_eq!(KeyboardLayout::Keypad.keys().len(), 17);
+        assert!(KeyboardLayout::Unknown.keys().is_empty());
+    }
+
+    #[test]
+    fn keys_are_valid() {
+        for layout in LAYOUTS.iter() {
+            let keys = layout.keys();
+            for (i, key) in keys.iter().enumerate() {
+                assert!(HIDCodes::from_u16(key.code).is_some(), "{:?}", layout);
+                for other in keys[i + 1..].iter() {
+                    assert_ne!(key.code, other.code, "{:?}", layout);
+                    assert!(!overlaps(key, other), "{:?} {:?} {:?}", layout, key, other);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn alphanumeric_rows_line_up() {
+        //Every row of the main block should be 15 key units wide
+        for layout in LAYOUTS[..4].iter() {
+            let keys = layout.keys();
+            let right_edge = |code: HIDCodes| {
+                let code = code as u16;
+                let key = keys.iter().find(|key| key.code == code).unwrap();
+                key.x + key.width
+            };
+            assert_eq!(right_edge(HIDCodes::Backspace), 15.0);
+            assert_eq!(right_edge(HIDCodes::Enter), 15.0);
+            assert_eq!(right_edge(HIDCodes::RightShift), 15.0);
+            assert_eq!(right_edge(HIDCodes::RightCtrl), 15.0);
+        }
+    }
+}
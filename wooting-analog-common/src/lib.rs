@@ -12,6 +12,13 @@ use std::ops::Deref;
 use std::os::raw::{c_char, c_int};
 use thiserror::Error;
 
+mod evdev;
+pub use evdev::{evdev_to_hid, hid_to_evdev};
+mod keyname;
+pub use keyname::{key_from_name, key_name};
+mod layout;
+pub use layout::{KeyGeometry, KeyboardLayout};
+
 #[cfg(target_os = "macos")]
 pub const DEFAULT_PLUGIN_DIR: &str = "/usr/local/share/WootingAnalogPlugins";
 #[cfg(target_os = "linux")]
@@ -21,9 +28,11 @@ pub const DEFAULT_PLUGIN_DIR: &str = "C:\\Program Files\\WootingAnalogPlugins";
 
 /// The core `DeviceInfo` struct which contains all the interesting information
 /// for a particular device. This is for use internally and should be ignored if you're
-/// trying to use it when trying to interact with the SDK using the wrapper
+/// trying to use it when trying to interact with the SDK using the wrapper. New fields may be added, so this should be created
+/// using `new_with_id`
 #[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
 pub struct DeviceInfo {
     /// Device Vendor ID `vid`
     pub vendor_id: u16,
@@ -37,6 +46,34 @@ pub struct DeviceInfo {
     pub device_id: DeviceID,
     /// Hardware type of the Device
     pub device_type: DeviceType,
+    /// Further information about the device, which plugins fill in as far as they know it
+    pub details: DeviceDetails,
+}
+
+/// Information about a device beyond what's needed to identify it. Everything is optional, as not every plugin is able to
+/// find out all of it. New fields may be added, so this should be created using `Default` and then filled in
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct DeviceDetails {
+    /// Serial number of the device
+    pub serial_number: Option<String>,
+    /// Version of the firmware running on the device
+    pub firmware_version: Option<String>,
+    /// Path of the interface the device is read through, as given by the OS (e.g. `/dev/hidraw3`)
+    pub interface_path: Option<String>,
+    /// Number of keys on the device which give analog values
+    pub analog_key_count: Option<u32>,
+    /// Number of distinct analog values the device reports, e.g. 256 for a device reporting 8 bit values
+    pub value_resolution: Option<u32>,
+    /// Rate the device nominally reports at, in Hz
+    pub report_rate: Option<u32>,
+    /// Name of the plugin the device is read through. This is filled in by the SDK, as more than one plugin can report the same
+    /// device, in which case only the one owning it is listed
+    pub plugin_name: Option<String>,
+    /// Whether the keys of the device are only ever fully pressed (1.0) or released (0.0), e.g. for an ordinary keyboard read
+    /// as a fallback for players without an analog one
+    pub digital: bool,
 }
 
 /// The core `DeviceInfo` struct which contains all the interesting information
@@ -103,10 +140,168 @@ impl DeviceInfo_FFI {
             },
             device_id: self.device_id.clone(),
             device_type: self.device_type.clone(),
+            details: DeviceDetails::default(),
         }
     }
 }
 
+/// The extended version of `DeviceInfo_FFI`, which also carries the `DeviceDetails` of the device. Unknown strings are null
+/// and unknown numbers are 0
+///
+/// # Notes
+/// * `size` is set by the caller to the size of the struct they were built with, and only the fields which fit within it are
+/// filled in. This allows fields to be added to the end of the struct without breaking older callers
+/// * The strings are owned by the SDK, see `wooting_analog_get_device_info_ex` for how long they're kept
+#[repr(C)]
+pub struct DeviceInfoEx_FFI {
+    /// Size of the struct in bytes
+    pub size: u32,
+    /// Device Vendor ID `vid`
+    pub vendor_id: u16,
+    /// Device Product ID `pid`
+    pub product_id: u16,
+    /// Device Manufacturer name
+    pub manufacturer_name: *const c_char,
+    /// Device name
+    pub device_name: *const c_char,
+    /// Unique device ID, which should be generated using `generate_device_id`
+    pub device_id: DeviceID,
+    /// Hardware type of the Device
+    pub device_type: DeviceType,
+    /// Serial number of the device
+    pub serial_number: *const c_char,
+    /// Version of the firmware running on the device
+    pub firmware_version: *const c_char,
+    /// Path of the interface the device is read through, as given by the OS
+    pub interface_path: *const c_char,
+    /// Number of keys on the device which give analog values
+    pub analog_key_count: u32,
+    /// Number of distinct analog values the device reports
+    pub value_resolution: u32,
+    /// Rate the device nominally reports at, in Hz
+    pub report_rate: u32,
+    /// Name of the plugin the device is read through, which is filled in by the SDK
+    pub plugin_name: *const c_char,
+    /// Whether the keys of the device are only ever fully pressed or released
+    pub digital: bool,
+}
+
+impl Default for DeviceInfoEx_FFI {
+    /// Gives an empty struct with `size` set to the full size of this version
+    fn default() -> Self {
+        DeviceInfoEx_FFI {
+            size: std::mem::size_of::<DeviceInfoEx_FFI>() as u32,
+            vendor_id: 0,
+            product_id: 0,
+            manufacturer_name: std::ptr::null(),
+            device_name: std::ptr::null(),
+            device_id: 0,
+            device_type: DeviceType::Other,
+            serial_number: std::ptr::null(),
+            firmware_version: std::ptr::null(),
+            interface_path: std::ptr::null(),
+            analog_key_count: 0,
+            value_resolution: 0,
+            report_rate: 0,
+            plugin_name: std::ptr::null(),
+            digital: false,
+        }
+    }
+}
+
+impl DeviceInfoEx_FFI {
+    /// Creates the struct for `device`, with the strings pointing into `strings`, which need to be kept around for as long as
+    /// the struct is used
+    pub fn new(device: &DeviceInfo, strings: &mut Vec<CString>) -> Self {
+        let mut keep = |string: Option<&String>| -> *const c_char {
+            match string.and_then(|string| CString::new(string.as_str()).ok()) {
+                Some(string) => {
+                    //Moving the CString into the Vec doesn't move the heap memory the pointer refers to
+                    let ptr = string.as_ptr();
+                    strings.push(string);
+                    ptr
+                }
+                None => std::ptr::null(),
+            }
+        };
+
+        DeviceInfoEx_FFI {
+            manufacturer_name: keep(Some(&device.manufacturer_name)),
+            device_name: keep(Some(&device.device_name)),
+            serial_number: keep(device.details.serial_number.as_ref()),
+            firmware_version: keep(device.details.firmware_version.as_ref()),
+            interface_path: keep(device.details.interface_path.as_ref()),
+            plugin_name: keep(device.details.plugin_name.as_ref()),
+            vendor_id: device.vendor_id,
+            product_id: device.product_id,
+            device_id: device.device_id,
+            device_type: device.device_type.clone(),
+            analog_key_count: device.details.analog_key_count.unwrap_or(0),
+            value_resolution: device.details.value_resolution.unwrap_or(0),
+            report_rate: device.details.report_rate.unwrap_or(0),
+            digital: device.details.digital,
+            ..Default::default()
+        }
+    }
+
+    /// Copies the struct at `info`, which may be an older or newer version of it with a different `size`. Fields which aren't
+    /// within its `size` are left as unknown
+    ///
+    /// # Safety
+    ///
+    /// `info` must be null or point to a struct which is at least as big as its `size`
+    pub unsafe fn read_versioned(info: *const DeviceInfoEx_FFI) -> Option<DeviceInfoEx_FFI> {
+        if info.is_null() {
+            return None;
+        }
+        let size = std::ptr::read_unaligned(info as *const u32) as usize;
+        let mut copy = DeviceInfoEx_FFI::default();
+        std::ptr::copy_nonoverlapping(
+            info as *const u8,
+            &mut copy as *mut _ as *mut u8,
+            size.min(std::mem::size_of::<DeviceInfoEx_FFI>()),
+        );
+        Some(copy)
+    }
+
+    /// Copies the struct into a `DeviceInfo`. This should have been created with `Default`, so that any fields an older SDK
+    /// didn't fill in are left as unknown
+    pub fn into_device_info(&self) -> DeviceInfo {
+        let string = |ptr: *const c_char| -> Option<String> {
+            if ptr.is_null() {
+                None
+            } else {
+                Some(
+                    unsafe { CStr::from_ptr(ptr) }
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            }
+        };
+        let number = |value: u32| Some(value).filter(|value| *value != 0);
+
+        let mut device = DeviceInfo::new_with_id(
+            self.vendor_id,
+            self.product_id,
+            string(self.manufacturer_name).unwrap_or_default(),
+            string(self.device_name).unwrap_or_default(),
+            self.device_id,
+            self.device_type.clone(),
+        );
+        device.details = DeviceDetails {
+            serial_number: string(self.serial_number),
+            firmware_version: string(self.firmware_version),
+            interface_path: string(self.interface_path),
+            analog_key_count: number(self.analog_key_count),
+            value_resolution: number(self.value_resolution),
+            report_rate: number(self.report_rate),
+            plugin_name: string(self.plugin_name),
+            digital: self.digital,
+        };
+        device
+    }
+}
+
 impl DeviceInfo {
     //    pub fn new(
     //        vendor_id: u16,
@@ -141,6 +336,7 @@ impl DeviceInfo {
             device_name,
             device_id,
             device_type,
+            details: DeviceDetails::default(),
         }
     }
 }
@@ -190,10 +386,26 @@ pub enum KeycodeType {
     VirtualKey = 2,
     /// Windows Virtual Keys which are translated to the current keyboard locale
     VirtualKeyTranslate = 3,
+    /// Linux input event codes (`KEY_*` in `linux/input-event-codes.h`), as given by evdev
+    Evdev = 4,
+    /// X keysyms which are translated using the XKB keymap of the current layout (Linux only). Keysyms up to 0xFFFF can be used
+    /// as codes (e.g. 'q' is 0x71, Escape is 0xFF1B), but not the Unicode keysyms above that. Keysyms overlap the range of custom
+    /// keys, so those can't be read in this mode
+    XkbKeysym = 5,
 }
 
 pub type DeviceID = u64;
 
+/// Identifies a reader of the SDK, see `wooting_analog_create_session`
+pub type SessionID = u32;
+
+/// Identifies a registered device event listener, see `wooting_analog_add_device_event_listener`
+pub type ListenerID = u32;
+
+/// Length of a dense analog buffer which covers every code on the HID Keyboard/Keypad page. Dense buffers are indexed by HID code,
+/// so a buffer of this length can hold the value of every standard key
+pub const DENSE_BUFFER_LEN: usize = 0x100;
+
 #[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Clone, Primitive)]
 #[repr(C)]
@@ -216,6 +428,146 @@ pub enum DeviceEventType {
     Disconnected = 2,
 }
 
+/// How a plugin has been behaving. Every call into a plugin has a time budget, plugins which keep overrunning it or panicking are
+/// quarantined
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy, Primitive)]
+#[repr(C)]
+pub enum PluginStatus {
+    /// The plugin is responding normally
+    Healthy = 0,
+    /// The last calls into the plugin overran their time budget or panicked, it will be quarantined if this keeps happening
+    Degraded = 1,
+    /// The plugin is no longer called and its devices have been reported as disconnected
+    Quarantined = 2,
+}
+
+/// How the two sides of a virtual axis are combined when keys on both sides are pressed
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy, Primitive)]
+#[repr(C)]
+pub enum AxisCombine {
+    /// The negative side is taken off the positive side, so pressing both equally gives 0
+    Difference = 0,
+    /// The side which is pressed further wins
+    MaxWins = 1,
+    /// The side which was pressed last wins
+    LastPressed = 2,
+}
+
+/// A virtual axis going from -1.0 to 1.0, made up of keys which move it in the positive (e.g. D) and negative (e.g. A) direction
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub struct AxisDefinition {
+    /// The keys which move the axis towards 1.0, the one pressed furthest is used
+    pub positive: Vec<u16>,
+    /// The keys which move the axis towards -1.0, the one pressed furthest is used
+    pub negative: Vec<u16>,
+    pub combine: AxisCombine,
+    /// Values closer to 0 than this are given as 0, the rest of the range is scaled so the axis still goes from 0 to 1
+    pub deadzone: f32,
+}
+
+/// How a key is moving, worked out from every report of the device it's on. Times are in microseconds on the clock given by
+/// `wooting_analog_kinematics_time`, where 0 means it hasn't happened
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[repr(C)]
+pub struct KeyKinematics {
+    /// How far the key is pressed, from 0.0 to 1.0
+    pub depth: f32,
+    /// How fast the key is moving in depth per second, positive while it's going down
+    pub velocity: f32,
+    /// How fast the velocity is changing in depth per second squared
+    pub acceleration: f32,
+    /// The furthest the key has been pressed during the current press, or the last one if it has been released
+    pub peak_depth: f32,
+    /// When the key was last pressed
+    pub pressed_at: u64,
+    /// When the key was released after it was last pressed, 0 while it's held down
+    pub released_at: u64,
+}
+
+impl KeyKinematics {
+    pub fn is_pressed(&self) -> bool {
+        self.depth > 0.0
+    }
+
+    /// Gives how long the key was held down during its last press, or `None` if it's still held down or hasn't been pressed
+    pub fn press_duration(&self) -> Option<std::time::Duration> {
+        if self.pressed_at == 0 || self.released_at == 0 {
+            None
+        } else {
+            Some(std::time::Duration::from_micros(
+                self.released_at - self.pressed_at,
+            ))
+        }
+    }
+}
+
+/// What a key does when it's bound for mouse emulation
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Primitive)]
+#[repr(C)]
+pub enum MouseAction {
+    /// Moves the pointer, the further the key is pressed the faster it moves
+    MoveUp = 0,
+    MoveDown = 1,
+    MoveLeft = 2,
+    MoveRight = 3,
+    /// Scrolls, the further the key is pressed the faster it scrolls
+    ScrollUp = 4,
+    ScrollDown = 5,
+    ScrollLeft = 6,
+    ScrollRight = 7,
+    /// Holds a mouse button down while the key is pressed past the halfway point
+    LeftButton = 8,
+    RightButton = 9,
+    MiddleButton = 10,
+}
+
+/// How fast mouse emulation moves the pointer or scrolls for how far a key is pressed. The speed is
+/// `max_speed * ((depth - deadzone) / (1 - deadzone)) ^ exponent`, so an exponent above 1 gives finer control near the top of the
+/// key travel and an exponent of 1 is linear
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[repr(C)]
+pub struct MouseCurve {
+    /// How far a key has to be pressed before it moves anything, from 0 up to (but not including) 1
+    pub deadzone: f32,
+    pub exponent: f32,
+    /// The speed with the key fully pressed, in pixels (or lines when scrolling) per second
+    pub max_speed: f32,
+}
+
+impl MouseCurve {
+    /// Gives the speed for a key pressed to `depth`
+    pub fn speed(&self, depth: f32) -> f32 {
+        if depth <= self.deadzone {
+            0.0
+        } else {
+            let depth = ((depth - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+            self.max_speed * depth.powf(self.exponent)
+        }
+    }
+
+    /// Returns true if the deadzone is from 0 up to 1, the exponent is above 0 and the speed isn't negative
+    pub fn is_valid(&self) -> bool {
+        (0.0..1.0).contains(&self.deadzone) && self.exponent > 0.0 && self.max_speed >= 0.0
+    }
+}
+
+/// A plugin loaded by the SDK
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub struct PluginInfo {
+    pub name: String,
+    /// The library the plugin was loaded from, which is what its priority is set by. This is `None` for plugins which weren't
+    /// loaded from a library
+    pub path: Option<String>,
+    pub status: PluginStatus,
+}
+
 #[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Clone, Primitive, Error)]
 #[repr(C)]
@@ -255,6 +607,12 @@ pub enum WootingAnalogResult {
     /// Indicates that the Analog SDK could not be found on the system
     #[error("The Wooting Analog SDK could not be found on the system")]
     DLLNotFound = -1990isize,
+    /// A plugin didn't respond within its time budget
+    #[error("A plugin didn't respond in time")]
+    Timeout = -1989isize,
+    /// Sending raw data to devices hasn't been enabled
+    #[error("Raw device access hasn't been enabled")]
+    PermissionDenied = -1988isize,
 }
 
 impl WootingAnalogResult {
@@ -409,6 +767,20 @@ impl Into<bool> for WootingAnalogResult {
     }
 }
 
+/// Functions giving back a string have no room for an error, so they give null
+impl From<WootingAnalogResult> for *const c_char {
+    fn from(_: WootingAnalogResult) -> Self {
+        std::ptr::null()
+    }
+}
+
+/// Functions giving back a time have no room for an error, so they give 0
+impl From<WootingAnalogResult> for u64 {
+    fn from(_: WootingAnalogResult) -> Self {
+        0
+    }
+}
+
 #[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Clone, Hash, Eq, Primitive)]
 #[repr(C)]
@@ -466,12 +838,12 @@ pub enum HIDCodes {
     BracketRight = 0x30, //BRACKET_RIGHT
     Backslash = 0x31,    //BACKSLASH
 
-    // = 0x32, //INTL_HASH
-    Semicolon = 0x33, //SEMICOLON
-    Quote = 0x34,     //QUOTE
-    Backquote = 0x35, //BACKQUOTE
-    Comma = 0x36,     //COMMA
-    Period = 0x37,    //PERIOD
+    InternationalHash = 0x32, //INTL_HASH
+    Semicolon = 0x33,         //SEMICOLON
+    Quote = 0x34,             //QUOTE
+    Backquote = 0x35,         //BACKQUOTE
+    Comma = 0x36,             //COMMA
+    Period = 0x37,            //PERIOD
 
     Slash = 0x38,    //SLASH
     CapsLock = 0x39, //CAPS_LOCK
@@ -540,12 +912,13 @@ pub enum HIDCodes {
     F22 = 0x71, //F22
     F23 = 0x72, //F23
 
-    F24 = 0x73,  //F24
-    Open = 0x74, //OPEN
-
-    Help = 0x75, //HELP
+    F24 = 0x73,    //F24
+    Open = 0x74,   //OPEN
+    Help = 0x75,   //HELP
+    Menu = 0x76,   //MENU
+    Select = 0x77, //SELECT
 
-    // = 0x77, //SELECT
+    Stop = 0x78,       //STOP
     Again = 0x79,      //AGAIN
     Undo = 0x7a,       //UNDO
     Cut = 0x7b,        //CUT
@@ -554,19 +927,99 @@ pub enum HIDCodes {
     Find = 0x7e,       //FIND
     VolumeMute = 0x7f, //VOLUME_MUTE
 
-    VolumeUp = 0x80,    //VOLUME_UP
-    VolumeDown = 0x81,  //VOLUME_DOWN
-    NumpadComma = 0x85, //NUMPAD_COMMA
+    VolumeUp = 0x80,          //VOLUME_UP
+    VolumeDown = 0x81,        //VOLUME_DOWN
+    LockingCapsLock = 0x82,   //LOCKING_CAPS_LOCK
+    LockingNumLock = 0x83,    //LOCKING_NUM_LOCK
+    LockingScrollLock = 0x84, //LOCKING_SCROLL_LOCK
+    NumpadComma = 0x85,       //NUMPAD_COMMA
+    NumpadEqualSign = 0x86,   //NUMPAD_EQUAL_SIGN
 
     InternationalRO = 0x87,  //INTL_RO
     KanaMode = 0x88,         //KANA_MODE
     InternationalYen = 0x89, //INTL_YEN
     Convert = 0x8a,          //CONVERT
     NonConvert = 0x8b,       //NON_CONVERT
-    Lang1 = 0x90,            //LANG1
-    Lang2 = 0x91,            //LANG2
-    Lang3 = 0x92,            //LANG3
-    Lang4 = 0x93,            //LANG4
+    International6 = 0x8c,   //INTL_6
+    International7 = 0x8d,   //INTL_7
+    International8 = 0x8e,   //INTL_8
+    International9 = 0x8f,   //INTL_9
+
+    Lang1 = 0x90, //LANG1
+    Lang2 = 0x91, //LANG2
+    Lang3 = 0x92, //LANG3
+    Lang4 = 0x93, //LANG4
+    Lang5 = 0x94, //LANG5
+    Lang6 = 0x95, //LANG6
+    Lang7 = 0x96, //LANG7
+    Lang8 = 0x97, //LANG8
+
+    Lang9 = 0x98,          //LANG9
+    AlternateErase = 0x99, //ALTERNATE_ERASE
+    SysReq = 0x9a,         //SYS_REQ
+    Cancel = 0x9b,         //CANCEL
+    Clear = 0x9c,          //CLEAR
+    Prior = 0x9d,          //PRIOR
+    Return = 0x9e,         //RETURN
+    Separator = 0x9f,      //SEPARATOR
+
+    Out = 0xa0,        //OUT
+    Oper = 0xa1,       //OPER
+    ClearAgain = 0xa2, //CLEAR_AGAIN
+    CrSel = 0xa3,      //CRSEL
+    ExSel = 0xa4,      //EXSEL
+
+    Numpad00 = 0xb0,           //NUMPAD_00
+    Numpad000 = 0xb1,          //NUMPAD_000
+    ThousandsSeparator = 0xb2, //THOUSANDS_SEPARATOR
+    DecimalSeparator = 0xb3,   //DECIMAL_SEPARATOR
+    CurrencyUnit = 0xb4,       //CURRENCY_UNIT
+    CurrencySubunit = 0xb5,    //CURRENCY_SUBUNIT
+    NumpadParenLeft = 0xb6,    //NUMPAD_PAREN_LEFT
+    NumpadParenRight = 0xb7,   //NUMPAD_PAREN_RIGHT
+
+    NumpadBraceLeft = 0xb8,  //NUMPAD_BRACE_LEFT
+    NumpadBraceRight = 0xb9, //NUMPAD_BRACE_RIGHT
+    NumpadTab = 0xba,        //NUMPAD_TAB
+    NumpadBackspace = 0xbb,  //NUMPAD_BACKSPACE
+    NumpadA = 0xbc,          //NUMPAD_A
+    NumpadB = 0xbd,          //NUMPAD_B
+    NumpadC = 0xbe,          //NUMPAD_C
+    NumpadD = 0xbf,          //NUMPAD_D
+
+    NumpadE = 0xc0,         //NUMPAD_E
+    NumpadF = 0xc1,         //NUMPAD_F
+    NumpadXor = 0xc2,       //NUMPAD_XOR
+    NumpadCaret = 0xc3,     //NUMPAD_CARET
+    NumpadPercent = 0xc4,   //NUMPAD_PERCENT
+    NumpadLess = 0xc5,      //NUMPAD_LESS
+    NumpadGreater = 0xc6,   //NUMPAD_GREATER
+    NumpadAmpersand = 0xc7, //NUMPAD_AMPERSAND
+
+    NumpadDoubleAmpersand = 0xc8, //NUMPAD_DOUBLE_AMPERSAND
+    NumpadBar = 0xc9,             //NUMPAD_BAR
+    NumpadDoubleBar = 0xca,       //NUMPAD_DOUBLE_BAR
+    NumpadColon = 0xcb,           //NUMPAD_COLON
+    NumpadHash = 0xcc,            //NUMPAD_HASH
+    NumpadSpace = 0xcd,           //NUMPAD_SPACE
+    NumpadAt = 0xce,              //NUMPAD_AT
+    NumpadExclamation = 0xcf,     //NUMPAD_EXCLAMATION
+
+    NumpadMemoryStore = 0xd0,    //NUMPAD_MEMORY_STORE
+    NumpadMemoryRecall = 0xd1,   //NUMPAD_MEMORY_RECALL
+    NumpadMemoryClear = 0xd2,    //NUMPAD_MEMORY_CLEAR
+    NumpadMemoryAdd = 0xd3,      //NUMPAD_MEMORY_ADD
+    NumpadMemorySubtract = 0xd4, //NUMPAD_MEMORY_SUBTRACT
+    NumpadMemoryMultiply = 0xd5, //NUMPAD_MEMORY_MULTIPLY
+    NumpadMemoryDivide = 0xd6,   //NUMPAD_MEMORY_DIVIDE
+    NumpadPlusMinus = 0xd7,      //NUMPAD_PLUS_MINUS
+
+    NumpadClear = 0xd8,       //NUMPAD_CLEAR
+    NumpadClearEntry = 0xd9,  //NUMPAD_CLEAR_ENTRY
+    NumpadBinary = 0xda,      //NUMPAD_BINARY
+    NumpadOctal = 0xdb,       //NUMPAD_OCTAL
+    NumpadDecimalBase = 0xdc, //NUMPAD_DECIMAL_BASE
+    NumpadHexadecimal = 0xdd, //NUMPAD_HEXADECIMAL
 
     LeftCtrl = 0xe0,   //CONTROL_LEFT
     LeftShift = 0xe1,  //SHIFT_LEFT
@@ -577,3 +1030,47 @@ pub enum HIDCodes {
     RightAlt = 0xe6,   //ALT_RIGHT
     RightMeta = 0xe7,  //META_RIGHT
 }
+
+/// Keys on the HID Consumer page (0x0C), e.g. media keys, are given as `CONSUMER_PAGE | usage`. These are their own set of codes
+/// which is the same in every `KeycodeType`, so they won't be mistaken for the Keyboard/Keypad page keys of the same usage
+pub const CONSUMER_PAGE: u16 = 0xC000;
+
+/// The commonly used keys of the HID Consumer page, as codes which can be read from the SDK (see `CONSUMER_PAGE`)
+#[cfg_attr(feature = "serdes", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone, Hash, Eq, Primitive)]
+#[repr(C)]
+pub enum ConsumerCodes {
+    Power = 0xC030,          //POWER
+    Sleep = 0xC032,          //SLEEP
+    BrightnessUp = 0xC06F,   //BRIGHTNESS_UP
+    BrightnessDown = 0xC070, //BRIGHTNESS_DOWN
+
+    Play = 0xC0B0,              //PLAY
+    Pause = 0xC0B1,             //PAUSE
+    Record = 0xC0B2,            //RECORD
+    FastForward = 0xC0B3,       //FAST_FORWARD
+    Rewind = 0xC0B4,            //REWIND
+    ScanNextTrack = 0xC0B5,     //SCAN_NEXT_TRACK
+    ScanPreviousTrack = 0xC0B6, //SCAN_PREVIOUS_TRACK
+    Stop = 0xC0B7,              //STOP
+
+    Eject = 0xC0B8,           //EJECT
+    PlayPause = 0xC0CD,       //PLAY_PAUSE
+    Mute = 0xC0E2,            //MUTE
+    VolumeIncrement = 0xC0E9, //VOLUME_INCREMENT
+    VolumeDecrement = 0xC0EA, //VOLUME_DECREMENT
+
+    ALConsumerControlConfig = 0xC183, //AL_CONSUMER_CONTROL_CONFIGURATION
+    ALEmailReader = 0xC18A,           //AL_EMAIL_READER
+    ALCalculator = 0xC192,            //AL_CALCULATOR
+    ALLocalBrowser = 0xC194,          //AL_LOCAL_MACHINE_BROWSER
+    ALInternetBrowser = 0xC196,       //AL_INTERNET_BROWSER
+
+    ACSearch = 0xC221,    //AC_SEARCH
+    ACHome = 0xC223,      //AC_HOME
+    ACBack = 0xC224,      //AC_BACK
+    ACForward = 0xC225,   //AC_FORWARD
+    ACStop = 0xC226,      //AC_STOP
+    ACRefresh = 0xC227,   //AC_REFRESH
+    ACBookmarks = 0xC22A, //AC_BOOKMARKS
+}
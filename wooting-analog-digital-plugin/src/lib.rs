@@ -0,0 +1,492 @@
+//! Plugin reading ordinary keyboards through evdev, giving pressed keys as 1.0 & released keys as 0.0. This lets games rely on
+//! the SDK alone for keyboard input, working with players who don't have an analog keyboard.
+#![cfg(target_os = "linux")]
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate libc;
+extern crate wooting_analog_plugin_dev;
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::os::raw::{c_float, c_ushort};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wooting_analog_plugin_dev::evdev::{
+    self, EvdevDevice, InputEvent, EV_KEY, EV_SYN, KEY_CNT, SYN_DROPPED, SYN_REPORT,
+};
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+use wooting_analog_plugin_dev::*;
+
+const PLUGIN_NAME: &str = "Wooting Digital Keyboard Plugin";
+/// How long the keyboard threads wait for events before checking if they should stop
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+//From linux/input-event-codes.h
+const KEY_ESC: u16 = 1;
+const KEY_A: u16 = 30;
+const KEY_Z: u16 = 44;
+const KEY_SPACE: u16 = 57;
+
+/// Wooting keyboards are read by the Wooting plugin, so they aren't listed a second time as digital keyboards. These are the
+/// vendor IDs they use, along with the product IDs of the legacy ones
+const WOOTING_VID: u16 = 0x31e3;
+const LEGACY_WOOTING: [(u16, u16); 2] = [(0x03eb, 0xff01), (0x03eb, 0xff02)];
+
+/// Whether a device with the given key codes is a keyboard, rather than e.g. a mouse, a power button or the media keys of a
+/// headset
+fn is_keyboard(keys: &[u16]) -> bool {
+    [KEY_ESC, KEY_A, KEY_Z, KEY_SPACE]
+        .iter()
+        .all(|key| keys.contains(key))
+}
+
+fn is_wooting(vendor_id: u16, product_id: u16) -> bool {
+    vendor_id == WOOTING_VID || LEGACY_WOOTING.contains(&(vendor_id, product_id))
+}
+
+/// The keys held down on a keyboard, by HID code
+#[derive(Debug, Default)]
+struct KeyboardState {
+    pressed: HashSet<u16>,
+    changed: bool,
+}
+
+impl KeyboardState {
+    /// Sets the keys which are held down from their Linux input event codes, ignoring those which don't have a HID code
+    fn set_pressed(&mut self, keys: &[u16]) {
+        let pressed: HashSet<u16> = keys.iter().filter_map(|&key| evdev_to_hid(key)).collect();
+        if pressed != self.pressed {
+            self.pressed = pressed;
+            self.changed = true;
+        }
+    }
+
+    fn analog(&self) -> HashMap<u16, f32> {
+        self.pressed.iter().map(|&key| (key, 1.0)).collect()
+    }
+
+    /// Applies `event`, giving the keys which are pressed once a report from the keyboard is complete and a key changed
+    fn handle(&mut self, event: &InputEvent) -> Option<HashMap<u16, f32>> {
+        match (event.kind, event.code) {
+            (EV_KEY, key) => {
+                if let Some(key) = evdev_to_hid(key) {
+                    //Auto repeat (2) counts as pressed
+                    self.changed |= if event.value == 0 {
+                        self.pressed.remove(&key)
+                    } else {
+                        self.pressed.insert(key)
+                    };
+                }
+                None
+            }
+            (EV_SYN, SYN_REPORT) if self.changed => {
+                self.changed = false;
+                Some(self.analog())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An opened keyboard, which is read on its own thread
+struct Device {
+    device_info: DeviceInfo,
+    path: PathBuf,
+    snapshots: Arc<SnapshotStore>,
+    connected: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Device {
+    /// Opens the device at `path`, giving `None` if it isn't a keyboard which should be read
+    fn open(path: &Path, snapshots: &Arc<SnapshotStore>) -> io::Result<Option<Self>> {
+        let mut device = EvdevDevice::open(path)?;
+        let id = device.input_id()?;
+        if is_wooting(id.vendor, id.product) || !is_keyboard(&device.event_codes(EV_KEY, KEY_CNT)?)
+        {
+            return Ok(None);
+        }
+
+        let device_id = device.device_id()?;
+        let mut device_info = DeviceInfo::new_with_id(
+            id.vendor,
+            id.product,
+            "Unknown".to_string(),
+            device.name()?,
+            device_id,
+            DeviceType::Keyboard,
+        );
+        let mut details = DeviceDetails::default();
+        details.serial_number = device.uniq().ok().filter(|uniq| !uniq.is_empty());
+        details.interface_path = Some(path.to_string_lossy().into_owned());
+        details.value_resolution = Some(2);
+        details.digital = true;
+        device_info.details = details;
+
+        let mut state = KeyboardState::default();
+        state.set_pressed(&device.pressed_keys()?);
+        snapshots.add_device(device_id);
+        snapshots.publish(device_id, state.analog());
+        let connected = Arc::new(AtomicBool::new(true));
+        let worker = {
+            let t_snapshots = Arc::clone(snapshots);
+            let t_connected = Arc::clone(&connected);
+            thread::spawn(move || {
+                while t_connected.load(Ordering::Relaxed) {
+                    let events = match device.read_events(READ_TIMEOUT) {
+                        Ok(events) => events,
+                        Err(e) => {
+                            if e.raw_os_error() != Some(libc::ENODEV) {
+                                error!("Read failed from {}: {}", device.path().display(), e);
+                            }
+                            t_connected.store(false, Ordering::Relaxed);
+                            return;
+                        }
+                    };
+                    for event in events.iter() {
+                        //Events were lost, so the keys which are held down are read from the keyboard again
+                        if (event.kind, event.code) == (EV_SYN, SYN_DROPPED) {
+                            if let Ok(keys) = device.pressed_keys() {
+                                state.set_pressed(&keys);
+                            }
+                        } else if let Some(analog) = state.handle(event) {
+                            t_snapshots.publish(device_id, analog);
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Some(Device {
+            device_info,
+            path: path.to_owned(),
+            snapshots: Arc::clone(snapshots),
+            connected,
+            worker: Some(worker),
+        }))
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        self.connected.store(false, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Couldn't join on the thread of {}", self.path.display());
+            }
+        }
+        self.snapshots.remove_device(self.device_info.device_id);
+    }
+}
+
+type DeviceEventCallback = Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>>;
+
+/// Looks for new keyboards in `input_dir` and gets rid of those which have been unplugged, firing their events. Devices which
+/// couldn't be opened or aren't keyboards are kept in `ignored`, so they aren't opened again
+fn refresh_devices(
+    input_dir: &Path,
+    devices: &Mutex<HashMap<DeviceID, Device>>,
+    ignored: &mut HashSet<PathBuf>,
+    snapshots: &Arc<SnapshotStore>,
+    device_event_cb: &DeviceEventCallback,
+) {
+    let fire = |event: DeviceEventType, device: &DeviceInfo| {
+        if let Some(cb) = device_event_cb.lock().unwrap().as_ref() {
+            cb(event, device);
+        }
+    };
+
+    let disconnected: Vec<DeviceID> = devices
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, device)| !device.connected.load(Ordering::Relaxed))
+        .map(|(&id, _)| id)
+        .collect();
+    for id in disconnected {
+        let device = devices.lock().unwrap().remove(&id);
+        if let Some(device) = device {
+            info!("{} has been disconnected", device.device_info.device_name);
+            fire(DeviceEventType::Disconnected, &device.device_info);
+        }
+    }
+
+    let paths = evdev::list_devices(input_dir);
+    ignored.retain(|path| paths.contains(path));
+    let open: HashSet<PathBuf> = devices
+        .lock()
+        .unwrap()
+        .values()
+        .map(|device| device.path.clone())
+        .collect();
+    for path in paths {
+        if open.contains(&path) || ignored.contains(&path) {
+            continue;
+        }
+        match Device::open(&path, snapshots) {
+            Ok(Some(device)) => {
+                let id = device.device_info.device_id;
+                if devices.lock().unwrap().contains_key(&id) {
+                    continue;
+                }
+                info!(
+                    "Found keyboard {} at {}",
+                    device.device_info.device_name,
+                    path.display()
+                );
+                let device_info = device.device_info.clone();
+                devices.lock().unwrap().insert(id, device);
+                fire(DeviceEventType::Connected, &device_info);
+            }
+            Ok(None) => {
+                ignored.insert(path);
+            }
+            Err(e) => {
+                debug!("Unable to open {}: {}", path.display(), e);
+                ignored.insert(path);
+            }
+        }
+    }
+}
+
+pub struct DigitalKeyboardPlugin {
+    initialised: Arc<AtomicBool>,
+    device_event_cb: DeviceEventCallback,
+    devices: Arc<Mutex<HashMap<DeviceID, Device>>>,
+    snapshots: Arc<SnapshotStore>,
+    input_dir: PathBuf,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DigitalKeyboardPlugin {
+    fn new() -> Self {
+        Self::with_input_dir(evdev::INPUT_DIR)
+    }
+
+    /// Creates the plugin looking for keyboards in `input_dir` rather than `/dev/input`
+    pub fn with_input_dir<P: AsRef<Path>>(input_dir: P) -> Self {
+        DigitalKeyboardPlugin {
+            initialised: Arc::new(AtomicBool::new(false)),
+            device_event_cb: Arc::new(Mutex::new(None)),
+            devices: Arc::new(Mutex::new(HashMap::new())),
+            snapshots: Arc::new(SnapshotStore::new()),
+            input_dir: input_dir.as_ref().to_owned(),
+            thread: None,
+        }
+    }
+}
+
+impl Plugin for DigitalKeyboardPlugin {
+    fn name(&mut self) -> SDKResult<&'static str> {
+        Ok(PLUGIN_NAME).into()
+    }
+
+    fn initialise(
+        &mut self,
+        callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+    ) -> SDKResult<u32> {
+        if let Err(e) = env_logger::try_init() {
+            warn!("Unable to initialize Env Logger: {}", e);
+        }
+        self.initialised.store(true, Ordering::Relaxed);
+
+        //The keyboards which are already connected are found before the callback is set, as they're given by the return value
+        let mut ignored = HashSet::new();
+        refresh_devices(
+            &self.input_dir,
+            &self.devices,
+            &mut ignored,
+            &self.snapshots,
+            &self.device_event_cb,
+        );
+        self.device_event_cb.lock().unwrap().replace(callback);
+
+        let t_devices = Arc::clone(&self.devices);
+        let t_snapshots = Arc::clone(&self.snapshots);
+        let t_device_event_cb = Arc::clone(&self.device_event_cb);
+        let input_dir = self.input_dir.clone();
+        self.thread = Some(evdev::spawn_rescan(
+            Arc::clone(&self.initialised),
+            move || {
+                refresh_devices(
+                    &input_dir,
+                    &t_devices,
+                    &mut ignored,
+                    &t_snapshots,
+                    &t_device_event_cb,
+                )
+            },
+        ));
+        Ok(self.devices.lock().unwrap().len() as u32).into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        self.initialised.load(Ordering::Relaxed)
+    }
+
+    fn unload(&mut self) {
+        self.initialised.store(false, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            t.join().unwrap();
+        }
+        self.devices.lock().unwrap().clear();
+        info!("{} unloaded", PLUGIN_NAME);
+    }
+
+    fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        Ok(self
+            .devices
+            .lock()
+            .unwrap()
+            .values()
+            .map(|device| device.device_info.clone())
+            .collect())
+        .into()
+    }
+
+    fn read_analog(&mut self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_analog(code, device_id)
+    }
+
+    fn read_full_buffer(
+        &mut self,
+        _max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<c_ushort, c_float>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_full_buffer(device_id)
+    }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        Some(Arc::clone(&self.snapshots))
+    }
+}
+
+declare_plugin!(DigitalKeyboardPlugin, DigitalKeyboardPlugin::new);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wooting_analog_plugin_dev::uinput::{UInputDevice, UInputSetup, BUS_VIRTUAL};
+
+    const KEY_W: u16 = 17;
+    const KEY_LEFTSHIFT: u16 = 42;
+    //Has no HID code
+    const KEY_FN: u16 = 0x1d0;
+
+    #[test]
+    fn keys_are_fully_pressed_or_released() {
+        let mut state = KeyboardState::default();
+        let report = InputEvent::new(EV_SYN, SYN_REPORT, 0);
+        assert_eq!(state.handle(&InputEvent::new(EV_KEY, KEY_W, 1)), None);
+        assert_eq!(
+            state.handle(&InputEvent::new(EV_KEY, KEY_LEFTSHIFT, 1)),
+            None
+        );
+        let analog = state.handle(&report).unwrap();
+        assert_eq!(analog.len(), 2);
+        assert_eq!(analog.get(&0x1a), Some(&1.0));
+        assert_eq!(analog.get(&0xe1), Some(&1.0));
+
+        //Auto repeat & keys without a HID code don't change anything
+        state.handle(&InputEvent::new(EV_KEY, KEY_W, 2));
+        state.handle(&InputEvent::new(EV_KEY, KEY_FN, 1));
+        assert_eq!(state.handle(&report), None);
+
+        state.handle(&InputEvent::new(EV_KEY, KEY_W, 0));
+        let analog = state.handle(&report).unwrap();
+        assert_eq!(analog.get(&0x1a), None);
+        assert_eq!(analog.get(&0xe1), Some(&1.0));
+
+        //After events were dropped the keys held down are set from the keyboard
+        state.set_pressed(&[KEY_W]);
+        assert!(state.changed);
+        assert_eq!(state.analog(), [(0x1a, 1.0)].iter().copied().collect());
+    }
+
+    #[test]
+    fn only_keyboards_are_read() {
+        assert!(is_keyboard(&[
+            KEY_ESC, 2, 3, KEY_W, KEY_A, KEY_Z, KEY_SPACE
+        ]));
+        //Mice & media keys
+        assert!(!is_keyboard(&[0x110, 0x111, 0x112]));
+        assert!(!is_keyboard(&[113, 114, 115, 163, 164, 165]));
+        assert!(is_wooting(0x31e3, 0x1402));
+        assert!(is_wooting(0x03eb, 0xff01));
+        assert!(!is_wooting(0x03eb, 0x2042));
+    }
+
+    #[test]
+    fn no_devices_in_empty_dir() {
+        let mut plugin = DigitalKeyboardPlugin::with_input_dir("/nonexistent");
+        assert_eq!(plugin.initialise(Box::new(|_, _| {})).0, Ok(0));
+        assert_eq!(plugin.device_info().0.map(|devices| devices.len()), Ok(0));
+        assert_eq!(
+            plugin.read_analog(0x1a, 0).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        plugin.unload();
+    }
+
+    /// Creates a keyboard through uinput
+    fn uinput_keyboard() -> io::Result<UInputDevice> {
+        UInputDevice::create(&UInputSetup {
+            name: "Test Keyboard".to_string(),
+            id: evdev::InputId {
+                bustype: BUS_VIRTUAL,
+                vendor: 0x1209,
+                product: 0xa0a2,
+                version: 1,
+            },
+            keys: (KEY_ESC..=KEY_SPACE).collect(),
+            ..Default::default()
+        })
+    }
+
+    fn press_key(keyboard: &mut UInputDevice, key: u16, pressed: bool) -> io::Result<()> {
+        keyboard.send(EV_KEY, key, i32::from(pressed))?;
+        keyboard.sync()
+    }
+
+    //Needs read & write access to /dev/uinput and /dev/input, e.g. run as root on a headless box with the uinput module loaded
+    #[test]
+    #[ignore]
+    fn virtual_keyboard() {
+        let mut keyboard = uinput_keyboard().unwrap();
+        //Give udev time to create the device node
+        thread::sleep(Duration::from_millis(500));
+
+        let mut plugin = DigitalKeyboardPlugin::new();
+        plugin.initialise(Box::new(|_, _| {})).0.unwrap();
+        let device = plugin
+            .device_info()
+            .0
+            .unwrap()
+            .into_iter()
+            .find(|device| device.device_name == "Test Keyboard")
+            .unwrap();
+        assert_eq!(device.device_type, DeviceType::Keyboard);
+        assert!(device.details.digital);
+
+        press_key(&mut keyboard, KEY_W, true).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(plugin.read_analog(0x1a, device.device_id).0, Ok(1.0));
+        press_key(&mut keyboard, KEY_W, false).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(plugin.read_analog(0x1a, device.device_id).0, Ok(0.0));
+        plugin.unload();
+    }
+}
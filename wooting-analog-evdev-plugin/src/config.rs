@@ -0,0 +1,383 @@
+//! Which axes of which devices are given as which keys, kept in a file so that e.g. racing pedals can be set up once for every app.
+//!
+//! The file has one binding per line, as the device, the axis and the key it's given as, separated by spaces, optionally followed
+//! by `invert`. Lines starting with '#' are ignored, e.g.
+//!
+//! ```text
+//! # Pedals, where the throttle rests at its max
+//! 046d:c262 ABS_Y W invert
+//! 046d:c262 ABS_Z S
+//! # Left stick of any gamepad as WASD
+//! * ABS_X- A
+//! * ABS_X+ D
+//! ```
+//!
+//! * The device is its vendor & product ID in hex, or `*` for every device which has the axis
+//! * The axis is its name from linux/input-event-codes.h (with or without the `ABS_` prefix) or its code in hex. It goes from 0
+//!   at its min to 1 at its max, or with a `-`/`+` suffix from 0 at its center to 1 at its min/max, for sticks & hats
+//! * The key is given by name (see `key_from_name`) or as a HID code in hex (e.g. `0x04`)
+//! * `invert` flips the axis, for axes which rest at their max
+use log::*;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use wooting_analog_plugin_dev::evdev::AbsInfo;
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+
+/// Environment variable which can be set to use a different file than the one in the user's config directory
+pub const AXES_PATH_VAR: &str = "WOOTING_ANALOG_EVDEV_AXES";
+
+const AXES_FILE: &str = "evdev-axes.txt";
+const CONFIG_DIR: &str = "wooting-analog-sdk";
+
+/// The names of the absolute axes, by code
+const AXIS_NAMES: [(&str, u16); 33] = [
+    ("X", 0x00),
+    ("Y", 0x01),
+    ("Z", 0x02),
+    ("RX", 0x03),
+    ("RY", 0x04),
+    ("RZ", 0x05),
+    ("THROTTLE", 0x06),
+    ("RUDDER", 0x07),
+    ("WHEEL", 0x08),
+    ("GAS", 0x09),
+    ("BRAKE", 0x0a),
+    ("HAT0X", 0x10),
+    ("HAT0Y", 0x11),
+    ("HAT1X", 0x12),
+    ("HAT1Y", 0x13),
+    ("HAT2X", 0x14),
+    ("HAT2Y", 0x15),
+    ("HAT3X", 0x16),
+    ("HAT3Y", 0x17),
+    ("PRESSURE", 0x18),
+    ("DISTANCE", 0x19),
+    ("TILT_X", 0x1a),
+    ("TILT_Y", 0x1b),
+    ("TOOL_WIDTH", 0x1c),
+    ("VOLUME", 0x20),
+    ("PROFILE", 0x21),
+    ("MISC", 0x28),
+    ("MT_TOUCH_MAJOR", 0x30),
+    ("MT_TOUCH_MINOR", 0x31),
+    ("MT_WIDTH_MAJOR", 0x32),
+    ("MT_WIDTH_MINOR", 0x33),
+    ("MT_POSITION_X", 0x35),
+    ("MT_POSITION_Y", 0x36),
+];
+
+/// Gives the code of the absolute axis with the given name, e.g. `ABS_GAS` or `GAS`, or its code in hex
+pub fn axis_from_name(name: &str) -> Option<u16> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("ABS_").unwrap_or(&name);
+    if let Some(hex) = name.strip_prefix("0X") {
+        return u16::from_str_radix(hex, 16).ok();
+    }
+    AXIS_NAMES
+        .iter()
+        .find(|(axis, _)| *axis == name)
+        .map(|&(_, code)| code)
+}
+
+/// The devices a binding applies to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceMatch {
+    Any,
+    Usb { vendor_id: u16, product_id: u16 },
+}
+
+impl DeviceMatch {
+    pub fn matches(&self, vendor_id: u16, product_id: u16) -> bool {
+        match *self {
+            DeviceMatch::Any => true,
+            DeviceMatch::Usb {
+                vendor_id: vid,
+                product_id: pid,
+            } => vid == vendor_id && pid == product_id,
+        }
+    }
+}
+
+/// The part of an axis which is given as a key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisRange {
+    /// From the min to the max
+    Full,
+    /// From the center to the min
+    Negative,
+    /// From the center to the max
+    Positive,
+}
+
+/// An axis given as a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisBinding {
+    pub device: DeviceMatch,
+    pub axis: u16,
+    pub range: AxisRange,
+    /// HID code of the key
+    pub key: u16,
+    pub invert: bool,
+}
+
+impl AxisBinding {
+    /// Gives how far the key is pressed for the `value` of the axis with `info`
+    pub fn analog(&self, value: i32, info: &AbsInfo) -> f32 {
+        let (min, max) = (f64::from(info.minimum), f64::from(info.maximum));
+        if max <= min {
+            return 0.0;
+        }
+        let mut value = f64::from(value);
+        if self.invert {
+            value = max + min - value;
+        }
+        let center = (min + max) / 2.0;
+        let flat = f64::from(info.flat.max(0));
+        let analog = match self.range {
+            AxisRange::Full => (value - min) / (max - min),
+            //The flat area around the center is left out, so the key is only pressed once the axis has moved out of it
+            AxisRange::Positive => (value - center - flat) / (max - center - flat),
+            AxisRange::Negative => (center - value - flat) / (center - min - flat),
+        };
+        if analog.is_nan() {
+            0.0
+        } else {
+            analog.max(0.0).min(1.0) as f32
+        }
+    }
+}
+
+/// Gives the file bindings are kept in, which is `AXES_PATH_VAR` if it's set, otherwise a file in the user's config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(AXES_PATH_VAR) {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join(CONFIG_DIR).join(AXES_FILE))
+}
+
+/// Loads the bindings in the file at `path`, lines which can't be read are skipped
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<AxisBinding>> {
+    let path = path.as_ref();
+    fs::read_to_string(path).map(|content| parse(&content, path))
+}
+
+/// Loads the bindings from the `default_path`, there are none if it doesn't exist
+pub fn load_default() -> Vec<AxisBinding> {
+    let path = match default_path() {
+        Some(path) => path,
+        None => {
+            warn!("Unable to find a config directory, no axes will be read");
+            return vec![];
+        }
+    };
+    match load(&path) {
+        Ok(bindings) => bindings,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            info!("{} doesn't exist, no axes will be read", path.display());
+            vec![]
+        }
+        Err(e) => {
+            warn!("Unable to read axes {}: {}", path.display(), e);
+            vec![]
+        }
+    }
+}
+
+fn parse_device(device: &str) -> Option<DeviceMatch> {
+    if device == "*" {
+        return Some(DeviceMatch::Any);
+    }
+    let mut ids = device.splitn(2, ':');
+    match (ids.next(), ids.next()) {
+        (Some(vid), Some(pid)) => Some(DeviceMatch::Usb {
+            vendor_id: u16::from_str_radix(vid, 16).ok()?,
+            product_id: u16::from_str_radix(pid, 16).ok()?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_axis(axis: &str) -> Option<(u16, AxisRange)> {
+    let (axis, range) = if let Some(axis) = axis.strip_suffix('-') {
+        (axis, AxisRange::Negative)
+    } else if let Some(axis) = axis.strip_suffix('+') {
+        (axis, AxisRange::Positive)
+    } else {
+        (axis, AxisRange::Full)
+    };
+    axis_from_name(axis).map(|axis| (axis, range))
+}
+
+fn parse_key(key: &str) -> Option<u16> {
+    match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => key_from_name(key),
+    }
+}
+
+fn parse_line(line: &str) -> Option<AxisBinding> {
+    let mut parts = line.split_whitespace();
+    let device = parse_device(parts.next()?)?;
+    let (axis, range) = parse_axis(parts.next()?)?;
+    let key = parse_key(parts.next()?)?;
+    let invert = match parts.next() {
+        None => false,
+        Some("invert") => true,
+        Some(_) => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(AxisBinding {
+        device,
+        axis,
+        range,
+        key,
+        invert,
+    })
+}
+
+fn parse(content: &str, path: &Path) -> Vec<AxisBinding> {
+    let mut bindings = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Some(binding) => bindings.push(binding),
+            None => warn!(
+                "Skipping invalid axis on line {} of {}",
+                i + 1,
+                path.display()
+            ),
+        }
+    }
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(minimum: i32, maximum: i32, flat: i32) -> AbsInfo {
+        AbsInfo {
+            minimum,
+            maximum,
+            flat,
+            ..Default::default()
+        }
+    }
+
+    fn binding(range: AxisRange, invert: bool) -> AxisBinding {
+        AxisBinding {
+            device: DeviceMatch::Any,
+            axis: 0,
+            range,
+            key: 0x04,
+            invert,
+        }
+    }
+
+    #[test]
+    fn parse_bindings() {
+        let content = "
+            # Pedals
+            046d:c262 ABS_Y W invert
+            046d:C262 brake 0x16
+            * X- A
+            * 0x10+ D
+            * ABS_NOPE A
+            046d W A
+            * X A sideways
+        ";
+        let bindings = parse(content, Path::new("test"));
+        let pedals = DeviceMatch::Usb {
+            vendor_id: 0x046d,
+            product_id: 0xc262,
+        };
+        assert_eq!(
+            bindings,
+            vec![
+                AxisBinding {
+                    device: pedals,
+                    axis: 0x01,
+                    range: AxisRange::Full,
+                    key: 0x1a,
+                    invert: true,
+                },
+                AxisBinding {
+                    device: pedals,
+                    axis: 0x0a,
+                    range: AxisRange::Full,
+                    key: 0x16,
+                    invert: false,
+                },
+                AxisBinding {
+                    device: DeviceMatch::Any,
+                    axis: 0x00,
+                    range: AxisRange::Negative,
+                    key: 0x04,
+                    invert: false,
+                },
+                AxisBinding {
+                    device: DeviceMatch::Any,
+                    axis: 0x10,
+                    range: AxisRange::Positive,
+                    key: 0x07,
+                    invert: false,
+                },
+            ]
+        );
+        assert!(pedals.matches(0x046d, 0xc262));
+        assert!(!pedals.matches(0x046d, 0xc261));
+    }
+
+    #[test]
+    fn full_range() {
+        let pedal = info(0, 1023, 0);
+        assert_eq!(binding(AxisRange::Full, false).analog(0, &pedal), 0.0);
+        assert_eq!(binding(AxisRange::Full, false).analog(1023, &pedal), 1.0);
+        assert_eq!(binding(AxisRange::Full, true).analog(1023, &pedal), 0.0);
+        assert_eq!(binding(AxisRange::Full, true).analog(0, &pedal), 1.0);
+
+        let trigger = info(-128, 127, 0);
+        assert_eq!(binding(AxisRange::Full, false).analog(-128, &trigger), 0.0);
+        assert_eq!(
+            binding(AxisRange::Full, false).analog(0, &trigger),
+            128.0 / 255.0
+        );
+        //Values out of the range are clamped
+        assert_eq!(binding(AxisRange::Full, false).analog(200, &trigger), 1.0);
+        //Axes without a range can't be pressed
+        assert_eq!(
+            binding(AxisRange::Full, false).analog(5, &info(0, 0, 0)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn half_ranges() {
+        let stick = info(-100, 100, 10);
+        let left = binding(AxisRange::Negative, false);
+        let right = binding(AxisRange::Positive, false);
+        assert_eq!(left.analog(0, &stick), 0.0);
+        assert_eq!(right.analog(0, &stick), 0.0);
+        //Within the flat area around the center
+        assert_eq!(right.analog(10, &stick), 0.0);
+        assert_eq!(right.analog(55, &stick), 0.5);
+        assert_eq!(left.analog(55, &stick), 0.0);
+        assert_eq!(left.analog(-100, &stick), 1.0);
+
+        //Hats go from -1 to 1
+        let hat = info(-1, 1, 0);
+        assert_eq!(right.analog(1, &hat), 1.0);
+        assert_eq!(left.analog(-1, &hat), 1.0);
+        assert_eq!(left.analog(1, &hat), 0.0);
+    }
+}
@@ -0,0 +1,573 @@
+//! Plugin giving the absolute axes of Linux input devices (e.g. racing pedals, gamepad triggers & sticks or sliders) as analog
+//! keys, so they can be read through the SDK along with analog keyboards. Which axes are given as which keys is set up in a file,
+//! see `config`.
+#![cfg(target_os = "linux")]
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate libc;
+extern crate wooting_analog_plugin_dev;
+
+pub mod config;
+
+use config::AxisBinding;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::os::raw::{c_float, c_ushort};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wooting_analog_plugin_dev::evdev::{
+    self, AbsInfo, EvdevDevice, InputEvent, ABS_CNT, EV_ABS, EV_SYN, SYN_DROPPED, SYN_REPORT,
+};
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+use wooting_analog_plugin_dev::*;
+
+const PLUGIN_NAME: &str = "Wooting Evdev Axis Plugin";
+/// How long the device threads wait for events before checking if they should stop
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The bound axes of a device, which works out the analog values of their keys from the events of the device
+#[derive(Debug)]
+struct DeviceAxes {
+    bindings: Vec<AxisBinding>,
+    info: HashMap<u16, AbsInfo>,
+    changed: bool,
+}
+
+impl DeviceAxes {
+    /// `info` holds the range & current value of every bound axis
+    fn new(bindings: Vec<AxisBinding>, info: HashMap<u16, AbsInfo>) -> Self {
+        DeviceAxes {
+            bindings,
+            info,
+            changed: false,
+        }
+    }
+
+    fn set_value(&mut self, axis: u16, value: i32) {
+        if let Some(info) = self.info.get_mut(&axis) {
+            if info.value != value {
+                info.value = value;
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Gives the keys which are pressed, each as far as the furthest of the axes bound to it
+    fn analog(&self) -> HashMap<u16, f32> {
+        let mut analog: HashMap<u16, f32> = HashMap::new();
+        for binding in self.bindings.iter() {
+            let value = match self.info.get(&binding.axis) {
+                Some(info) => binding.analog(info.value, info),
+                None => continue,
+            };
+            if value > 0.0 {
+                let key = analog.entry(binding.key).or_insert(0.0);
+                *key = key.max(value);
+            }
+        }
+        analog
+    }
+
+    /// Applies `event`, giving the keys which are pressed once a report from the device is complete and an axis changed
+    fn handle(&mut self, event: &InputEvent) -> Option<HashMap<u16, f32>> {
+        match (event.kind, event.code) {
+            (EV_ABS, axis) => {
+                self.set_value(axis, event.value);
+                None
+            }
+            (EV_SYN, SYN_REPORT) if self.changed => {
+                self.changed = false;
+                Some(self.analog())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An opened device with at least one bound axis, which is read on its own thread
+struct Device {
+    device_info: DeviceInfo,
+    path: PathBuf,
+    snapshots: Arc<SnapshotStore>,
+    connected: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Device {
+    /// Opens the device at `path`, giving `None` if none of the `bindings` apply to it
+    fn open(
+        path: &Path,
+        bindings: &[AxisBinding],
+        snapshots: &Arc<SnapshotStore>,
+    ) -> io::Result<Option<Self>> {
+        let mut device = EvdevDevice::open(path)?;
+        let id = device.input_id()?;
+        let axes = device.event_codes(EV_ABS, ABS_CNT)?;
+        let bindings: Vec<AxisBinding> = bindings
+            .iter()
+            .filter(|binding| {
+                binding.device.matches(id.vendor, id.product) && axes.contains(&binding.axis)
+            })
+            .cloned()
+            .collect();
+        if bindings.is_empty() {
+            return Ok(None);
+        }
+
+        let mut info = HashMap::new();
+        for binding in bindings.iter() {
+            info.insert(binding.axis, device.abs_info(binding.axis)?);
+        }
+        let device_id = device.device_id()?;
+        let mut device_info = DeviceInfo::new_with_id(
+            id.vendor,
+            id.product,
+            "Unknown".to_string(),
+            device.name()?,
+            device_id,
+            DeviceType::Other,
+        );
+        device_info.details = device_details(&device, &bindings, &info);
+
+        let mut axes = DeviceAxes::new(bindings, info);
+        snapshots.add_device(device_id);
+        snapshots.publish(device_id, axes.analog());
+        let connected = Arc::new(AtomicBool::new(true));
+        let worker = {
+            let t_snapshots = Arc::clone(snapshots);
+            let t_connected = Arc::clone(&connected);
+            thread::spawn(move || {
+                while t_connected.load(Ordering::Relaxed) {
+                    let events = match device.read_events(READ_TIMEOUT) {
+                        Ok(events) => events,
+                        Err(e) => {
+                            if e.raw_os_error() != Some(libc::ENODEV) {
+                                error!("Read failed from {}: {}", device.path().display(), e);
+                            }
+                            t_connected.store(false, Ordering::Relaxed);
+                            return;
+                        }
+                    };
+                    for event in events.iter() {
+                        //Events were lost, so the axes are read from the device again
+                        if (event.kind, event.code) == (EV_SYN, SYN_DROPPED) {
+                            for axis in axes.info.keys().copied().collect::<Vec<u16>>() {
+                                if let Ok(info) = device.abs_info(axis) {
+                                    axes.set_value(axis, info.value);
+                                }
+                            }
+                        } else if let Some(analog) = axes.handle(event) {
+                            t_snapshots.publish(device_id, analog);
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Some(Device {
+            device_info,
+            path: path.to_owned(),
+            snapshots: Arc::clone(snapshots),
+            connected,
+            worker: Some(worker),
+        }))
+    }
+}
+
+fn device_details(
+    device: &EvdevDevice,
+    bindings: &[AxisBinding],
+    info: &HashMap<u16, AbsInfo>,
+) -> DeviceDetails {
+    let mut details = DeviceDetails::default();
+    details.serial_number = device.uniq().ok().filter(|uniq| !uniq.is_empty());
+    details.interface_path = Some(device.path().to_string_lossy().into_owned());
+    let keys: HashSet<u16> = bindings.iter().map(|binding| binding.key).collect();
+    details.analog_key_count = Some(keys.len() as u32);
+    //The coarsest of the axes
+    details.value_resolution = info
+        .values()
+        .map(|info| (i64::from(info.maximum) - i64::from(info.minimum) + 1).max(1) as u32)
+        .min();
+    details
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        self.connected.store(false, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Couldn't join on the thread of {}", self.path.display());
+            }
+        }
+        self.snapshots.remove_device(self.device_info.device_id);
+    }
+}
+
+type DeviceEventCallback = Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>>;
+
+/// Looks for new devices in `input_dir` and gets rid of those which have been unplugged, firing their events. Devices which
+/// couldn't be opened or don't have any bound axes are kept in `ignored`, so they aren't opened again
+fn refresh_devices(
+    input_dir: &Path,
+    bindings: &[AxisBinding],
+    devices: &Mutex<HashMap<DeviceID, Device>>,
+    ignored: &mut HashSet<PathBuf>,
+    snapshots: &Arc<SnapshotStore>,
+    device_event_cb: &DeviceEventCallback,
+) {
+    let fire = |event: DeviceEventType, device: &DeviceInfo| {
+        if let Some(cb) = device_event_cb.lock().unwrap().as_ref() {
+            cb(event, device);
+        }
+    };
+
+    let disconnected: Vec<DeviceID> = devices
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, device)| !device.connected.load(Ordering::Relaxed))
+        .map(|(&id, _)| id)
+        .collect();
+    for id in disconnected {
+        let device = devices.lock().unwrap().remove(&id);
+        if let Some(device) = device {
+            info!("{} has been disconnected", device.device_info.device_name);
+            fire(DeviceEventType::Disconnected, &device.device_info);
+        }
+    }
+
+    let paths = evdev::list_devices(input_dir);
+    ignored.retain(|path| paths.contains(path));
+    let open: HashSet<PathBuf> = devices
+        .lock()
+        .unwrap()
+        .values()
+        .map(|device| device.path.clone())
+        .collect();
+    for path in paths {
+        if open.contains(&path) || ignored.contains(&path) {
+            continue;
+        }
+        match Device::open(&path, bindings, snapshots) {
+            Ok(Some(device)) => {
+                let id = device.device_info.device_id;
+                if devices.lock().unwrap().contains_key(&id) {
+                    continue;
+                }
+                info!(
+                    "Found {} with bound axes at {}",
+                    device.device_info.device_name,
+                    path.display()
+                );
+                let device_info = device.device_info.clone();
+                devices.lock().unwrap().insert(id, device);
+                fire(DeviceEventType::Connected, &device_info);
+            }
+            Ok(None) => {
+                ignored.insert(path);
+            }
+            Err(e) => {
+                debug!("Unable to open {}: {}", path.display(), e);
+                ignored.insert(path);
+            }
+        }
+    }
+}
+
+pub struct EvdevAxisPlugin {
+    initialised: Arc<AtomicBool>,
+    device_event_cb: DeviceEventCallback,
+    devices: Arc<Mutex<HashMap<DeviceID, Device>>>,
+    snapshots: Arc<SnapshotStore>,
+    bindings: Arc<Vec<AxisBinding>>,
+    input_dir: PathBuf,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EvdevAxisPlugin {
+    fn new() -> Self {
+        Self::with_bindings(config::load_default(), evdev::INPUT_DIR)
+    }
+
+    /// Creates the plugin with the given bindings rather than those from the config file, looking for devices in `input_dir`
+    pub fn with_bindings<P: AsRef<Path>>(bindings: Vec<AxisBinding>, input_dir: P) -> Self {
+        EvdevAxisPlugin {
+            initialised: Arc::new(AtomicBool::new(false)),
+            device_event_cb: Arc::new(Mutex::new(None)),
+            devices: Arc::new(Mutex::new(HashMap::new())),
+            snapshots: Arc::new(SnapshotStore::new()),
+            bindings: Arc::new(bindings),
+            input_dir: input_dir.as_ref().to_owned(),
+            thread: None,
+        }
+    }
+}
+
+impl Plugin for EvdevAxisPlugin {
+    fn name(&mut self) -> SDKResult<&'static str> {
+        Ok(PLUGIN_NAME).into()
+    }
+
+    fn initialise(
+        &mut self,
+        callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+    ) -> SDKResult<u32> {
+        if let Err(e) = env_logger::try_init() {
+            warn!("Unable to initialize Env Logger: {}", e);
+        }
+        self.initialised.store(true, Ordering::Relaxed);
+        if self.bindings.is_empty() {
+            info!("No axes are bound, so no devices will be read");
+            self.device_event_cb.lock().unwrap().replace(callback);
+            return Ok(0).into();
+        }
+
+        //The devices which are already connected are found before the callback is set, as they're given by the return value
+        let mut ignored = HashSet::new();
+        refresh_devices(
+            &self.input_dir,
+            &self.bindings,
+            &self.devices,
+            &mut ignored,
+            &self.snapshots,
+            &self.device_event_cb,
+        );
+        self.device_event_cb.lock().unwrap().replace(callback);
+
+        let t_devices = Arc::clone(&self.devices);
+        let t_snapshots = Arc::clone(&self.snapshots);
+        let t_device_event_cb = Arc::clone(&self.device_event_cb);
+        let t_bindings = Arc::clone(&self.bindings);
+        let input_dir = self.input_dir.clone();
+        self.thread = Some(evdev::spawn_rescan(
+            Arc::clone(&self.initialised),
+            move || {
+                refresh_devices(
+                    &input_dir,
+                    &t_bindings,
+                    &t_devices,
+                    &mut ignored,
+                    &t_snapshots,
+                    &t_device_event_cb,
+                )
+            },
+        ));
+        Ok(self.devices.lock().unwrap().len() as u32).into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        self.initialised.load(Ordering::Relaxed)
+    }
+
+    fn unload(&mut self) {
+        self.initialised.store(false, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            t.join().unwrap();
+        }
+        self.devices.lock().unwrap().clear();
+        info!("{} unloaded", PLUGIN_NAME);
+    }
+
+    fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        Ok(self
+            .devices
+            .lock()
+            .unwrap()
+            .values()
+            .map(|device| device.device_info.clone())
+            .collect())
+        .into()
+    }
+
+    fn read_analog(&mut self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_analog(code, device_id)
+    }
+
+    fn read_full_buffer(
+        &mut self,
+        _max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<c_ushort, c_float>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_full_buffer(device_id)
+    }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        Some(Arc::clone(&self.snapshots))
+    }
+}
+
+declare_plugin!(EvdevAxisPlugin, EvdevAxisPlugin::new);
+
+#[cfg(test)]
+mod tests {
+    use super::config::{AxisRange, DeviceMatch};
+    use super::*;
+    use wooting_analog_plugin_dev::uinput::{UInputDevice, UInputSetup, BUS_VIRTUAL};
+
+    const ABS_GAS: u16 = 0x09;
+    const ABS_BRAKE: u16 = 0x0a;
+    const ABS_HAT0X: u16 = 0x10;
+
+    fn binding(axis: u16, range: AxisRange, key: u16) -> AxisBinding {
+        AxisBinding {
+            device: DeviceMatch::Any,
+            axis,
+            range,
+            key,
+            invert: false,
+        }
+    }
+
+    fn info(minimum: i32, maximum: i32) -> AbsInfo {
+        AbsInfo {
+            minimum,
+            maximum,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn device_axes() {
+        let bindings = vec![
+            binding(ABS_GAS, AxisRange::Full, 0x1a),
+            binding(ABS_HAT0X, AxisRange::Negative, 0x04),
+            binding(ABS_HAT0X, AxisRange::Positive, 0x07),
+            //Both pedals are bound to the same key
+            binding(ABS_BRAKE, AxisRange::Full, 0x1a),
+        ];
+        let info = [
+            (ABS_GAS, info(0, 100)),
+            (ABS_BRAKE, info(0, 100)),
+            (ABS_HAT0X, info(-1, 1)),
+        ];
+        let mut axes = DeviceAxes::new(bindings, info.iter().copied().collect());
+        assert_eq!(axes.analog(), HashMap::new());
+
+        let report = InputEvent::new(EV_SYN, SYN_REPORT, 0);
+        assert_eq!(axes.handle(&InputEvent::new(EV_ABS, ABS_GAS, 50)), None);
+        assert_eq!(axes.handle(&InputEvent::new(EV_ABS, ABS_HAT0X, 1)), None);
+        let analog = axes.handle(&report).unwrap();
+        assert_eq!(analog.get(&0x1a), Some(&0.5));
+        assert_eq!(analog.get(&0x07), Some(&1.0));
+        assert_eq!(analog.get(&0x04), None);
+
+        //Nothing changed, so there's nothing to publish
+        assert_eq!(axes.handle(&report), None);
+        //Axes which aren't bound are ignored
+        axes.handle(&InputEvent::new(EV_ABS, 0x00, 20));
+        assert_eq!(axes.handle(&report), None);
+
+        axes.handle(&InputEvent::new(EV_ABS, ABS_BRAKE, 75));
+        axes.handle(&InputEvent::new(EV_ABS, ABS_HAT0X, 0));
+        let analog = axes.handle(&report).unwrap();
+        assert_eq!(analog.get(&0x1a), Some(&0.75));
+        assert_eq!(analog.get(&0x07), None);
+    }
+
+    #[test]
+    fn no_devices_without_bindings() {
+        let mut plugin = EvdevAxisPlugin::with_bindings(vec![], evdev::INPUT_DIR);
+        assert_eq!(plugin.initialise(Box::new(|_, _| {})).0, Ok(0));
+        assert_eq!(plugin.device_info().0.map(|devices| devices.len()), Ok(0));
+        assert_eq!(
+            plugin.read_analog(0x1a, 0).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        plugin.unload();
+    }
+
+    const PEDALS_VENDOR_ID: u16 = 0x1209;
+    const PEDALS_PRODUCT_ID: u16 = 0xa0a1;
+
+    /// Creates pedals through uinput
+    fn uinput_pedals() -> io::Result<UInputDevice> {
+        UInputDevice::create(&UInputSetup {
+            name: "Test Pedals".to_string(),
+            id: evdev::InputId {
+                bustype: BUS_VIRTUAL,
+                vendor: PEDALS_VENDOR_ID,
+                product: PEDALS_PRODUCT_ID,
+                version: 1,
+            },
+            absolute_axes: vec![(ABS_GAS, 0, 1023), (ABS_BRAKE, 0, 1023)],
+            ..Default::default()
+        })
+    }
+
+    fn press(pedals: &mut UInputDevice, axis: u16, value: i32) -> io::Result<()> {
+        pedals.send(EV_ABS, axis, value)?;
+        pedals.sync()
+    }
+
+    //Needs read & write access to /dev/uinput and /dev/input, e.g. run as root on a headless box with the uinput module loaded
+    #[test]
+    #[ignore]
+    fn virtual_pedals() {
+        let pedals = DeviceMatch::Usb {
+            vendor_id: PEDALS_VENDOR_ID,
+            product_id: PEDALS_PRODUCT_ID,
+        };
+        let bindings = vec![
+            AxisBinding {
+                device: pedals,
+                axis: ABS_GAS,
+                range: AxisRange::Full,
+                key: 0x1a,
+                invert: false,
+            },
+            AxisBinding {
+                device: pedals,
+                axis: ABS_BRAKE,
+                range: AxisRange::Full,
+                key: 0x16,
+                invert: false,
+            },
+        ];
+        let mut virtual_pedals = uinput_pedals().unwrap();
+        //Give udev time to create the device node
+        thread::sleep(Duration::from_millis(500));
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let t_events = Arc::clone(&events);
+        let mut plugin = EvdevAxisPlugin::with_bindings(bindings, evdev::INPUT_DIR);
+        assert_eq!(
+            plugin
+                .initialise(Box::new(move |event, _| t_events
+                    .lock()
+                    .unwrap()
+                    .push(event)))
+                .0,
+            Ok(1)
+        );
+        let devices = plugin.device_info().0.unwrap();
+        assert_eq!(devices[0].device_name, "Test Pedals");
+        assert_eq!(devices[0].details.analog_key_count, Some(2));
+        let device_id = devices[0].device_id;
+
+        press(&mut virtual_pedals, ABS_GAS, 1023).unwrap();
+        press(&mut virtual_pedals, ABS_BRAKE, 256).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(plugin.read_analog(0x1a, device_id).0, Ok(1.0));
+        assert_eq!(plugin.read_analog(0x16, 0).0, Ok(256.0 / 1023.0));
+
+        drop(virtual_pedals);
+        thread::sleep(evdev::RESCAN_INTERVAL * 2);
+        assert_eq!(*events.lock().unwrap(), vec![DeviceEventType::Disconnected]);
+        assert_eq!(plugin.device_info().0.map(|devices| devices.len()), Ok(0));
+        plugin.unload();
+    }
+}
@@ -0,0 +1,148 @@
+//! Where the plugin listens & the key senders have to authenticate with, kept in a file with one setting per line. Lines starting
+//! with '#' are ignored, e.g.
+//!
+//! ```text
+//! # Take packets from other machines, rather than just this one
+//! listen 0.0.0.0:21520
+//! # Only take packets sent with this key, the sender has to be given the same one
+//! key correct-horse-battery-staple
+//! # Seconds without packets from a sender before its devices are disconnected
+//! timeout 2
+//! ```
+use crate::protocol::DEFAULT_PORT;
+use log::*;
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Environment variable which can be set to use a different file than the one in the user's config directory
+pub const CONFIG_PATH_VAR: &str = "WOOTING_ANALOG_NETWORK_CONFIG";
+
+const CONFIG_FILE: &str = "network-receiver.txt";
+const CONFIG_DIR: &str = "wooting-analog-sdk";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiverConfig {
+    /// Address the plugin listens on, only this machine by default so that nothing on the network can press keys unless it's
+    /// been set up to
+    pub listen: SocketAddr,
+    /// Key the packets have to be authenticated with, any packet is taken if there's none
+    pub key: Option<Vec<u8>>,
+    /// How long a sender can go without sending anything before its devices are disconnected
+    pub timeout: Duration,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        ReceiverConfig {
+            listen: SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+            key: None,
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ReceiverConfig {
+    /// Loads the config in the file at `path`, lines which can't be read are skipped
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        fs::read_to_string(path).map(|content| parse(&content, path))
+    }
+
+    /// Loads the config from the `default_path`, giving the default config if it doesn't exist
+    pub fn load_default() -> Self {
+        let path = match default_path() {
+            Some(path) => path,
+            None => return ReceiverConfig::default(),
+        };
+        match ReceiverConfig::load(&path) {
+            Ok(config) => config,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => ReceiverConfig::default(),
+            Err(e) => {
+                warn!("Unable to read {}: {}", path.display(), e);
+                ReceiverConfig::default()
+            }
+        }
+    }
+}
+
+/// Gives the path of the config file, which is `wooting-analog-sdk/network-receiver.txt` in the user's config directory unless
+/// it's set by `CONFIG_PATH_VAR`
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_PATH_VAR) {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+fn parse_line(config: &mut ReceiverConfig, line: &str) -> Option<()> {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let name = parts.next()?;
+    let value = parts.next()?.trim();
+    match name {
+        "listen" => config.listen = value.parse().ok()?,
+        "key" if !value.is_empty() => config.key = Some(value.as_bytes().to_vec()),
+        "timeout" => {
+            let secs: f32 = value.parse().ok()?;
+            if !secs.is_finite() || secs <= 0.0 {
+                return None;
+            }
+            config.timeout = Duration::from_secs_f32(secs);
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+fn parse(content: &str, path: &Path) -> ReceiverConfig {
+    let mut config = ReceiverConfig::default();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if parse_line(&mut config, line).is_none() {
+            //The line isn't logged, as it could have the key in it
+            warn!(
+                "Skipping invalid setting on line {} of {}",
+                i + 1,
+                path.display()
+            );
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let config = parse(
+            "# Comment\n\
+             listen 0.0.0.0:1234\n\
+             key  two words \n\
+             timeout 0.5\n\
+             timeout -1\n\
+             listen nowhere\n\
+             colour blue\n",
+            Path::new("network-receiver.txt"),
+        );
+        assert_eq!(config.listen, "0.0.0.0:1234".parse().unwrap());
+        assert_eq!(config.key.as_deref(), Some(&b"two words"[..]));
+        assert_eq!(config.timeout, Duration::from_millis(500));
+
+        assert_eq!(
+            parse("", Path::new("network-receiver.txt")),
+            ReceiverConfig::default()
+        );
+        assert!(ReceiverConfig::default().listen.ip().is_loopback());
+    }
+}
@@ -0,0 +1,586 @@
+//! Plugin receiving analog keyboards from another machine over UDP, for setups where the keyboard isn't plugged into the
+//! machine running the game (e.g. streaming or cloud gaming). The devices are read on the other machine by
+//! `wooting-analog-network-sender` and mirrored here, see `protocol` for how they're sent and `config` for setting up where the
+//! plugin listens.
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate hmac_sha256;
+extern crate wooting_analog_plugin_dev;
+
+pub mod config;
+pub mod protocol;
+
+use config::ReceiverConfig;
+use protocol::{is_newer, Message, Packet};
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, UdpSocket};
+use std::os::raw::{c_float, c_ushort};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+use wooting_analog_plugin_dev::*;
+
+const PLUGIN_NAME: &str = "Wooting Network Plugin";
+/// How long the receiving thread waits for packets before checking if it should stop
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+type DeviceEvents = Vec<(DeviceEventType, DeviceInfo)>;
+
+/// A sender which packets have been received from
+#[derive(Debug)]
+struct RemoteSender {
+    addr: SocketAddr,
+    last_seen: Instant,
+    /// Highest sequence number received
+    last_seq: u32,
+    /// Number of packets which never arrived
+    lost: u64,
+    devices_seq: Option<u32>,
+    devices: HashMap<DeviceID, DeviceInfo>,
+    state_seqs: HashMap<DeviceID, u32>,
+}
+
+/// Mirrors the devices of the senders which packets are received from
+struct Mirror {
+    senders: HashMap<u32, RemoteSender>,
+    snapshots: Arc<SnapshotStore>,
+    timeout: Duration,
+}
+
+impl Mirror {
+    fn new(snapshots: Arc<SnapshotStore>, timeout: Duration) -> Self {
+        Mirror {
+            senders: HashMap::new(),
+            snapshots,
+            timeout,
+        }
+    }
+
+    fn devices(&self) -> Vec<DeviceInfo> {
+        self.senders
+            .values()
+            .flat_map(|sender| sender.devices.values().cloned())
+            .collect()
+    }
+
+    /// Applies the `packet` received from `addr`, giving the device events it caused
+    fn handle(&mut self, packet: Packet, addr: SocketAddr, now: Instant) -> DeviceEvents {
+        let sender = self.senders.entry(packet.sender).or_insert_with(|| {
+            info!("Receiving devices from {}", addr);
+            RemoteSender {
+                addr,
+                last_seen: now,
+                last_seq: packet.seq.wrapping_sub(1),
+                lost: 0,
+                devices_seq: None,
+                devices: HashMap::new(),
+                state_seqs: HashMap::new(),
+            }
+        });
+        let newest = is_newer(packet.seq, sender.last_seq);
+        if newest {
+            let lost = packet.seq.wrapping_sub(sender.last_seq) - 1;
+            if lost > 0 {
+                sender.lost += u64::from(lost);
+                debug!("Lost {} packets from {}", sender.lost, addr);
+            }
+            sender.last_seq = packet.seq;
+        }
+        sender.last_seen = now;
+        sender.addr = addr;
+
+        let seq = packet.seq;
+        match packet.message {
+            Message::Devices(devices) => {
+                if sender.devices_seq.map_or(true, |last| is_newer(seq, last)) {
+                    sender.devices_seq = Some(seq);
+                    return self.set_devices(packet.sender, devices);
+                }
+            }
+            Message::State { device_id, analog } => {
+                //Packets older than the last state of the device are dropped, so the keys don't jump back
+                let newer = sender
+                    .state_seqs
+                    .get(&device_id)
+                    .map_or(true, |&last| is_newer(seq, last));
+                if newer && sender.devices.contains_key(&device_id) {
+                    sender.state_seqs.insert(device_id, seq);
+                    self.snapshots.publish(
+                        device_id,
+                        analog
+                            .into_iter()
+                            .filter(|&(_, value)| value > 0.0)
+                            .collect(),
+                    );
+                }
+            }
+            Message::Bye => {
+                if newest {
+                    info!("{} has stopped sending", addr);
+                    return self.remove_sender(packet.sender);
+                }
+            }
+        }
+        vec![]
+    }
+
+    /// Sets the devices which are connected to `sender`, giving the events of those which were connected or disconnected
+    fn set_devices(&mut self, sender_id: u32, devices: Vec<DeviceInfo>) -> DeviceEvents {
+        let taken: HashSet<DeviceID> = self
+            .senders
+            .iter()
+            .filter(|(&id, _)| id != sender_id)
+            .flat_map(|(_, sender)| sender.devices.keys().copied())
+            .collect();
+        let sender = match self.senders.get_mut(&sender_id) {
+            Some(sender) => sender,
+            None => return vec![],
+        };
+
+        let mut events = vec![];
+        let ids: HashSet<DeviceID> = devices.iter().map(|device| device.device_id).collect();
+        let removed: Vec<DeviceID> = sender
+            .devices
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect();
+        for id in removed {
+            if let Some(device) = sender.devices.remove(&id) {
+                sender.state_seqs.remove(&id);
+                self.snapshots.remove_device(id);
+                events.push((DeviceEventType::Disconnected, device));
+            }
+        }
+        for mut device in devices {
+            if sender.devices.contains_key(&device.device_id) {
+                continue;
+            }
+            if taken.contains(&device.device_id) {
+                warn!(
+                    "{} is already being received from another sender",
+                    device.device_name
+                );
+                continue;
+            }
+            device.details.interface_path = Some(sender.addr.to_string());
+            self.snapshots.add_device(device.device_id);
+            sender.devices.insert(device.device_id, device.clone());
+            events.push((DeviceEventType::Connected, device));
+        }
+        events
+    }
+
+    fn remove_sender(&mut self, sender_id: u32) -> DeviceEvents {
+        match self.senders.remove(&sender_id) {
+            Some(sender) => sender
+                .devices
+                .into_iter()
+                .map(|(id, device)| {
+                    self.snapshots.remove_device(id);
+                    (DeviceEventType::Disconnected, device)
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Disconnects the devices of the senders which haven't sent anything within the timeout
+    fn expire(&mut self, now: Instant) -> DeviceEvents {
+        let expired: Vec<u32> = self
+            .senders
+            .iter()
+            .filter(|(_, sender)| now.duration_since(sender.last_seen) > self.timeout)
+            .map(|(&id, _)| id)
+            .collect();
+        let mut events = vec![];
+        for id in expired {
+            if let Some(sender) = self.senders.get(&id) {
+                warn!("Nothing received from {} for a while", sender.addr);
+            }
+            events.extend(self.remove_sender(id));
+        }
+        events
+    }
+
+    fn clear(&mut self) {
+        let senders: Vec<u32> = self.senders.keys().copied().collect();
+        for id in senders {
+            self.remove_sender(id);
+        }
+    }
+}
+
+type DeviceEventCallback = Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>>;
+
+pub struct NetworkPlugin {
+    initialised: Arc<AtomicBool>,
+    device_event_cb: DeviceEventCallback,
+    snapshots: Arc<SnapshotStore>,
+    mirror: Arc<Mutex<Mirror>>,
+    config: ReceiverConfig,
+    local_addr: Option<SocketAddr>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NetworkPlugin {
+    fn new() -> Self {
+        Self::with_config(ReceiverConfig::load_default())
+    }
+
+    /// Creates the plugin with the given config rather than the one from the config file
+    pub fn with_config(config: ReceiverConfig) -> Self {
+        let snapshots = Arc::new(SnapshotStore::new());
+        NetworkPlugin {
+            initialised: Arc::new(AtomicBool::new(false)),
+            device_event_cb: Arc::new(Mutex::new(None)),
+            mirror: Arc::new(Mutex::new(Mirror::new(
+                Arc::clone(&snapshots),
+                config.timeout,
+            ))),
+            snapshots,
+            config,
+            local_addr: None,
+            thread: None,
+        }
+    }
+
+    /// The address the plugin is listening on, once it's been initialised
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+}
+
+impl Plugin for NetworkPlugin {
+    fn name(&mut self) -> SDKResult<&'static str> {
+        Ok(PLUGIN_NAME).into()
+    }
+
+    fn initialise(
+        &mut self,
+        callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+    ) -> SDKResult<u32> {
+        if let Err(e) = env_logger::try_init() {
+            warn!("Unable to initialize Env Logger: {}", e);
+        }
+        let socket = match UdpSocket::bind(self.config.listen) {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!("Unable to listen on {}: {}", self.config.listen, e);
+                return Err(WootingAnalogResult::Failure).into();
+            }
+        };
+        if let Err(e) = socket.set_read_timeout(Some(READ_TIMEOUT)) {
+            error!("Unable to set the read timeout: {}", e);
+            return Err(WootingAnalogResult::Failure).into();
+        }
+        self.local_addr = socket.local_addr().ok();
+        if self.config.key.is_none() && !self.config.listen.ip().is_loopback() {
+            warn!(
+                "Listening on {} without a key, so anything on the network can press keys",
+                self.config.listen
+            );
+        } else {
+            info!("Listening on {}", self.config.listen);
+        }
+
+        self.device_event_cb.lock().unwrap().replace(callback);
+        self.initialised.store(true, Ordering::Relaxed);
+
+        let t_initialised = Arc::clone(&self.initialised);
+        let t_mirror = Arc::clone(&self.mirror);
+        let t_device_event_cb = Arc::clone(&self.device_event_cb);
+        let key = self.config.key.clone();
+        self.thread = Some(thread::spawn(move || {
+            let mut buffer = [0; 2048];
+            while t_initialised.load(Ordering::Relaxed) {
+                let packet = match socket.recv_from(&mut buffer) {
+                    Ok((len, addr)) => match Packet::decode(&buffer[..len], key.as_deref()) {
+                        Ok(packet) => Some((packet, addr)),
+                        Err(e) => {
+                            debug!("Dropping packet from {}: {}", addr, e);
+                            None
+                        }
+                    },
+                    Err(ref e)
+                        if e.kind() == std::io::ErrorKind::WouldBlock
+                            || e.kind() == std::io::ErrorKind::TimedOut =>
+                    {
+                        None
+                    }
+                    Err(e) => {
+                        error!("Unable to receive: {}", e);
+                        thread::sleep(READ_TIMEOUT);
+                        None
+                    }
+                };
+
+                let events = {
+                    let mut mirror = t_mirror.lock().unwrap();
+                    let now = Instant::now();
+                    let mut events = match packet {
+                        Some((packet, addr)) => mirror.handle(packet, addr, now),
+                        None => vec![],
+                    };
+                    events.extend(mirror.expire(now));
+                    events
+                };
+                if let Some(cb) = t_device_event_cb.lock().unwrap().as_ref() {
+                    for (event, device) in events.iter() {
+                        cb(event.clone(), device);
+                    }
+                }
+            }
+        }));
+        Ok(0).into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        self.initialised.load(Ordering::Relaxed)
+    }
+
+    fn unload(&mut self) {
+        self.initialised.store(false, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            t.join().unwrap();
+        }
+        self.mirror.lock().unwrap().clear();
+        self.local_addr = None;
+        info!("{} unloaded", PLUGIN_NAME);
+    }
+
+    fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        Ok(self.mirror.lock().unwrap().devices()).into()
+    }
+
+    fn read_analog(&mut self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_analog(code, device_id)
+    }
+
+    fn read_full_buffer(
+        &mut self,
+        _max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<c_ushort, c_float>> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        self.snapshots.read_full_buffer(device_id)
+    }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        Some(Arc::clone(&self.snapshots))
+    }
+}
+
+declare_plugin!(NetworkPlugin, NetworkPlugin::new);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard(device_id: DeviceID) -> DeviceInfo {
+        DeviceInfo::new_with_id(
+            0x31e3,
+            0x1402,
+            "Wooting".to_string(),
+            "Wooting Two HE".to_string(),
+            device_id,
+            DeviceType::Keyboard,
+        )
+    }
+
+    fn packet(sender: u32, seq: u32, message: Message) -> Packet {
+        Packet {
+            sender,
+            seq,
+            message,
+        }
+    }
+
+    fn state(device_id: DeviceID, analog: &[(u16, f32)]) -> Message {
+        Message::State {
+            device_id,
+            analog: analog.to_vec(),
+        }
+    }
+
+    fn event_ids(events: &DeviceEvents) -> Vec<(DeviceEventType, DeviceID)> {
+        events
+            .iter()
+            .map(|(event, device)| (event.clone(), device.device_id))
+            .collect()
+    }
+
+    #[test]
+    fn mirrors_sender_devices() {
+        let snapshots = Arc::new(SnapshotStore::new());
+        let mut mirror = Mirror::new(Arc::clone(&snapshots), Duration::from_secs(2));
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let start = Instant::now();
+        let read = |device_id, code| {
+            snapshots
+                .device_snapshot(device_id)
+                .map(|snapshot| snapshot.read_analog(code))
+        };
+
+        //State for a device which hasn't been given yet is ignored
+        assert!(mirror
+            .handle(packet(1, 1, state(10, &[(4, 1.0)])), addr, start)
+            .is_empty());
+        let events = mirror.handle(
+            packet(1, 2, Message::Devices(vec![keyboard(10), keyboard(11)])),
+            addr,
+            start,
+        );
+        let mut ids = event_ids(&events);
+        ids.sort_by_key(|(_, id)| *id);
+        assert_eq!(
+            ids,
+            vec![
+                (DeviceEventType::Connected, 10),
+                (DeviceEventType::Connected, 11)
+            ]
+        );
+        assert_eq!(
+            events[0].1.details.interface_path.as_deref(),
+            Some("127.0.0.1:5000")
+        );
+
+        mirror.handle(packet(1, 5, state(10, &[(4, 1.0), (5, 0.0)])), addr, start);
+        assert_eq!(read(10, 4), Some(1.0));
+        assert_eq!(mirror.senders[&1].lost, 2);
+        //Packets which arrive late don't undo newer ones
+        mirror.handle(packet(1, 4, state(10, &[(4, 0.5)])), addr, start);
+        assert_eq!(read(10, 4), Some(1.0));
+        mirror.handle(packet(1, 3, state(11, &[(6, 0.5)])), addr, start);
+        assert_eq!(read(11, 6), Some(0.5));
+
+        //A device missing from the list has been disconnected, even if an older list arrives afterwards
+        let events = mirror.handle(
+            packet(1, 7, Message::Devices(vec![keyboard(11)])),
+            addr,
+            start,
+        );
+        assert_eq!(
+            event_ids(&events),
+            vec![(DeviceEventType::Disconnected, 10)]
+        );
+        assert!(mirror
+            .handle(
+                packet(1, 6, Message::Devices(vec![keyboard(10), keyboard(11)])),
+                addr,
+                start
+            )
+            .is_empty());
+        assert_eq!(read(10, 4), None);
+
+        //Devices are only received from one sender at a time
+        let other: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+        assert!(mirror
+            .handle(
+                packet(2, 1, Message::Devices(vec![keyboard(11)])),
+                other,
+                start
+            )
+            .is_empty());
+
+        let events = mirror.handle(packet(1, 8, Message::Bye), addr, start);
+        assert_eq!(
+            event_ids(&events),
+            vec![(DeviceEventType::Disconnected, 11)]
+        );
+        assert_eq!(mirror.devices(), vec![]);
+    }
+
+    #[test]
+    fn silent_senders_time_out() {
+        let snapshots = Arc::new(SnapshotStore::new());
+        let mut mirror = Mirror::new(snapshots, Duration::from_secs(2));
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let start = Instant::now();
+        mirror.handle(
+            packet(1, 1, Message::Devices(vec![keyboard(10)])),
+            addr,
+            start,
+        );
+        assert!(mirror.expire(start + Duration::from_secs(1)).is_empty());
+        let events = mirror.expire(start + Duration::from_secs(3));
+        assert_eq!(
+            event_ids(&events),
+            vec![(DeviceEventType::Disconnected, 10)]
+        );
+        assert!(mirror.senders.is_empty());
+    }
+
+    fn wait_for<F: FnMut() -> bool>(mut condition: F) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn receives_over_localhost() {
+        let key: &[u8] = b"secret";
+        let mut plugin = NetworkPlugin::with_config(ReceiverConfig {
+            listen: "127.0.0.1:0".parse().unwrap(),
+            key: Some(key.to_vec()),
+            ..Default::default()
+        });
+        let events = Arc::new(Mutex::new(vec![]));
+        let t_events = Arc::clone(&events);
+        assert_eq!(
+            plugin
+                .initialise(Box::new(move |event, device| t_events
+                    .lock()
+                    .unwrap()
+                    .push((event, device.device_id))))
+                .0,
+            Ok(0)
+        );
+        let to = plugin.local_addr().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = |packet: Packet, key: Option<&[u8]>| {
+            socket.send_to(&packet.encode(key), to).unwrap();
+        };
+
+        send(
+            packet(9, 1, Message::Devices(vec![keyboard(10)])),
+            Some(key),
+        );
+        send(packet(9, 2, state(10, &[(0x1a, 1.0)])), Some(key));
+        assert!(wait_for(|| plugin.read_analog(0x1a, 10).0 == Ok(1.0)));
+        assert_eq!(plugin.device_info().0.map(|devices| devices.len()), Ok(1));
+
+        //Packets without the key are dropped
+        send(packet(9, 3, state(10, &[(0x1a, 0.2)])), None);
+        send(packet(9, 4, state(10, &[(0x1a, 0.4)])), Some(b"wrong"));
+        send(packet(9, 5, state(10, &[(0x1a, 0.6)])), Some(key));
+        assert!(wait_for(|| plugin.read_analog(0x1a, 10).0 == Ok(0.6)));
+
+        send(packet(9, 6, Message::Bye), Some(key));
+        assert!(wait_for(|| events.lock().unwrap().len() == 2));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (DeviceEventType::Connected, 10),
+                (DeviceEventType::Disconnected, 10)
+            ]
+        );
+        plugin.unload();
+    }
+}
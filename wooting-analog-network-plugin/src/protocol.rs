@@ -0,0 +1,406 @@
+//! The protocol the sender & this plugin talk over UDP. Every datagram is one packet:
+//!
+//! | Bytes | Field                                                                 |
+//! | ----- | --------------------------------------------------------------------- |
+//! | 2     | Magic, `WA`                                                           |
+//! | 1     | Version, packets with any other version are dropped                   |
+//! | 1     | Kind of message                                                       |
+//! | 1     | Flags, bit 0 is set when the packet ends with an authentication tag   |
+//! | 4     | ID of the sender, picked at random each time it starts                |
+//! | 4     | Sequence number, going up by one with every packet from the sender    |
+//! | ...   | The message                                                           |
+//! | 16    | Optional tag, the first 16 bytes of HMAC-SHA256 over everything else  |
+//!
+//! Numbers are little endian. Every message gives the full state of what it's about (e.g. all the keys which are pressed on a
+//! device, rather than the keys which changed), and the sender repeats them regularly, so a lost packet is made up for by the
+//! next one. The sequence numbers are used to drop packets which are duplicated, replayed or arrive out of order.
+use std::fmt;
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+
+pub const VERSION: u8 = 1;
+/// Port the plugin listens on if none is configured
+pub const DEFAULT_PORT: u16 = 21520;
+/// Largest packet sent, which fits into a datagram on any network without being fragmented
+pub const MAX_PACKET_LEN: usize = 1200;
+/// Most keys given in a `State`, which is more than a keyboard can have pressed at once
+pub const MAX_STATE_KEYS: usize = 256;
+/// Most devices given in `Devices`, along with how long their names can be in bytes
+pub const MAX_DEVICES: usize = 12;
+const MAX_NAME_LEN: usize = 32;
+
+const MAGIC: [u8; 2] = *b"WA";
+const HEADER_LEN: usize = 13;
+const TAG_LEN: usize = 16;
+const FLAG_AUTHENTICATED: u8 = 1;
+
+const KIND_DEVICES: u8 = 1;
+const KIND_STATE: u8 = 2;
+const KIND_BYE: u8 = 3;
+
+const FLAG_DIGITAL: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// All the devices which are connected to the sender. Devices which were given before & are missing have been disconnected
+    Devices(Vec<DeviceInfo>),
+    /// All the keys which are pressed on a device, by HID code
+    State {
+        device_id: DeviceID,
+        analog: Vec<(u16, f32)>,
+    },
+    /// The sender is stopping, so all of its devices have been disconnected
+    Bye,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub sender: u32,
+    pub seq: u32,
+    pub message: Message,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    /// It isn't a packet of this protocol
+    NotAPacket,
+    UnsupportedVersion(u8),
+    Malformed,
+    /// A key is needed, but the packet doesn't have a tag
+    Unauthenticated,
+    /// The tag doesn't match, so the packet was changed or sent with a different key
+    BadTag,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::NotAPacket => write!(f, "not a packet"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported protocol version {}", version)
+            }
+            DecodeError::Malformed => write!(f, "malformed packet"),
+            DecodeError::Unauthenticated => write!(f, "packet isn't authenticated"),
+            DecodeError::BadTag => write!(f, "authentication failed"),
+        }
+    }
+}
+
+/// Whether sequence number `seq` comes after `last`, allowing for the numbers wrapping around
+pub fn is_newer(seq: u32, last: u32) -> bool {
+    (seq.wrapping_sub(last) as i32) > 0
+}
+
+fn tag(key: &[u8], data: &[u8]) -> [u8; TAG_LEN] {
+    let mac = hmac_sha256::HMAC::mac(data, key);
+    let mut tag = [0; TAG_LEN];
+    tag.copy_from_slice(&mac[..TAG_LEN]);
+    tag
+}
+
+/// Compares the tags in constant time, so it can't be found out how much of a tag is right from how long it takes
+fn tags_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn put_str(out: &mut Vec<u8>, string: &str) {
+    let mut len = string.len().min(MAX_NAME_LEN);
+    while !string.is_char_boundary(len) {
+        len -= 1;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(string[..len].as_bytes());
+}
+
+/// Reads values from the front of a packet
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < len {
+            return Err(DecodeError::Malformed);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn str(&mut self) -> Result<String, DecodeError> {
+        let len = usize::from(self.u8()?);
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| DecodeError::Malformed)
+    }
+}
+
+impl Packet {
+    /// Encodes the packet, authenticating it if there's a `key`. Devices & keys past `MAX_DEVICES` & `MAX_STATE_KEYS` are left out
+    pub fn encode(&self, key: Option<&[u8]>) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MAX_PACKET_LEN);
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+        out.push(match self.message {
+            Message::Devices(_) => KIND_DEVICES,
+            Message::State { .. } => KIND_STATE,
+            Message::Bye => KIND_BYE,
+        });
+        out.push(if key.is_some() { FLAG_AUTHENTICATED } else { 0 });
+        out.extend_from_slice(&self.sender.to_le_bytes());
+        out.extend_from_slice(&self.seq.to_le_bytes());
+
+        match &self.message {
+            Message::Devices(devices) => {
+                let devices = &devices[..devices.len().min(MAX_DEVICES)];
+                out.push(devices.len() as u8);
+                for device in devices.iter() {
+                    out.extend_from_slice(&device.device_id.to_le_bytes());
+                    out.extend_from_slice(&device.vendor_id.to_le_bytes());
+                    out.extend_from_slice(&device.product_id.to_le_bytes());
+                    out.push(device.device_type.clone() as u8);
+                    out.push(if device.details.digital {
+                        FLAG_DIGITAL
+                    } else {
+                        0
+                    });
+                    put_str(&mut out, &device.manufacturer_name);
+                    put_str(&mut out, &device.device_name);
+                }
+            }
+            Message::State { device_id, analog } => {
+                let analog = &analog[..analog.len().min(MAX_STATE_KEYS)];
+                out.extend_from_slice(&device_id.to_le_bytes());
+                out.extend_from_slice(&(analog.len() as u16).to_le_bytes());
+                for &(code, value) in analog.iter() {
+                    //Values are sent as 16 bit fractions, which is far finer than any device reports
+                    let value = (value.max(0.0).min(1.0) * f32::from(u16::MAX)).round() as u16;
+                    out.extend_from_slice(&code.to_le_bytes());
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Message::Bye => {}
+        }
+
+        if let Some(key) = key {
+            let tag = tag(key, &out);
+            out.extend_from_slice(&tag);
+        }
+        out
+    }
+
+    /// Decodes the packet in `data`. If there's a `key` the packet has to be authenticated with it, otherwise any tag is ignored
+    pub fn decode(data: &[u8], key: Option<&[u8]>) -> Result<Packet, DecodeError> {
+        if data.len() < HEADER_LEN || data[..2] != MAGIC {
+            return Err(DecodeError::NotAPacket);
+        }
+        if data[2] != VERSION {
+            return Err(DecodeError::UnsupportedVersion(data[2]));
+        }
+        let authenticated = data[4] & FLAG_AUTHENTICATED != 0;
+        let data = if authenticated {
+            if data.len() < HEADER_LEN + TAG_LEN {
+                return Err(DecodeError::Malformed);
+            }
+            let (data, packet_tag) = data.split_at(data.len() - TAG_LEN);
+            if let Some(key) = key {
+                if !tags_match(&tag(key, data), packet_tag) {
+                    return Err(DecodeError::BadTag);
+                }
+            }
+            data
+        } else if key.is_some() {
+            return Err(DecodeError::Unauthenticated);
+        } else {
+            data
+        };
+
+        let mut reader = Reader(&data[5..]);
+        let sender = reader.u32()?;
+        let seq = reader.u32()?;
+        let message = match data[3] {
+            KIND_DEVICES => {
+                let count = reader.u8()?;
+                let mut devices = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    let device_id = reader.u64()?;
+                    let vendor_id = reader.u16()?;
+                    let product_id = reader.u16()?;
+                    let device_type =
+                        DeviceType::from_u8(reader.u8()?).ok_or(DecodeError::Malformed)?;
+                    let flags = reader.u8()?;
+                    let mut device = DeviceInfo::new_with_id(
+                        vendor_id,
+                        product_id,
+                        reader.str()?,
+                        reader.str()?,
+                        device_id,
+                        device_type,
+                    );
+                    device.details.digital = flags & FLAG_DIGITAL != 0;
+                    devices.push(device);
+                }
+                Message::Devices(devices)
+            }
+            KIND_STATE => {
+                let device_id = reader.u64()?;
+                let count = reader.u16()?;
+                let mut analog = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    let code = reader.u16()?;
+                    let value = f32::from(reader.u16()?) / f32::from(u16::MAX);
+                    analog.push((code, value));
+                }
+                Message::State { device_id, analog }
+            }
+            KIND_BYE => Message::Bye,
+            _ => return Err(DecodeError::Malformed),
+        };
+        if !reader.0.is_empty() {
+            return Err(DecodeError::Malformed);
+        }
+        Ok(Packet {
+            sender,
+            seq,
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard() -> DeviceInfo {
+        let mut device = DeviceInfo::new_with_id(
+            0x31e3,
+            0x1402,
+            "Wooting".to_string(),
+            "Wooting Two HE".to_string(),
+            0x1234_5678_9abc,
+            DeviceType::Keyboard,
+        );
+        device.details.digital = true;
+        device
+    }
+
+    fn round_trip(message: Message, key: Option<&[u8]>) -> Message {
+        let packet = Packet {
+            sender: 7,
+            seq: 42,
+            message,
+        };
+        let data = packet.encode(key);
+        assert!(data.len() <= MAX_PACKET_LEN);
+        let decoded = Packet::decode(&data, key).unwrap();
+        assert_eq!((decoded.sender, decoded.seq), (7, 42));
+        decoded.message
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let devices = Message::Devices(vec![keyboard(), keyboard()]);
+        assert_eq!(round_trip(devices.clone(), None), devices);
+        assert_eq!(round_trip(Message::Bye, Some(b"secret")), Message::Bye);
+
+        let state = Message::State {
+            device_id: 3,
+            analog: vec![(0x1a, 1.0), (0x04, 0.5), (0xe1, 2.0)],
+        };
+        match round_trip(state, None) {
+            Message::State { device_id, analog } => {
+                assert_eq!(device_id, 3);
+                assert_eq!(analog[0], (0x1a, 1.0));
+                assert!((analog[1].1 - 0.5).abs() < 0.0001);
+                //Values are kept within 0..1
+                assert_eq!(analog[2], (0xe1, 1.0));
+            }
+            message => panic!("Got {:?}", message),
+        }
+
+        //Everything fits into one datagram, even with the longest names
+        let mut device = keyboard();
+        device.device_name = "ü".repeat(100);
+        let Message::Devices(devices) = round_trip(Message::Devices(vec![device; 20]), Some(b"k"))
+        else {
+            panic!("Expected devices");
+        };
+        assert_eq!(devices.len(), MAX_DEVICES);
+        assert_eq!(devices[0].device_name, "ü".repeat(MAX_NAME_LEN / 2));
+    }
+
+    #[test]
+    fn bad_packets_are_rejected() {
+        let packet = Packet {
+            sender: 1,
+            seq: 1,
+            message: Message::Bye,
+        };
+        let key: &[u8] = b"secret";
+        let signed = packet.encode(Some(key));
+
+        assert_eq!(Packet::decode(b"hello", None), Err(DecodeError::NotAPacket));
+        let mut future = signed.clone();
+        future[2] = VERSION + 1;
+        assert_eq!(
+            Packet::decode(&future, Some(key)),
+            Err(DecodeError::UnsupportedVersion(VERSION + 1))
+        );
+        assert_eq!(
+            Packet::decode(&packet.encode(None), Some(key)),
+            Err(DecodeError::Unauthenticated)
+        );
+        assert_eq!(
+            Packet::decode(&signed, Some(b"other")),
+            Err(DecodeError::BadTag)
+        );
+        let mut tampered = signed.clone();
+        tampered[9] ^= 1;
+        assert_eq!(
+            Packet::decode(&tampered, Some(key)),
+            Err(DecodeError::BadTag)
+        );
+        //Without a key the tag isn't checked
+        assert_eq!(Packet::decode(&signed, None), Ok(packet));
+
+        let state = Packet {
+            sender: 1,
+            seq: 2,
+            message: Message::State {
+                device_id: 1,
+                analog: vec![(4, 1.0)],
+            },
+        }
+        .encode(None);
+        assert_eq!(
+            Packet::decode(&state[..state.len() - 1], None),
+            Err(DecodeError::Malformed)
+        );
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        assert!(is_newer(2, 1));
+        assert!(!is_newer(1, 1));
+        assert!(!is_newer(1, 2));
+        assert!(is_newer(0, u32::MAX));
+        assert!(is_newer(5, u32::MAX - 5));
+    }
+}
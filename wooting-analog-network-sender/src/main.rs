@@ -0,0 +1,160 @@
+//! Reads the analog keyboards connected to this machine through the Wooting Analog SDK & sends them to
+//! `wooting-analog-network-plugin` on another machine, e.g.
+//!
+//! ```text
+//! wooting-analog-network-sender 192.168.1.20 --key-file ~/.config/wooting-analog-sdk/network-key.txt
+//! ```
+extern crate clap;
+extern crate ctrlc;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate wooting_analog_network_plugin;
+extern crate wooting_analog_plugin_dev;
+extern crate wooting_analog_sdk;
+
+mod sender;
+
+use clap::{App, Arg};
+use sender::Sender;
+use std::fs;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wooting_analog_network_plugin::protocol::{DEFAULT_PORT, MAX_STATE_KEYS};
+use wooting_analog_sdk::sdk::AnalogSDK;
+
+const DEFAULT_RATE: &str = "250";
+
+/// Gives the address to send to, which can be left without a port to use the default one
+fn parse_address(address: &str) -> Option<SocketAddr> {
+    if let Ok(ip) = address.parse::<IpAddr>() {
+        return Some(SocketAddr::new(ip, DEFAULT_PORT));
+    }
+    if let Ok(mut addrs) = address.to_socket_addrs() {
+        return addrs.next();
+    }
+    (address, DEFAULT_PORT).to_socket_addrs().ok()?.next()
+}
+
+fn exit_with(message: String) -> ! {
+    error!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let matches = App::new("Wooting Analog Network Sender")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Sends the analog keyboards connected to this machine to wooting-analog-network-plugin on another one")
+        .arg(
+            Arg::with_name("address")
+                .help("Address of the receiving machine, with the port if it's not the default")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("key-file")
+                .long("key-file")
+                .takes_value(true)
+                .help("File with the key the receiver has been set up with"),
+        )
+        .arg(
+            Arg::with_name("rate")
+                .long("rate")
+                .takes_value(true)
+                .default_value(DEFAULT_RATE)
+                .help("How many times a second the keyboards are read"),
+        )
+        .get_matches();
+
+    let address = matches.value_of("address").unwrap();
+    let to = parse_address(address)
+        .unwrap_or_else(|| exit_with(format!("Unable to find address {}", address)));
+    let key = matches
+        .value_of("key-file")
+        .map(|path| match fs::read_to_string(path) {
+            Ok(key) if !key.trim().is_empty() => key.trim().as_bytes().to_vec(),
+            Ok(_) => exit_with(format!("{} is empty", path)),
+            Err(e) => exit_with(format!("Unable to read {}: {}", path, e)),
+        });
+    let rate: f32 = match matches.value_of("rate").unwrap().parse() {
+        Ok(rate) if rate > 0.0 && rate <= 1000.0 => rate,
+        _ => exit_with("The rate has to be between 0 & 1000".to_string()),
+    };
+    if key.is_none() {
+        warn!(
+            "Sending without a key, so the packets can be read & faked by anything on the network"
+        );
+    }
+
+    let mut sender = Sender::new(to, key)
+        .unwrap_or_else(|e| exit_with(format!("Unable to send to {}: {}", to, e)));
+
+    let mut sdk = AnalogSDK::new();
+    if let Err(e) = sdk.initialise().0 {
+        exit_with(format!("Unable to initialise the SDK: {:?}", e));
+    }
+    let session = match sdk.create_session().0 {
+        Ok(session) => session,
+        Err(e) => exit_with(format!("Unable to create a session: {:?}", e)),
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    let t_running = Arc::clone(&running);
+    if let Err(e) = ctrlc::set_handler(move || t_running.store(false, Ordering::Relaxed)) {
+        warn!(
+            "Unable to handle Ctrl-C, the receiver won't be told when stopping: {}",
+            e
+        );
+    }
+
+    info!("Sending to {}", to);
+    let interval = Duration::from_secs_f32(1.0 / rate);
+    while running.load(Ordering::Relaxed) {
+        let devices = sdk.get_device_info().0.unwrap_or_default();
+        let result = sender.update(devices, |device_id| {
+            sdk.read_full_buffer_session(session, MAX_STATE_KEYS, device_id)
+                .0
+                .ok()
+        });
+        if let Err(e) = result {
+            //Keep going, as the receiver may just not be reachable yet
+            warn!("Unable to send to {}: {}", to, e);
+            thread::sleep(Duration::from_secs(1));
+            continue;
+        }
+        thread::sleep(interval);
+    }
+
+    if let Err(e) = sender.stop() {
+        warn!("Unable to tell {} the sender is stopping: {}", to, e);
+    }
+    sdk.unload();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        assert_eq!(
+            parse_address("10.0.0.2"),
+            Some(SocketAddr::new("10.0.0.2".parse().unwrap(), DEFAULT_PORT))
+        );
+        assert_eq!(parse_address("10.0.0.2:9000"), "10.0.0.2:9000".parse().ok());
+        assert_eq!(
+            parse_address("::1"),
+            Some(SocketAddr::new("::1".parse().unwrap(), DEFAULT_PORT))
+        );
+        assert_eq!(
+            parse_address("localhost:9000").map(|addr| addr.port()),
+            Some(9000)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wooting_analog_network_plugin::protocol::{Message, Packet};
+use wooting_analog_plugin_dev::wooting_analog_common::*;
+
+/// How often the full state is sent again when nothing has changed, so the receiver makes up for lost packets & knows the
+/// sender is still there
+pub const HEARTBEAT: Duration = Duration::from_millis(250);
+
+/// Sends the devices & their pressed keys to a receiver
+pub struct Sender {
+    socket: UdpSocket,
+    key: Option<Vec<u8>>,
+    id: u32,
+    seq: u32,
+    devices: Vec<DeviceInfo>,
+    states: HashMap<DeviceID, HashMap<u16, f32>>,
+    last_heartbeat: Option<Instant>,
+}
+
+impl Sender {
+    pub fn new(to: SocketAddr, key: Option<Vec<u8>>) -> io::Result<Self> {
+        let bind: SocketAddr = if to.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(to)?;
+        //The ID only has to differ from the last time the sender ran, so the receiver doesn't take the sequence numbers as old
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos() ^ time.as_secs() as u32);
+        Ok(Sender {
+            socket,
+            key,
+            id: nanos ^ process::id().rotate_left(16),
+            seq: 0,
+            devices: vec![],
+            states: HashMap::new(),
+            last_heartbeat: None,
+        })
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.seq = self.seq.wrapping_add(1);
+        let packet = Packet {
+            sender: self.id,
+            seq: self.seq,
+            message,
+        };
+        self.socket
+            .send(&packet.encode(self.key.as_deref()))
+            .map(|_| ())
+    }
+
+    /// Sends what's changed since the last update, along with everything else when it's time for a heartbeat. `read` gives the
+    /// keys which changed on a device, with the ones which have been released at 0.0
+    pub fn update<F>(&mut self, devices: Vec<DeviceInfo>, mut read: F) -> io::Result<()>
+    where
+        F: FnMut(DeviceID) -> Option<HashMap<u16, f32>>,
+    {
+        let now = Instant::now();
+        let heartbeat = self
+            .last_heartbeat
+            .map_or(true, |last| now.duration_since(last) >= HEARTBEAT);
+        if heartbeat {
+            self.last_heartbeat = Some(now);
+        }
+
+        if heartbeat || devices != self.devices {
+            self.states
+                .retain(|id, _| devices.iter().any(|device| device.device_id == *id));
+            self.devices = devices;
+            self.send(Message::Devices(self.devices.clone()))?;
+        }
+
+        let ids: Vec<DeviceID> = self.devices.iter().map(|device| device.device_id).collect();
+        for device_id in ids {
+            let state = self.states.entry(device_id).or_insert_with(HashMap::new);
+            let mut changed = false;
+            for (code, value) in read(device_id).unwrap_or_default() {
+                if value > 0.0 {
+                    changed |= state.insert(code, value) != Some(value);
+                } else {
+                    changed |= state.remove(&code).is_some();
+                }
+            }
+            if heartbeat || changed {
+                let analog = state.iter().map(|(&code, &value)| (code, value)).collect();
+                self.send(Message::State { device_id, analog })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the receiver the sender is stopping, so it disconnects the devices straight away rather than after its timeout
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.send(Message::Bye)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use wooting_analog_network_plugin::config::ReceiverConfig;
+    use wooting_analog_network_plugin::NetworkPlugin;
+    use wooting_analog_plugin_dev::Plugin;
+
+    fn wait_for<F: FnMut() -> bool>(mut condition: F) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn sends_to_plugin() {
+        let key = b"shared".to_vec();
+        let mut plugin = NetworkPlugin::with_config(ReceiverConfig {
+            listen: "127.0.0.1:0".parse().unwrap(),
+            key: Some(key.clone()),
+            ..Default::default()
+        });
+        let events = Arc::new(Mutex::new(vec![]));
+        let t_events = Arc::clone(&events);
+        assert_eq!(
+            plugin
+                .initialise(Box::new(move |event, device| t_events
+                    .lock()
+                    .unwrap()
+                    .push((event, device.device_id))))
+                .0,
+            Ok(0)
+        );
+        let mut sender = Sender::new(plugin.local_addr().unwrap(), Some(key)).unwrap();
+        let keyboard = DeviceInfo::new_with_id(
+            0x31e3,
+            0x1402,
+            "Wooting".to_string(),
+            "Wooting Two HE".to_string(),
+            42,
+            DeviceType::Keyboard,
+        );
+
+        let mut pressed = Some(vec![(0x04, 0.6), (0x16, 1.0)]);
+        sender
+            .update(vec![keyboard.clone()], |_| {
+                pressed.take().map(|keys| keys.into_iter().collect())
+            })
+            .unwrap();
+        assert!(wait_for(|| plugin.read_analog(0x16, 42).0 == Ok(1.0)));
+        assert_eq!(plugin.read_analog(0x04, 0).0, Ok(0.6));
+
+        //Only the change is read, the other key stays pressed
+        let mut released = Some(vec![(0x16, 0.0)]);
+        sender
+            .update(vec![keyboard], |_| {
+                released.take().map(|keys| keys.into_iter().collect())
+            })
+            .unwrap();
+        assert!(wait_for(|| plugin.read_analog(0x16, 42).0 == Ok(0.0)));
+        assert_eq!(plugin.read_analog(0x04, 42).0, Ok(0.6));
+
+        sender.update(vec![], |_| None).unwrap();
+        assert!(wait_for(|| plugin.device_info().0 == Ok(vec![])));
+        sender.stop().unwrap();
+        assert!(wait_for(|| events.lock().unwrap().len() == 2));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (DeviceEventType::Connected, 42),
+                (DeviceEventType::Disconnected, 42)
+            ]
+        );
+        plugin.unload();
+    }
+}
@@ -0,0 +1,727 @@
+//! The interface Rust plugins are loaded through. The SDK and a plugin can be built with different versions of rustc, which
+//! are free to lay out trait objects, `Vec`s, `String`s etc. differently, so none of those can be passed between them.
+//! Instead `declare_plugin!` exports `_plugin_vtable`, giving a `PluginVTable` of `extern "C"` functions which call into the
+//! `Plugin`. These are compiled into the plugin along with this crate, so only `#[repr(C)]` types cross over to the SDK.
+//!
+//! Panics are caught before they reach the SDK, as unwinding out of an `extern "C"` function is undefined behaviour.
+use crate::{AnalogSnapshot, Plugin, SnapshotStore};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::mem::{self, MaybeUninit};
+use std::os::raw::{c_char, c_float, c_int, c_uint, c_ushort, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use wooting_analog_common::*;
+
+/// Version of the `PluginVTable` layout. This is only increased when entries of the table change, so the SDK can refuse plugins it
+/// doesn't know how to call. New entries are added to the end of the table instead, which the table's `size` tells the SDK about
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// An instance of a plugin, created by `PluginVTable::create`
+pub type PluginHandle = *mut c_void;
+
+/// The `SnapshotStore` of a plugin, given by `PluginVTable::snapshot_store`
+pub type SnapshotHandle = *const c_void;
+
+/// Called by the plugin for every device event with the `user_data` given to `initialise`, the `DeviceEventType` as an int and
+/// the device it's about. The device is only valid for the duration of the call
+pub type DeviceEventFn =
+    unsafe extern "C" fn(user_data: *mut c_void, event: c_uint, device: *const DeviceInfoEx_FFI);
+
+/// Called by a `SnapshotStore` for every report published into it with the `user_data` given to `snapshot_set_listener`, the
+/// device, the sequence number of the report and its `len` pressed keys as codes & analog values. The buffers are only valid for
+/// the duration of the call, which is made on the thread which published the report
+pub type SnapshotReportFn = unsafe extern "C" fn(
+    user_data: *mut c_void,
+    device: DeviceID,
+    sequence: u64,
+    codes: *const c_ushort,
+    analog: *const c_float,
+    len: c_uint,
+);
+
+/// The functions of a plugin. Everything which can fail gives a `WootingAnalogResult` in place of its return when it does, in the
+/// same way as the C API (i.e. as a negative int or float)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PluginVTable {
+    /// The `PLUGIN_ABI_VERSION` the plugin was built with. This is first so that it can be checked without knowing the layout of
+    /// the rest of the table
+    pub abi_version: u32,
+    /// Size of the table the plugin was built with, entries beyond it were added after the plugin was built and are left as `None`
+    pub size: u32,
+    /// The version of `wooting-analog-plugin-dev` the plugin was built with, as a null terminated string. This is only for logging
+    pub plugin_dev_version: *const c_char,
+    /// Constructs the plugin, which needs to be given to `destroy` once it's no longer used
+    pub create: unsafe extern "C" fn() -> PluginHandle,
+    pub destroy: unsafe extern "C" fn(PluginHandle),
+    /// Gives the null terminated name of the plugin, or null if it failed. This stays valid until the plugin is destroyed
+    pub name: unsafe extern "C" fn(PluginHandle) -> *const c_char,
+    /// See `Plugin::initialise`, device events are given to `callback` along with `user_data`
+    pub initialise: unsafe extern "C" fn(PluginHandle, DeviceEventFn, *mut c_void) -> c_int,
+    pub is_initialised: unsafe extern "C" fn(PluginHandle) -> bool,
+    pub unload: unsafe extern "C" fn(PluginHandle),
+    /// Fills `buffer` (of length `len`) with pointers to the info of the connected devices and gives the number of devices filled
+    /// in. If `buffer` is null the number of connected devices is given instead. The structs stay valid until the next call
+    pub device_info:
+        unsafe extern "C" fn(PluginHandle, *mut *const DeviceInfoEx_FFI, c_uint) -> c_int,
+    pub read_analog: unsafe extern "C" fn(PluginHandle, c_ushort, DeviceID) -> c_float,
+    /// Fills the code & analog buffers (both of length `len`) with the pressed keys and gives the number filled in
+    pub read_full_buffer:
+        unsafe extern "C" fn(PluginHandle, *mut c_ushort, *mut c_float, c_uint, DeviceID) -> c_int,
+    pub read_dense_buffer:
+        unsafe extern "C" fn(PluginHandle, *mut c_float, c_uint, DeviceID) -> c_int,
+    /// Gives the `KeyboardLayout` of the device
+    pub device_layout: unsafe extern "C" fn(PluginHandle, DeviceID) -> c_int,
+    /// Gives the plugin's `SnapshotStore`, or null if it doesn't have one. This is called once after `initialise`, and the store
+    /// stays valid until the plugin is destroyed
+    pub snapshot_store: unsafe extern "C" fn(PluginHandle) -> SnapshotHandle,
+    /// Reads from the `SnapshotStore`, which unlike every other function can be called from many threads at the same time
+    pub snapshot_read_analog: unsafe extern "C" fn(SnapshotHandle, c_ushort, DeviceID) -> c_float,
+    pub snapshot_read_dense_buffer:
+        unsafe extern "C" fn(SnapshotHandle, *mut c_float, c_uint, DeviceID) -> c_int,
+
+    //Entries from here on were added to the end of the table later, so they're optional
+    /// Sets the function the `SnapshotStore` gives every report to, or clears it if it's null. `user_data` needs to stay valid
+    /// until the function is cleared and any report it was being given has been handled
+    pub snapshot_set_listener: Option<
+        unsafe extern "C" fn(SnapshotHandle, Option<SnapshotReportFn>, *mut c_void) -> c_int,
+    >,
+    /// Sends the feature report in `data` (of length `len`) to the device
+    pub send_feature_report:
+        Option<unsafe extern "C" fn(PluginHandle, DeviceID, *const u8, c_uint) -> c_int>,
+    /// Reads the feature report with the ID in the first byte of `buffer` (of length `len`) into it, giving the length read
+    pub get_feature_report:
+        Option<unsafe extern "C" fn(PluginHandle, DeviceID, *mut u8, c_uint) -> c_int>,
+}
+
+//The table only holds function pointers & a pointer to a static string
+unsafe impl Sync for PluginVTable {}
+unsafe impl Send for PluginVTable {}
+
+impl PluginVTable {
+    /// Gives the table for the plugins constructed by `create`, which should give the result of `new_handle`. This is used by
+    /// `declare_plugin!`
+    pub const fn new(create: unsafe extern "C" fn() -> PluginHandle) -> Self {
+        PluginVTable {
+            abi_version: PLUGIN_ABI_VERSION,
+            size: mem::size_of::<PluginVTable>() as u32,
+            plugin_dev_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+            create,
+            destroy,
+            name,
+            initialise,
+            is_initialised,
+            unload,
+            device_info,
+            read_analog,
+            read_full_buffer,
+            read_dense_buffer,
+            device_layout,
+            snapshot_store,
+            snapshot_read_analog,
+            snapshot_read_dense_buffer,
+            snapshot_set_listener: Some(snapshot_set_listener),
+            send_feature_report: Some(send_feature_report),
+            get_feature_report: Some(get_feature_report),
+        }
+    }
+
+    /// Size of the entries every plugin has, which is the table up to the first of the optional entries
+    fn required_size() -> usize {
+        let table = MaybeUninit::<PluginVTable>::uninit();
+        let start = table.as_ptr();
+        unsafe { ptr::addr_of!((*start).snapshot_set_listener) as usize - start as usize }
+    }
+
+    /// Copies the table at `vtable`, leaving the entries beyond its `size` as `None`. Gives `None` if the table doesn't have all
+    /// of the required entries
+    ///
+    /// # Safety
+    /// `vtable` has to point to a table which is at least as long as its `size`, with the same `abi_version` as the SDK
+    pub unsafe fn read_sized(vtable: *const PluginVTable) -> Option<PluginVTable> {
+        let size = ptr::read((vtable as *const u32).add(1)) as usize;
+        if size < Self::required_size() {
+            return None;
+        }
+
+        //The optional entries are all `Option`s, which are `None` when zeroed
+        let mut copy = MaybeUninit::<PluginVTable>::zeroed();
+        ptr::copy_nonoverlapping(
+            vtable as *const u8,
+            copy.as_mut_ptr() as *mut u8,
+            size.min(mem::size_of::<PluginVTable>()),
+        );
+        Some(copy.assume_init())
+    }
+}
+
+/// The plugin along with the memory of everything handed to the SDK
+struct PluginInstance {
+    plugin: Box<dyn Plugin>,
+    name: Option<CString>,
+    devices: Vec<DeviceInfoEx_FFI>,
+    device_strings: Vec<CString>,
+    snapshots: Option<Arc<SnapshotStore>>,
+}
+
+/// Constructs the plugin with `constructor`, giving the handle for `PluginVTable::create` to return. This is null if the
+/// constructor panics
+pub fn new_handle<F: FnOnce() -> Box<dyn Plugin>>(constructor: F) -> PluginHandle {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(PluginInstance {
+            plugin: constructor(),
+            name: None,
+            devices: vec![],
+            device_strings: vec![],
+            snapshots: None,
+        })) as PluginHandle
+    })
+}
+
+/// Calls `f`, giving `on_panic` if it panics
+fn guard<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// Calls `f` with the plugin behind `handle`, giving `on_error` if the handle is null or `f` panics
+unsafe fn with_instance<T, F: FnOnce(&mut PluginInstance) -> T>(
+    handle: PluginHandle,
+    on_error: T,
+    f: F,
+) -> T {
+    match (handle as *mut PluginInstance).as_mut() {
+        Some(instance) => guard(on_error, || f(instance)),
+        None => on_error,
+    }
+}
+
+unsafe extern "C" fn destroy(handle: PluginHandle) {
+    if !handle.is_null() {
+        guard((), || drop(Box::from_raw(handle as *mut PluginInstance)));
+    }
+}
+
+unsafe extern "C" fn name(handle: PluginHandle) -> *const c_char {
+    with_instance(handle, ptr::null(), |instance| {
+        if instance.name.is_none() {
+            instance.name = instance
+                .plugin
+                .name()
+                .0
+                .ok()
+                .and_then(|name| CString::new(name).ok());
+        }
+        instance
+            .name
+            .as_ref()
+            .map_or(ptr::null(), |name| name.as_ptr())
+    })
+}
+
+unsafe extern "C" fn initialise(
+    handle: PluginHandle,
+    callback: DeviceEventFn,
+    user_data: *mut c_void,
+) -> c_int {
+    //Raw pointers aren't Send, the SDK is responsible for the user data being usable from any thread
+    let user_data = user_data as usize;
+    let on_event = move |event: DeviceEventType, device: &DeviceInfo| {
+        let mut strings = vec![];
+        let info = DeviceInfoEx_FFI::new(device, &mut strings);
+        callback(user_data as *mut c_void, event as c_uint, &info);
+    };
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        instance.plugin.initialise(Box::new(on_event)).into()
+    })
+}
+
+unsafe extern "C" fn is_initialised(handle: PluginHandle) -> bool {
+    with_instance(handle, false, |instance| instance.plugin.is_initialised())
+}
+
+unsafe extern "C" fn unload(handle: PluginHandle) {
+    with_instance(handle, (), |instance| instance.plugin.unload())
+}
+
+unsafe extern "C" fn device_info(
+    handle: PluginHandle,
+    buffer: *mut *const DeviceInfoEx_FFI,
+    len: c_uint,
+) -> c_int {
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        let devices = match instance.plugin.device_info().0 {
+            Ok(devices) => devices,
+            Err(e) => return e.into(),
+        };
+        if buffer.is_null() {
+            return devices.len() as c_int;
+        }
+
+        instance.device_strings.clear();
+        let strings = &mut instance.device_strings;
+        instance.devices = devices
+            .iter()
+            .map(|device| DeviceInfoEx_FFI::new(device, strings))
+            .collect();
+        let count = instance.devices.len().min(len as usize);
+        let buffer = slice::from_raw_parts_mut(buffer, count);
+        for (ptr, device) in buffer.iter_mut().zip(instance.devices.iter()) {
+            *ptr = device;
+        }
+        count as c_int
+    })
+}
+
+unsafe extern "C" fn read_analog(
+    handle: PluginHandle,
+    code: c_ushort,
+    device: DeviceID,
+) -> c_float {
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        instance.plugin.read_analog(code, device).into()
+    })
+}
+
+unsafe extern "C" fn read_full_buffer(
+    handle: PluginHandle,
+    code_buffer: *mut c_ushort,
+    analog_buffer: *mut c_float,
+    len: c_uint,
+    device: DeviceID,
+) -> c_int {
+    if code_buffer.is_null() || analog_buffer.is_null() {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        let data: HashMap<c_ushort, c_float> =
+            match instance.plugin.read_full_buffer(len as usize, device).0 {
+                Ok(data) => data,
+                Err(e) => return e.into(),
+            };
+        let codes = slice::from_raw_parts_mut(code_buffer, len as usize);
+        let analog = slice::from_raw_parts_mut(analog_buffer, len as usize);
+        let mut count = 0;
+        for ((code, value), (code_out, analog_out)) in data
+            .into_iter()
+            .zip(codes.iter_mut().zip(analog.iter_mut()))
+        {
+            *code_out = code;
+            *analog_out = value;
+            count += 1;
+        }
+        count
+    })
+}
+
+unsafe extern "C" fn read_dense_buffer(
+    handle: PluginHandle,
+    buffer: *mut c_float,
+    len: c_uint,
+    device: DeviceID,
+) -> c_int {
+    if buffer.is_null() {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        let buffer = slice::from_raw_parts_mut(buffer, len as usize);
+        let result: WootingAnalogResult = instance.plugin.read_dense_buffer(buffer, device).into();
+        result.into()
+    })
+}
+
+unsafe extern "C" fn device_layout(handle: PluginHandle, device: DeviceID) -> c_int {
+    with_instance(
+        handle,
+        WootingAnalogResult::Failure.into(),
+        |instance| match instance.plugin.device_layout(device).0 {
+            Ok(layout) => layout as c_int,
+            Err(e) => e.into(),
+        },
+    )
+}
+
+unsafe extern "C" fn send_feature_report(
+    handle: PluginHandle,
+    device: DeviceID,
+    data: *const u8,
+    len: c_uint,
+) -> c_int {
+    if data.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        let data = slice::from_raw_parts(data, len as usize);
+        let result: WootingAnalogResult = instance.plugin.send_feature_report(device, data).into();
+        result.into()
+    })
+}
+
+unsafe extern "C" fn get_feature_report(
+    handle: PluginHandle,
+    device: DeviceID,
+    buffer: *mut u8,
+    len: c_uint,
+) -> c_int {
+    if buffer.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+    with_instance(handle, WootingAnalogResult::Failure.into(), |instance| {
+        let buffer = slice::from_raw_parts_mut(buffer, len as usize);
+        match instance.plugin.get_feature_report(device, buffer).0 {
+            Ok(read) => read.min(buffer.len()) as c_int,
+            Err(e) => e.into(),
+        }
+    })
+}
+
+unsafe extern "C" fn snapshot_store(handle: PluginHandle) -> SnapshotHandle {
+    with_instance(handle, ptr::null(), |instance| {
+        instance.snapshots = instance.plugin.snapshot_store();
+        instance
+            .snapshots
+            .as_ref()
+            .map_or(ptr::null(), |store| Arc::as_ptr(store) as SnapshotHandle)
+    })
+}
+
+unsafe extern "C" fn snapshot_read_analog(
+    store: SnapshotHandle,
+    code: c_ushort,
+    device: DeviceID,
+) -> c_float {
+    match (store as *const SnapshotStore).as_ref() {
+        Some(store) => guard(WootingAnalogResult::Failure.into(), || {
+            store.read_analog(code, device).into()
+        }),
+        None => WootingAnalogResult::InvalidArgument.into(),
+    }
+}
+
+unsafe extern "C" fn snapshot_read_dense_buffer(
+    store: SnapshotHandle,
+    buffer: *mut c_float,
+    len: c_uint,
+    device: DeviceID,
+) -> c_int {
+    match (store as *const SnapshotStore).as_ref() {
+        Some(store) if !buffer.is_null() => guard(WootingAnalogResult::Failure.into(), || {
+            let buffer = slice::from_raw_parts_mut(buffer, len as usize);
+            let result: WootingAnalogResult = store.read_dense_buffer(buffer, device).into();
+            result.into()
+        }),
+        _ => WootingAnalogResult::InvalidArgument.into(),
+    }
+}
+
+thread_local! {
+    //The codes & analog values of the report being passed to the listener, kept around so each report doesn't need new buffers
+    static REPORT_BUFFERS: RefCell<(Vec<c_ushort>, Vec<c_float>)> = RefCell::new((Vec::new(), Vec::new()));
+}
+
+/// The `user_data` given to `snapshot_set_listener`, which is only ever passed back to the SDK
+struct UserData(*mut c_void);
+
+//It's up to the SDK to make the listener safe to call from any thread
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+unsafe extern "C" fn snapshot_set_listener(
+    store: SnapshotHandle,
+    listener: Option<SnapshotReportFn>,
+    user_data: *mut c_void,
+) -> c_int {
+    let store = match (store as *const SnapshotStore).as_ref() {
+        Some(store) => store,
+        None => return WootingAnalogResult::InvalidArgument.into(),
+    };
+    let listener = listener.map(|listener| {
+        let user_data = UserData(user_data);
+        Box::new(move |snapshot: &AnalogSnapshot| {
+            REPORT_BUFFERS.with(|buffers| {
+                let (codes, analog) = &mut *buffers.borrow_mut();
+                codes.clear();
+                analog.clear();
+                for (&code, &value) in snapshot.analog() {
+                    codes.push(code);
+                    analog.push(value);
+                }
+                listener(
+                    user_data.0,
+                    snapshot.device_id(),
+                    snapshot.sequence(),
+                    codes.as_ptr(),
+                    analog.as_ptr(),
+                    codes.len() as c_uint,
+                );
+            })
+        }) as crate::ReportListener
+    });
+    guard(WootingAnalogResult::Failure.into(), || {
+        store.set_listener(listener);
+        WootingAnalogResult::Ok.into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct DummyPlugin {
+        device: DeviceInfo,
+        snapshots: Arc<SnapshotStore>,
+        initialised: bool,
+    }
+
+    impl DummyPlugin {
+        fn new() -> Self {
+            let mut device = DeviceInfo::new_with_id(
+                0x31e3,
+                0x1100,
+                "Wooting".to_owned(),
+                "Dummy".to_owned(),
+                7,
+                DeviceType::Keyboard,
+            );
+            device.details.serial_number = Some("A01".to_owned());
+            DummyPlugin {
+                device,
+                snapshots: Arc::new(SnapshotStore::new()),
+                initialised: false,
+            }
+        }
+    }
+
+    impl Plugin for DummyPlugin {
+        fn name(&mut self) -> SDKResult<&'static str> {
+            Ok("Dummy Plugin").into()
+        }
+
+        fn initialise(
+            &mut self,
+            callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+        ) -> SDKResult<u32> {
+            self.initialised = true;
+            self.snapshots.add_device(7);
+            self.snapshots
+                .publish(7, [(4, 0.5)].iter().cloned().collect());
+            callback(DeviceEventType::Connected, &self.device);
+            Ok(1).into()
+        }
+
+        fn is_initialised(&mut self) -> bool {
+            self.initialised
+        }
+
+        fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+            Ok(vec![self.device.clone()]).into()
+        }
+
+        fn read_analog(&mut self, code: u16, device: DeviceID) -> SDKResult<f32> {
+            if code == 0 {
+                panic!("Dummy plugin can't read code 0");
+            }
+            self.snapshots.read_analog(code, device)
+        }
+
+        fn read_full_buffer(
+            &mut self,
+            _max_length: usize,
+            _device: DeviceID,
+        ) -> SDKResult<HashMap<c_ushort, c_float>> {
+            Ok([(4, 0.5)].iter().cloned().collect()).into()
+        }
+
+        fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+            Some(Arc::clone(&self.snapshots))
+        }
+
+        fn device_layout(&mut self, _device: DeviceID) -> SDKResult<KeyboardLayout> {
+            Ok(KeyboardLayout::ISO).into()
+        }
+
+        fn get_feature_report(&mut self, _device: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+            buffer[1] = buffer[0] + 1;
+            Ok(2).into()
+        }
+    }
+
+    unsafe extern "C" fn create() -> PluginHandle {
+        new_handle(|| Box::new(DummyPlugin::new()))
+    }
+
+    static EVENTS: AtomicU32 = AtomicU32::new(0);
+    static REPORTS: AtomicU32 = AtomicU32::new(0);
+
+    unsafe extern "C" fn on_report(
+        user_data: *mut c_void,
+        device: DeviceID,
+        sequence: u64,
+        codes: *const c_ushort,
+        analog: *const c_float,
+        len: c_uint,
+    ) {
+        assert_eq!(user_data as usize, 42);
+        assert_eq!((device, sequence, len), (7, 2, 1));
+        assert_eq!((*codes, *analog), (5, 0.25));
+        REPORTS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    unsafe extern "C" fn on_event(
+        user_data: *mut c_void,
+        event: c_uint,
+        device: *const DeviceInfoEx_FFI,
+    ) {
+        assert_eq!(user_data as usize, 42);
+        assert_eq!(event, DeviceEventType::Connected as c_uint);
+        let device = DeviceInfoEx_FFI::read_versioned(device)
+            .unwrap()
+            .into_device_info();
+        assert_eq!(device.device_id, 7);
+        assert_eq!(device.details.serial_number.as_deref(), Some("A01"));
+        EVENTS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn vtable_round_trip() {
+        let vtable = PluginVTable::new(create);
+        assert_eq!(vtable.abi_version, PLUGIN_ABI_VERSION);
+        unsafe {
+            assert_eq!(
+                CStr::from_ptr(vtable.plugin_dev_version).to_str(),
+                Ok(crate::ANALOG_SDK_PLUGIN_VERSION)
+            );
+
+            let handle = (vtable.create)();
+            assert!(!handle.is_null());
+            assert_eq!(
+                CStr::from_ptr((vtable.name)(handle)).to_str(),
+                Ok("Dummy Plugin")
+            );
+            assert!(!(vtable.is_initialised)(handle));
+            assert_eq!((vtable.initialise)(handle, on_event, 42 as *mut c_void), 1);
+            assert!((vtable.is_initialised)(handle));
+            assert_eq!(EVENTS.load(Ordering::Relaxed), 1);
+
+            assert_eq!((vtable.device_info)(handle, ptr::null_mut(), 0), 1);
+            let mut devices = [ptr::null(); 2];
+            assert_eq!((vtable.device_info)(handle, devices.as_mut_ptr(), 2), 1);
+            let device = DeviceInfoEx_FFI::read_versioned(devices[0])
+                .unwrap()
+                .into_device_info();
+            assert_eq!(device.device_id, 7);
+            assert_eq!(device.device_name, "Dummy");
+
+            assert_eq!((vtable.read_analog)(handle, 4, 7), 0.5);
+            assert_eq!(
+                (vtable.read_analog)(handle, 4, 8),
+                Into::<f32>::into(WootingAnalogResult::NoDevices)
+            );
+            //Panics are turned into a failure rather than unwinding into the caller
+            assert_eq!(
+                (vtable.read_analog)(handle, 0, 7),
+                Into::<f32>::into(WootingAnalogResult::Failure)
+            );
+
+            let mut codes = [0; 4];
+            let mut analog = [0.0; 4];
+            assert_eq!(
+                (vtable.read_full_buffer)(handle, codes.as_mut_ptr(), analog.as_mut_ptr(), 4, 0),
+                1
+            );
+            assert_eq!((codes[0], analog[0]), (4, 0.5));
+            let mut dense = [0.0; 8];
+            assert_eq!(
+                (vtable.read_dense_buffer)(handle, dense.as_mut_ptr(), 8, 0),
+                Into::<c_int>::into(WootingAnalogResult::Ok)
+            );
+            assert_eq!(dense[4], 0.5);
+            assert_eq!(
+                (vtable.device_layout)(handle, 7),
+                KeyboardLayout::ISO as c_int
+            );
+            //Sending reports is left to the default
+            assert_eq!(
+                (vtable.send_feature_report.unwrap())(handle, 7, [1, 2].as_ptr(), 2),
+                Into::<c_int>::into(WootingAnalogResult::NotAvailable)
+            );
+            let mut report = [5, 0, 0];
+            assert_eq!(
+                (vtable.get_feature_report.unwrap())(handle, 7, report.as_mut_ptr(), 3),
+                2
+            );
+            assert_eq!(report, [5, 6, 0]);
+            assert_eq!(
+                (vtable.get_feature_report.unwrap())(handle, 7, ptr::null_mut(), 3),
+                Into::<c_int>::into(WootingAnalogResult::InvalidArgument)
+            );
+
+            let store = (vtable.snapshot_store)(handle);
+            assert!(!store.is_null());
+            assert_eq!((vtable.snapshot_read_analog)(store, 4, 0), 0.5);
+            let mut dense = [0.0; 8];
+            assert_eq!(
+                (vtable.snapshot_read_dense_buffer)(store, dense.as_mut_ptr(), 8, 7),
+                Into::<c_int>::into(WootingAnalogResult::Ok)
+            );
+            assert_eq!(dense[4], 0.5);
+
+            assert_eq!(
+                (vtable.snapshot_set_listener.unwrap())(store, Some(on_report), 42 as *mut c_void),
+                Into::<c_int>::into(WootingAnalogResult::Ok)
+            );
+            let snapshots = (store as *const SnapshotStore).as_ref().unwrap();
+            snapshots.publish(7, [(5, 0.25)].iter().cloned().collect());
+            (vtable.snapshot_set_listener.unwrap())(store, None, ptr::null_mut());
+            snapshots.publish(7, HashMap::new());
+            assert_eq!(REPORTS.load(Ordering::Relaxed), 1);
+
+            (vtable.unload)(handle);
+            (vtable.destroy)(handle);
+        }
+    }
+
+    #[test]
+    fn sized_tables() {
+        let vtable = PluginVTable::new(create);
+        unsafe {
+            let copy = PluginVTable::read_sized(&vtable).unwrap();
+            assert!(copy.get_feature_report.is_some());
+
+            //A plugin built before the optional entries were added only has the required ones
+            let mut old = vtable;
+            old.size = PluginVTable::required_size() as u32;
+            let copy = PluginVTable::read_sized(&old).unwrap();
+            assert_eq!(copy.size, old.size);
+            assert!(copy.snapshot_set_listener.is_none());
+            assert!(copy.send_feature_report.is_none());
+            assert!(copy.get_feature_report.is_none());
+
+            old.size -= 1;
+            assert!(PluginVTable::read_sized(&old).is_none());
+        }
+    }
+
+    #[test]
+    fn null_handles() {
+        let vtable = PluginVTable::new(create);
+        unsafe {
+            assert!((vtable.name)(ptr::null_mut()).is_null());
+            assert!(!(vtable.is_initialised)(ptr::null_mut()));
+            assert_eq!(
+                (vtable.device_info)(ptr::null_mut(), ptr::null_mut(), 0),
+                Into::<c_int>::into(WootingAnalogResult::Failure)
+            );
+            assert_eq!(
+                (vtable.snapshot_read_analog)(ptr::null(), 4, 0),
+                Into::<f32>::into(WootingAnalogResult::InvalidArgument)
+            );
+            (vtable.destroy)(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Generation of device IDs, which apps store to keep e.g. per-device bindings, so the same device must always be given the same
+//! ID, no matter the version of the SDK, plugin or the Rust compiler they were built with.
+//!
+//! IDs are the 64 bit FNV-1a hash of the vendor ID & product ID (each as 2 little endian bytes) followed by the serial number. For
+//! devices without a serial number the interface path is hashed in its place, prefixed with a 0xFF byte, which can't appear in
+//! UTF-8, so a path can never give the same ID as a serial number.
+use wooting_analog_common::DeviceID;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Marks the hashed identifier as an interface path rather than a serial number
+const PATH_MARKER: u8 = 0xFF;
+
+/// 64 bit FNV-1a, which is fully specified, so unlike `DefaultHasher` it won't change between Rust releases
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn hash_device(vendor_id: u16, product_id: u16, identifier: &[&[u8]]) -> DeviceID {
+    let mut hasher = Fnv1a::new();
+    hasher
+        .write(&vendor_id.to_le_bytes())
+        .write(&product_id.to_le_bytes());
+    for part in identifier {
+        hasher.write(part);
+    }
+    //0 is used to refer to any device, so it can't be given to a device
+    match hasher.finish() {
+        0 => 1,
+        id => id,
+    }
+}
+
+/// Generates the ID of the device with the given serial number, vendor ID & product ID
+///
+/// # Notes
+///
+/// Identical devices without a serial number would all be given the same ID, so `generate_device_id_from_path` should be used
+/// for those instead
+pub fn generate_device_id(serial_number: &str, vendor_id: u16, product_id: u16) -> DeviceID {
+    hash_device(vendor_id, product_id, &[serial_number.as_bytes()])
+}
+
+/// Generates the ID of a device which doesn't have a serial number, from the path of the interface it's read through. This
+/// tells identical devices apart, but the ID is only as stable as the path the OS gives, which usually changes if the device is
+/// plugged into a different port
+pub fn generate_device_id_from_path(
+    interface_path: &str,
+    vendor_id: u16,
+    product_id: u16,
+) -> DeviceID {
+    hash_device(
+        vendor_id,
+        product_id,
+        &[&[PATH_MARKER], interface_path.as_bytes()],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_reference_values() {
+        assert_eq!(Fnv1a::new().finish(), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Fnv1a::new().write(b"a").finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        //These must never change, as apps store the IDs
+        assert_eq!(
+            generate_device_id("A01B2234W031H00123", 0x31e3, 0x1100),
+            0xc21c_c908_4c9b_cbbc
+        );
+        assert_eq!(
+            generate_device_id_from_path("/dev/hidraw3", 0x31e3, 0x1100),
+            0xcef0_01c9_558b_4c3a
+        );
+    }
+
+    #[test]
+    fn ids_are_distinct() {
+        let serial = generate_device_id("/dev/hidraw3", 0x31e3, 0x1100);
+        assert_ne!(
+            serial,
+            generate_device_id_from_path("/dev/hidraw3", 0x31e3, 0x1100)
+        );
+        assert_ne!(serial, generate_device_id("/dev/hidraw3", 0x31e3, 0x1200));
+        assert_ne!(
+            generate_device_id_from_path("/dev/hidraw3", 0x31e3, 0x1100),
+            generate_device_id_from_path("/dev/hidraw4", 0x31e3, 0x1100)
+        );
+    }
+}
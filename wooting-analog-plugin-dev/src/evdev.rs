@@ -0,0 +1,327 @@
+//! Just enough of the Linux evdev interface (`/dev/input/event*`) for plugins to find input devices and read their events, using
+//! the ioctls from linux/input.h directly.
+//!
+//! Reading the devices needs read access to them, which is usually given through the `input` group.
+use crate::{generate_device_id, generate_device_id_from_path};
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wooting_analog_common::DeviceID;
+
+pub const INPUT_DIR: &str = "/dev/input";
+/// How often `spawn_rescan` looks for new devices
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+//From linux/input-event-codes.h
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_ABS: u16 = 0x03;
+pub const SYN_REPORT: u16 = 0;
+pub const SYN_DROPPED: u16 = 3;
+pub const KEY_CNT: u16 = 0x300;
+pub const ABS_CNT: u16 = 0x40;
+
+const IOC_READ: c_ulong = 2;
+const IOC_TYPE: c_ulong = b'E' as c_ulong;
+const NAME_LEN: usize = 256;
+
+//The ioctls of linux/input.h, as given by the _IOC macro
+const fn ioc_read(nr: c_ulong, size: usize) -> c_ulong {
+    (IOC_READ << 30) | ((size as c_ulong) << 16) | (IOC_TYPE << 8) | nr
+}
+const EVIOCGID: c_ulong = ioc_read(0x02, mem::size_of::<InputId>());
+const fn eviocgname(len: usize) -> c_ulong {
+    ioc_read(0x06, len)
+}
+const fn eviocgphys(len: usize) -> c_ulong {
+    ioc_read(0x07, len)
+}
+const fn eviocguniq(len: usize) -> c_ulong {
+    ioc_read(0x08, len)
+}
+const fn eviocgkey(len: usize) -> c_ulong {
+    ioc_read(0x18, len)
+}
+const fn eviocgbit(kind: u16, len: usize) -> c_ulong {
+    ioc_read(0x20 + kind as c_ulong, len)
+}
+const fn eviocgabs(axis: u16) -> c_ulong {
+    ioc_read(0x40 + axis as c_ulong, mem::size_of::<AbsInfo>())
+}
+
+/// `struct input_id`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct InputId {
+    pub bustype: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+}
+
+/// `struct input_absinfo`, the range & current value of an absolute axis
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AbsInfo {
+    pub value: i32,
+    pub minimum: i32,
+    pub maximum: i32,
+    /// Changes smaller than this are filtered out by the kernel as noise
+    pub fuzz: i32,
+    /// Values within this of the center are reported as the center
+    pub flat: i32,
+    pub resolution: i32,
+}
+
+/// `struct input_event`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct InputEvent {
+    pub time: libc::timeval,
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl InputEvent {
+    pub fn new(kind: u16, code: u16, value: i32) -> Self {
+        InputEvent {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            kind,
+            code,
+            value,
+        }
+    }
+}
+
+impl std::fmt::Debug for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("InputEvent")
+            .field("kind", &self.kind)
+            .field("code", &self.code)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+fn bit_is_set(bits: &[u8], bit: u16) -> bool {
+    bits.get(usize::from(bit / 8))
+        .map_or(false, |byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Gives the bits which are set, for the bitmaps given by the kernel
+fn set_bits(bits: &[u8], count: u16) -> Vec<u16> {
+    (0..count).filter(|&bit| bit_is_set(bits, bit)).collect()
+}
+
+/// Gives the event devices in `dir` (e.g. `/dev/input/event3`), sorted by path
+pub fn list_devices<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(OsStr::to_str)
+                    .map_or(false, |name| name.starts_with("event"))
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    paths.sort();
+    paths
+}
+
+/// Spawns the thread which calls `rescan` every `RESCAN_INTERVAL` for as long as `running` is true, so plugins pick up devices
+/// which are plugged in after they're initialised. It checks `running` often, so joining it after clearing it doesn't hold up
+/// unloading
+pub fn spawn_rescan<F: FnMut() + Send + 'static>(
+    running: Arc<AtomicBool>,
+    mut rescan: F,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut waited = Duration::from_secs(0);
+        while running.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(10));
+            waited += Duration::from_millis(10);
+            if waited >= RESCAN_INTERVAL {
+                waited = Duration::from_secs(0);
+                rescan();
+            }
+        }
+    })
+}
+
+/// An open evdev device
+#[derive(Debug)]
+pub struct EvdevDevice {
+    file: File,
+    path: PathBuf,
+}
+
+impl EvdevDevice {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open(path)?;
+        Ok(EvdevDevice {
+            file,
+            path: path.to_owned(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn ioctl<T>(&self, request: c_ulong, value: *mut T) -> io::Result<c_int> {
+        match unsafe { libc::ioctl(self.file.as_raw_fd(), request, value) } {
+            ret if ret < 0 => Err(io::Error::last_os_error()),
+            ret => Ok(ret),
+        }
+    }
+
+    fn string(&self, request: fn(usize) -> c_ulong) -> io::Result<String> {
+        let mut buffer = [0u8; NAME_LEN];
+        let len = self.ioctl(request(NAME_LEN), buffer.as_mut_ptr())? as usize;
+        let string = &buffer[..len.min(NAME_LEN)];
+        let end = string.iter().position(|&b| b == 0).unwrap_or(string.len());
+        Ok(String::from_utf8_lossy(&string[..end]).into_owned())
+    }
+
+    pub fn name(&self) -> io::Result<String> {
+        self.string(eviocgname)
+    }
+
+    /// The physical location of the device, e.g. `usb-0000:00:14.0-2/input0`, which is empty for some virtual devices
+    pub fn phys(&self) -> io::Result<String> {
+        self.string(eviocgphys)
+    }
+
+    /// The unique identifier of the device (usually its serial number), which is empty for most devices
+    pub fn uniq(&self) -> io::Result<String> {
+        self.string(eviocguniq)
+    }
+
+    pub fn input_id(&self) -> io::Result<InputId> {
+        let mut id = InputId::default();
+        self.ioctl(EVIOCGID, &mut id)?;
+        Ok(id)
+    }
+
+    /// Gives the codes of the events of type `kind` the device can send, e.g. the axes for `EV_ABS`
+    pub fn event_codes(&self, kind: u16, count: u16) -> io::Result<Vec<u16>> {
+        let mut bits = vec![0u8; usize::from(count / 8 + 1)];
+        self.ioctl(eviocgbit(kind, bits.len()), bits.as_mut_ptr())?;
+        Ok(set_bits(&bits, count))
+    }
+
+    pub fn abs_info(&self, axis: u16) -> io::Result<AbsInfo> {
+        let mut info = AbsInfo::default();
+        self.ioctl(eviocgabs(axis), &mut info)?;
+        Ok(info)
+    }
+
+    /// Gives the codes of the keys which are currently held down
+    pub fn pressed_keys(&self) -> io::Result<Vec<u16>> {
+        let mut bits = vec![0u8; usize::from(KEY_CNT / 8)];
+        self.ioctl(eviocgkey(bits.len()), bits.as_mut_ptr())?;
+        Ok(set_bits(&bits, KEY_CNT))
+    }
+
+    /// Gives the ID of the device, from its unique identifier if it has one, otherwise from its physical location so that it's
+    /// kept when it's plugged back into the same port
+    pub fn device_id(&self) -> io::Result<DeviceID> {
+        let id = self.input_id()?;
+        let uniq = self.uniq().unwrap_or_default();
+        if !uniq.is_empty() {
+            return Ok(generate_device_id(&uniq, id.vendor, id.product));
+        }
+        let phys = self.phys().unwrap_or_default();
+        let location = if phys.is_empty() {
+            self.path.to_string_lossy().into_owned()
+        } else {
+            phys
+        };
+        Ok(generate_device_id_from_path(
+            &location, id.vendor, id.product,
+        ))
+    }
+
+    /// Waits up to `timeout` for events and reads those which are waiting. Fails with `ENODEV` once the device is unplugged
+    pub fn read_events(&mut self, timeout: Duration) -> io::Result<Vec<InputEvent>> {
+        let mut poll = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as c_int) } {
+            ret if ret < 0 => {
+                let e = io::Error::last_os_error();
+                return if e.kind() == io::ErrorKind::Interrupted {
+                    Ok(vec![])
+                } else {
+                    Err(e)
+                };
+            }
+            0 => return Ok(vec![]),
+            _ => {}
+        }
+        if poll.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0 {
+            return Err(io::Error::from_raw_os_error(libc::ENODEV));
+        }
+
+        let mut events = vec![InputEvent::new(0, 0, 0); 64];
+        let buffer = unsafe {
+            slice::from_raw_parts_mut(
+                events.as_mut_ptr() as *mut u8,
+                events.len() * mem::size_of::<InputEvent>(),
+            )
+        };
+        match self.file.read(buffer) {
+            Ok(len) => {
+                events.truncate(len / mem::size_of::<InputEvent>());
+                Ok(events)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ioctl_numbers() {
+        //As given by the C macros on x86_64
+        assert_eq!(EVIOCGID, 0x8008_4502);
+        assert_eq!(eviocgname(256), 0x8100_4506);
+        assert_eq!(eviocgbit(EV_ABS, 8), 0x8008_4523);
+        assert_eq!(eviocgabs(0x09), 0x8018_4549);
+        assert_eq!(eviocgkey(96), 0x8060_4518);
+    }
+
+    #[test]
+    fn bitmaps() {
+        let bits = [0b0000_0101, 0, 0b1000_0000];
+        assert_eq!(set_bits(&bits, 24), vec![0, 2, 23]);
+        assert!(!bit_is_set(&bits, 30));
+    }
+}
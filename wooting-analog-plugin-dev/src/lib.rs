@@ -1,10 +1,23 @@
+extern crate arc_swap;
 extern crate ffi_support;
+#[cfg(target_os = "linux")]
+extern crate libc;
 pub extern crate wooting_analog_common;
 
+pub mod abi;
+mod device_id;
+#[cfg(target_os = "linux")]
+pub mod evdev;
+mod snapshot;
+#[cfg(target_os = "linux")]
+pub mod uinput;
+pub use device_id::{generate_device_id, generate_device_id_from_path};
+pub use snapshot::{AnalogSnapshot, ReportListener, SnapshotStore};
+
 use ffi_support::FfiStr;
 use std::collections::HashMap;
-use std::hash::Hasher;
 use std::os::raw::{c_float, c_ushort};
+use std::sync::Arc;
 use wooting_analog_common::*;
 
 pub static ANALOG_SDK_PLUGIN_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -41,12 +54,69 @@ pub trait Plugin {
 
     /// Function called to get the full analog read buffer for a particular device with ID `device`. `max_length` is the maximum amount
     /// of keys that can be accepted, any more beyond this will be ignored by the SDK.
-    /// If `device` is 0 then no specific device is specified and the data should be read from all devices and combined
+    /// If `device` is 0 then no specific device is specified and the data should be read from all devices and combined.
+    /// Only keys which are currently pressed should be returned, the SDK keeps track of released keys for each of its readers
     fn read_full_buffer(
         &mut self,
         max_length: usize,
         device: DeviceID,
     ) -> SDKResult<HashMap<c_ushort, c_float>>;
+
+    /// Function called to read the analog value of every key on the device with ID `device` into `buffer`, which is indexed by HID code.
+    /// If `device` is 0 then no specific device is specified and the data should be read from all devices and combined
+    ///
+    /// # Notes
+    ///
+    /// The SDK zeroes `buffer` before the call and it may already contain values from other plugins, so values should be combined
+    /// with what's already there by taking the greater of the two. Codes which don't fit in `buffer` should be skipped.
+    /// The default goes through `read_full_buffer`, so plugins which want this to be allocation free should override it
+    fn read_dense_buffer(&mut self, buffer: &mut [c_float], device: DeviceID) -> SDKResult<()> {
+        self.read_full_buffer(buffer.len(), device)
+            .0
+            .map(|data| {
+                for (code, analog) in data {
+                    if let Some(value) = buffer.get_mut(code as usize) {
+                        *value = value.max(analog);
+                    }
+                }
+            })
+            .into()
+    }
+
+    /// Gives the SDK the `SnapshotStore` the plugin publishes its device's analog data into. This is called once after `initialise`.
+    ///
+    /// # Notes
+    ///
+    /// When a store is given, the SDK will serve `read_analog` from it directly, without calling into the plugin or waiting on
+    /// any locks, so many threads can read at the same time. Plugins which don't have one should leave the default of `None`,
+    /// in which case reads go through `read_analog` as usual.
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        None
+    }
+
+    /// Gives the physical layout of the device with ID `device`, which the SDK uses to give apps the position & size of its keys.
+    /// This is only called for devices the plugin gave in `device_info`, plugins which don't know the layout of their devices
+    /// can leave the default of `Unknown`
+    fn device_layout(&mut self, _device: DeviceID) -> SDKResult<KeyboardLayout> {
+        Ok(KeyboardLayout::Unknown).into()
+    }
+
+    /// Sends the feature report `data` to the device with ID `device`, which is how vendor commands are given to a device. The first
+    /// byte is the report ID, or 0 if the device doesn't use report IDs. Plugins which can't send reports to their devices can
+    /// leave the default of `NotAvailable`
+    ///
+    /// # Notes
+    ///
+    /// The SDK only calls this when the app has enabled raw device access, so plugins don't need to check what's being sent
+    fn send_feature_report(&mut self, _device: DeviceID, _data: &[u8]) -> SDKResult<()> {
+        Err(WootingAnalogResult::NotAvailable).into()
+    }
+
+    /// Reads the feature report with the ID in the first byte of `buffer` from the device with ID `device` into `buffer`, giving
+    /// its length including the report ID. This is how the responses to commands given with `send_feature_report` are read
+    fn get_feature_report(&mut self, _device: DeviceID, _buffer: &mut [u8]) -> SDKResult<usize> {
+        Err(WootingAnalogResult::NotAvailable).into()
+    }
 }
 
 /// Declare a plugin type and its constructor.
@@ -54,37 +124,26 @@ pub trait Plugin {
 /// # Notes
 ///
 /// This works by automatically generating an `extern "C"` function with a
-/// pre-defined signature and symbol name. Therefore you will only be able to
-/// declare one plugin per library.
+/// pre-defined signature and symbol name, which gives the SDK the `abi::PluginVTable`
+/// it calls the plugin through. Therefore you will only be able to declare one
+/// plugin per library.
 #[macro_export]
 macro_rules! declare_plugin {
     ($plugin_type:ty, $constructor:path) => {
         #[no_mangle]
-        pub extern "C" fn _plugin_create() -> *mut $crate::Plugin {
-            // make sure the constructor is the correct type.
-            let constructor: fn() -> $plugin_type = $constructor;
-
-            let object = constructor();
-            let boxed: Box<$crate::Plugin> = Box::new(object);
-            Box::into_raw(boxed)
-        }
+        pub extern "C" fn _plugin_vtable() -> *const $crate::abi::PluginVTable {
+            unsafe extern "C" fn create() -> $crate::abi::PluginHandle {
+                // make sure the constructor is the correct type.
+                let constructor: fn() -> $plugin_type = $constructor;
+                $crate::abi::new_handle(|| Box::new(constructor()))
+            }
 
-        #[no_mangle]
-        pub extern "C" fn plugin_version() -> &'static str {
-            ANALOG_SDK_PLUGIN_VERSION
+            static VTABLE: $crate::abi::PluginVTable = $crate::abi::PluginVTable::new(create);
+            &VTABLE
         }
     };
 }
 
-pub fn generate_device_id(serial_number: &str, vendor_id: u16, product_id: u16) -> DeviceID {
-    use std::collections::hash_map::DefaultHasher;
-    let mut s = DefaultHasher::new();
-    s.write_u16(vendor_id);
-    s.write_u16(product_id);
-    s.write(serial_number.as_bytes());
-    s.finish()
-}
-
 mod ffi {
     use super::*;
 
@@ -103,4 +162,16 @@ mod ffi {
         };
         super::generate_device_id(&serial, vendor_id, product_id)
     }
+
+    #[no_mangle]
+    pub extern "C" fn generate_device_id_from_path(
+        interface_path: FfiStr,
+        vendor_id: u16,
+        product_id: u16,
+    ) -> DeviceID {
+        match interface_path.as_opt_str() {
+            Some(path) => super::generate_device_id_from_path(path, vendor_id, product_id),
+            None => 0,
+        }
+    }
 }
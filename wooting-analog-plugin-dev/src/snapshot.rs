@@ -0,0 +1,354 @@
+use arc_swap::{ArcSwap, ArcSwapOption};
+use std::collections::HashMap;
+use std::os::raw::{c_float, c_ushort};
+use std::sync::Arc;
+use wooting_analog_common::{DeviceID, SDKResult, WootingAnalogResult};
+
+/// An immutable view of the analog state of a single device at the point in time it was published.
+///
+/// Snapshots are never modified once published, a device worker publishes a fresh one for every report
+/// it receives, so readers can hold onto one for as long as they like without blocking the writer.
+#[derive(Debug, Clone, Default)]
+pub struct AnalogSnapshot {
+    device_id: DeviceID,
+    sequence: u64,
+    analog: HashMap<c_ushort, c_float>,
+}
+
+impl AnalogSnapshot {
+    pub fn new(device_id: DeviceID, sequence: u64, analog: HashMap<c_ushort, c_float>) -> Self {
+        AnalogSnapshot {
+            device_id,
+            sequence,
+            analog,
+        }
+    }
+
+    /// The ID of the device this snapshot was taken from
+    pub fn device_id(&self) -> DeviceID {
+        self.device_id
+    }
+
+    /// Monotonically increasing number of the report this snapshot was built from. Starts at 0 for a freshly connected device
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The analog value of the given HID `code`, keys which aren't pressed are 0.0
+    pub fn read_analog(&self, code: c_ushort) -> c_float {
+        *self.analog.get(&code).unwrap_or(&0.0)
+    }
+
+    /// Combines the analog values of this snapshot into `buffer` (indexed by HID code), keeping the greater value where the buffer
+    /// already has one. Codes which don't fit in `buffer` are skipped
+    pub fn read_dense(&self, buffer: &mut [c_float]) {
+        for (&code, &analog) in self.analog.iter() {
+            if let Some(value) = buffer.get_mut(code as usize) {
+                *value = value.max(analog);
+            }
+        }
+    }
+
+    /// All currently pressed keys (HID code -> analog value). Released keys are not included
+    pub fn analog(&self) -> &HashMap<c_ushort, c_float> {
+        &self.analog
+    }
+}
+
+type SnapshotCell = Arc<ArcSwap<AnalogSnapshot>>;
+
+/// Called with every snapshot published into a `SnapshotStore`, on the thread which published it
+pub type ReportListener = Box<dyn Fn(&AnalogSnapshot) + Send + Sync>;
+
+/// Lock-free store of the latest `AnalogSnapshot` of each device a plugin has connected.
+///
+/// The plugin's device workers `publish` into it, while any number of threads can read from it concurrently
+/// without ever waiting on a lock. Plugins which have one should hand it to the SDK through `Plugin::snapshot_store`
+/// so reads can skip the plugin altogether.
+#[derive(Default)]
+pub struct SnapshotStore {
+    devices: ArcSwap<HashMap<DeviceID, SnapshotCell>>,
+    listener: ArcSwapOption<ReportListener>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Start tracking the device with the given `device_id`, it starts with an empty snapshot (no keys pressed)
+    pub fn add_device(&self, device_id: DeviceID) {
+        self.devices.rcu(|devices| {
+            let mut devices = HashMap::clone(devices);
+            devices.insert(
+                device_id,
+                Arc::new(ArcSwap::from_pointee(AnalogSnapshot::new(
+                    device_id,
+                    0,
+                    HashMap::new(),
+                ))),
+            );
+            devices
+        });
+    }
+
+    /// Stop tracking the device with the given `device_id`. Readers which still hold one of its snapshots can keep using it. The
+    /// listener is given a last snapshot with no keys pressed, as they can't be held down on a device which isn't there
+    pub fn remove_device(&self, device_id: DeviceID) {
+        let mut removed = None;
+        self.devices.rcu(|devices| {
+            let mut devices = HashMap::clone(devices);
+            removed = devices.remove(&device_id);
+            devices
+        });
+        if let (Some(cell), Some(listener)) = (removed, self.listener.load().as_ref()) {
+            let sequence = cell.load().sequence + 1;
+            listener(&AnalogSnapshot::new(device_id, sequence, HashMap::new()));
+        }
+    }
+
+    /// Remove all devices from the store
+    pub fn clear(&self) {
+        self.devices.store(Default::default());
+    }
+
+    /// Publish a new set of pressed keys for the device with the given `device_id`. Returns false if the device isn't in the store
+    pub fn publish(&self, device_id: DeviceID, analog: HashMap<c_ushort, c_float>) -> bool {
+        match self.devices.load().get(&device_id) {
+            Some(cell) => {
+                let sequence = cell.load().sequence + 1;
+                let snapshot = Arc::new(AnalogSnapshot::new(device_id, sequence, analog));
+                cell.store(Arc::clone(&snapshot));
+                if let Some(listener) = self.listener.load().as_ref() {
+                    listener(&snapshot);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the `listener` which is called with every snapshot as it's published, replacing the one set before. This is how the
+    /// SDK is told about every report a device sends, rather than only seeing the ones which happen to be there when it reads
+    pub fn set_listener(&self, listener: Option<ReportListener>) {
+        self.listener.store(listener.map(Arc::new));
+    }
+
+    /// Get the latest snapshot of the device with the given `device_id`
+    pub fn device_snapshot(&self, device_id: DeviceID) -> Option<Arc<AnalogSnapshot>> {
+        self.devices
+            .load()
+            .get(&device_id)
+            .map(|cell| cell.load_full())
+    }
+
+    /// Calls `f` with the latest snapshot of every device in the store. This doesn't allocate, so it's safe to use in hot read paths
+    pub fn for_each<F: FnMut(&AnalogSnapshot)>(&self, mut f: F) {
+        for cell in self.devices.load().values() {
+            f(&cell.load());
+        }
+    }
+
+    /// Reads the analog value of the given HID `code` from the device with the given `device_id`. If `device_id` is 0 the
+    /// greatest value of all the devices is given
+    pub fn read_analog(&self, code: c_ushort, device_id: DeviceID) -> SDKResult<c_float> {
+        if device_id == 0 {
+            let mut value: c_float = -1.0;
+            self.for_each(|snapshot| {
+                value = value.max(snapshot.read_analog(code));
+            });
+            if value < 0.0 {
+                Err(WootingAnalogResult::NoDevices).into()
+            } else {
+                Ok(value).into()
+            }
+        } else {
+            match self.device_snapshot(device_id) {
+                Some(snapshot) => Ok(snapshot.read_analog(code)).into(),
+                None => Err(WootingAnalogResult::NoDevices).into(),
+            }
+        }
+    }
+
+    /// Combines the analog values of the device with the given `device_id` into `buffer` (indexed by HID code), or those of all
+    /// devices if `device_id` is 0. See `AnalogSnapshot::read_dense`
+    pub fn read_dense_buffer(&self, buffer: &mut [c_float], device_id: DeviceID) -> SDKResult<()> {
+        if device_id == 0 {
+            let mut any_device = false;
+            self.for_each(|snapshot| {
+                snapshot.read_dense(buffer);
+                any_device = true;
+            });
+            if any_device {
+                Ok(()).into()
+            } else {
+                Err(WootingAnalogResult::NoDevices).into()
+            }
+        } else {
+            match self.device_snapshot(device_id) {
+                Some(snapshot) => {
+                    snapshot.read_dense(buffer);
+                    Ok(()).into()
+                }
+                None => Err(WootingAnalogResult::NoDevices).into(),
+            }
+        }
+    }
+
+    /// Gives the pressed keys of the device with the given `device_id`, or those of all devices if `device_id` is 0, keeping the
+    /// greatest value of each key
+    pub fn read_full_buffer(&self, device_id: DeviceID) -> SDKResult<HashMap<c_ushort, c_float>> {
+        if device_id == 0 {
+            let mut analog: HashMap<c_ushort, c_float> = HashMap::new();
+            let mut any_device = false;
+            self.for_each(|snapshot| {
+                any_device = true;
+                for (&code, &value) in snapshot.analog().iter() {
+                    let key = analog.entry(code).or_insert(0.0);
+                    *key = key.max(value);
+                }
+            });
+            if any_device {
+                Ok(analog).into()
+            } else {
+                Err(WootingAnalogResult::NoDevices).into()
+            }
+        } else {
+            match self.device_snapshot(device_id) {
+                Some(snapshot) => Ok(snapshot.analog().clone()).into(),
+                None => Err(WootingAnalogResult::NoDevices).into(),
+            }
+        }
+    }
+
+    /// Returns true if the device with the given `device_id` is in the store
+    pub fn contains(&self, device_id: DeviceID) -> bool {
+        self.devices.load().contains_key(&device_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.load().is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.load().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn publish_and_read() {
+        let store = SnapshotStore::new();
+        assert!(store.is_empty());
+        assert!(!store.publish(1, HashMap::new()));
+
+        store.add_device(1);
+        assert_eq!(store.device_snapshot(1).unwrap().sequence(), 0);
+        assert_eq!(store.device_snapshot(1).unwrap().read_analog(4), 0.0);
+
+        let held = store.device_snapshot(1).unwrap();
+        assert!(store.publish(1, [(4, 0.5)].iter().cloned().collect()));
+        let snapshot = store.device_snapshot(1).unwrap();
+        assert_eq!(snapshot.sequence(), 1);
+        assert_eq!(snapshot.read_analog(4), 0.5);
+        let mut dense = [0.0; 8];
+        dense[4] = 0.75;
+        dense[5] = 0.25;
+        snapshot.read_dense(&mut dense);
+        assert_eq!(dense[4], 0.75);
+        assert_eq!(dense[5], 0.25);
+        //Snapshots which were already handed out are unaffected by later publishes
+        assert_eq!(held.read_analog(4), 0.0);
+
+        store.remove_device(1);
+        assert!(store.device_snapshot(1).is_none());
+        assert_eq!(snapshot.read_analog(4), 0.5);
+    }
+
+    #[test]
+    fn read_combines_devices() {
+        let store = SnapshotStore::new();
+        assert!(store.read_analog(4, 0).0.is_err());
+        assert!(store.read_full_buffer(0).0.is_err());
+
+        store.add_device(1);
+        store.add_device(2);
+        store.publish(1, [(4, 0.5), (5, 0.25)].iter().cloned().collect());
+        store.publish(2, [(4, 0.75)].iter().cloned().collect());
+        assert_eq!(store.read_analog(4, 0).0, Ok(0.75));
+        assert_eq!(store.read_analog(4, 1).0, Ok(0.5));
+        assert!(store.read_analog(4, 3).0.is_err());
+        let buffer = store.read_full_buffer(0).0.unwrap();
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer[&4], 0.75);
+        assert_eq!(buffer[&5], 0.25);
+        assert_eq!(store.read_full_buffer(2).0.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn listener_sees_every_report() {
+        let store = SnapshotStore::new();
+        store.add_device(1);
+        let reports = Arc::new(std::sync::Mutex::new(vec![]));
+        let t_reports = Arc::clone(&reports);
+        store.set_listener(Some(Box::new(move |snapshot: &AnalogSnapshot| {
+            t_reports
+                .lock()
+                .unwrap()
+                .push((snapshot.sequence(), snapshot.read_analog(4)));
+        })));
+
+        store.publish(1, [(4, 0.5)].iter().cloned().collect());
+        store.publish(1, [(4, 0.75)].iter().cloned().collect());
+        assert!(!store.publish(2, HashMap::new()));
+        //Removing the device releases its keys
+        store.remove_device(1);
+        store.set_listener(None);
+        store.add_device(1);
+        store.publish(1, [(4, 0.5)].iter().cloned().collect());
+
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![(1, 0.5), (2, 0.75), (3, 0.0)]
+        );
+    }
+
+    #[test]
+    fn concurrent_readers() {
+        let store = Arc::new(SnapshotStore::new());
+        store.add_device(1);
+        store.add_device(2);
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        let mut devices = 0;
+                        store.for_each(|snapshot| {
+                            //Every published snapshot has all keys set to the same value
+                            let value = snapshot.read_analog(4);
+                            assert_eq!(snapshot.read_analog(5), value);
+                            devices += 1;
+                        });
+                        assert_eq!(devices, 2);
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..1_000 {
+            let value = (i % 255) as f32 / 255.0;
+            store.publish(1, [(4, value), (5, value)].iter().cloned().collect());
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(store.device_snapshot(1).unwrap().sequence(), 1_000);
+    }
+}
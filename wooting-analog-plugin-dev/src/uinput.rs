@@ -0,0 +1,158 @@
+//! Virtual input devices created through `/dev/uinput`, which the kernel treats like any other device, e.g. for the mouse emulated
+//! by the SDK, or to test plugins which read evdev devices.
+//!
+//! Creating a device needs write access to `/dev/uinput`, which is usually given through a udev rule or the `input` group.
+use crate::evdev::{InputEvent, InputId, ABS_CNT, EV_ABS, EV_KEY, EV_REL, EV_SYN, SYN_REPORT};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::slice;
+
+pub const UINPUT_PATH: &str = "/dev/uinput";
+pub const BUS_VIRTUAL: u16 = 0x06;
+
+//From linux/uinput.h
+const UI_SET_EVBIT: c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: c_ulong = 0x4004_5565;
+const UI_SET_RELBIT: c_ulong = 0x4004_5566;
+const UI_SET_ABSBIT: c_ulong = 0x4004_5567;
+const UI_DEV_CREATE: c_ulong = 0x5501;
+const UI_DEV_DESTROY: c_ulong = 0x5502;
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+
+/// The legacy `uinput_user_dev` setup, which is supported by every kernel with uinput
+#[repr(C)]
+struct UInputUserDev {
+    name: [u8; UINPUT_MAX_NAME_SIZE],
+    id: InputId,
+    ff_effects_max: u32,
+    absmax: [i32; ABS_CNT as usize],
+    absmin: [i32; ABS_CNT as usize],
+    absfuzz: [i32; ABS_CNT as usize],
+    absflat: [i32; ABS_CNT as usize],
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+/// What a virtual device is called and which events it can send
+#[derive(Debug, Default, Clone)]
+pub struct UInputSetup {
+    /// Cut short to 79 bytes
+    pub name: String,
+    pub id: InputId,
+    pub keys: Vec<u16>,
+    pub relative_axes: Vec<u16>,
+    /// The absolute axes along with their minimum & maximum
+    pub absolute_axes: Vec<(u16, i32, i32)>,
+}
+
+/// A device created through uinput, which is removed again when this is dropped
+#[derive(Debug)]
+pub struct UInputDevice {
+    file: File,
+}
+
+impl UInputDevice {
+    pub fn create(setup: &UInputSetup) -> io::Result<Self> {
+        UInputDevice::create_at(UINPUT_PATH, setup)
+    }
+
+    /// Creates the device through the uinput device at `path`
+    pub fn create_at<P: AsRef<Path>>(path: P, setup: &UInputSetup) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open(path)?;
+        let fd = file.as_raw_fd();
+        let ioctl = |request, value: u16| {
+            if unsafe { libc::ioctl(fd, request, c_int::from(value)) } < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        };
+
+        let mut device = UInputUserDev {
+            name: [0; UINPUT_MAX_NAME_SIZE],
+            id: setup.id,
+            ff_effects_max: 0,
+            absmax: [0; ABS_CNT as usize],
+            absmin: [0; ABS_CNT as usize],
+            absfuzz: [0; ABS_CNT as usize],
+            absflat: [0; ABS_CNT as usize],
+        };
+        //The name has to stay null terminated
+        let name = setup.name.as_bytes();
+        let len = name.len().min(UINPUT_MAX_NAME_SIZE - 1);
+        device.name[..len].copy_from_slice(&name[..len]);
+
+        if !setup.keys.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_KEY)?;
+            for &key in setup.keys.iter() {
+                ioctl(UI_SET_KEYBIT, key)?;
+            }
+        }
+        if !setup.relative_axes.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_REL)?;
+            for &axis in setup.relative_axes.iter() {
+                ioctl(UI_SET_RELBIT, axis)?;
+            }
+        }
+        if !setup.absolute_axes.is_empty() {
+            ioctl(UI_SET_EVBIT, EV_ABS)?;
+            for &(axis, minimum, maximum) in setup.absolute_axes.iter() {
+                if axis >= ABS_CNT {
+                    return Err(io::Error::from_raw_os_error(libc::EINVAL));
+                }
+                ioctl(UI_SET_ABSBIT, axis)?;
+                device.absmin[usize::from(axis)] = minimum;
+                device.absmax[usize::from(axis)] = maximum;
+            }
+        }
+
+        file.write_all(as_bytes(&device))?;
+        if unsafe { libc::ioctl(fd, UI_DEV_CREATE) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(UInputDevice { file })
+    }
+
+    /// Sends an event, which is only passed on once it's followed by `sync`
+    pub fn send(&mut self, kind: u16, code: u16, value: i32) -> io::Result<()> {
+        self.file
+            .write_all(as_bytes(&InputEvent::new(kind, code, value)))
+    }
+
+    /// Passes on the events sent since the last sync as a single report
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.send(EV_SYN, SYN_REPORT, 0)
+    }
+}
+
+impl Drop for UInputDevice {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn struct_layout() {
+        assert_eq!(mem::size_of::<UInputUserDev>(), 1116);
+        assert_eq!(
+            mem::size_of::<InputEvent>(),
+            mem::size_of::<libc::timeval>() + 8
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! In-memory HID transport for testing the plugin without hardware. Devices are plugged into a `FakeHid` and are given
+//! reports to send, which the plugin reads like it would from a real device
+use crate::transport::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+#[derive(Default)]
+struct FakeDevice {
+    info: HidDeviceInfo,
+    connected: bool,
+    reports: VecDeque<Vec<u8>>,
+    //By report ID
+    feature_reports: HashMap<u8, Vec<u8>>,
+    sent_feature_reports: Vec<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct Bus {
+    devices: HashMap<String, FakeDevice>,
+    fail_open: bool,
+}
+
+/// Handle to the fake devices, which can be cloned to keep control of them after giving the transport to the plugin
+#[derive(Clone, Default)]
+pub struct FakeHid {
+    bus: Arc<(Mutex<Bus>, Condvar)>,
+}
+
+impl FakeHid {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Plugs in a device, replacing the one at the same path. Reports which were queued or sent to it are dropped
+    pub fn connect(&self, info: HidDeviceInfo) {
+        let (bus, _) = &*self.bus;
+        bus.lock().unwrap().devices.insert(
+            info.path.clone(),
+            FakeDevice {
+                info,
+                connected: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Unplugs the device at `path`, so reads from it fail & it isn't listed anymore
+    pub fn disconnect(&self, path: &str) {
+        let (bus, changed) = &*self.bus;
+        if let Some(device) = bus.lock().unwrap().devices.get_mut(path) {
+            device.connected = false;
+        }
+        changed.notify_all();
+    }
+
+    /// Queues an input report to be read from the device at `path`
+    pub fn send_report(&self, path: &str, report: &[u8]) {
+        let (bus, changed) = &*self.bus;
+        if let Some(device) = bus.lock().unwrap().devices.get_mut(path) {
+            device.reports.push_back(report.to_vec());
+        }
+        changed.notify_all();
+    }
+
+    /// Sets the feature report which is read from the device at `path` for the report ID in the first byte of `report`
+    pub fn set_feature_report(&self, path: &str, report: &[u8]) {
+        if let Some(device) = self.bus.0.lock().unwrap().devices.get_mut(path) {
+            device.feature_reports.insert(report[0], report.to_vec());
+        }
+    }
+
+    /// Gives the feature reports which have been sent to the device at `path`, in the order they were sent
+    pub fn sent_feature_reports(&self, path: &str) -> Vec<Vec<u8>> {
+        self.bus.0.lock().unwrap().devices[path]
+            .sent_feature_reports
+            .clone()
+    }
+
+    /// Makes opening devices fail, like when there's no permission to
+    pub fn set_fail_open(&self, fail: bool) {
+        self.bus.0.lock().unwrap().fail_open = fail;
+    }
+
+    /// Gives a transport over the fake devices
+    pub fn transport(&self) -> Box<dyn HidTransport> {
+        Box::new(self.clone())
+    }
+}
+
+impl HidTransport for FakeHid {
+    fn enumerate(&mut self) -> TransportResult<Vec<HidDeviceInfo>> {
+        let (bus, _) = &*self.bus;
+        Ok(bus
+            .lock()
+            .unwrap()
+            .devices
+            .values()
+            .filter(|device| device.connected)
+            .map(|device| device.info.clone())
+            .collect())
+    }
+
+    fn open(&self, device_info: &HidDeviceInfo) -> TransportResult<Box<dyn HidConnection>> {
+        let (bus, _) = &*self.bus;
+        let bus = bus.lock().unwrap();
+        if bus.fail_open {
+            return Err(TransportError::Other("Permission denied".to_string()));
+        }
+        match bus.devices.get(&device_info.path) {
+            Some(device) if device.connected => Ok(Box::new(FakeConnection {
+                bus: Arc::clone(&self.bus),
+                path: device_info.path.clone(),
+            })),
+            _ => Err(TransportError::Other("No such device".to_string())),
+        }
+    }
+}
+
+struct FakeConnection {
+    bus: Arc<(Mutex<Bus>, Condvar)>,
+    path: String,
+}
+
+impl HidConnection for FakeConnection {
+    fn read_timeout(&self, buffer: &mut [u8], timeout_ms: i32) -> TransportResult<usize> {
+        let (bus, changed) = &*self.bus;
+        let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+        let mut bus = bus.lock().unwrap();
+        loop {
+            let device = match bus.devices.get_mut(&self.path) {
+                Some(device) if device.connected => device,
+                _ => return Err(TransportError::Disconnected),
+            };
+            if let Some(report) = device.reports.pop_front() {
+                let len = report.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&report[..len]);
+                return Ok(len);
+            }
+            let (next, wait) = changed.wait_timeout(bus, timeout).unwrap();
+            bus = next;
+            if wait.timed_out() {
+                return Ok(0);
+            }
+        }
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> TransportResult<()> {
+        match self.bus.0.lock().unwrap().devices.get_mut(&self.path) {
+            Some(device) if device.connected => {
+                device.sent_feature_reports.push(data.to_vec());
+                Ok(())
+            }
+            _ => Err(TransportError::Disconnected),
+        }
+    }
+
+    fn get_feature_report(&self, buffer: &mut [u8]) -> TransportResult<usize> {
+        match self.bus.0.lock().unwrap().devices.get(&self.path) {
+            Some(device) if device.connected => match device.feature_reports.get(&buffer[0]) {
+                Some(report) => {
+                    let len = report.len().min(buffer.len());
+                    buffer[..len].copy_from_slice(&report[..len]);
+                    Ok(len)
+                }
+                None => Err(TransportError::Other("No such report".to_string())),
+            },
+            _ => Err(TransportError::Disconnected),
+        }
+    }
+}
@@ -5,16 +5,21 @@ extern crate wooting_analog_plugin_dev;
 #[macro_use]
 extern crate objekt;
 
-use hidapi::DeviceInfo as DeviceInfoHID;
-use hidapi::{HidApi, HidDevice};
+#[cfg(test)]
+mod fake;
+mod transport;
+
 use log::{error, info};
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::os::raw::{c_float, c_ushort};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Duration;
 use std::{str, thread};
+use transport::*;
 use wooting_analog_plugin_dev::wooting_analog_common::*;
 use wooting_analog_plugin_dev::*;
 
@@ -24,6 +29,17 @@ const ANALOG_BUFFER_SIZE: usize = 48;
 const ANALOG_MAX_SIZE: usize = 40;
 const WOOTING_VID: u16 = 0x31e3;
 const WOOTING_PID_MODE_MASK: u16 = 0xFFF0;
+/// Prefix the firmware gives keys on the HID Consumer page (e.g. media keys) in the analog report, with the usage in the low byte
+const WOOTING_CONSUMER_PREFIX: u16 = 0x0C00;
+
+/// Convert the `code` of a key in the analog report into the code given to the SDK, moving Consumer page keys to `CONSUMER_PAGE`
+fn report_code_to_code(code: u16) -> u16 {
+    if code & 0xFF00 == WOOTING_CONSUMER_PREFIX {
+        CONSUMER_PAGE | (code & 0xFF)
+    } else {
+        code
+    }
+}
 
 /// Struct holding the information we need to find the device and the analog interface
 struct DeviceHardwareID {
@@ -39,17 +55,17 @@ trait DeviceImplementation: objekt::Clone + Send {
     fn device_hardware_id(&self) -> DeviceHardwareID;
 
     /// Used to determine if the given `device` matches the hardware id given by `device_hardware_id`
-    fn matches(&self, device: &DeviceInfoHID) -> bool {
+    fn matches(&self, device: &HidDeviceInfo) -> bool {
         let hid = self.device_hardware_id();
         let pid = if hid.has_modes {
-            device.product_id() & WOOTING_PID_MODE_MASK
+            device.product_id & WOOTING_PID_MODE_MASK
         } else {
-            device.product_id()
+            device.product_id
         };
         //Check if the pid & hid match
         (hid.pid.is_none() || hid.pid.map_or(false, |hid_pid| pid == hid_pid))
-            && device.vendor_id().eq(&hid.vid)
-            && device.usage_page().eq(&hid.usage_page)
+            && device.vendor_id.eq(&hid.vid)
+            && device.usage_page.eq(&hid.usage_page)
     }
 
     /// Convert the given raw `value` into the appropriate float value. The given value should be 0.0f-1.0f
@@ -62,7 +78,7 @@ trait DeviceImplementation: objekt::Clone + Send {
     /// `max_length` is not the max length of the report, it is the max number of key + analog value pairs to read
     fn get_analog_buffer(
         &self,
-        device: &HidDevice,
+        device: &dyn HidConnection,
         max_length: usize,
     ) -> SDKResult<Option<HashMap<c_ushort, c_float>>> {
         let mut buffer: [u8; ANALOG_BUFFER_SIZE] = [0; ANALOG_BUFFER_SIZE];
@@ -75,10 +91,13 @@ trait DeviceImplementation: objekt::Clone + Send {
                     return Ok(None).into();
                 }
             }
+            Err(TransportError::Disconnected) => {
+                return Err(WootingAnalogResult::DeviceDisconnected).into();
+            }
             Err(e) => {
                 error!("Failed to read buffer: {}", e);
 
-                return Err(WootingAnalogResult::DeviceDisconnected).into();
+                return Err(WootingAnalogResult::Failure).into();
             }
         }
         //println!("{:?}", buffer);
@@ -89,7 +108,7 @@ trait DeviceImplementation: objekt::Clone + Send {
                 .filter(|&s| s[2] != 0) //Get rid of entries where the analog value is 0
                 .map(|s| {
                     (
-                        ((u16::from(s[0])) << 8) | u16::from(s[1]), // Convert the first 2 bytes into the u16 code
+                        report_code_to_code((u16::from(s[0]) << 8) | u16::from(s[1])), // Convert the first 2 bytes into the u16 code
                         self.analog_value_to_float(s[2]), //Convert the remaining byte into the float analog value
                     )
                 })
@@ -98,13 +117,31 @@ trait DeviceImplementation: objekt::Clone + Send {
         .into()
     }
 
+    /// Gives the physical layout of the device with the given `product_id`. ISO boards have the same product ID as ANSI ones,
+    /// so full size and 80% boards are reported with the ANSI variant of their layout
+    fn layout(&self, _product_id: u16) -> KeyboardLayout {
+        KeyboardLayout::Unknown
+    }
+
     /// Get the unique device ID from the given `device_info`
-    fn get_device_id(&self, device_info: &DeviceInfoHID) -> DeviceID {
-        wooting_analog_plugin_dev::generate_device_id(
-            device_info.serial_number().as_ref().unwrap_or(&"NO SERIAL"),
-            device_info.vendor_id(),
-            device_info.product_id(),
-        )
+    fn get_device_id(&self, device_info: &HidDeviceInfo) -> DeviceID {
+        match device_info
+            .serial_number
+            .as_deref()
+            .filter(|serial| !serial.is_empty())
+        {
+            Some(serial) => wooting_analog_plugin_dev::generate_device_id(
+                serial,
+                device_info.vendor_id,
+                device_info.product_id,
+            ),
+            //Identical boards without a serial would all get the same ID, so tell them apart by where they're plugged in
+            None => wooting_analog_plugin_dev::generate_device_id_from_path(
+                &device_info.path,
+                device_info.vendor_id,
+                device_info.product_id,
+            ),
+        }
     }
 }
 
@@ -126,6 +163,10 @@ impl DeviceImplementation for WootingOne {
     fn analog_value_to_float(&self, value: u8) -> f32 {
         ((f32::from(value) * 1.2) / 255_f32).min(1.0)
     }
+
+    fn layout(&self, _product_id: u16) -> KeyboardLayout {
+        KeyboardLayout::ANSI80
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -144,6 +185,10 @@ impl DeviceImplementation for WootingTwo {
     fn analog_value_to_float(&self, value: u8) -> f32 {
         ((f32::from(value) * 1.2) / 255_f32).min(1.0)
     }
+
+    fn layout(&self, _product_id: u16) -> KeyboardLayout {
+        KeyboardLayout::ANSI
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -158,32 +203,87 @@ impl DeviceImplementation for WootingNewFirmware {
             has_modes: true,
         }
     }
+
+    fn layout(&self, product_id: u16) -> KeyboardLayout {
+        //The upper byte of the product ID identifies the product line
+        match product_id >> 8 {
+            0x11 | 0x14 => KeyboardLayout::ANSI80,
+            0x12 => KeyboardLayout::ANSI,
+            0x13 => KeyboardLayout::ANSI60,
+            _ => KeyboardLayout::Unknown,
+        }
+    }
+}
+
+/// Gives what can be found out about the device from its HID `device_info`. The report rate isn't known, as hidapi doesn't
+/// give the polling interval of the interface
+fn device_details(device_info: &HidDeviceInfo, layout: KeyboardLayout) -> DeviceDetails {
+    let release = device_info.release_number;
+    let mut details = DeviceDetails::default();
+    details.serial_number = device_info.serial_number.clone();
+    //The release number is binary coded decimal, e.g. 0x0123 is 1.23
+    details.firmware_version = Some(format!("{:x}.{:02x}", release >> 8, release & 0xFF));
+    details.interface_path = Some(device_info.path.clone());
+    details.analog_key_count = Some(layout.keys().len() as u32).filter(|count| *count > 0);
+    //Analog values are reported as a single byte
+    details.value_resolution = Some(256);
+    details
+}
+
+/// How long to wait for the device's worker to make a feature report call, which is kept below the SDK's default call timeout
+const FEATURE_REPORT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A call made on the connection of a device by its worker, as that's what owns the connection
+enum DeviceCommand {
+    SendFeatureReport(Vec<u8>, Sender<TransportResult<()>>),
+    GetFeatureReport(Vec<u8>, Sender<TransportResult<Vec<u8>>>),
+}
+
+impl DeviceCommand {
+    fn run(self, device: &dyn HidConnection) {
+        //The caller may have stopped waiting, in which case there's nobody to give the result to
+        match self {
+            DeviceCommand::SendFeatureReport(data, reply) => {
+                let _ = reply.send(device.send_feature_report(&data));
+            }
+            DeviceCommand::GetFeatureReport(mut buffer, reply) => {
+                let res = device.get_feature_report(&mut buffer).map(|len| {
+                    buffer.truncate(len);
+                    buffer
+                });
+                let _ = reply.send(res);
+            }
+        }
+    }
 }
 
 /// A fully contained device which uses `device_impl` to interface with the `device`
 struct Device {
     pub device_info: DeviceInfo,
-    buffer: Arc<Mutex<HashMap<c_ushort, c_float>>>,
+    layout: KeyboardLayout,
+    snapshots: Arc<SnapshotStore>,
     connected: Arc<AtomicBool>,
-    pressed_keys: Vec<u16>,
+    commands: Sender<DeviceCommand>,
     worker: Option<JoinHandle<i32>>,
 }
 unsafe impl Send for Device {}
 
 impl Device {
     fn new(
-        device_info: &DeviceInfoHID,
-        device: HidDevice,
+        device_info: &HidDeviceInfo,
+        device: Box<dyn HidConnection>,
         device_impl: Box<dyn DeviceImplementation>,
+        snapshots: &Arc<SnapshotStore>,
     ) -> (DeviceID, Self) {
         let id_hash = device_impl.get_device_id(device_info);
+        let layout = device_impl.layout(device_info.product_id);
 
-        let buffer: Arc<Mutex<HashMap<c_ushort, c_float>>> =
-            Arc::new(Mutex::new(Default::default()));
+        snapshots.add_device(id_hash);
         let connected = Arc::new(AtomicBool::new(true));
+        let (commands, t_commands) = mpsc::channel::<DeviceCommand>();
 
         let worker = {
-            let t_buffer = Arc::clone(&buffer);
+            let t_snapshots = Arc::clone(snapshots);
             let t_connected = Arc::clone(&connected);
 
             thread::spawn(move || loop {
@@ -192,14 +292,12 @@ impl Device {
                 }
 
                 match device_impl
-                    .get_analog_buffer(&device, ANALOG_MAX_SIZE)
+                    .get_analog_buffer(device.as_ref(), ANALOG_MAX_SIZE)
                     .into()
                 {
                     Ok(data) => {
                         if let Some(data) = data {
-                            let mut m = t_buffer.lock().unwrap();
-                            m.clear();
-                            m.extend(data);
+                            t_snapshots.publish(id_hash, data);
                         }
                     }
                     Err(e) => {
@@ -210,54 +308,70 @@ impl Device {
                         return 0;
                     }
                 }
+
+                //Commands are only waited on for as long as a read takes, as reports arrive far more often
+                while let Ok(command) = t_commands.try_recv() {
+                    command.run(device.as_ref());
+                }
             })
         };
 
+        let mut hid_device_info = DeviceInfo::new_with_id(
+            device_info.vendor_id,
+            device_info.product_id,
+            device_info
+                .manufacturer
+                .as_deref()
+                .unwrap_or("ERR COULD NOT BE FOUND")
+                .to_string(),
+            device_info
+                .product
+                .as_deref()
+                .unwrap_or("ERR COULD NOT BE FOUND")
+                .to_string(),
+            id_hash,
+            DeviceType::Keyboard,
+        );
+        hid_device_info.details = device_details(device_info, layout);
+
         (
             id_hash,
             Device {
-                device_info: DeviceInfo::new_with_id(
-                    device_info.vendor_id(),
-                    device_info.product_id(),
-                    device_info
-                        .manufacturer_string()
-                        .unwrap_or("ERR COULD NOT BE FOUND")
-                        .to_string(),
-                    device_info
-                        .product_string()
-                        .unwrap_or("ERR COULD NOT BE FOUND")
-                        .to_string(),
-                    id_hash,
-                    DeviceType::Keyboard,
-                ),
+                device_info: hid_device_info,
+                layout,
                 connected,
-                buffer,
-                pressed_keys: vec![],
+                snapshots: Arc::clone(snapshots),
+                commands,
                 worker: Some(worker),
             },
         )
     }
 
-    fn read_analog(&mut self, code: u16) -> SDKResult<c_float> {
-        (*self.buffer.lock().unwrap().get(&code).unwrap_or(&0.0)).into()
+    fn snapshot(&self) -> SDKResult<Arc<AnalogSnapshot>> {
+        self.snapshots
+            .device_snapshot(self.device_info.device_id)
+            .ok_or(WootingAnalogResult::DeviceDisconnected)
+            .into()
     }
 
-    fn read_full_buffer(&mut self, _max_length: usize) -> SDKResult<HashMap<c_ushort, c_float>> {
-        let mut buffer = self.buffer.lock().unwrap().clone();
-        //Collect the new pressed keys
-        let new_pressed_keys: Vec<u16> = buffer.keys().map(|x| *x).collect();
+    fn read_full_buffer(&self, _max_length: usize) -> SDKResult<HashMap<c_ushort, c_float>> {
+        self.snapshot()
+            .0
+            .map(|snapshot| snapshot.analog().clone())
+            .into()
+    }
 
-        //Put the old pressed keys into the buffer
-        for key in self.pressed_keys.drain(..) {
-            if !buffer.contains_key(&key) {
-                buffer.insert(key, 0.0);
-            }
+    /// Gives `command` to the worker, giving back where its result will be sent
+    fn command<T>(
+        &self,
+        command: impl FnOnce(Sender<TransportResult<T>>) -> DeviceCommand,
+    ) -> SDKResult<Receiver<TransportResult<T>>> {
+        let (reply, result) = mpsc::channel();
+        match self.commands.send(command(reply)) {
+            Ok(()) => Ok(result).into(),
+            //The worker has stopped as the device was disconnected
+            Err(_) => Err(WootingAnalogResult::DeviceDisconnected).into(),
         }
-
-        //Store the newPressedKeys for the next call
-        self.pressed_keys = new_pressed_keys;
-
-        Ok(buffer).into()
     }
 }
 
@@ -271,36 +385,106 @@ impl Drop for Device {
                 .join()
                 .expect("Couldn't join on the associated thread");
         }
+        self.snapshots.remove_device(self.device_info.device_id);
     }
 }
 
+fn device_impls() -> Vec<Box<dyn DeviceImplementation>> {
+    vec![
+        Box::new(WootingOne()),
+        Box::new(WootingTwo()),
+        Box::new(WootingNewFirmware()),
+    ]
+}
+
+/// Creates the transport the devices are found & read through, each time the plugin is initialised
+type TransportFactory = Box<dyn Fn() -> TransportResult<Box<dyn HidTransport>> + Send>;
+
 pub struct WootingPlugin {
     initialised: Arc<AtomicBool>,
     device_event_cb: Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>>,
     devices: Arc<Mutex<HashMap<DeviceID, Device>>>,
+    snapshots: Arc<SnapshotStore>,
+    transport: TransportFactory,
     thread: Option<JoinHandle<()>>,
 }
 
 const PLUGIN_NAME: &str = "Wooting Official Plugin";
 impl WootingPlugin {
     fn new() -> Self {
+        Self::with_transport(Box::new(|| {
+            //Only the devices we could support are listed, as opening other HID devices can be slow
+            let filters = device_impls()
+                .iter()
+                .map(|device_impl| {
+                    let hid = device_impl.device_hardware_id();
+                    (hid.vid, hid.pid.unwrap_or(0))
+                })
+                .collect();
+            HidapiTransport::new(filters)
+                .map(|transport| Box::new(transport) as Box<dyn HidTransport>)
+        }))
+    }
+
+    fn with_transport(transport: TransportFactory) -> Self {
         WootingPlugin {
             initialised: Arc::new(false.into()),
             device_event_cb: Arc::new(Mutex::new(None)),
             devices: Arc::new(Mutex::new(Default::default())),
+            snapshots: Arc::new(SnapshotStore::new()),
+            transport,
             thread: None,
         }
     }
 
+    /// Has the worker of the device with ID `device_id` run a command on its connection, waiting for the result
+    fn device_command<T>(
+        &self,
+        device_id: DeviceID,
+        command: impl FnOnce(Sender<TransportResult<T>>) -> DeviceCommand,
+    ) -> SDKResult<T> {
+        if !self.initialised.load(Ordering::Relaxed) {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        //The devices aren't kept locked while waiting, so the device can still be removed if it's disconnected
+        let result = match self.devices.lock().unwrap().get(&device_id) {
+            Some(device) => device.command(command),
+            None => Err(WootingAnalogResult::NoDevices).into(),
+        };
+        let result = match result.0 {
+            Ok(result) => result,
+            Err(e) => return Err(e).into(),
+        };
+        match result.recv_timeout(FEATURE_REPORT_TIMEOUT) {
+            Ok(Ok(value)) => Ok(value).into(),
+            Ok(Err(TransportError::Disconnected)) | Err(RecvTimeoutError::Disconnected) => {
+                Err(WootingAnalogResult::DeviceDisconnected).into()
+            }
+            Ok(Err(TransportError::Other(e))) => {
+                error!("Feature report failed on device {:#x}: {}", device_id, e);
+                Err(WootingAnalogResult::Failure).into()
+            }
+            Err(RecvTimeoutError::Timeout) => Err(WootingAnalogResult::Timeout).into(),
+        }
+    }
+
     fn init_worker(&mut self) -> SDKResult<u32> {
         let init_device_closure =
-            |hid: &HidApi,
+            |hid: &mut dyn HidTransport,
              devices: &Arc<Mutex<HashMap<DeviceID, Device>>>,
              device_event_cb: &Arc<
                 Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>,
             >,
-             device_impls: &Vec<Box<dyn DeviceImplementation>>| {
-                let device_infos: Vec<&DeviceInfoHID> = hid.device_list().collect();
+             device_impls: &Vec<Box<dyn DeviceImplementation>>,
+             snapshots: &Arc<SnapshotStore>| {
+                let device_infos = match hid.enumerate() {
+                    Ok(device_infos) => device_infos,
+                    Err(e) => {
+                        error!("We got error while refreshing devices. Err: {}", e);
+                        return;
+                    }
+                };
 
                 for device_info in device_infos.iter() {
                     for device_impl in device_impls.iter() {
@@ -311,17 +495,21 @@ impl WootingPlugin {
                                 .contains_key(&device_impl.get_device_id(device_info))
                         {
                             // info!("Found device impl match: {:?}", device_info);
-                            match device_info.open_device(&hid) {
+                            match hid.open(device_info) {
                                 Ok(dev) => {
-                                    let (id, device) =
-                                        Device::new(device_info, dev, device_impl.clone());
+                                    let (id, device) = Device::new(
+                                        device_info,
+                                        dev,
+                                        device_impl.clone(),
+                                        snapshots,
+                                    );
                                     {
                                         devices.lock().unwrap().insert(id, device);
                                     }
 
                                     info!(
                                         "Found and opened the {:?} successfully!",
-                                        device_info.product_string()
+                                        device_info.product
                                     );
 
                                     device_event_cb.lock().unwrap().as_ref().and_then(|cb| {
@@ -348,27 +536,9 @@ impl WootingPlugin {
                 }
             };
 
-        let refresh_devices = |hid: &mut HidApi| -> hidapi::HidResult<()> {
-                hid.reset_devices()?;
-                hid.add_devices(WOOTING_VID, 0)?;
-                hid.add_devices(0x03EB, 0xFF01)?;
-                hid.add_devices(0x03EB, 0xFF02)?;
-                Ok(())
-            };
-
-        let device_impls: Vec<Box<dyn DeviceImplementation>> = vec![
-            Box::new(WootingOne()),
-            Box::new(WootingTwo()),
-            Box::new(WootingNewFirmware()),
-        ];
-        let mut hid = match HidApi::new_without_enumerate() {
-            Ok(mut api) => {
-                //An attempt at trying to ensure that all the devices have been found in the initialisation of the plugins
-                if let Err(e) = refresh_devices(&mut api) {
-                    error!("We got error while refreshing devices. Err: {}", e);
-                }
-                api
-            }
+        let device_impls = device_impls();
+        let mut hid = match (self.transport)() {
+            Ok(hid) => hid,
             Err(e) => {
                 error!("Error obtaining HIDAPI: {}", e);
                 return Err(WootingAnalogResult::Failure).into();
@@ -376,10 +546,19 @@ impl WootingPlugin {
         };
 
         //We wanna call it in this thread first so we can get hold of any connected devices now so we can return an accurate result for initialise
-        init_device_closure(&hid, &self.devices, &self.device_event_cb, &device_impls);
-
+        init_device_closure(
+            hid.as_mut(),
+            &self.devices,
+            &self.device_event_cb,
+            &device_impls,
+            &self.snapshots,
+        );
+
+        //Set before the thread starts, otherwise it could see it unset & stop straight away
+        self.initialised.store(true, Ordering::Relaxed);
         let t_initialised = Arc::clone(&self.initialised);
         let t_devices = Arc::clone(&self.devices);
+        let t_snapshots = Arc::clone(&self.snapshots);
         let t_device_event_cb = Arc::clone(&self.device_event_cb);
         self.thread = Some(thread::spawn(move || {
             let mut i = 0;
@@ -405,10 +584,13 @@ impl WootingPlugin {
                         }
                     }
 
-                    if let Err(e) = refresh_devices(&mut hid) {
-                        error!("We got error while refreshing devices. Err: {}", e);
-                    }
-                    init_device_closure(&hid, &t_devices, &t_device_event_cb, &device_impls);
+                    init_device_closure(
+                        hid.as_mut(),
+                        &t_devices,
+                        &t_device_event_cb,
+                        &device_impls,
+                        &t_snapshots,
+                    );
                 }
                 thread::sleep(std::time::Duration::from_millis(10));
                 i += 10;
@@ -457,39 +639,24 @@ impl Plugin for WootingPlugin {
             return Err(WootingAnalogResult::UnInitialized).into();
         }
 
-        if self.devices.lock().unwrap().is_empty() {
-            return Err(WootingAnalogResult::NoDevices).into();
-        }
-
         //If the Device ID is 0 we want to go through all the connected devices
         //and combine the analog values
         if device_id == 0 {
             let mut analog: f32 = -1.0;
-            let mut error: WootingAnalogResult = WootingAnalogResult::Ok;
-            for (_id, device) in self.devices.lock().unwrap().iter_mut() {
-                match device.read_analog(code).into() {
-                    Ok(val) => {
-                        analog = analog.max(val);
-                    }
-                    Err(e) => {
-                        error = e;
-                    }
-                }
-            }
+            self.snapshots.for_each(|snapshot| {
+                analog = analog.max(snapshot.read_analog(code));
+            });
 
             if analog < 0.0 {
-                Err(error).into()
+                Err(WootingAnalogResult::NoDevices).into()
             } else {
                 analog.into()
             }
         } else
         //If the device id is not 0, we try and find a connected device with that ID and read from it
         {
-            match self.devices.lock().unwrap().get_mut(&device_id) {
-                Some(device) => match device.read_analog(code).into() {
-                    Ok(val) => val.into(),
-                    Err(e) => Err(e).into(),
-                },
+            match self.snapshots.device_snapshot(device_id) {
+                Some(snapshot) => snapshot.read_analog(code).into(),
                 None => Err(WootingAnalogResult::NoDevices).into(),
             }
         }
@@ -556,6 +723,366 @@ impl Plugin for WootingPlugin {
 
         Ok(devices).into()
     }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        Some(Arc::clone(&self.snapshots))
+    }
+
+    fn device_layout(&mut self, device: DeviceID) -> SDKResult<KeyboardLayout> {
+        match self.devices.lock().unwrap().get(&device) {
+            Some(device) => Ok(device.layout).into(),
+            None => Err(WootingAnalogResult::NoDevices).into(),
+        }
+    }
+
+    fn send_feature_report(&mut self, device: DeviceID, data: &[u8]) -> SDKResult<()> {
+        let data = data.to_vec();
+        self.device_command(device, |reply| {
+            DeviceCommand::SendFeatureReport(data, reply)
+        })
+    }
+
+    fn get_feature_report(&mut self, device: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+        let request = buffer.to_vec();
+        self.device_command(device, |reply| {
+            DeviceCommand::GetFeatureReport(request, reply)
+        })
+        .0
+        .map(|report| {
+            buffer[..report.len()].copy_from_slice(&report);
+            report.len()
+        })
+        .into()
+    }
 }
 
 declare_plugin!(WootingPlugin, WootingPlugin::new);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::FakeHid;
+    use std::time::{Duration, Instant};
+
+    fn wooting_two_he(path: &str) -> HidDeviceInfo {
+        HidDeviceInfo {
+            path: path.to_string(),
+            vendor_id: WOOTING_VID,
+            product_id: 0x1402,
+            usage_page: 0xFF54,
+            serial_number: Some(format!("A02B2140W011H-{}", path)),
+            manufacturer: Some("Wooting".to_string()),
+            product: Some("Wooting Two HE".to_string()),
+            release_number: 0x0206,
+        }
+    }
+
+    /// Analog report in the format of 2 byte code + 1 byte analog value for each key
+    fn report(keys: &[(u16, u8)]) -> Vec<u8> {
+        keys.iter()
+            .flat_map(|&(code, value)| vec![(code >> 8) as u8, code as u8, value])
+            .collect()
+    }
+
+    fn analog(keys: &[(u16, f32)]) -> HashMap<c_ushort, c_float> {
+        keys.iter().copied().collect()
+    }
+
+    fn wait_for<F: FnMut() -> bool>(mut condition: F) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn parse_reports() {
+        let hid = FakeHid::new();
+        let device_info = wooting_two_he("/dev/hidraw3");
+        hid.connect(device_info.clone());
+        let connection = hid.transport().open(&device_info).unwrap();
+        let device_impl = WootingNewFirmware();
+        let read = |max_length| {
+            device_impl
+                .get_analog_buffer(connection.as_ref(), max_length)
+                .0
+        };
+
+        //Keys with a value of 0 are left out
+        hid.send_report(
+            &device_info.path,
+            &report(&[(0x04, 255), (0x0129, 51), (0x2c, 0)]),
+        );
+        assert_eq!(
+            read(ANALOG_MAX_SIZE),
+            Ok(Some(analog(&[(0x04, 1.0), (0x0129, 0.2)])))
+        );
+        //Only up to `max_length` entries are read
+        hid.send_report(
+            &device_info.path,
+            &report(&[(0x04, 255), (0x05, 255), (0x06, 255)]),
+        );
+        assert_eq!(read(2), Ok(Some(analog(&[(0x04, 1.0), (0x05, 1.0)]))));
+        //Nothing arriving within the timeout keeps the last values
+        assert_eq!(read(ANALOG_MAX_SIZE), Ok(None));
+
+        hid.disconnect(&device_info.path);
+        assert_eq!(
+            read(ANALOG_MAX_SIZE),
+            Err(WootingAnalogResult::DeviceDisconnected)
+        );
+    }
+
+    #[test]
+    fn media_keys() {
+        let hid = FakeHid::new();
+        let device_info = wooting_two_he("/dev/hidraw3");
+        hid.connect(device_info.clone());
+        let connection = hid.transport().open(&device_info).unwrap();
+        let device_impl = WootingNewFirmware();
+
+        //Media keys are given on the Consumer page, the rest of the keys are left as they are
+        hid.send_report(
+            &device_info.path,
+            &report(&[(0x0CCD, 255), (0x0CE9, 51), (0x04, 255)]),
+        );
+        assert_eq!(
+            device_impl
+                .get_analog_buffer(connection.as_ref(), ANALOG_MAX_SIZE)
+                .0,
+            Ok(Some(analog(&[
+                (ConsumerCodes::PlayPause as u16, 1.0),
+                (ConsumerCodes::VolumeIncrement as u16, 0.2),
+                (HIDCodes::A as u16, 1.0)
+            ])))
+        );
+    }
+
+    #[test]
+    fn legacy_scaling() {
+        //The old firmware reports values up to 1/1.2 of the range for a fully pressed key
+        for device_impl in [
+            Box::new(WootingOne()) as Box<dyn DeviceImplementation>,
+            Box::new(WootingTwo()),
+        ]
+        .iter()
+        {
+            assert_eq!(device_impl.analog_value_to_float(0), 0.0);
+            assert!((device_impl.analog_value_to_float(100) - 120.0 / 255.0).abs() < 1e-6);
+            assert!(device_impl.analog_value_to_float(212) < 1.0);
+            assert_eq!(device_impl.analog_value_to_float(213), 1.0);
+            assert_eq!(device_impl.analog_value_to_float(255), 1.0);
+        }
+        let device_impl = WootingNewFirmware();
+        assert_eq!(device_impl.analog_value_to_float(51), 0.2);
+        assert_eq!(device_impl.analog_value_to_float(255), 1.0);
+
+        let hid = FakeHid::new();
+        let device_info = HidDeviceInfo {
+            path: "/dev/hidraw0".to_string(),
+            vendor_id: 0x03EB,
+            product_id: 0xFF02,
+            usage_page: 0xFF54,
+            ..Default::default()
+        };
+        hid.connect(device_info.clone());
+        let connection = hid.transport().open(&device_info).unwrap();
+        hid.send_report(&device_info.path, &report(&[(0x04, 240), (0x05, 85)]));
+        let values = WootingTwo()
+            .get_analog_buffer(connection.as_ref(), ANALOG_MAX_SIZE)
+            .0
+            .unwrap()
+            .unwrap();
+        assert_eq!(values[&0x04], 1.0);
+        assert!((values[&0x05] - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn match_devices() {
+        let device_info = |vendor_id, product_id, usage_page| HidDeviceInfo {
+            vendor_id,
+            product_id,
+            usage_page,
+            ..wooting_two_he("/dev/hidraw3")
+        };
+        let new_firmware = WootingNewFirmware();
+        //The lower bits of the product ID give the mode the keyboard is in
+        assert!(new_firmware.matches(&device_info(WOOTING_VID, 0x1402, 0xFF54)));
+        assert!(new_firmware.matches(&device_info(WOOTING_VID, 0x1403, 0xFF54)));
+        assert!(new_firmware.matches(&device_info(WOOTING_VID, 0x130F, 0xFF54)));
+        assert!(!new_firmware.matches(&device_info(WOOTING_VID, 0x1402, 0xFF55)));
+        assert!(!new_firmware.matches(&device_info(0x03EB, 0x1402, 0xFF54)));
+
+        //The old firmware doesn't have modes, so the product ID has to match exactly
+        assert!(WootingTwo().matches(&device_info(0x03EB, 0xFF02, 0xFF54)));
+        assert!(!WootingTwo().matches(&device_info(0x03EB, 0xFF03, 0xFF54)));
+        assert!(!WootingTwo().matches(&device_info(0x03EB, 0xFF01, 0xFF54)));
+        assert!(WootingOne().matches(&device_info(0x03EB, 0xFF01, 0xFF54)));
+
+        assert_eq!(new_firmware.layout(0x1402), KeyboardLayout::ANSI80);
+        assert_eq!(new_firmware.layout(0x1203), KeyboardLayout::ANSI);
+        assert_eq!(new_firmware.layout(0x1301), KeyboardLayout::ANSI60);
+
+        //Boards without a serial are told apart by their path
+        let mut first = wooting_two_he("/dev/hidraw3");
+        let mut second = wooting_two_he("/dev/hidraw4");
+        assert_ne!(
+            new_firmware.get_device_id(&first),
+            new_firmware.get_device_id(&second)
+        );
+        first.serial_number = None;
+        second.serial_number = Some(String::new());
+        assert_ne!(
+            new_firmware.get_device_id(&first),
+            new_firmware.get_device_id(&second)
+        );
+    }
+
+    #[test]
+    fn device_events() {
+        let hid = FakeHid::new();
+        let first = wooting_two_he("/dev/hidraw3");
+        hid.connect(first.clone());
+        //Devices which aren't Wootings are left alone
+        hid.connect(HidDeviceInfo {
+            path: "/dev/hidraw1".to_string(),
+            vendor_id: 0x046D,
+            product_id: 0xC52B,
+            usage_page: 0xFF00,
+            ..Default::default()
+        });
+
+        let t_hid = hid.clone();
+        let mut plugin = WootingPlugin::with_transport(Box::new(move || Ok(t_hid.transport())));
+        let events = Arc::new(Mutex::new(vec![]));
+        let t_events = Arc::clone(&events);
+        assert_eq!(
+            plugin
+                .initialise(Box::new(move |event, device| t_events
+                    .lock()
+                    .unwrap()
+                    .push((event, device.device_id))))
+                .0,
+            Ok(1)
+        );
+        let devices = plugin.device_info().0.unwrap();
+        assert_eq!(devices.len(), 1);
+        let first_id = devices[0].device_id;
+        assert_eq!(devices[0].device_name, "Wooting Two HE");
+        assert_eq!(devices[0].details.firmware_version.as_deref(), Some("2.06"));
+        assert_eq!(
+            devices[0].details.interface_path.as_deref(),
+            Some("/dev/hidraw3")
+        );
+        assert_eq!(plugin.device_layout(first_id).0, Ok(KeyboardLayout::ANSI80));
+
+        hid.send_report(&first.path, &report(&[(0x04, 255)]));
+        assert!(wait_for(|| plugin.read_analog(0x04, first_id).0 == Ok(1.0)));
+        assert_eq!(
+            plugin.read_full_buffer(ANALOG_MAX_SIZE, 0).0,
+            Ok(analog(&[(0x04, 1.0)]))
+        );
+
+        //Devices which can't be opened are tried again on the next scan
+        hid.set_fail_open(true);
+        let second = wooting_two_he("/dev/hidraw10");
+        hid.connect(second.clone());
+        thread::sleep(Duration::from_millis(600));
+        assert_eq!(plugin.device_info().0.map(|devices| devices.len()), Ok(1));
+        hid.set_fail_open(false);
+        assert!(wait_for(|| events.lock().unwrap().len() == 1));
+        let second_id = events.lock().unwrap()[0].1;
+        assert_eq!(
+            events.lock().unwrap()[0],
+            (DeviceEventType::Connected, second_id)
+        );
+        assert_ne!(first_id, second_id);
+
+        hid.disconnect(&first.path);
+        assert!(wait_for(|| events.lock().unwrap().len() == 2));
+        assert_eq!(
+            events.lock().unwrap()[1],
+            (DeviceEventType::Disconnected, first_id)
+        );
+        assert_eq!(
+            plugin.read_analog(0x04, first_id).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        assert_eq!(plugin.read_analog(0x04, second_id).0, Ok(0.0));
+
+        //Plugging it back in connects it again with the same ID
+        hid.connect(first.clone());
+        assert!(wait_for(|| events.lock().unwrap().len() == 3));
+        assert_eq!(
+            events.lock().unwrap()[2],
+            (DeviceEventType::Connected, first_id)
+        );
+
+        plugin.unload();
+        assert!(!plugin.is_initialised());
+    }
+
+    #[test]
+    fn feature_reports() {
+        let hid = FakeHid::new();
+        let device_info = wooting_two_he("/dev/hidraw3");
+        hid.connect(device_info.clone());
+        let t_hid = hid.clone();
+        let mut plugin = WootingPlugin::with_transport(Box::new(move || Ok(t_hid.transport())));
+        assert_eq!(
+            plugin.send_feature_report(1, &[1]).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+        assert_eq!(plugin.initialise(Box::new(|_, _| {})).0, Ok(1));
+        let device_id = plugin.device_info().0.unwrap()[0].device_id;
+
+        assert_eq!(
+            plugin.send_feature_report(device_id, &[1, 0xD1, 0x07]).0,
+            Ok(())
+        );
+        assert_eq!(
+            hid.sent_feature_reports(&device_info.path),
+            vec![vec![1, 0xD1, 0x07]]
+        );
+        hid.set_feature_report(&device_info.path, &[1, 0x07, 0x02]);
+        let mut buffer = [1, 0, 0, 0];
+        assert_eq!(plugin.get_feature_report(device_id, &mut buffer).0, Ok(3));
+        assert_eq!(buffer, [1, 0x07, 0x02, 0]);
+        let mut buffer = [2, 0];
+        assert_eq!(
+            plugin.get_feature_report(device_id, &mut buffer).0,
+            Err(WootingAnalogResult::Failure)
+        );
+        assert_eq!(
+            plugin.send_feature_report(device_id + 1, &[1]).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+
+        //Until the device is removed on the next scan, it's known to have been disconnected
+        hid.disconnect(&device_info.path);
+        assert!(matches!(
+            plugin.send_feature_report(device_id, &[1]).0,
+            Err(WootingAnalogResult::DeviceDisconnected) | Err(WootingAnalogResult::NoDevices)
+        ));
+        assert!(wait_for(
+            || plugin.send_feature_report(device_id, &[1]).0 == Err(WootingAnalogResult::NoDevices)
+        ));
+        plugin.unload();
+    }
+
+    #[test]
+    fn transport_unavailable() {
+        let mut plugin = WootingPlugin::with_transport(Box::new(|| {
+            Err(TransportError::Other("No HID access".to_string()))
+        }));
+        assert_eq!(
+            plugin.initialise(Box::new(|_, _| {})).0,
+            Err(WootingAnalogResult::Failure)
+        );
+        assert!(!plugin.is_initialised());
+    }
+}
@@ -0,0 +1,128 @@
+use hidapi::{HidApi, HidDevice};
+use std::ffi::CString;
+use std::fmt;
+
+/// What can be found out about a HID interface without opening it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HidDeviceInfo {
+    pub path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usage_page: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// Binary coded decimal, e.g. 0x0123 is 1.23
+    pub release_number: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportError {
+    /// The device has gone away, it has to be found & opened again to be used
+    Disconnected,
+    Other(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransportError::Disconnected => write!(f, "Device disconnected"),
+            TransportError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+pub type TransportResult<T> = Result<T, TransportError>;
+
+/// Gives access to HID devices, so the plugin can be run on something other than real hardware
+pub trait HidTransport: Send {
+    /// Lists the HID interfaces which are currently connected
+    fn enumerate(&mut self) -> TransportResult<Vec<HidDeviceInfo>>;
+
+    /// Opens the interface given by `device_info`
+    fn open(&self, device_info: &HidDeviceInfo) -> TransportResult<Box<dyn HidConnection>>;
+}
+
+/// An open HID interface. Dropping it closes the interface
+pub trait HidConnection: Send {
+    /// Reads an input report into `buffer`, giving its length, or 0 if there wasn't one within `timeout_ms`. Gives
+    /// `TransportError::Disconnected` once the device has gone away
+    fn read_timeout(&self, buffer: &mut [u8], timeout_ms: i32) -> TransportResult<usize>;
+
+    /// Sends the feature report `data`, the first byte being the report ID
+    fn send_feature_report(&self, data: &[u8]) -> TransportResult<()>;
+
+    /// Reads the feature report with the ID in the first byte of `buffer` into it, giving its length including the report ID
+    fn get_feature_report(&self, buffer: &mut [u8]) -> TransportResult<usize>;
+}
+
+/// Transport going through hidapi, only listing the devices with the given vendor & product IDs
+pub struct HidapiTransport {
+    api: HidApi,
+    filters: Vec<(u16, u16)>,
+}
+
+impl HidapiTransport {
+    /// Creates the transport, a product ID of 0 in `filters` matches any product from that vendor
+    pub fn new(filters: Vec<(u16, u16)>) -> TransportResult<Self> {
+        HidApi::new_without_enumerate()
+            .map(|api| HidapiTransport { api, filters })
+            .map_err(|e| TransportError::Other(e.to_string()))
+    }
+}
+
+impl HidTransport for HidapiTransport {
+    fn enumerate(&mut self) -> TransportResult<Vec<HidDeviceInfo>> {
+        let HidapiTransport { api, filters } = self;
+        let refresh = api.reset_devices().and_then(|_| {
+            filters
+                .iter()
+                .try_for_each(|&(vid, pid)| api.add_devices(vid, pid))
+        });
+        //Some devices may still have been found, so the error isn't given back
+        if let Err(e) = refresh {
+            error!("We got error while refreshing devices. Err: {}", e);
+        }
+        Ok(api
+            .device_list()
+            .map(|device| HidDeviceInfo {
+                path: device.path().to_string_lossy().into_owned(),
+                vendor_id: device.vendor_id(),
+                product_id: device.product_id(),
+                usage_page: device.usage_page(),
+                serial_number: device.serial_number().map(str::to_owned),
+                manufacturer: device.manufacturer_string().map(str::to_owned),
+                product: device.product_string().map(str::to_owned),
+                release_number: device.release_number(),
+            })
+            .collect())
+    }
+
+    fn open(&self, device_info: &HidDeviceInfo) -> TransportResult<Box<dyn HidConnection>> {
+        let path = CString::new(device_info.path.as_str())
+            .map_err(|e| TransportError::Other(e.to_string()))?;
+        match self.api.open_path(&path) {
+            Ok(device) => Ok(Box::new(device)),
+            Err(e) => Err(TransportError::Other(e.to_string())),
+        }
+    }
+}
+
+impl HidConnection for HidDevice {
+    fn read_timeout(&self, buffer: &mut [u8], timeout_ms: i32) -> TransportResult<usize> {
+        HidDevice::read_timeout(self, buffer, timeout_ms).map_err(|e| {
+            //hidapi doesn't tell why a read failed, which is almost always the device being unplugged
+            error!("Failed to read buffer: {}", e);
+            TransportError::Disconnected
+        })
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> TransportResult<()> {
+        HidDevice::send_feature_report(self, data).map_err(|e| TransportError::Other(e.to_string()))
+    }
+
+    fn get_feature_report(&self, buffer: &mut [u8]) -> TransportResult<usize> {
+        HidDevice::get_feature_report(self, buffer)
+            .map_err(|e| TransportError::Other(e.to_string()))
+    }
+}
@@ -0,0 +1,194 @@
+//! Measures `read_analog` throughput when several threads (e.g. a render and an input thread) read from the SDK at once.
+//!
+//! * `snapshot`: the plugin hands its `SnapshotStore` to the SDK, reads don't touch any locks
+//! * `plugin_lock`: the plugin has no store, so every read goes through the plugin's Mutex
+//! * `global_mutex`: the whole SDK sits behind a single Mutex, how the FFI used to work
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use wooting_analog_common::*;
+use wooting_analog_plugin_dev::*;
+use wooting_analog_sdk::sdk::AnalogSDK;
+
+const DEVICE_ID: DeviceID = 1;
+const THREADS: [u64; 4] = [1, 2, 4, 8];
+
+/// In-memory plugin with a single device that has a handful of keys pressed
+struct BenchPlugin {
+    snapshots: Arc<SnapshotStore>,
+    expose_snapshots: bool,
+}
+
+impl Plugin for BenchPlugin {
+    fn name(&mut self) -> SDKResult<&'static str> {
+        Ok("Bench Plugin").into()
+    }
+
+    fn initialise(
+        &mut self,
+        _callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+    ) -> SDKResult<u32> {
+        Ok(1).into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        true
+    }
+
+    fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+        Ok(vec![DeviceInfo::new_with_id(
+            0x31e3,
+            0x1200,
+            "Wooting".to_owned(),
+            "Bench Device".to_owned(),
+            DEVICE_ID,
+            DeviceType::Keyboard,
+        )])
+        .into()
+    }
+
+    fn read_analog(&mut self, code: u16, device: DeviceID) -> SDKResult<f32> {
+        match self.snapshots.device_snapshot(DEVICE_ID) {
+            Some(snapshot) if device == 0 || device == DEVICE_ID => {
+                Ok(snapshot.read_analog(code)).into()
+            }
+            _ => Err(WootingAnalogResult::NoDevices).into(),
+        }
+    }
+
+    fn read_full_buffer(
+        &mut self,
+        _max_length: usize,
+        _device: DeviceID,
+    ) -> SDKResult<HashMap<u16, f32>> {
+        match self.snapshots.device_snapshot(DEVICE_ID) {
+            Some(snapshot) => Ok(snapshot.analog().clone()).into(),
+            None => Err(WootingAnalogResult::NoDevices).into(),
+        }
+    }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        if self.expose_snapshots {
+            Some(Arc::clone(&self.snapshots))
+        } else {
+            None
+        }
+    }
+}
+
+/// Keeps publishing new reports into the store at roughly 1kHz, like a device worker would, until dropped
+struct Publisher {
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Publisher {
+    fn start(snapshots: Arc<SnapshotStore>) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let t_running = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            let mut i: u32 = 0;
+            while t_running.load(Ordering::Relaxed) {
+                let value = (i % 255) as f32 / 255.0;
+                snapshots.publish(
+                    DEVICE_ID,
+                    [(0x04, value), (0x1a, value), (0x16, value), (0x07, value)]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                );
+                i = i.wrapping_add(1);
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        Publisher {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
+
+fn new_sdk(expose_snapshots: bool) -> (AnalogSDK, Publisher) {
+    let snapshots = Arc::new(SnapshotStore::new());
+    snapshots.add_device(DEVICE_ID);
+    let publisher = Publisher::start(Arc::clone(&snapshots));
+
+    let mut sdk = AnalogSDK::new();
+    let plugin = BenchPlugin {
+        snapshots,
+        expose_snapshots,
+    };
+    assert_eq!(sdk.initialise_with_plugins(vec![Box::new(plugin)]).0, Ok(1));
+    (sdk, publisher)
+}
+
+/// Runs `iters` reads on each of `threads` threads at the same time, returning how long it took for all of them to finish
+fn run_threads<F>(threads: u64, iters: u64, read: Arc<F>) -> Duration
+where
+    F: Fn() -> f32 + Send + Sync + 'static,
+{
+    let barrier = Arc::new(Barrier::new(threads as usize + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let barrier = Arc::clone(&barrier);
+            let read = Arc::clone(&read);
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..iters {
+                    criterion::black_box(read());
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn concurrent_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_analog");
+
+    for &threads in THREADS.iter() {
+        //Every iteration is one read on each of the threads
+        group.throughput(Throughput::Elements(threads));
+
+        for &(name, expose_snapshots) in [("snapshot", true), ("plugin_lock", false)].iter() {
+            let (sdk, _publisher) = new_sdk(expose_snapshots);
+            let sdk = Arc::new(RwLock::new(sdk));
+            let read = Arc::new(move || sdk.read().unwrap().read_analog(0x1a, 0).0.unwrap());
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter_custom(|iters| run_threads(threads, iters, Arc::clone(&read)))
+            });
+        }
+
+        let (sdk, _publisher) = new_sdk(false);
+        let sdk = Arc::new(Mutex::new(sdk));
+        let read = Arc::new(move || sdk.lock().unwrap().read_analog(0x1a, 0).0.unwrap());
+        group.bench_with_input(
+            BenchmarkId::new("global_mutex", threads),
+            &threads,
+            |b, &threads| b.iter_custom(|iters| run_threads(threads, iters, Arc::clone(&read))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_read);
+criterion_main!(benches);
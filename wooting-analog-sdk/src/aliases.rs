@@ -0,0 +1,268 @@
+//! User given names for devices (e.g. "left keypad"), kept in a file so that they persist between runs of any app using the SDK.
+//!
+//! The file has one alias per line, as the device ID in hex followed by a space and the alias. Lines starting with '#' are ignored.
+use log::*;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use wooting_analog_common::*;
+
+/// Environment variable which can be set to use a different alias file than the one in the user's config directory
+pub const ALIASES_PATH_VAR: &str = "WOOTING_ANALOG_SDK_ALIASES";
+
+const ALIASES_FILE: &str = "device-aliases.txt";
+const CONFIG_DIR: &str = "wooting-analog-sdk";
+
+/// The max length of an alias in bytes
+pub const MAX_ALIAS_LEN: usize = 256;
+
+#[derive(Debug, Default)]
+pub struct DeviceAliases {
+    path: Option<PathBuf>,
+    //Only read from `path` once they're first needed, so the file isn't touched by apps which don't use aliases
+    aliases: OnceLock<BTreeMap<DeviceID, String>>,
+}
+
+impl DeviceAliases {
+    /// Gives the file aliases are kept in by default, which is `ALIASES_PATH_VAR` if it's set, otherwise a file in the user's
+    /// config directory
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(ALIASES_PATH_VAR) {
+            return Some(PathBuf::from(path));
+        }
+
+        #[cfg(target_os = "windows")]
+        let config_dir = env::var_os("APPDATA").map(PathBuf::from);
+        #[cfg(target_os = "macos")]
+        let config_dir =
+            env::var_os("HOME").map(|home| Path::new(&home).join("Library/Application Support"));
+        #[cfg(target_os = "linux")]
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+
+        config_dir.map(|dir| dir.join(CONFIG_DIR).join(ALIASES_FILE))
+    }
+
+    /// Uses the aliases kept in the file at `path`, which changes are then saved to. The file is read when the aliases are first
+    /// needed, if it doesn't exist yet there are no aliases, lines which can't be read are skipped
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        DeviceAliases {
+            path: Some(path.as_ref().to_owned()),
+            aliases: OnceLock::new(),
+        }
+    }
+
+    /// Uses the aliases from the `default_path`
+    pub fn load_default() -> Self {
+        match Self::default_path() {
+            Some(path) => Self::load(path),
+            None => {
+                warn!("Unable to find a config directory, device aliases won't be saved");
+                Self::default()
+            }
+        }
+    }
+
+    fn entries(&self) -> &BTreeMap<DeviceID, String> {
+        self.aliases.get_or_init(|| match &self.path {
+            Some(path) => read(path),
+            None => BTreeMap::new(),
+        })
+    }
+
+    pub fn get(&self, device_id: DeviceID) -> Option<&str> {
+        self.entries().get(&device_id).map(String::as_str)
+    }
+
+    /// Finds the device with the given alias, ignoring case
+    pub fn find(&self, alias: &str) -> Option<DeviceID> {
+        let alias = alias.trim().to_lowercase();
+        self.entries()
+            .iter()
+            .find(|(_, name)| name.to_lowercase() == alias)
+            .map(|(&id, _)| id)
+    }
+
+    /// Gives the device with ID `device_id` the given alias, or removes its alias if `alias` is `None`, and saves the change
+    ///
+    /// # Expected Returns
+    /// * `Ok`: The alias has been set & saved
+    /// * `InvalidArgument`: `device_id` is 0, or the alias is empty, too long, has a line break or is already used by another device
+    /// * `Failure`: The aliases couldn't be saved, in which case they're left unchanged
+    pub fn set(&mut self, device_id: DeviceID, alias: Option<&str>) -> SDKResult<()> {
+        if device_id == 0 {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        let mut aliases = self.entries().clone();
+        match alias.map(str::trim) {
+            Some(alias) => {
+                if alias.is_empty()
+                    || alias.len() > MAX_ALIAS_LEN
+                    || alias.contains(|c| c == '\n' || c == '\r')
+                    || self.find(alias).map_or(false, |id| id != device_id)
+                {
+                    return Err(WootingAnalogResult::InvalidArgument).into();
+                }
+                aliases.insert(device_id, alias.to_owned());
+            }
+            None => {
+                aliases.remove(&device_id);
+            }
+        }
+
+        if let Some(path) = &self.path {
+            if let Err(e) = save(path, &aliases) {
+                error!("Unable to save device aliases to {}: {}", path.display(), e);
+                return Err(WootingAnalogResult::Failure).into();
+            }
+        }
+        self.aliases = OnceLock::from(aliases);
+        Ok(()).into()
+    }
+}
+
+fn read(path: &Path) -> BTreeMap<DeviceID, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content, path),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => {
+            warn!("Unable to read device aliases {}: {}", path.display(), e);
+            BTreeMap::new()
+        }
+    }
+}
+
+fn parse(content: &str, path: &Path) -> BTreeMap<DeviceID, String> {
+    let mut aliases = BTreeMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = line.split_once(' ').and_then(|(id, alias)| {
+            let alias = alias.trim();
+            match DeviceID::from_str_radix(id, 16) {
+                Ok(id) if id != 0 && !alias.is_empty() => Some((id, alias.to_owned())),
+                _ => None,
+            }
+        });
+        match entry {
+            Some((id, alias)) => {
+                aliases.insert(id, alias);
+            }
+            None => warn!(
+                "Skipping invalid device alias on line {} of {}",
+                i + 1,
+                path.display()
+            ),
+        }
+    }
+    aliases
+}
+
+/// Writes the aliases to a temporary file which then replaces the old one, so the file is never left half written
+fn save(path: &Path, aliases: &BTreeMap<DeviceID, String>) -> io::Result<()> {
+    let mut content =
+        String::from("# Device aliases of the Wooting Analog SDK, as <device id> <alias>\n");
+    for (id, alias) in aliases {
+        content.push_str(&format!("{:016x} {}\n", id, alias));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    //The directory is removed once the `TempDir` is dropped
+    fn temp_path() -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_DIR).join(ALIASES_FILE);
+        (dir, path)
+    }
+
+    #[test]
+    fn aliases_persist() {
+        let (_dir, path) = temp_path();
+        let mut aliases = DeviceAliases::load(&path);
+        assert_eq!(aliases.get(1), None);
+        assert_eq!(aliases.set(1, Some(" left keypad ")).0, Ok(()));
+        assert_eq!(aliases.set(0xdead_beef_0000_0002, Some("Right")).0, Ok(()));
+        assert_eq!(aliases.get(1), Some("left keypad"));
+
+        let mut loaded = DeviceAliases::load(&path);
+        assert_eq!(loaded.get(1), Some("left keypad"));
+        assert_eq!(loaded.get(0xdead_beef_0000_0002), Some("Right"));
+        assert_eq!(loaded.find("LEFT KEYPAD"), Some(1));
+        assert_eq!(loaded.find("middle"), None);
+
+        assert_eq!(loaded.set(1, None).0, Ok(()));
+        let loaded = DeviceAliases::load(&path);
+        assert_eq!(loaded.get(1), None);
+        assert_eq!(loaded.get(0xdead_beef_0000_0002), Some("Right"));
+    }
+
+    #[test]
+    fn invalid_aliases() {
+        let (_dir, path) = temp_path();
+        let mut aliases = DeviceAliases::load(path);
+        assert_eq!(aliases.set(1, Some("Left")).0, Ok(()));
+        for alias in ["", "  ", "two\nlines", "left"].iter() {
+            assert_eq!(
+                aliases.set(2, Some(alias)).0,
+                Err(WootingAnalogResult::InvalidArgument),
+                "{:?}",
+                alias
+            );
+        }
+        assert_eq!(
+            aliases.set(2, Some(&"a".repeat(MAX_ALIAS_LEN + 1))).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(
+            aliases.set(0, Some("Zero")).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        //A device can be renamed to a different case of its own alias
+        assert_eq!(aliases.set(1, Some("LEFT")).0, Ok(()));
+        assert_eq!(aliases.get(2), None);
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() {
+        let (_dir, path) = temp_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "# comment\n00000000000000ff Keypad\nnot-hex Bad\n0000000000000003\n\n10 Ten\n",
+        )
+        .unwrap();
+        let aliases = DeviceAliases::load(&path);
+        assert_eq!(aliases.get(0xff), Some("Keypad"));
+        assert_eq!(aliases.get(0x10), Some("Ten"));
+        assert_eq!(aliases.get(3), None);
+        assert_eq!(aliases.entries().len(), 2);
+    }
+
+    #[test]
+    fn file_is_read_when_needed() {
+        let (_dir, path) = temp_path();
+        let aliases = DeviceAliases::load(&path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "0000000000000001 Left\n").unwrap();
+        assert_eq!(aliases.get(1), Some("Left"));
+    }
+}
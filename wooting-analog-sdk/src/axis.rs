@@ -0,0 +1,304 @@
+//! Virtual axes made up of analog keys, e.g. A & D giving a -1.0 to 1.0 axis for steering. Two axes can be paired up into a stick
+//! (e.g. WASD), in which case they're normalised together so that pressing diagonally isn't faster than pressing straight.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use wooting_analog_common::*;
+
+/// Which side of an axis was pressed last, for `AxisCombine::LastPressed`
+#[derive(Debug, Default)]
+struct PressState {
+    positive_down: bool,
+    negative_down: bool,
+    positive_last: bool,
+}
+
+#[derive(Debug)]
+struct Axis {
+    //Kept by HID code
+    definition: AxisDefinition,
+    partner: Option<String>,
+    state: Mutex<PressState>,
+}
+
+impl Axis {
+    /// Gives the value of the axis from the `analog` data (HID code -> analog value), before the deadzone
+    fn raw_value(&self, analog: &HashMap<u16, f32>) -> f32 {
+        let side = |keys: &[u16]| {
+            keys.iter()
+                .filter_map(|code| analog.get(code))
+                .fold(0.0f32, |max, &value| max.max(value))
+        };
+        let positive = side(&self.definition.positive);
+        let negative = side(&self.definition.negative);
+
+        match self.definition.combine {
+            AxisCombine::Difference => positive - negative,
+            AxisCombine::MaxWins if positive >= negative => positive,
+            AxisCombine::MaxWins => -negative,
+            AxisCombine::LastPressed => {
+                let mut state = self.state.lock().unwrap();
+                if positive > 0.0 && !state.positive_down {
+                    state.positive_last = true;
+                }
+                if negative > 0.0 && !state.negative_down {
+                    state.positive_last = false;
+                }
+                state.positive_down = positive > 0.0;
+                state.negative_down = negative > 0.0;
+
+                match (state.positive_down, state.negative_down) {
+                    (true, true) if state.positive_last => positive,
+                    (true, true) => -negative,
+                    _ => positive - negative,
+                }
+            }
+        }
+    }
+
+    /// Gives the value of the axis from the `analog` data, normalised together with `partner` if it's paired. The stick is normalised
+    /// before the deadzone of the axis is applied, so the deadzone is the same whichever way the stick is pushed
+    fn value(&self, partner: Option<&Axis>, analog: &HashMap<u16, f32>) -> f32 {
+        let raw = self.raw_value(analog);
+        let value = match partner {
+            Some(partner) => normalise_circular(raw, partner.raw_value(analog)).0,
+            None => raw,
+        };
+        apply_deadzone(value, self.definition.deadzone)
+    }
+}
+
+/// Gives 0 for values closer to 0 than `deadzone`, scaling the rest so that they still go up to 1
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        0.0
+    } else {
+        value.signum() * ((value.abs() - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+/// Scales `(x, y)` down onto the unit circle if it's outside of it
+fn normalise_circular(x: f32, y: f32) -> (f32, f32) {
+    let length = (x * x + y * y).sqrt();
+    if length > 1.0 {
+        (x / length, y / length)
+    } else {
+        (x, y)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Axes {
+    axes: HashMap<String, Axis>,
+}
+
+impl Axes {
+    /// Defines the axis called `name`, replacing any axis with the same name. The keys of `definition` are HID codes
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: `name` is empty or the deadzone isn't from 0 up to (but not including) 1
+    pub fn define(&mut self, name: &str, definition: AxisDefinition) -> SDKResult<()> {
+        if name.is_empty() || !(0.0..1.0).contains(&definition.deadzone) {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        //Redefining an axis keeps it paired
+        let partner = self.axes.get(name).and_then(|axis| axis.partner.clone());
+        self.axes.insert(
+            name.to_owned(),
+            Axis {
+                definition,
+                partner,
+                state: Default::default(),
+            },
+        );
+        Ok(()).into()
+    }
+
+    /// Removes the axis called `name`, unpairing it
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `name`
+    pub fn remove(&mut self, name: &str) -> SDKResult<()> {
+        match self.axes.remove(name) {
+            Some(axis) => {
+                self.unpair(axis.partner.as_deref());
+                Ok(()).into()
+            }
+            None => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+
+    /// Pairs up the axes `x` & `y` into a stick, so they're normalised together. Either of them is unpaired from the axis it was
+    /// paired with before
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: One of the axes doesn't exist, or they're the same axis
+    pub fn pair(&mut self, x: &str, y: &str) -> SDKResult<()> {
+        if x == y || !self.axes.contains_key(x) || !self.axes.contains_key(y) {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        for (name, partner) in [(x, y), (y, x)].iter() {
+            let old = self
+                .axes
+                .get_mut(*name)
+                .and_then(|axis| std::mem::replace(&mut axis.partner, Some((*partner).to_owned())));
+            if old.as_deref() != Some(*partner) {
+                self.unpair(old.as_deref());
+            }
+        }
+        Ok(()).into()
+    }
+
+    fn unpair(&mut self, name: Option<&str>) {
+        if let Some(axis) = name.and_then(|name| self.axes.get_mut(name)) {
+            axis.partner = None;
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.axes.contains_key(name)
+    }
+
+    fn get(&self, name: &str) -> Option<(&Axis, Option<&Axis>)> {
+        self.axes.get(name).map(|axis| {
+            let partner = axis
+                .partner
+                .as_ref()
+                .and_then(|partner| self.axes.get(partner));
+            (axis, partner)
+        })
+    }
+
+    /// Gives the value of the axis called `name` from the `analog` data (HID code -> analog value) read from the devices
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `name`
+    pub fn read(&self, name: &str, analog: &HashMap<u16, f32>) -> SDKResult<f32> {
+        match self.get(name) {
+            Some((axis, partner)) => Ok(axis.value(partner, analog)).into(),
+            None => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+
+    /// Gives the values of the axes called `x` & `y` from the same `analog` data, e.g. the two axes of a stick
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `x` or `y`
+    pub fn read_pair(&self, x: &str, y: &str, analog: &HashMap<u16, f32>) -> SDKResult<(f32, f32)> {
+        match (self.get(x), self.get(y)) {
+            (Some((x, x_partner)), Some((y, y_partner))) => {
+                Ok((x.value(x_partner, analog), y.value(y_partner, analog))).into()
+            }
+            _ => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u16 = 0x04;
+    const D: u16 = 0x07;
+    const S: u16 = 0x16;
+    const W: u16 = 0x1a;
+
+    fn axis(positive: u16, negative: u16, combine: AxisCombine, deadzone: f32) -> AxisDefinition {
+        AxisDefinition {
+            positive: vec![positive],
+            negative: vec![negative],
+            combine,
+            deadzone,
+        }
+    }
+
+    fn analog(keys: &[(u16, f32)]) -> HashMap<u16, f32> {
+        keys.iter().copied().collect()
+    }
+
+    fn read(axes: &Axes, name: &str, keys: &[(u16, f32)]) -> f32 {
+        axes.read(name, &analog(keys)).0.unwrap()
+    }
+
+    #[test]
+    fn combine_rules() {
+        let mut axes = Axes::default();
+        axes.define("diff", axis(D, A, AxisCombine::Difference, 0.0));
+        axes.define("max", axis(D, A, AxisCombine::MaxWins, 0.0));
+        axes.define("last", axis(D, A, AxisCombine::LastPressed, 0.0));
+
+        let both = [(D, 0.75), (A, 0.25)];
+        assert_eq!(read(&axes, "diff", &both), 0.5);
+        assert_eq!(read(&axes, "max", &both), 0.75);
+        assert_eq!(read(&axes, "max", &[(A, 0.5)]), -0.5);
+
+        assert_eq!(read(&axes, "last", &[(D, 0.75)]), 0.75);
+        //A was pressed after D, so it wins even though it isn't pressed as far
+        assert_eq!(read(&axes, "last", &both), -0.25);
+        assert_eq!(read(&axes, "last", &[(D, 0.75)]), 0.75);
+        assert_eq!(read(&axes, "last", &[]), 0.0);
+
+        assert_eq!(
+            axes.read("missing", &analog(&[])).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn deadzone_is_rescaled() {
+        let mut axes = Axes::default();
+        axes.define("x", axis(D, A, AxisCombine::Difference, 0.5));
+
+        assert_eq!(read(&axes, "x", &[(D, 0.25)]), 0.0);
+        assert_eq!(read(&axes, "x", &[(A, 0.75)]), -0.5);
+        assert_eq!(read(&axes, "x", &[(D, 1.0)]), 1.0);
+        assert_eq!(
+            axes.define("y", axis(W, S, AxisCombine::Difference, 1.0)).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn paired_axes_are_circular() {
+        let mut axes = Axes::default();
+        axes.define("x", axis(D, A, AxisCombine::Difference, 0.0));
+        axes.define("y", axis(W, S, AxisCombine::Difference, 0.0));
+        assert_eq!(axes.pair("x", "y").0, Ok(()));
+
+        let diagonal = [(D, 1.0), (W, 1.0)];
+        let x = read(&axes, "x", &diagonal);
+        let y = read(&axes, "y", &diagonal);
+        assert!((x - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(x, y);
+        //Inside of the circle nothing changes
+        assert_eq!(read(&axes, "x", &[(D, 0.5), (W, 0.5)]), 0.5);
+
+        assert_eq!(axes.read_pair("x", "y", &analog(&diagonal)).0, Ok((x, y)));
+
+        assert_eq!(axes.remove("y").0, Ok(()));
+        assert_eq!(read(&axes, "x", &diagonal), 1.0);
+        assert_eq!(
+            axes.read_pair("x", "y", &analog(&diagonal)).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn sticks_are_normalised_before_the_deadzone() {
+        let mut axes = Axes::default();
+        axes.define("x", axis(D, A, AxisCombine::Difference, 0.5));
+        axes.define("y", axis(W, S, AxisCombine::Difference, 0.5));
+        assert_eq!(axes.pair("x", "y").0, Ok(()));
+
+        //Fully diagonal is ~0.707 on each axis once normalised, which is then rescaled from the deadzone
+        let (x, y) = axes
+            .read_pair("x", "y", &analog(&[(D, 1.0), (W, 1.0)]))
+            .0
+            .unwrap();
+        let expected = (std::f32::consts::FRAC_1_SQRT_2 - 0.5) / 0.5;
+        assert!((x - expected).abs() < 1e-6);
+        assert_eq!(x, y);
+        assert_eq!(read(&axes, "x", &[(D, 1.0)]), 1.0);
+    }
+}
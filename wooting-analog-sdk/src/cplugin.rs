@@ -1,149 +1,311 @@
-use ffi_support::FfiStr;
-use libloading::{Library, Symbol};
+use crate::events::PluginEventCallback;
+use libloading::Library;
 use log::*;
 use std::collections::HashMap;
 use std::ffi::CStr;
-use std::os::raw::{c_float, c_int, c_uint, c_ushort, c_void};
+use std::os::raw::{c_char, c_float, c_int, c_uint, c_ushort, c_void};
+use std::ptr;
 use wooting_analog_common::*;
 use wooting_analog_plugin_dev::*;
 
-macro_rules! lib_wrap {
-    //(@as_item $i:item) => {$i};
-
-    (
-        $(
-            fn $fn_names:ident($($fn_arg_names:ident: $fn_arg_tys:ty),*) $(-> $fn_ret_tys:ty)*;
-        )*
-    ) => {
-        $(
-            //lib_wrap! {
-            //    @as_item
-                #[no_mangle]
-                fn $fn_names(&mut self, $($fn_arg_names: $fn_arg_tys),*) $(-> $fn_ret_tys)* {
-                    unsafe {
-                        type FnPtr = unsafe fn($($fn_arg_tys),*) $(-> $fn_ret_tys)*;
-                        //TODO: Retain the obtained function pointer between calls
-                        let func :  Option<Symbol<FnPtr>>  = self.lib.get(stringify!($fn_names).as_bytes()).map_err(|e| {
-                                    error!("{}", e);
-                                }).ok();
-                        match func {
-                            Some(f) => f($($fn_arg_names),*).into(),
-                            _ => Default::default()
-
-                        }
-                    }
-                }
-            //}
-        )*
-    };
+/// Version of the `CPluginDescriptor` layout, which is the first field of the descriptor
+const CPLUGIN_ABI_VERSION: u32 = 1;
+
+/// The version of plugins which export each of their functions on their own, rather than through a descriptor
+const CPLUGIN_ABI_VERSION_V0: u32 = 0;
+
+/// The number of keys read through `read_full_buffer` when a plugin doesn't have `read_analog`
+const READ_ANALOG_FALLBACK_LEN: c_uint = 256;
+
+type DeviceEventFn = unsafe extern "C" fn(*mut c_void, c_int, *const DeviceInfoEx_FFI);
+type DeviceEventFnV0 = extern "C" fn(*mut c_void, DeviceEventType, *mut DeviceInfo);
+type NameFn = unsafe extern "C" fn() -> *const c_char;
+type IsInitialisedFn = unsafe extern "C" fn() -> bool;
+type UnloadFn = unsafe extern "C" fn();
+type ReadFullBufferFn =
+    unsafe extern "C" fn(*mut c_ushort, *mut c_float, c_uint, DeviceID) -> c_int;
+type ReadAnalogFn = unsafe extern "C" fn(c_ushort, DeviceID) -> c_float;
+
+/// The functions of a plugin, as given by `wooting_analog_plugin_descriptor` (see `includes/plugin.h`). Functions which aren't
+/// provided are null
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CPluginDescriptor {
+    abi_version: u32,
+    /// Size of the descriptor the plugin was built with, so that functions can be added to the end of it without a new version
+    size: u32,
+    name: Option<NameFn>,
+    initialise: Option<unsafe extern "C" fn(*mut c_void, DeviceEventFn) -> c_int>,
+    is_initialised: Option<IsInitialisedFn>,
+    unload: Option<UnloadFn>,
+    read_full_buffer: Option<ReadFullBufferFn>,
+    device_info: Option<unsafe extern "C" fn(*mut *const DeviceInfoEx_FFI, c_uint) -> c_int>,
+    read_analog: Option<ReadAnalogFn>,
 }
 
-macro_rules! lib_wrap_option {
-    //(@as_item $i:item) => {$i};
-
-    (
-        $(
-            fn $fn_names:ident($($fn_arg_names:ident: $fn_arg_tys:ty),*) $(-> $fn_ret_tys:ty)*;
-        )*
-    ) => {
-        $(
-            //lib_wrap! {
-            //    @as_item
-                #[no_mangle]
-                fn $fn_names(&mut self, $($fn_arg_names: $fn_arg_tys),*) $(-> SDKResult<$fn_ret_tys>)* {
-                    unsafe {
-                        type FnPtr = unsafe fn($($fn_arg_tys),*) $(-> $fn_ret_tys)*;
-                        let func :Option<Symbol<FnPtr>>  = self.lib.get(stringify!($fn_names).as_bytes()).map_err(|e| {
-                                    error!("{}", e);
-                                }).ok();
-                        match func {
-                            Some(f) => f($($fn_arg_names),*).into(),
-                            _ => Err(WootingAnalogResult::FunctionNotFound).into()
-
-                        }
-                    }
-                }
-            //}
-        )*
-    };
+/// The functions of a v0 plugin, which are looked up once when it's loaded
+#[derive(Clone, Copy)]
+struct FunctionsV0 {
+    name: Option<NameFn>,
+    initialise: Option<unsafe extern "C" fn(*mut c_void, DeviceEventFnV0) -> c_int>,
+    is_initialised: Option<IsInitialisedFn>,
+    unload: Option<UnloadFn>,
+    read_full_buffer: Option<ReadFullBufferFn>,
+    device_info: Option<unsafe extern "C" fn(*mut *mut DeviceInfo, c_uint) -> c_int>,
+    read_analog: Option<ReadAnalogFn>,
 }
 
-const CPLUGIN_ABI_VERSION: u32 = 0;
+enum Functions {
+    V0(FunctionsV0),
+    V1(CPluginDescriptor),
+}
 
 pub struct CPlugin {
-    lib: Library,
-    //funcs: HashMap<&'static str, Option<Symbol>>
+    functions: Functions,
+    //The callback given to the plugin in `initialise`, which is freed along with the plugin
+    callback: *mut PluginEventCallback,
+    //Kept last so the library is only unloaded once the plugin is done with
+    _lib: Library,
 }
 
 impl CPlugin {
+    /// Loads the C plugin in `lib`, preferring the descriptor of v1 plugins over the separately exported functions of v0 plugins
+    ///
+    /// # Expected Returns
+    /// * `FunctionNotFound`: `lib` isn't a C plugin, or doesn't provide all the functions which are required
+    /// * `IncompatibleVersion`: The plugin was built for a version of the ABI the SDK doesn't know
     pub fn new(lib: Library) -> SDKResult<CPlugin> {
-        unsafe {
-            if let Some(ver) = lib.get::<*mut u32>(b"ANALOG_SDK_PLUGIN_ABI_VERSION").ok() {
-                let v = **ver;
-                info!("Got cplugin abi: {:?}", v);
-                if v != CPLUGIN_ABI_VERSION {
-                    error!(
-                        "CPlugin ABI version does not match! Given: {}, Expected: {}",
-                        v, CPLUGIN_ABI_VERSION
-                    );
-                    return Err(WootingAnalogResult::IncompatibleVersion).into();
+        let functions = unsafe {
+            type GetDescriptor = unsafe extern "C" fn() -> *const CPluginDescriptor;
+
+            if let Ok(get_descriptor) =
+                lib.get::<GetDescriptor>(b"wooting_analog_plugin_descriptor")
+            {
+                match read_descriptor(get_descriptor()).0 {
+                    Ok(descriptor) => Functions::V1(descriptor),
+                    Err(e) => return Err(e).into(),
+                }
+            } else {
+                if let Ok(ver) = lib.get::<*const u32>(b"ANALOG_SDK_PLUGIN_ABI_VERSION") {
+                    let v = **ver;
+                    info!("Got cplugin abi: {:?}", v);
+                    if v != CPLUGIN_ABI_VERSION_V0 {
+                        error!(
+                            "CPlugin ABI version does not match! Given: {}, Expected: {}",
+                            v, CPLUGIN_ABI_VERSION_V0
+                        );
+                        return Err(WootingAnalogResult::IncompatibleVersion).into();
+                    }
+                }
+
+                let functions = FunctionsV0 {
+                    name: lib.get(b"_name").map(|f| *f).ok(),
+                    initialise: lib.get(b"_initialise").map(|f| *f).ok(),
+                    is_initialised: lib.get(b"is_initialised").map(|f| *f).ok(),
+                    unload: lib.get(b"unload").map(|f| *f).ok(),
+                    read_full_buffer: lib.get(b"_read_full_buffer").map(|f| *f).ok(),
+                    device_info: lib.get(b"_device_info").map(|f| *f).ok(),
+                    read_analog: lib.get(b"read_analog").map(|f| *f).ok(),
+                };
+                if functions.name.is_none() {
+                    return Err(WootingAnalogResult::FunctionNotFound).into();
                 }
+                warn!("Plugin uses the v0 C plugin ABI, which is deprecated. It should be updated to export wooting_analog_plugin_descriptor");
+                Functions::V0(functions)
             }
-        }
+        };
 
         Ok(CPlugin {
-            lib,
-            //funcs: HashMap::new()
+            functions,
+            callback: ptr::null_mut(),
+            _lib: lib,
         })
         .into()
     }
 
-    lib_wrap_option! {
-        //c_name has to be over here due to it not being part of the Plugin trait
-        fn _initialise(data: *mut c_void, callback: extern "C" fn(*mut c_void, DeviceEventType, *mut DeviceInfo)) -> i32;
-        fn _name() -> FfiStr<'static>;
+    fn name_fn(&self) -> Option<NameFn> {
+        match &self.functions {
+            Functions::V0(f) => f.name,
+            Functions::V1(f) => f.name,
+        }
+    }
 
-        fn _read_full_buffer(code_buffer: *const c_ushort, analog_buffer: *const c_float, len: c_uint, device: DeviceID) -> c_int;
-        fn _device_info(buffer: *mut *mut DeviceInfo_FFI, len: c_uint) -> c_int;
+    fn read_full_buffer_fn(&self) -> Option<ReadFullBufferFn> {
+        match &self.functions {
+            Functions::V0(f) => f.read_full_buffer,
+            Functions::V1(f) => f.read_full_buffer,
+        }
     }
-}
 
-extern "C" fn call_closure(data: *mut c_void, event: DeviceEventType, device_raw: *mut DeviceInfo) {
-    debug!("Got into the callclosure");
-    unsafe {
-        if data.is_null() {
-            error!("We got a null data pointer in call_closure!");
-            return;
+    fn read_full_buffer_raw(
+        &self,
+        code_buffer: &mut [c_ushort],
+        analog_buffer: &mut [c_float],
+        device: DeviceID,
+    ) -> SDKResult<u32> {
+        match self.read_full_buffer_fn() {
+            Some(f) => {
+                let len = code_buffer.len().min(analog_buffer.len());
+                let ret: SDKResult<u32> = unsafe {
+                    f(
+                        code_buffer.as_mut_ptr(),
+                        analog_buffer.as_mut_ptr(),
+                        len as c_uint,
+                        device,
+                    )
+                }
+                .into();
+                ret.0.map(|count| count.min(len as u32)).into()
+            }
+            None => Err(WootingAnalogResult::FunctionNotFound).into(),
         }
+    }
+}
 
-        let device = Box::from_raw(device_raw);
+/// Copies the descriptor at `descriptor`, leaving out any functions beyond its `size`, and checks it has the required functions
+unsafe fn read_descriptor(descriptor: *const CPluginDescriptor) -> SDKResult<CPluginDescriptor> {
+    if descriptor.is_null() {
+        error!("C plugin gave a null descriptor");
+        return Err(WootingAnalogResult::Failure).into();
+    }
 
-        let callback_ptr =
-            Box::from_raw(data as *mut Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>);
+    let abi_version = ptr::read_unaligned(descriptor as *const u32);
+    info!("Got cplugin abi: {:?}", abi_version);
+    if abi_version != CPLUGIN_ABI_VERSION {
+        error!(
+            "CPlugin ABI version does not match! Given: {}, Expected: {}",
+            abi_version, CPLUGIN_ABI_VERSION
+        );
+        return Err(WootingAnalogResult::IncompatibleVersion).into();
+    }
 
-        (*callback_ptr)(event, &device);
-        //Throw it back into raw to prevent it being dropped so the callback can be called multiple times
-        Box::into_raw(callback_ptr);
-        //We also want to convert this back to a pointer as we want the C Plugin to be in control and aware of
-        //when this memory is being dropped
-        Box::into_raw(device);
+    let mut copy = CPluginDescriptor {
+        abi_version,
+        size: 0,
+        name: None,
+        initialise: None,
+        is_initialised: None,
+        unload: None,
+        read_full_buffer: None,
+        device_info: None,
+        read_analog: None,
+    };
+    let size = ptr::read_unaligned((descriptor as *const u32).add(1)) as usize;
+    ptr::copy_nonoverlapping(
+        descriptor as *const u8,
+        &mut copy as *mut _ as *mut u8,
+        size.min(std::mem::size_of::<CPluginDescriptor>()),
+    );
+
+    if copy.name.is_none()
+        || copy.initialise.is_none()
+        || copy.is_initialised.is_none()
+        || copy.read_full_buffer.is_none()
+        || copy.device_info.is_none()
+    {
+        error!("C plugin descriptor is missing required functions");
+        return Err(WootingAnalogResult::FunctionNotFound).into();
+    }
+    Ok(copy).into()
+}
+
+unsafe fn callback_from(data: *mut c_void) -> Option<&'static PluginEventCallback> {
+    let callback = (data as *const PluginEventCallback).as_ref();
+    if callback.is_none() {
+        error!("We got a null data pointer from the C plugin!");
+    }
+    callback
+}
+
+unsafe extern "C" fn call_closure(
+    data: *mut c_void,
+    event: c_int,
+    device: *const DeviceInfoEx_FFI,
+) {
+    if let Some(callback) = callback_from(data) {
+        match (
+            DeviceEventType::from_i32(event),
+            DeviceInfoEx_FFI::read_versioned(device),
+        ) {
+            (Some(event), Some(device)) => callback(event, &device.into_device_info()),
+            _ => warn!("Ignoring invalid device event {} from C plugin", event),
+        }
+    }
+}
+
+extern "C" fn call_closure_v0(data: *mut c_void, event: DeviceEventType, device: *mut DeviceInfo) {
+    //The C Plugin is in control of the device's memory, so it's only borrowed here
+    unsafe {
+        if let (Some(callback), Some(device)) = (callback_from(data), device.as_ref()) {
+            callback(event, device);
+        }
+    }
+}
+
+impl Drop for CPlugin {
+    fn drop(&mut self) {
+        if !self.callback.is_null() {
+            drop(unsafe { Box::from_raw(self.callback) });
+        }
     }
 }
 
 impl Plugin for CPlugin {
     fn name(&mut self) -> SDKResult<&'static str> {
-        self._name().0.map(|s| s.as_str()).into()
+        let name = match self.name_fn() {
+            Some(f) => unsafe { f() },
+            None => return Err(WootingAnalogResult::FunctionNotFound).into(),
+        };
+        if name.is_null() {
+            return Err(WootingAnalogResult::Failure).into();
+        }
+        //The name is in the plugin's library, which is kept loaded for as long as the plugin is around
+        match unsafe { CStr::from_ptr(name) }.to_str() {
+            Ok(name) => Ok(name).into(),
+            Err(_) => Err(WootingAnalogResult::Failure).into(),
+        }
     }
 
-    fn initialise(
-        &mut self,
-        callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
-    ) -> SDKResult<u32> {
-        let data = Box::into_raw(Box::new(callback));
-        self._initialise(data as *mut _, call_closure)
-            .0
-            .map(|res| res as u32)
-            .into()
+    fn initialise(&mut self, callback: PluginEventCallback) -> SDKResult<u32> {
+        //If the plugin is initialised again it has to stop using the earlier callback before that's freed
+        if !self.callback.is_null() {
+            self.unload();
+            drop(unsafe { Box::from_raw(self.callback) });
+        }
+        self.callback = Box::into_raw(Box::new(callback));
+        let data = self.callback as *mut c_void;
+        unsafe {
+            match &self.functions {
+                Functions::V0(FunctionsV0 {
+                    initialise: Some(f),
+                    ..
+                }) => f(data, call_closure_v0),
+                Functions::V1(CPluginDescriptor {
+                    initialise: Some(f),
+                    ..
+                }) => f(data, call_closure),
+                _ => WootingAnalogResult::FunctionNotFound.into(),
+            }
+        }
+        .into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        let f = match &self.functions {
+            Functions::V0(f) => f.is_initialised,
+            Functions::V1(f) => f.is_initialised,
+        };
+        f.map_or(false, |f| unsafe { f() })
+    }
+
+    fn unload(&mut self) {
+        let f = match &self.functions {
+            Functions::V0(f) => f.unload,
+            Functions::V1(f) => f.unload,
+        };
+        if let Some(f) = f {
+            unsafe { f() };
+        }
     }
 
     fn read_full_buffer(
@@ -151,71 +313,93 @@ impl Plugin for CPlugin {
         max_length: usize,
         device: DeviceID,
     ) -> SDKResult<HashMap<c_ushort, c_float>> {
-        let mut code_buffer: Vec<c_ushort> = Vec::with_capacity(max_length);
-        let mut analog_buffer: Vec<c_float> = Vec::with_capacity(max_length);
-        code_buffer.resize(max_length, 0);
-        analog_buffer.resize(max_length, 0.0);
-        let count: usize = {
-            let ret = self
-                ._read_full_buffer(
-                    code_buffer.as_ptr(),
-                    analog_buffer.as_ptr(),
-                    max_length as c_uint,
-                    device,
-                )
-                .0;
-            if let Err(e) = ret {
-                //debug!("Error got: {:?}",e);
-                return Err(e).into();
-            }
-            let ret = ret.unwrap();
-            max_length.min(ret as usize)
-        };
-
-        let mut analog_data: HashMap<c_ushort, c_float> = HashMap::with_capacity(count);
-        //println!("Count was {}", count);
-        for i in 0..count {
-            analog_data.insert(code_buffer[i], analog_buffer[i]);
-        }
-
-        Ok(analog_data).into()
+        let mut code_buffer: Vec<c_ushort> = vec![0; max_length];
+        let mut analog_buffer: Vec<c_float> = vec![0.0; max_length];
+        self.read_full_buffer_raw(&mut code_buffer, &mut analog_buffer, device)
+            .0
+            .map(|count| {
+                code_buffer
+                    .into_iter()
+                    .zip(analog_buffer)
+                    .take(count as usize)
+                    .collect()
+            })
+            .into()
     }
 
     fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
-        let mut device_infos: Vec<*mut DeviceInfo_FFI> = vec![std::ptr::null_mut(); 10];
+        match &self.functions {
+            Functions::V0(FunctionsV0 {
+                device_info: Some(f),
+                ..
+            }) => {
+                //v0 plugins can't be asked how many devices they have
+                let mut buffer: Vec<*mut DeviceInfo> = vec![ptr::null_mut(); 10];
+                let ret: SDKResult<u32> =
+                    unsafe { f(buffer.as_mut_ptr(), buffer.len() as c_uint) }.into();
+                ret.0
+                    .map(|num| {
+                        buffer
+                            .iter()
+                            .take(num as usize)
+                            //The structs were created by the plugin with `new_device_info`, which it keeps ownership of
+                            .filter_map(|&device| unsafe { device.as_ref() }.cloned())
+                            .collect()
+                    })
+                    .into()
+            }
+            Functions::V1(CPluginDescriptor {
+                device_info: Some(f),
+                ..
+            }) => {
+                let count: SDKResult<u32> = unsafe { f(ptr::null_mut(), 0) }.into();
+                let count = match count.0 {
+                    Ok(count) => count,
+                    Err(e) => return Err(e).into(),
+                };
 
-        match self
-            ._device_info(device_infos.as_mut_ptr(), device_infos.len() as c_uint)
-            .0
-            .map(|no| no as u32)
-        {
-            Ok(num) => unsafe {
-                device_infos.truncate(num as usize);
-                let devices = device_infos
-                    .drain(..)
-                    .map(|dev| {
-                        DeviceInfo {
-                            vendor_id: (*dev).vendor_id,
-                            product_id: (*dev).product_id,
-                            manufacturer_name: CStr::from_ptr((*dev).manufacturer_name).to_str().unwrap().to_owned(),
-                            device_name: CStr::from_ptr((*dev).device_name).to_str().unwrap().to_owned(),
-                            device_id: (*dev).device_id,
-                            device_type: (*dev).device_type.clone(),
-                        }
+                let mut buffer: Vec<*const DeviceInfoEx_FFI> = vec![ptr::null(); count as usize];
+                let ret: SDKResult<u32> = unsafe { f(buffer.as_mut_ptr(), count) }.into();
+                ret.0
+                    .map(|num| {
+                        buffer
+                            .iter()
+                            .take(num as usize)
+                            .filter_map(|&device| unsafe {
+                                DeviceInfoEx_FFI::read_versioned(device)
+                            })
+                            .map(|device| device.into_device_info())
+                            .collect()
                     })
-                    .collect();
-                Ok(devices).into()
-            },
-            Err(e) => Err(e).into(),
+                    .into()
+            }
+            _ => Err(WootingAnalogResult::FunctionNotFound).into(),
         }
     }
 
-    lib_wrap! {
-        fn is_initialised() -> bool;
-        fn unload();
-    }
-    lib_wrap_option! {
-        fn read_analog(code: u16, device: DeviceID) -> f32;
-        //fn neg(x: u32, y: u32) -> u32;
+    fn read_analog(&mut self, code: u16, device: DeviceID) -> SDKResult<f32> {
+        let f = match &self.functions {
+            Functions::V0(f) => f.read_analog,
+            Functions::V1(f) => f.read_analog,
+        };
+        match f {
+            Some(f) => unsafe { f(code, device) }.into(),
+            //Only pressed keys are given by read_full_buffer, so if the key isn't there it isn't pressed
+            None => {
+                let mut codes = [0; READ_ANALOG_FALLBACK_LEN as usize];
+                let mut analog = [0.0; READ_ANALOG_FALLBACK_LEN as usize];
+                self.read_full_buffer_raw(&mut codes, &mut analog, device)
+                    .0
+                    .map(|count| {
+                        codes
+                            .iter()
+                            .zip(analog.iter())
+                            .take(count as usize)
+                            .find(|(c, _)| **c == code)
+                            .map_or(0.0, |(_, value)| *value)
+                    })
+                    .into()
+            }
+        }
     }
 }
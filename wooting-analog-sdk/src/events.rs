@@ -0,0 +1,343 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use wooting_analog_common::*;
+
+/// The max number of events kept around for `poll`, once this is reached the oldest event is dropped for every new one
+pub const MAX_QUEUED_EVENTS: usize = 64;
+
+pub type DeviceEventCallback = Box<dyn Fn(DeviceEventType, DeviceInfo) + Send>;
+
+/// The callback plugins are initialised with, which they call with the events of their devices
+pub type PluginEventCallback = Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>;
+
+/// The registered device event listeners, in the order they were added. Each listener has its own lock so that the list doesn't
+/// need to be held while calling them, which allows listeners to add or remove listeners from inside their callback
+pub type DeviceEventListeners = Arc<Mutex<Vec<(ListenerID, Arc<DeviceEventListener>)>>>;
+
+thread_local! {
+    //Set on the dispatcher thread, which is the only thread listeners are called on
+    static DISPATCHING: Cell<bool> = Cell::new(false);
+}
+
+/// A device event listener, which can be removed while the dispatcher is holding on to it
+pub struct DeviceEventListener {
+    //Held while the listener is being called
+    callback: Mutex<DeviceEventCallback>,
+    removed: AtomicBool,
+}
+
+impl DeviceEventListener {
+    pub fn new(callback: DeviceEventCallback) -> Arc<Self> {
+        Arc::new(DeviceEventListener {
+            callback: Mutex::new(callback),
+            removed: AtomicBool::new(false),
+        })
+    }
+
+    fn call(&self, event: DeviceEventType, device: DeviceInfo) {
+        let callback = self.callback.lock().unwrap();
+        if !self.removed.load(Ordering::SeqCst) {
+            callback(event, device);
+        }
+    }
+
+    /// Stops the listener from being called, though it may still be in the middle of being called
+    pub fn stop(&self) {
+        self.removed.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits for the stopped listener to return if it's being called right now, so once this returns whatever it uses can be
+    /// freed. This doesn't wait when called from inside a listener, as nothing else can be calling listeners at the same time.
+    /// The SDK shouldn't be held while waiting, as the listener could be waiting on it
+    pub fn wait(&self) {
+        if !DISPATCHING.with(Cell::get) {
+            drop(self.callback.lock());
+        }
+    }
+}
+
+enum DispatchMessage {
+    Event(DeviceEventType, DeviceInfo),
+    Shutdown,
+}
+
+/// Hands the device events of all plugins to a single thread, which delivers them to the callback and the poll queue in the order
+/// they were received. This ensures consumers never see e.g. a device disconnect before the matching connect.
+pub struct EventDispatcher {
+    sender: Mutex<Sender<DispatchMessage>>,
+    queue: Arc<Mutex<VecDeque<(DeviceEventType, DeviceInfo)>>>,
+    //Set when the app polls, so that apps which only use listeners aren't warned about the queue they never look at
+    polled: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventDispatcher {
+    /// Starts the dispatcher thread, which calls each of the `listeners` for every event
+    pub fn start(listeners: DeviceEventListeners) -> Self {
+        let (sender, receiver) = channel();
+        let queue: Arc<Mutex<VecDeque<(DeviceEventType, DeviceInfo)>>> =
+            Arc::new(Mutex::new(VecDeque::with_capacity(MAX_QUEUED_EVENTS)));
+        let t_queue = Arc::clone(&queue);
+        let polled = Arc::new(AtomicBool::new(false));
+        let t_polled = Arc::clone(&polled);
+
+        let thread = thread::spawn(move || {
+            DISPATCHING.with(|dispatching| dispatching.set(true));
+            debug!("Device event dispatcher running");
+            while let Ok(DispatchMessage::Event(event, device)) = receiver.recv() {
+                {
+                    let mut queue = t_queue.lock().unwrap();
+                    if queue.len() >= MAX_QUEUED_EVENTS {
+                        //Only warned about once for every time the app polls, as this goes on for each event until it does
+                        if t_polled.swap(false, Ordering::Relaxed) {
+                            warn!("Device event queue is full, dropping the oldest events");
+                        }
+                        queue.pop_front();
+                    }
+                    queue.push_back((event.clone(), device.clone()));
+                }
+
+                let callbacks: Vec<Arc<DeviceEventListener>> = listeners
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(_, cb)| Arc::clone(cb))
+                    .collect();
+                for cb in callbacks {
+                    cb.call(event.clone(), device.clone());
+                }
+            }
+            debug!("Device event dispatcher stopped");
+        });
+
+        EventDispatcher {
+            sender: Mutex::new(sender),
+            queue,
+            polled,
+            thread: Some(thread),
+        }
+    }
+
+    /// Gives a callback which queues the events it is called with, to be given to a plugin
+    pub fn plugin_callback(&self) -> PluginEventCallback {
+        let sender = self.sender.lock().unwrap().clone();
+        Box::new(move |event: DeviceEventType, device: &DeviceInfo| {
+            //This only fails if the dispatcher has been stopped, in which case no one is interested in the event anymore
+            if sender
+                .send(DispatchMessage::Event(event, device.clone()))
+                .is_err()
+            {
+                debug!("Dropping device event as the dispatcher has been stopped");
+            }
+        })
+    }
+
+    /// Takes the oldest event which has been dispatched
+    pub fn poll(&self) -> Option<(DeviceEventType, DeviceInfo)> {
+        self.polled.store(true, Ordering::Relaxed);
+        self.queue.lock().unwrap().pop_front()
+    }
+
+    /// Stops the dispatcher thread once it has finished with the events that came before. This doesn't wait for the thread, as the
+    /// callback may be waiting on the SDK, which the caller could be holding on to
+    pub fn stop(&mut self) {
+        let _ = self.sender.lock().unwrap().send(DispatchMessage::Shutdown);
+        self.thread.take();
+    }
+}
+
+impl Drop for EventDispatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn device(device_id: DeviceID) -> DeviceInfo {
+        DeviceInfo::new_with_id(
+            0x03eb,
+            0xff02,
+            "Wooting".to_owned(),
+            "Test Device".to_owned(),
+            device_id,
+            DeviceType::Keyboard,
+        )
+    }
+
+    fn wait_for<F: Fn() -> bool>(f: F) {
+        for _ in 0..100 {
+            if f() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Timed out waiting for the dispatcher");
+    }
+
+    #[test]
+    fn events_are_delivered_in_order() {
+        let received: Arc<Mutex<Vec<(DeviceEventType, DeviceID, thread::ThreadId)>>> =
+            Arc::new(Mutex::new(vec![]));
+        let t_received = Arc::clone(&received);
+        let callback = Box::new(move |event, device: DeviceInfo| {
+            t_received
+                .lock()
+                .unwrap()
+                .push((event, device.device_id, thread::current().id()));
+        });
+
+        let mut dispatcher = EventDispatcher::start(Arc::new(Mutex::new(vec![(
+            1,
+            DeviceEventListener::new(callback),
+        )])));
+        let plugin_cb = dispatcher.plugin_callback();
+        for id in 1..=10 {
+            plugin_cb(DeviceEventType::Connected, &device(id));
+            plugin_cb(DeviceEventType::Disconnected, &device(id));
+        }
+
+        wait_for(|| received.lock().unwrap().len() == 20);
+        let received = received.lock().unwrap();
+        for (i, (event, id, thread)) in received.iter().enumerate() {
+            assert_eq!(*id, i as DeviceID / 2 + 1);
+            if i % 2 == 0 {
+                assert_eq!(*event, DeviceEventType::Connected);
+            } else {
+                assert_eq!(*event, DeviceEventType::Disconnected);
+            }
+            //Everything should come from the same dispatcher thread
+            assert_eq!(*thread, received[0].2);
+            assert_ne!(*thread, thread::current().id());
+        }
+
+        for id in 1..=10 {
+            assert_eq!(
+                dispatcher
+                    .poll()
+                    .map(|(event, device)| (event, device.device_id)),
+                Some((DeviceEventType::Connected, id))
+            );
+            assert_eq!(
+                dispatcher
+                    .poll()
+                    .map(|(event, device)| (event, device.device_id)),
+                Some((DeviceEventType::Disconnected, id))
+            );
+        }
+        assert!(dispatcher.poll().is_none());
+
+        dispatcher.stop();
+    }
+
+    #[test]
+    fn poll_queue_is_bounded() {
+        let dispatcher = EventDispatcher::start(Default::default());
+        let plugin_cb = dispatcher.plugin_callback();
+        let total = MAX_QUEUED_EVENTS as DeviceID + 5;
+        for id in 1..=total {
+            plugin_cb(DeviceEventType::Connected, &device(id));
+        }
+        plugin_cb(DeviceEventType::Disconnected, &device(total));
+
+        //Once the last event has made it through, the oldest ones should have been dropped
+        wait_for(|| {
+            dispatcher
+                .queue
+                .lock()
+                .unwrap()
+                .back()
+                .map(|(event, _)| event.clone())
+                == Some(DeviceEventType::Disconnected)
+        });
+        assert_eq!(dispatcher.queue.lock().unwrap().len(), MAX_QUEUED_EVENTS);
+        assert_eq!(
+            dispatcher.poll().map(|(_, device)| device.device_id),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn every_listener_is_called() {
+        let listeners: DeviceEventListeners = Default::default();
+        let calls: Arc<Mutex<Vec<ListenerID>>> = Arc::new(Mutex::new(vec![]));
+        for id in 1..=2 {
+            let t_calls = Arc::clone(&calls);
+            let t_listeners = Arc::clone(&listeners);
+            let callback = Box::new(move |_, _: DeviceInfo| {
+                t_calls.lock().unwrap().push(id);
+                //Listeners should be able to remove themselves from inside their callback
+                let mut listeners = t_listeners.lock().unwrap();
+                let index = listeners
+                    .iter()
+                    .position(|(listener, _)| *listener == id)
+                    .unwrap();
+                let (_, listener) = listeners.remove(index);
+                drop(listeners);
+                listener.stop();
+                listener.wait();
+            });
+            listeners
+                .lock()
+                .unwrap()
+                .push((id, DeviceEventListener::new(callback)));
+        }
+
+        let dispatcher = EventDispatcher::start(Arc::clone(&listeners));
+        let plugin_cb = dispatcher.plugin_callback();
+        plugin_cb(DeviceEventType::Connected, &device(1));
+        wait_for(|| calls.lock().unwrap().len() == 2);
+        assert_eq!(*calls.lock().unwrap(), vec![1, 2]);
+        assert!(listeners.lock().unwrap().is_empty());
+
+        //With both listeners gone, further events should only end up in the poll queue
+        plugin_cb(DeviceEventType::Disconnected, &device(1));
+        wait_for(|| dispatcher.queue.lock().unwrap().len() == 2);
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn removal_waits_for_listener() {
+        let (started, t_started) = channel();
+        let (t_release, release) = channel::<()>();
+        let release = Mutex::new(release);
+        let done = Arc::new(AtomicBool::new(false));
+        let t_done = Arc::clone(&done);
+        let listener = DeviceEventListener::new(Box::new(move |_, _| {
+            started.send(()).unwrap();
+            release.lock().unwrap().recv().unwrap();
+            t_done.store(true, Ordering::SeqCst);
+        }));
+
+        let dispatcher =
+            EventDispatcher::start(Arc::new(Mutex::new(vec![(1, Arc::clone(&listener))])));
+        let plugin_cb = dispatcher.plugin_callback();
+        plugin_cb(DeviceEventType::Connected, &device(1));
+        t_started.recv().unwrap();
+
+        //The listener is stuck in its callback, so removing it has to wait for it to return
+        let remover = {
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                listener.stop();
+                listener.wait();
+                done.load(Ordering::SeqCst)
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        t_release.send(()).unwrap();
+        assert!(remover.join().unwrap());
+
+        //Once removed it isn't called again
+        plugin_cb(DeviceEventType::Disconnected, &device(1));
+        wait_for(|| dispatcher.queue.lock().unwrap().len() == 2);
+        assert!(t_started.try_recv().is_err());
+    }
+}
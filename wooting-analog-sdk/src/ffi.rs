@@ -1,13 +1,19 @@
+use crate::mouse::*;
 use crate::sdk::*;
+use ffi_support::FfiStr;
 use std::cell::RefCell;
-use std::os::raw::{c_float, c_int, c_uint, c_ushort};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_float, c_int, c_uint, c_ushort, c_void};
+use std::sync::{Mutex, RwLock};
 use std::{panic, slice};
 use wooting_analog_common::FromPrimitive;
 use wooting_analog_common::*;
 
 lazy_static! {
-    pub static ref ANALOG_SDK: Mutex<AnalogSDK> = {
+    /// The SDK is behind a `RwLock` so that any number of threads can read analog values at the same time, only calls which change
+    /// the state of the SDK (initialising, setting callbacks, etc) need exclusive access
+    pub static ref ANALOG_SDK: RwLock<AnalogSDK> = {
         // Initialising logger with default "off".
         // If the library user wants logging, they can set the RUST_LOG environment variable, e.g. to "info".
         // TODO: Consider using file logging or allowing the user to set a custom log callback.
@@ -15,8 +21,20 @@ lazy_static! {
             println!("ERROR: Could not initialise logging. '{:?}'", e);
         }
 
-        Mutex::new(AnalogSDK::new())
+        RwLock::new(AnalogSDK::new())
     };
+
+    /// The names of every key by HID code, kept around so that `wooting_analog_key_name` can give out pointers to them
+    static ref KEY_NAMES: HashMap<u16, CString> = (0..0x100)
+        .chain(CONSUMER_PAGE..CONSUMER_PAGE + 0x1000)
+        .filter_map(|code| {
+            wooting_analog_common::key_name(code).and_then(|name| CString::new(name).ok().map(|name| (code, name)))
+        })
+        .collect();
+
+    /// The mouse emulation started by `wooting_analog_start_mouse_emulation`, which reads the SDK from its own thread
+    //The emulation along with the read session it reads through
+    static ref MOUSE_EMULATION: Mutex<Option<(MouseEmulation, SessionID)>> = Mutex::new(None);
 }
 
 /// Initialises the Analog SDK, this needs to be successfully called before any other functions
@@ -29,15 +47,15 @@ lazy_static! {
 pub extern "C" fn wooting_analog_initialise() -> c_int {
     let result = panic::catch_unwind(|| {
         trace!("wooting_analog_initialise called");
-        ANALOG_SDK.lock().unwrap().initialise().into()
+        ANALOG_SDK.write().unwrap().initialise().into()
     });
     trace!("catch unwind result: {:?}", result);
     match result {
         Ok(c) => c,
-        Err(e) =>{
+        Err(e) => {
             error!("An error occurred in wooting_analog_initialise: {:?}", e);
             WootingAnalogResult::Failure.into()
-        } ,
+        }
     }
 }
 
@@ -56,7 +74,7 @@ pub extern "C" fn wooting_analog_version() -> c_int {
 /// Returns a bool indicating if the Analog SDK has been initialised
 #[no_mangle]
 pub extern "C" fn wooting_analog_is_initialised() -> bool {
-    ANALOG_SDK.lock().unwrap().initialised
+    ANALOG_SDK.read().unwrap().initialised
 }
 
 /// Uninitialises the SDK, returning it to an empty state, similar to how it would be before first initialisation
@@ -77,7 +95,10 @@ pub extern "C" fn wooting_analog_uninitialise() -> WootingAnalogResult {
                 }
             }
         });
-        ANALOG_SDK.lock().unwrap().unload();
+        POLLED_DEVICE.with(|polled| polled.borrow_mut().take());
+        //The mouse emulation reads the SDK, so it has to be stopped before the SDK is locked to unload it
+        stop_mouse_emulation();
+        ANALOG_SDK.write().unwrap().unload();
     });
 
     trace!("catch unwind result {:?}", result);
@@ -91,8 +112,10 @@ pub extern "C" fn wooting_analog_uninitialise() -> WootingAnalogResult {
 ///
 /// # Notes
 /// * `VirtualKey` and `VirtualKeyTranslate` are only available on Windows
-/// * With all modes except `VirtualKeyTranslate`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
+/// * `XkbKeysym` is only available on Linux, with libxkbcommon installed
+/// * With all modes except `VirtualKeyTranslate` and `XkbKeysym`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
 /// * With `VirtualKeyTranslate`, if you request Q, it will be the key that inputs Q on the current layout, not the key that is Q on the standard layout.
+/// * With `XkbKeysym`, the codes are X keysyms (e.g. 0x71 for q) and point to the key that inputs it on the layout set with `wooting_analog_set_xkb_keymap`. Custom keys can't be read in this mode, as they overlap the keysyms.
 ///
 /// # Expected Returns
 /// * `Ok`: The Keycode mode was changed successfully
@@ -101,7 +124,7 @@ pub extern "C" fn wooting_analog_uninitialise() -> WootingAnalogResult {
 /// * `UnInitialized`: The SDK is not initialised
 #[no_mangle]
 pub extern "C" fn wooting_analog_set_keycode_mode(mode: c_uint) -> WootingAnalogResult {
-    if !ANALOG_SDK.lock().unwrap().initialised {
+    if !ANALOG_SDK.read().unwrap().initialised {
         return WootingAnalogResult::UnInitialized;
     }
 
@@ -113,13 +136,134 @@ pub extern "C" fn wooting_analog_set_keycode_mode(mode: c_uint) -> WootingAnalog
                 return WootingAnalogResult::NotAvailable;
             }
         }
-        ANALOG_SDK.lock().unwrap().keycode_mode = key_mode;
+        #[cfg(not(target_os = "linux"))]
+        {
+            if key_mode == KeycodeType::XkbKeysym {
+                return WootingAnalogResult::NotAvailable;
+            }
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if key_mode == KeycodeType::XkbKeysym && !crate::keycode::xkb::is_available() {
+                return WootingAnalogResult::NotAvailable;
+            }
+        }
+        ANALOG_SDK.write().unwrap().keycode_mode = key_mode;
         WootingAnalogResult::Ok
     } else {
         WootingAnalogResult::InvalidArgument
     }
 }
 
+/// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, compiled from the system's XKB data for the given
+/// `layout` and `variant` (e.g. "fr" and "oss"). `variant` may be null, and empty names are filled in by libxkbcommon from the
+/// `XKB_DEFAULT_*` environment variables or its own defaults, which is also the keymap used until this is called
+///
+/// # Expected Returns
+/// * `Ok`: The keymap was compiled and will be used from now on
+/// * `InvalidArgument`: No keymap could be compiled for the given names
+/// * `NotAvailable`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+/// * `UnInitialized`: The SDK is not initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_xkb_keymap(
+    layout: FfiStr,
+    variant: FfiStr,
+) -> WootingAnalogResult {
+    if !ANALOG_SDK.read().unwrap().initialised {
+        return WootingAnalogResult::UnInitialized;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use crate::keycode::xkb;
+        let layout = layout.as_opt_str().unwrap_or_default();
+        let variant = variant.as_opt_str().unwrap_or_default();
+        match xkb::XkbKeymap::from_names(layout, variant).0 {
+            Ok(keymap) => {
+                xkb::set_keymap(Some(keymap));
+                WootingAnalogResult::Ok
+            }
+            Err(e) => e,
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (layout, variant);
+        WootingAnalogResult::NotAvailable
+    }
+}
+
+/// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, loaded from the keymap file at `path` (in the
+/// format written by e.g. `xkbcomp $DISPLAY keymap.xkb`)
+///
+/// # Expected Returns
+/// * `Ok`: The keymap was loaded and will be used from now on
+/// * `InvalidArgument`: The file couldn't be read or isn't a valid keymap
+/// * `NotAvailable`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+/// * `UnInitialized`: The SDK is not initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_xkb_keymap_file(path: FfiStr) -> WootingAnalogResult {
+    if !ANALOG_SDK.read().unwrap().initialised {
+        return WootingAnalogResult::UnInitialized;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use crate::keycode::xkb;
+        let path = match path.as_opt_str() {
+            Some(path) => path,
+            None => return WootingAnalogResult::InvalidArgument,
+        };
+        match xkb::XkbKeymap::from_file(path).0 {
+            Ok(keymap) => {
+                xkb::set_keymap(Some(keymap));
+                WootingAnalogResult::Ok
+            }
+            Err(e) => e,
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        WootingAnalogResult::NotAvailable
+    }
+}
+
+/// Gives the canonical name of the key with identifier `code` in the given keycode `mode`, e.g. "Q" for 0x10 in `ScanCode1`. The
+/// names don't depend on the layout, and are the names of the `HIDCodes`/`ConsumerCodes` variants. This can be used without
+/// initialising the SDK.
+///
+/// # Expected Returns
+/// A null terminated string which remains valid for the lifetime of the SDK and must not be freed, or null if the `mode` isn't
+/// a valid `KeycodeType` or the `code` isn't a known key in it
+#[no_mangle]
+pub extern "C" fn wooting_analog_key_name(code: c_ushort, mode: c_uint) -> *const c_char {
+    KeycodeType::from_u32(mode)
+        .and_then(|mode| crate::keycode::code_to_hid(code, &mode))
+        .and_then(|code| KEY_NAMES.get(&code))
+        .map_or(std::ptr::null(), |name| name.as_ptr())
+}
+
+/// Gives the identifier in the given keycode `mode` of the key with the given `name`. Names are case insensitive, ignore spaces,
+/// underscores and dashes, and common aliases (e.g. "Esc", "LShift" or "KP_Enter") are accepted alongside the names given by
+/// `wooting_analog_key_name`. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `ret>=0`: The identifier of the key in the given `mode`
+/// * `InvalidArgument`: The `mode` isn't a valid `KeycodeType` or `name` is null
+/// * `NoMapping`: There's no key with the given name, or it has no identifier in the given `mode`
+#[no_mangle]
+pub extern "C" fn wooting_analog_key_from_name(name: FfiStr, mode: c_uint) -> c_int {
+    let (name, mode) = match (name.as_opt_str(), KeycodeType::from_u32(mode)) {
+        (Some(name), Some(mode)) => (name, mode),
+        _ => return WootingAnalogResult::InvalidArgument.into(),
+    };
+    match crate::keycode::key_from_name(name, &mode) {
+        Some(code) => c_int::from(code),
+        None => WootingAnalogResult::NoMapping.into(),
+    }
+}
+
 /// Reads the Analog value of the key with identifier `code` from any connected device. The set of key identifiers that is used
 /// depends on the Keycode mode set using `wooting_analog_set_mode`.
 ///
@@ -165,7 +309,7 @@ pub extern "C" fn wooting_analog_read_analog_device(
     device_id: DeviceID,
 ) -> c_float {
     ANALOG_SDK
-        .lock()
+        .read()
         .unwrap()
         .read_analog(code, device_id)
         .into()
@@ -176,7 +320,10 @@ pub extern "C" fn wooting_analog_read_analog_device(
 ///
 /// # Notes
 /// * You must copy the DeviceInfo struct or its data if you wish to use it after the callback has completed, as the memory will be freed straight after
-/// * The execution of the callback is performed in a separate thread so it is fine to put time consuming code and further SDK calls inside your callback
+/// * The callback is called from a single SDK thread, in the order the events happened, so it is fine to put further SDK calls inside your callback.
+/// Time consuming code will hold up the events which come after
+/// * If events need to be handled on a specific thread, use `wooting_analog_poll_device_event` instead
+/// * Setting the callback replaces the one which was set before, use `wooting_analog_add_device_event_listener` to have several listeners
 ///
 /// # Expected Returns
 /// * `Ok`: The callback was set successfully
@@ -186,7 +333,7 @@ pub extern "C" fn wooting_analog_set_device_event_cb(
     cb: extern "C" fn(DeviceEventType, *mut DeviceInfo_FFI),
 ) -> WootingAnalogResult {
     ANALOG_SDK
-        .lock()
+        .write()
         .unwrap()
         .set_device_event_cb(move |event, device: DeviceInfo| {
             // Create pointer to the C version of Device Info to pass to the callback
@@ -208,7 +355,109 @@ pub extern "C" fn wooting_analog_set_device_event_cb(
 /// * `UnInitialized`: The SDK is not initialised
 #[no_mangle]
 pub extern "C" fn wooting_analog_clear_device_event_cb() -> WootingAnalogResult {
-    ANALOG_SDK.lock().unwrap().clear_device_event_cb().into()
+    ANALOG_SDK.write().unwrap().clear_device_event_cb().into()
+}
+
+/// Adds a listener which is called when there is a DeviceEvent, alongside any other listeners and the callback set with `wooting_analog_set_device_event_cb`.
+/// The listener gets given the type of event `DeviceEventType`, a pointer to the DeviceInfo struct that the event applies to and the `user_data`
+/// pointer that was given here, which the SDK doesn't touch.
+///
+/// # Notes
+/// * You must copy the DeviceInfo struct or its data if you wish to use it after the listener has completed, as the memory will be freed straight after
+/// * Listeners are called one after the other from a single SDK thread, in the order they were added
+/// * Listeners can add or remove listeners (including themselves) from inside the callback
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `>=0` The ID of the listener, which is used to remove it with `wooting_analog_remove_device_event_listener`
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_add_device_event_listener(
+    cb: extern "C" fn(DeviceEventType, *mut DeviceInfo_FFI, *mut c_void),
+    user_data: *mut c_void,
+) -> c_int {
+    let user_data = UserData(user_data);
+    ANALOG_SDK
+        .read()
+        .unwrap()
+        .add_device_event_listener(move |event, device: DeviceInfo| {
+            let mut device: DeviceInfo_FFI = device.into();
+            cb(event, &mut device, user_data.0);
+        })
+        .into()
+}
+
+/// Removes the listener with the given `id` that was added with `wooting_analog_add_device_event_listener`
+///
+/// # Notes
+/// * Once this returns the listener won't be called again, so its `user_data` can be freed. If the listener is being called on the
+/// SDK thread right now, this waits for it to return first
+/// * When a listener is removed from inside a listener this doesn't wait, as it can't be running at the same time
+///
+/// # Expected Returns
+/// * `Ok`: The listener was removed
+/// * `InvalidArgument`: There is no listener with the given ID
+/// * `UnInitialized`: The SDK is not initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_remove_device_event_listener(
+    id: ListenerID,
+) -> WootingAnalogResult {
+    let result = ANALOG_SDK.read().unwrap().remove_device_event_listener(id);
+    //The SDK is released before waiting, as the listener could be waiting on it
+    match result.0 {
+        Ok(listener) => {
+            listener.wait();
+            WootingAnalogResult::Ok
+        }
+        Err(e) => e,
+    }
+}
+
+/// The `user_data` given with a listener. It is only ever handed back to the listener, so it is up to the caller to make sure
+/// it can be used from the thread the listener is called on
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+
+thread_local!(static POLLED_DEVICE: RefCell<Option<Box<DeviceInfo_FFI>>> = RefCell::new(None));
+
+/// Takes the oldest device event which hasn't been polled yet, writing the type of event into `event` and a pointer to the DeviceInfo
+/// struct the event applies to into `device`. This is an alternative to `wooting_analog_set_device_event_cb` for applications which need to
+/// handle device events on their own thread, e.g. by calling this once a frame until it returns 0.
+///
+/// # Notes
+/// * Events are given in the order they happened. The SDK keeps the last 64 events, older ones are dropped if they aren't polled in time
+/// * Events are queued regardless of whether a callback has been set, and the callback doesn't affect what this returns
+/// * The memory of the DeviceInfo struct will only be kept until the next call of this function, so if you wish to use any data from it, please copy it
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `1`: An event was written into `event` & `device`
+/// * `0`: There are no events waiting
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_poll_device_event(
+    event: *mut DeviceEventType,
+    device: *mut *mut DeviceInfo_FFI,
+) -> c_int {
+    assert!(!event.is_null());
+    assert!(!device.is_null());
+
+    match ANALOG_SDK.read().unwrap().poll_device_event().0 {
+        Ok(Some((polled_event, polled_device))) => {
+            let mut device_box: Box<DeviceInfo_FFI> = Box::new(polled_device.into());
+            unsafe {
+                *event = polled_event;
+                *device = device_box.as_mut();
+            }
+            //Keep the memory around until the next poll, dropping the previously polled device
+            POLLED_DEVICE.with(|polled| polled.borrow_mut().replace(device_box));
+            1
+        }
+        Ok(None) => 0,
+        Err(e) => e.into(),
+    }
 }
 
 thread_local!(static CONNECTED_DEVICES: RefCell<Option<Vec<*mut DeviceInfo_FFI>>> = RefCell::new(None));
@@ -227,7 +476,7 @@ pub extern "C" fn wooting_analog_get_connected_devices_info(
     buffer: *mut *mut DeviceInfo_FFI,
     len: c_uint,
 ) -> c_int {
-    let result: SDKResult<Vec<DeviceInfo>> = ANALOG_SDK.lock().unwrap().get_device_info();
+    let result: SDKResult<Vec<DeviceInfo>> = ANALOG_SDK.read().unwrap().get_device_info();
     match result.0 {
         Ok(mut devices) => {
             let device_no = (len as usize).min(devices.len());
@@ -235,32 +484,916 @@ pub extern "C" fn wooting_analog_get_connected_devices_info(
             let buff = unsafe {
                 assert!(!buffer.is_null());
 
-                slice::from_raw_parts_mut(buffer, device_no)
-            };
+                slice::from_raw_parts_mut(buffer, device_no)
+            };
+
+            devices.truncate(device_no);
+            // Convert all the DeviceInfo's into DeviceInfo_C pointers
+            let c_devices: Vec<*mut DeviceInfo_FFI> = devices
+                .drain(..)
+                .map(|dev| Box::into_raw(Box::new(dev.into())))
+                .collect();
+
+            buff.swap_with_slice(c_devices.clone().as_mut());
+            //We want to keep track of the structs that we've allocated and free up the last set that had been
+            //given
+            CONNECTED_DEVICES.with(|devs| {
+                let old = (*devs.borrow_mut()).replace(c_devices);
+                if let Some(mut old_devices) = old {
+                    for dev in old_devices.drain(..) {
+                        unsafe {
+                            Box::from_raw(dev);
+                        }
+                    }
+                }
+            });
+            device_no as i32
+        }
+        Err(e) => e.into(),
+    }
+}
+
+thread_local!(static DEVICE_INFO_EX_STRINGS: RefCell<Vec<CString>> = RefCell::new(vec![]));
+
+/// Fills in `info` with the extended information of the device with id `device_id`, including the serial number, firmware version
+/// and interface path. If `device_id` is 0 the first connected device is used.
+///
+/// # Notes
+/// * `info->size` must be set to `sizeof(WootingAnalog_DeviceInfoEx_FFI)` before the call. Only the fields which fit within it are
+/// filled in, and it's set to the size which was filled in, so older and newer versions of the struct can be used with any SDK
+/// * Information the plugin of the device doesn't know is given as null strings and 0 numbers
+/// * The memory of the strings will only be kept until the next call of this function on the same thread, so if you wish to use
+/// any data from them, please copy it
+///
+/// # Expected Returns
+/// * `Ok`: `info` has been filled in
+/// * `InvalidArgument`: `info` is null or its `size` is too small to hold the `size` field
+/// * `UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `NoDevices`: Indicates the device with id `device_id` is not connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_device_info_ex(
+    device_id: DeviceID,
+    info: *mut DeviceInfoEx_FFI,
+) -> WootingAnalogResult {
+    if info.is_null() {
+        return WootingAnalogResult::InvalidArgument;
+    }
+    //Only the size is read, as the caller's struct may be smaller than ours
+    let size = unsafe { std::ptr::read_unaligned(info as *const u32) } as usize;
+    if size < std::mem::size_of::<u32>() {
+        return WootingAnalogResult::InvalidArgument;
+    }
+
+    let result: SDKResult<Vec<DeviceInfo>> = ANALOG_SDK.read().unwrap().get_device_info();
+    let device = match result.0 {
+        Ok(devices) => devices
+            .into_iter()
+            .find(|device| device_id == 0 || device.device_id == device_id),
+        Err(e) => return e,
+    };
+    let device = match device {
+        Some(device) => device,
+        None => return WootingAnalogResult::NoDevices,
+    };
+
+    DEVICE_INFO_EX_STRINGS.with(|strings| {
+        let mut strings = strings.borrow_mut();
+        strings.clear();
+        let mut full = DeviceInfoEx_FFI::new(&device, &mut strings);
+        let filled = size.min(std::mem::size_of::<DeviceInfoEx_FFI>());
+        full.size = filled as u32;
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                &full as *const DeviceInfoEx_FFI as *const u8,
+                info as *mut u8,
+                filled,
+            );
+        }
+    });
+    WootingAnalogResult::Ok
+}
+
+/// Gives the physical layout of the device with id `device_id` as a `KeyboardLayout`, if `device_id` is 0 the first connected
+/// device is used. The geometry of the layout's keys can be fetched with `wooting_analog_get_device_geometry`
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The `KeyboardLayout` of the device, which is `Unknown` if the plugin of the device doesn't know its layout
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_device_layout(device_id: DeviceID) -> c_int {
+    let result: SDKResult<KeyboardLayout> = ANALOG_SDK.read().unwrap().get_device_layout(device_id);
+    match result.0 {
+        Ok(layout) => layout as c_int,
+        Err(e) => e.into(),
+    }
+}
+
+/// Fills up the given `buffer` (that has length `len`) with the position & size of every key of the device with id `device_id`
+/// (as many that can fit in the buffer), ordered by row and then from left to right. If `device_id` is 0 the first connected device
+/// is used. Positions & sizes are in key units, where 1.0 is the size of a standard alphanumeric key, measured from the top left of
+/// the keyboard.
+///
+/// # Notes
+/// * The codes that are filled into the `buffer` are of the KeycodeType set with wooting_analog_set_mode, keys which have no code in it are left out
+/// * If `buffer` is null, nothing is filled in and the number of keys is given, so that the buffer can be allocated first
+/// * Nothing is filled in if the layout of the device isn't known
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The number of keys that have been filled into the buffer
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_device_geometry(
+    device_id: DeviceID,
+    buffer: *mut KeyGeometry,
+    len: c_uint,
+) -> c_int {
+    let result: SDKResult<Vec<KeyGeometry>> =
+        ANALOG_SDK.read().unwrap().get_device_geometry(device_id);
+    match result.0 {
+        Ok(keys) => {
+            if buffer.is_null() {
+                return keys.len() as c_int;
+            }
+
+            let key_no = (len as usize).min(keys.len());
+            let buff = unsafe { slice::from_raw_parts_mut(buffer, key_no) };
+            buff.copy_from_slice(&keys[..key_no]);
+            key_no as c_int
+        }
+        Err(e) => e.into(),
+    }
+}
+
+/// Allows `wooting_analog_send_feature_report` to send data to devices, which is refused by default. Only enable this if you know
+/// what the data does, as vendor commands can change the settings of a device or even the firmware on it. This can be used without
+/// initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: Sending feature reports is now allowed, or refused if `enabled` is false
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_raw_device_access(enabled: bool) -> WootingAnalogResult {
+    ANALOG_SDK.write().unwrap().set_raw_device_access(enabled);
+    WootingAnalogResult::Ok
+}
+
+/// Sends the feature report `data` (that has length `len`) to the device with id `device_id`, which is how vendor commands are given
+/// to a device. The first byte is the report ID, or 0 if the device doesn't use report IDs. The response to a command can be read
+/// with `wooting_analog_get_feature_report`.
+///
+/// # Notes
+/// * This needs raw device access to have been enabled with `wooting_analog_set_raw_device_access`
+///
+/// # Expected Returns
+/// * `Ok`: The report has been sent to the device
+/// * `UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `PermissionDenied`: Raw device access hasn't been enabled
+/// * `InvalidArgument`: `device_id` is 0, or `data` is null or `len` is 0
+/// * `NoDevices`: Indicates the device with id `device_id` is not connected
+/// * `NotAvailable`: The plugin of the device can't send feature reports
+#[no_mangle]
+pub extern "C" fn wooting_analog_send_feature_report(
+    device_id: DeviceID,
+    data: *const u8,
+    len: c_uint,
+) -> WootingAnalogResult {
+    if data.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument;
+    }
+
+    let data = unsafe { slice::from_raw_parts(data, len as usize) };
+    let result = ANALOG_SDK
+        .read()
+        .unwrap()
+        .send_feature_report(device_id, data);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Reads the feature report with the ID in the first byte of `buffer` (that has length `len`) from the device with id `device_id`
+/// into `buffer`. This doesn't need raw device access, as only the report ID is sent to the device.
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The length of the report that has been read into the buffer, including the report ID
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::InvalidArgument`: `device_id` is 0, or `buffer` is null or `len` is 0
+/// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+/// * `WootingAnalogResult::NotAvailable`: The plugin of the device can't read feature reports
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_feature_report(
+    device_id: DeviceID,
+    buffer: *mut u8,
+    len: c_uint,
+) -> c_int {
+    if buffer.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+
+    let buff = unsafe { slice::from_raw_parts_mut(buffer, len as usize) };
+    let result = ANALOG_SDK
+        .read()
+        .unwrap()
+        .get_feature_report(device_id, buff);
+    match result.0 {
+        Ok(read) => read as c_int,
+        Err(e) => e.into(),
+    }
+}
+
+/// Gives the device with id `device_id` the given `alias` (e.g. "left keypad"), so that users can tell their devices apart. Aliases
+/// are saved, so they're kept between runs and shared by every app using the SDK. A null `alias` removes the device's alias.
+/// This can be used without initialising the SDK.
+///
+/// # Notes
+/// * The device doesn't need to be connected
+/// * Leading & trailing whitespace is removed from the alias, and it can be at most 256 bytes long
+///
+/// # Expected Returns
+/// * `Ok`: The alias has been set & saved
+/// * `InvalidArgument`: `device_id` is 0, or the alias is empty, too long, has a line break or is already used by another device
+/// * `Failure`: The alias couldn't be saved, in which case it's left unchanged
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_device_alias(
+    device_id: DeviceID,
+    alias: FfiStr,
+) -> WootingAnalogResult {
+    let result = ANALOG_SDK
+        .write()
+        .unwrap()
+        .set_device_alias(device_id, alias.as_opt_str());
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Fills `buffer` (that has length `len`) with the null terminated alias of the device with id `device_id`. If the alias doesn't
+/// fit, it's cut short to fit. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The length of the alias in bytes, not including the null terminator. If this is `len` or more it has been cut short.
+/// 0 means the device has no alias
+/// * `InvalidArgument`: `buffer` is null or `len` is 0
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_device_alias(
+    device_id: DeviceID,
+    buffer: *mut c_char,
+    len: c_uint,
+) -> c_int {
+    if buffer.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+
+    let alias = ANALOG_SDK
+        .read()
+        .unwrap()
+        .get_device_alias(device_id)
+        .unwrap_or_default();
+    let copied = alias.len().min(len as usize - 1);
+    let buff = unsafe { slice::from_raw_parts_mut(buffer as *mut u8, copied + 1) };
+    buff[..copied].copy_from_slice(&alias.as_bytes()[..copied]);
+    buff[copied] = 0;
+    alias.len() as c_int
+}
+
+/// Finds the device with the given `alias`, ignoring case, and writes its id into `device_id`. This can be used without
+/// initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The id of the device has been written into `device_id`. The device may not be connected
+/// * `InvalidArgument`: `alias` or `device_id` is null
+/// * `NoDevices`: No device has the given alias
+#[no_mangle]
+pub extern "C" fn wooting_analog_find_device_by_alias(
+    alias: FfiStr,
+    device_id: *mut DeviceID,
+) -> WootingAnalogResult {
+    let alias = match alias.as_opt_str() {
+        Some(alias) if !device_id.is_null() => alias,
+        _ => return WootingAnalogResult::InvalidArgument,
+    };
+    match ANALOG_SDK.read().unwrap().find_device_by_alias(alias) {
+        Some(id) => {
+            unsafe { *device_id = id };
+            WootingAnalogResult::Ok
+        }
+        None => WootingAnalogResult::NoDevices,
+    }
+}
+
+/// Sets the file device aliases are kept in, loading the aliases already in it. By default they're kept in
+/// `wooting-analog-sdk/device-aliases.txt` in the user's config directory, or the file given by the `WOOTING_ANALOG_SDK_ALIASES`
+/// environment variable. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The aliases in the file will be used from now on. If the file doesn't exist yet, it's created once an alias is set
+/// * `InvalidArgument`: `path` is null
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_device_alias_file(path: FfiStr) -> WootingAnalogResult {
+    match path.as_opt_str() {
+        Some(path) => {
+            ANALOG_SDK
+                .write()
+                .unwrap()
+                .set_alias_file(std::path::Path::new(path));
+            WootingAnalogResult::Ok
+        }
+        None => WootingAnalogResult::InvalidArgument,
+    }
+}
+
+/// Gives the number of loaded plugins, which can be looked at with `wooting_analog_get_plugin_status`,
+/// `wooting_analog_get_plugin_name` & `wooting_analog_get_plugin_path`
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The number of loaded plugins
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_plugin_count() -> c_int {
+    match ANALOG_SDK.read().unwrap().get_plugin_status().0 {
+        Ok(plugins) => plugins.len() as c_int,
+        Err(e) => e.into(),
+    }
+}
+
+/// Gives the `PluginStatus` of the plugin at `index`, which is from 0 up to `wooting_analog_get_plugin_count`. A plugin becomes
+/// `Degraded` when calls into it time out or fail, and `Quarantined` once that keeps happening, at which point its devices are
+/// reported as disconnected and it isn't called anymore.
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The `PluginStatus` of the plugin
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::InvalidArgument`: There's no plugin at `index`
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_plugin_status(index: c_uint) -> c_int {
+    match ANALOG_SDK.read().unwrap().get_plugin_status().0 {
+        Ok(plugins) => match plugins.get(index as usize) {
+            Some(plugin) => plugin.status as c_int,
+            None => WootingAnalogResult::InvalidArgument.into(),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+/// Fills `buffer` (that has length `len`) with the null terminated name of the plugin at `index`, which is from 0 up to
+/// `wooting_analog_get_plugin_count`. If the name doesn't fit, it's cut short to fit.
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The length of the name in bytes, not including the null terminator. If this is `len` or more it has been cut short
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::InvalidArgument`: `buffer` is null, `len` is 0 or there's no plugin at `index`
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_plugin_name(
+    index: c_uint,
+    buffer: *mut c_char,
+    len: c_uint,
+) -> c_int {
+    copy_plugin_string(index, buffer, len, |plugin| plugin.name)
+}
+
+/// Fills `buffer` (that has length `len`) with the null terminated path of the library the plugin at `index` was loaded from, which
+/// is from 0 up to `wooting_analog_get_plugin_count`. If the path doesn't fit, it's cut short to fit. Plugins which weren't loaded
+/// from a library of their own have an empty path.
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `ret>=0`: The length of the path in bytes, not including the null terminator. If this is `len` or more it has been cut short
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::InvalidArgument`: `buffer` is null, `len` is 0 or there's no plugin at `index`
+#[no_mangle]
+pub extern "C" fn wooting_analog_get_plugin_path(
+    index: c_uint,
+    buffer: *mut c_char,
+    len: c_uint,
+) -> c_int {
+    copy_plugin_string(index, buffer, len, |plugin| plugin.path.unwrap_or_default())
+}
+
+fn copy_plugin_string<F>(index: c_uint, buffer: *mut c_char, len: c_uint, field: F) -> c_int
+where
+    F: FnOnce(PluginInfo) -> String,
+{
+    if buffer.is_null() || len == 0 {
+        return WootingAnalogResult::InvalidArgument.into();
+    }
+
+    let value = match ANALOG_SDK.read().unwrap().get_plugin_status().0 {
+        Ok(mut plugins) if (index as usize) < plugins.len() => {
+            field(plugins.swap_remove(index as usize))
+        }
+        Ok(_) => return WootingAnalogResult::InvalidArgument.into(),
+        Err(e) => return e.into(),
+    };
+    let copied = value.len().min(len as usize - 1);
+    let buff = unsafe { slice::from_raw_parts_mut(buffer as *mut u8, copied + 1) };
+    buff[..copied].copy_from_slice(&value.as_bytes()[..copied]);
+    buff[copied] = 0;
+    value.len() as c_int
+}
+
+/// Sets how long (in milliseconds) a call into a plugin may take before the SDK stops waiting for it, 250ms by default. A plugin
+/// whose calls keep going over it is quarantined. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The timeout will be used from now on
+/// * `InvalidArgument`: `timeout_ms` is 0
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_plugin_call_timeout(
+    timeout_ms: c_uint,
+) -> WootingAnalogResult {
+    let result = ANALOG_SDK
+        .write()
+        .unwrap()
+        .set_plugin_call_timeout(std::time::Duration::from_millis(timeout_ms.into()));
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Sets the `priority` of the plugin loaded from `path` (as given by `wooting_analog_get_plugin_path`), which decides which plugin
+/// owns a device when more than one plugin reports it, e.g. an old and a new version of the same plugin, which can have the same
+/// name. The device is only listed, read & reported in device events through the plugin with the highest priority, or the one loaded
+/// first if they're the same. Plugins which weren't loaded from a library of their own are given by their name instead. Plugins have
+/// a priority of 0 unless they're given one, and plugins in the plugin directory itself are loaded before those in its sub
+/// directories. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The priority will be used from now on
+/// * `InvalidArgument`: `path` is null
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_plugin_priority(
+    path: FfiStr,
+    priority: c_int,
+) -> WootingAnalogResult {
+    match path.as_opt_str() {
+        Some(path) => {
+            ANALOG_SDK
+                .write()
+                .unwrap()
+                .set_plugin_priority(std::path::Path::new(path), priority);
+            WootingAnalogResult::Ok
+        }
+        None => WootingAnalogResult::InvalidArgument,
+    }
+}
+
+/// Reports the key `from` as the `len` keys in `to` on the device with id `device_id`, or on every device if it's 0, replacing any
+/// earlier remap of the key. This is applied to the keys read from the device before they're given, e.g. to move the analog travel
+/// of a broken switch to another key, or to report a key as several keys. If `len` is 0 the key is disabled. Remaps for a specific
+/// device take precedence over those for every device. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * The codes are of the KeycodeType set with wooting_analog_set_mode, the remap is kept by HID code so it isn't affected by
+/// changing it
+///
+/// # Expected Returns
+/// * `Ok`: The remap will be used from now on
+/// * `InvalidArgument`: `to` is null while `len` isn't 0
+/// * `NoMapping`: One of the codes doesn't have a HID code
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_key_remap(
+    device_id: DeviceID,
+    from: c_ushort,
+    to: *const c_ushort,
+    len: c_uint,
+) -> WootingAnalogResult {
+    let to = match (to.is_null(), len) {
+        (_, 0) => &[],
+        (true, _) => return WootingAnalogResult::InvalidArgument,
+        (false, len) => unsafe { slice::from_raw_parts(to, len as usize) },
+    };
+    let result = ANALOG_SDK
+        .write()
+        .unwrap()
+        .set_key_remap(device_id, from, to);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Removes the remap of the key `from` set for the device with id `device_id`, or the one for every device if it's 0. This can be
+/// used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The key is no longer remapped for the device
+/// * `InvalidArgument`: The key isn't remapped for the device
+/// * `NoMapping`: `from` doesn't have a HID code
+#[no_mangle]
+pub extern "C" fn wooting_analog_remove_key_remap(
+    device_id: DeviceID,
+    from: c_ushort,
+) -> WootingAnalogResult {
+    let result = ANALOG_SDK
+        .write()
+        .unwrap()
+        .remove_key_remap(device_id, from);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Removes every key remap. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: No keys are remapped anymore
+#[no_mangle]
+pub extern "C" fn wooting_analog_clear_key_remaps() -> WootingAnalogResult {
+    ANALOG_SDK.write().unwrap().clear_key_remaps();
+    WootingAnalogResult::Ok
+}
+
+/// Replaces every key remap with those in the file at `path`. The file has one remap per line, as the device id in hex (or `*` for
+/// every device), the key being remapped and then the keys it's reported as, separated by spaces, e.g. `* CapsLock Escape`. Keys
+/// are given by name (as accepted by `wooting_analog_key_from_name`) or as a HID code in hex (e.g. `0x04`), lines starting with `#`
+/// are ignored. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The remaps in the file will be used from now on, lines which couldn't be read are skipped
+/// * `InvalidArgument`: `path` is null
+/// * `Failure`: The file couldn't be read, in which case the remaps are left unchanged
+#[no_mangle]
+pub extern "C" fn wooting_analog_load_key_remap_file(path: FfiStr) -> WootingAnalogResult {
+    let path = match path.as_opt_str() {
+        Some(path) => path,
+        None => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK
+        .write()
+        .unwrap()
+        .load_key_remap_file(std::path::Path::new(path));
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Defines the virtual axis called `name`, replacing any axis with the same name. The axis goes from -1.0 to 1.0, made up of the
+/// `positive_len` keys in `positive` and the `negative_len` keys in `negative`, where each side is as far as the furthest pressed
+/// key of it. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * `combine` is how the sides are combined when both are pressed, as one of the AxisCombine enum
+/// * Values which are closer to 0 than `deadzone` are given as 0, the rest are scaled so that they still go up to 1
+/// * The codes are of the KeycodeType set with wooting_analog_set_mode, the axis is kept by HID code so it isn't affected by
+/// changing it
+///
+/// # Expected Returns
+/// * `Ok`: The axis can be read with wooting_analog_read_axis
+/// * `InvalidArgument`: `name` is null or empty, `combine` isn't one of the AxisCombine enum, `deadzone` isn't from 0 up to (but
+/// not including) 1, or one of the key arrays is null while its length isn't 0
+/// * `NoMapping`: One of the codes doesn't have a HID code
+#[no_mangle]
+pub extern "C" fn wooting_analog_define_axis(
+    name: FfiStr,
+    positive: *const c_ushort,
+    positive_len: c_uint,
+    negative: *const c_ushort,
+    negative_len: c_uint,
+    combine: c_uint,
+    deadzone: c_float,
+) -> WootingAnalogResult {
+    let keys = |keys: *const c_ushort, len: c_uint| -> Option<Vec<u16>> {
+        match (keys.is_null(), len) {
+            (_, 0) => Some(vec![]),
+            (true, _) => None,
+            (false, len) => Some(unsafe { slice::from_raw_parts(keys, len as usize) }.to_vec()),
+        }
+    };
+    let definition = match (
+        keys(positive, positive_len),
+        keys(negative, negative_len),
+        AxisCombine::from_u32(combine),
+    ) {
+        (Some(positive), Some(negative), Some(combine)) => AxisDefinition {
+            positive,
+            negative,
+            combine,
+            deadzone,
+        },
+        _ => return WootingAnalogResult::InvalidArgument,
+    };
+    let name = match name.as_opt_str() {
+        Some(name) => name,
+        None => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK.write().unwrap().define_axis(name, definition);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Removes the virtual axis called `name`, unpairing it from the axis it was paired with. This can be used without initialising
+/// the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The axis has been removed
+/// * `InvalidArgument`: `name` is null or there's no axis called `name`
+#[no_mangle]
+pub extern "C" fn wooting_analog_remove_axis(name: FfiStr) -> WootingAnalogResult {
+    let name = match name.as_opt_str() {
+        Some(name) => name,
+        None => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK.write().unwrap().remove_axis(name);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Pairs up the virtual axes called `x` & `y` into a stick, which normalises them together so that their combined length is at
+/// most 1.0, e.g. so that moving diagonally with WASD isn't faster than moving straight. Either of them is unpaired from the axis
+/// it was paired with before. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The axes will be normalised together from now on
+/// * `InvalidArgument`: One of the names is null, one of the axes doesn't exist, or they're the same axis
+#[no_mangle]
+pub extern "C" fn wooting_analog_pair_axes(x: FfiStr, y: FfiStr) -> WootingAnalogResult {
+    let (x, y) = match (x.as_opt_str(), y.as_opt_str()) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK.write().unwrap().pair_axes(x, y);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Reads the value of the virtual axis called `name` (defined with wooting_analog_define_axis) from all connected devices
+///
+/// # Expected Returns
+/// The float return value can be either a -1->1 axis value, or (if < -1) is part of the WootingAnalogResult enum, which is how errors are given back on this call.
+/// So if the value is below -1, you should cast it as WootingAnalogResult to see what the error is.
+/// * `-1.0f - 1.0f`: The value of the axis called `name`
+/// * `WootingAnalogResult::InvalidArgument`: `name` is null or there's no axis called `name`
+/// * `WootingAnalogResult::UnInitialized`: The SDK is not initialised
+/// * `WootingAnalogResult::NoDevices`: There are no connected devices
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_axis(name: FfiStr) -> c_float {
+    match name.as_opt_str() {
+        Some(name) => ANALOG_SDK.read().unwrap().read_axis(name).into(),
+        None => WootingAnalogResult::InvalidArgument.into(),
+    }
+}
+
+/// Reads the values of the virtual axes called `x` & `y` (defined with wooting_analog_define_axis) from all connected devices at once
+/// into `x_value` & `y_value`, e.g. the two axes of a stick, so that they're both from the same read. Each goes from -1.0 to 1.0
+///
+/// # Expected Returns
+/// * `Ok`: `x_value` & `y_value` have been filled in
+/// * `InvalidArgument`: One of the names or values is null, or there's no axis called `x` or `y`
+/// * `UnInitialized`: The SDK is not initialised
+/// * `NoDevices`: There are no connected devices
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_axis_pair(
+    x: FfiStr,
+    y: FfiStr,
+    x_value: *mut c_float,
+    y_value: *mut c_float,
+) -> WootingAnalogResult {
+    let (x, y) = match (x.as_opt_str(), y.as_opt_str()) {
+        (Some(x), Some(y)) if !x_value.is_null() && !y_value.is_null() => (x, y),
+        _ => return WootingAnalogResult::InvalidArgument,
+    };
+    match ANALOG_SDK.read().unwrap().read_axis_pair(x, y).0 {
+        Ok((x, y)) => {
+            unsafe {
+                *x_value = x;
+                *y_value = y;
+            }
+            WootingAnalogResult::Ok
+        }
+        Err(e) => e,
+    }
+}
+
+/// Fills in `kinematics` with how the key with identifier `code` is moving on the device with id `device_id`, or on the device
+/// where it's pressed furthest if `device_id` is 0. This gives how far the key is pressed, how fast it's moving, its acceleration,
+/// the furthest it got during its last press, and when that press started & ended. It's worked out from every report the device
+/// sends rather than from the reads of the SDK, so it doesn't matter how often it's read.
+///
+/// # Notes
+/// * The code is of the KeycodeType set with wooting_analog_set_mode. If keys are remapped onto it, the one pressed furthest is given
+/// * Times are in microseconds on the clock given by `wooting_analog_kinematics_time`, the press duration of a released key is
+/// `released_at - pressed_at`
+/// * Only devices of plugins which publish their reports into a `SnapshotStore` are tracked
+///
+/// # Expected Returns
+/// * `Ok`: `kinematics` has been filled in
+/// * `InvalidArgument`: `kinematics` is null
+/// * `UnInitialized`: The SDK is not initialised
+/// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+/// * `NoDevices`: No reports have come in from the device with id `device_id` (or from any device if it's 0)
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_key_kinematics(
+    code: c_ushort,
+    device_id: DeviceID,
+    kinematics: *mut KeyKinematics,
+) -> WootingAnalogResult {
+    let kinematics = match unsafe { kinematics.as_mut() } {
+        Some(kinematics) => kinematics,
+        None => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK
+        .read()
+        .unwrap()
+        .read_key_kinematics(code, device_id);
+    match result.0 {
+        Ok(key) => {
+            *kinematics = key;
+            WootingAnalogResult::Ok
+        }
+        Err(e) => e,
+    }
+}
+
+/// Gives the current time in microseconds on the clock which the times given by `wooting_analog_read_key_kinematics` are on, e.g.
+/// to tell how long a key which is still pressed has been held for. This can be used without initialising the SDK.
+#[no_mangle]
+pub extern "C" fn wooting_analog_kinematics_time() -> u64 {
+    ANALOG_SDK.read().unwrap().kinematics_time()
+}
+
+/// Binds the key with identifier `code` to `action` of the emulated mouse, as one of the MouseAction enum, replacing what the key
+/// was bound to. Any number of keys can be bound to the same action, e.g. both arrows & IJKL to move the pointer. This can be used
+/// without initialising the SDK.
+///
+/// # Notes
+/// * The code is of the KeycodeType set with wooting_analog_set_mode, the binding is kept by HID code so it isn't affected by
+/// changing it
+/// * Keys which move the pointer or scroll go faster the further they're pressed, keys bound to buttons hold them down while
+/// they're pressed past halfway
+///
+/// # Expected Returns
+/// * `Ok`: The key is bound
+/// * `InvalidArgument`: `action` isn't one of the MouseAction enum
+/// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+#[no_mangle]
+pub extern "C" fn wooting_analog_bind_mouse_action(
+    code: c_ushort,
+    action: c_uint,
+) -> WootingAnalogResult {
+    let action = match MouseAction::from_u32(action) {
+        Some(action) => action,
+        None => return WootingAnalogResult::InvalidArgument,
+    };
+    let result = ANALOG_SDK.write().unwrap().bind_mouse_action(code, action);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Unbinds the key with identifier `code` from the emulated mouse. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok`: The key isn't bound anymore
+/// * `InvalidArgument`: The key isn't bound
+/// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+#[no_mangle]
+pub extern "C" fn wooting_analog_unbind_mouse_action(code: c_ushort) -> WootingAnalogResult {
+    let result = ANALOG_SDK.write().unwrap().unbind_mouse_action(code);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Sets the acceleration curve of the emulated mouse pointer, which gives how fast it moves in pixels per second with how far
+/// the keys moving it are pressed. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * By default the pointer has a deadzone of 0.1, an exponent of 2 and a max speed of 1500 pixels per second
+/// * Moving diagonally is kept to the max speed as well
+///
+/// # Expected Returns
+/// * `Ok`: The curve is used from the next update of the mouse
+/// * `InvalidArgument`: The deadzone isn't from 0 up to (but not including) 1, the exponent isn't above 0, or the max speed is
+/// below 0
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_mouse_pointer_curve(curve: MouseCurve) -> WootingAnalogResult {
+    let result = ANALOG_SDK.write().unwrap().set_mouse_pointer_curve(curve);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Sets the acceleration curve of the emulated mouse wheel, which gives how fast it scrolls in lines per second with how
+/// far the keys scrolling it are pressed. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * By default scrolling has a deadzone of 0.1, an exponent of 1.5 and a max speed of 20 lines per second
+///
+/// # Expected Returns
+/// * `Ok`: The curve is used from the next update of the mouse
+/// * `InvalidArgument`: The deadzone isn't from 0 up to (but not including) 1, the exponent isn't above 0, or the max speed is
+/// below 0
+#[no_mangle]
+pub extern "C" fn wooting_analog_set_mouse_scroll_curve(curve: MouseCurve) -> WootingAnalogResult {
+    let result = ANALOG_SDK.write().unwrap().set_mouse_scroll_curve(curve);
+    match result.0 {
+        Ok(()) => WootingAnalogResult::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Starts emulating a mouse with the keys bound by wooting_analog_bind_mouse_action, reading all devices every 8ms. The mouse is a
+/// virtual device, so it moves the system pointer like any other mouse, e.g. to give people who can't use a mouse a cursor
+/// controlled by how far they press keys.
+///
+/// # Notes
+/// * This is only available on Linux, where the mouse is created through `/dev/uinput`, which needs write access to it (usually
+/// given through a udev rule or the `input` group)
+/// * The bound keys are still read by everyone else reading the SDK
+/// * The keys are read through a read session of its own, so it doesn't take released keys from anyone else reading the SDK
+/// * The emulation is stopped by wooting_analog_stop_mouse_emulation or when the SDK is uninitialised
+///
+/// # Expected Returns
+/// * `Ok`: The mouse is being emulated, or already was
+/// * `UnInitialized`: The SDK is not initialised
+/// * `NotAvailable`: Mouse emulation isn't available on this platform
+/// * `Failure`: The virtual mouse couldn't be created, e.g. because `/dev/uinput` couldn't be opened
+#[no_mangle]
+pub extern "C" fn wooting_analog_start_mouse_emulation() -> WootingAnalogResult {
+    if !ANALOG_SDK.read().unwrap().initialised {
+        return WootingAnalogResult::UnInitialized;
+    }
+    let mut emulation = MOUSE_EMULATION.lock().unwrap();
+    if emulation.as_ref().map_or(false, |(e, _)| e.is_running()) {
+        return WootingAnalogResult::Ok;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mouse = match crate::mouse::uinput::UInputMouse::new() {
+            Ok(mouse) => mouse,
+            Err(e) => {
+                error!("Unable to create the virtual mouse: {}", e);
+                return WootingAnalogResult::Failure;
+            }
+        };
+        //An emulation which stopped by itself leaves its session behind
+        if let Some((_, session)) = emulation.take() {
+            let _ = ANALOG_SDK.read().unwrap().destroy_session(session);
+        }
+        let session = match ANALOG_SDK.read().unwrap().create_session().0 {
+            Ok(session) => session,
+            Err(e) => return e,
+        };
+        *emulation = Some((
+            MouseEmulation::start(
+                move || ANALOG_SDK.read().unwrap().read_mouse_input(session).0.ok(),
+                mouse,
+            ),
+            session,
+        ));
+        WootingAnalogResult::Ok
+    }
+    #[cfg(not(target_os = "linux"))]
+    WootingAnalogResult::NotAvailable
+}
 
-            devices.truncate(device_no);
-            // Convert all the DeviceInfo's into DeviceInfo_C pointers
-            let c_devices: Vec<*mut DeviceInfo_FFI> = devices
-                .drain(..)
-                .map(|dev| Box::into_raw(Box::new(dev.into())))
-                .collect();
+/// Stops emulating the mouse started by wooting_analog_start_mouse_emulation, letting go of any buttons it was holding. The key
+/// bindings & curves are kept.
+///
+/// # Expected Returns
+/// * `Ok`: The mouse isn't being emulated anymore
+#[no_mangle]
+pub extern "C" fn wooting_analog_stop_mouse_emulation() -> WootingAnalogResult {
+    stop_mouse_emulation();
+    WootingAnalogResult::Ok
+}
 
-            buff.swap_with_slice(c_devices.clone().as_mut());
-            //We want to keep track of the structs that we've allocated and free up the last set that had been
-            //given
-            CONNECTED_DEVICES.with(|devs| {
-                let old = (*devs.borrow_mut()).replace(c_devices);
-                if let Some(mut old_devices) = old {
-                    for dev in old_devices.drain(..) {
-                        unsafe {
-                            Box::from_raw(dev);
-                        }
-                    }
-                }
-            });
-            device_no as i32
-        }
-        Err(e) => e.into(),
+fn stop_mouse_emulation() {
+    if let Some((mut emulation, session)) = MOUSE_EMULATION.lock().unwrap().take() {
+        emulation.stop();
+        //Fails once the SDK is uninitialised, which has dropped the session already
+        let _ = ANALOG_SDK.read().unwrap().destroy_session(session);
     }
 }
 
@@ -274,6 +1407,7 @@ pub extern "C" fn wooting_analog_get_connected_devices_info(
 /// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
 /// * If two devices have the same key pressed, the greater value will be given
 /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+/// * Released keys are tracked in a session shared with every other caller, use `wooting_analog_read_full_buffer_session` if there are multiple readers
 ///
 /// # Expected Returns
 /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
@@ -299,6 +1433,7 @@ pub extern "C" fn wooting_analog_read_full_buffer(
 /// key & analog value pairs that can be filled in.
 /// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
 /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+/// * Released keys are tracked in a session shared with every other caller, use `wooting_analog_read_full_buffer_session` if there are multiple readers
 ///
 /// # Expected Returns
 /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
@@ -312,6 +1447,77 @@ pub extern "C" fn wooting_analog_read_full_buffer_device(
     analog_buffer: *mut c_float,
     len: c_uint,
     device_id: DeviceID,
+) -> c_int {
+    read_full_buffer_into(code_buffer, analog_buffer, len, |sdk| {
+        sdk.read_full_buffer(len as usize, device_id)
+    })
+}
+
+/// Creates a new session to read with using `wooting_analog_read_full_buffer_session`. Each session keeps track of released keys
+/// on its own, so if there are multiple parts of your application reading the full buffer, they should each have their own session
+/// to ensure none of them miss out on a key being released.
+///
+/// # Notes
+/// * The session should be destroyed with `wooting_analog_destroy_session` once it is no longer needed
+/// * Sessions are destroyed when the SDK is uninitialised
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `>=0` The ID of the new session
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_create_session() -> c_int {
+    ANALOG_SDK.read().unwrap().create_session().into()
+}
+
+/// Destroys the `session` that was created with `wooting_analog_create_session`
+///
+/// # Expected Returns
+/// * `Ok`: The session was destroyed
+/// * `InvalidArgument`: There is no session with the given ID
+/// * `UnInitialized`: The SDK is not initialised
+#[no_mangle]
+pub extern "C" fn wooting_analog_destroy_session(session: SessionID) -> WootingAnalogResult {
+    ANALOG_SDK.read().unwrap().destroy_session(session).into()
+}
+
+/// The same as `wooting_analog_read_full_buffer_device`, except released keys are tracked for the given `session` (created with
+/// `wooting_analog_create_session`), rather than being shared with every other reader. Pass a `device_id` of 0 to read from all devices.
+///
+/// # Notes
+/// * `len` is the length of code_buffer & analog_buffer, if the buffers are of unequal length, then pass the lower of the two, as it is the max amount of
+/// key & analog value pairs that can be filled in.
+/// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
+/// * When a key is released it will be returned with an analog value of 0.0f in the first call with this `session` after the key has been released
+/// * If more keys are pressed than fit, the ones pressed furthest are given ahead of any releases. Releases which don't fit are given in a later call
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `>=0` means the value indicates how many keys & analog values have been read into the buffers
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::InvalidArgument`: There is no session with the given ID
+/// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_full_buffer_session(
+    session: SessionID,
+    code_buffer: *mut c_ushort,
+    analog_buffer: *mut c_float,
+    len: c_uint,
+    device_id: DeviceID,
+) -> c_int {
+    read_full_buffer_into(code_buffer, analog_buffer, len, |sdk| {
+        sdk.read_full_buffer_session(session, len as usize, device_id)
+    })
+}
+
+/// Fills the given buffers with the result of `read`
+fn read_full_buffer_into(
+    code_buffer: *mut c_ushort,
+    analog_buffer: *mut c_float,
+    len: c_uint,
+    read: impl FnOnce(&AnalogSDK) -> SDKResult<HashMap<u16, f32>>,
 ) -> c_int {
     let codes = unsafe {
         assert!(!code_buffer.is_null());
@@ -325,12 +1531,7 @@ pub extern "C" fn wooting_analog_read_full_buffer_device(
         slice::from_raw_parts_mut(analog_buffer, len as usize)
     };
 
-    match ANALOG_SDK
-        .lock()
-        .unwrap()
-        .read_full_buffer(len as usize, device_id)
-        .0
-    {
+    match read(&ANALOG_SDK.read().unwrap()).0 {
         Ok(analog_data) => {
             //Fill up given slices
             let mut count: usize = 0;
@@ -349,6 +1550,64 @@ pub extern "C" fn wooting_analog_read_full_buffer_device(
     }
 }
 
+/// Fills up `analog_buffer` (that has length `len`) with the analog value of every key on all devices, indexed by HID code. i.e. the
+/// analog value for A (HID 0x04) is at index 4 of `analog_buffer`. This doesn't allocate, so it is the recommended way to read every key
+/// once per frame.
+///
+/// # Notes
+/// * The buffer is always indexed by HID code, regardless of the KeycodeType set with wooting_analog_set_mode
+/// * Keys which aren't pressed are set to 0.0f, so there is no need to keep track of released keys
+/// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+/// * If two devices have the same key pressed, the greater value will be given
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `>=0` means the value indicates how many keys are pressed in the buffer
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::NoDevices`: Indicates no devices are connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_dense_buffer(
+    analog_buffer: *mut c_float,
+    len: c_uint,
+) -> c_int {
+    wooting_analog_read_dense_buffer_device(analog_buffer, len, 0)
+}
+
+/// Fills up `analog_buffer` (that has length `len`) with the analog value of every key on the device with id `device_id`, indexed by HID code.
+/// i.e. the analog value for A (HID 0x04) is at index 4 of `analog_buffer`. This doesn't allocate, so it is the recommended way to read every key
+/// once per frame.
+///
+/// # Notes
+/// * The buffer is always indexed by HID code, regardless of the KeycodeType set with wooting_analog_set_mode
+/// * Keys which aren't pressed are set to 0.0f, so there is no need to keep track of released keys
+/// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+///
+/// # Expected Returns
+/// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+/// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+/// * `>=0` means the value indicates how many keys are pressed in the buffer
+/// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+/// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+#[no_mangle]
+pub extern "C" fn wooting_analog_read_dense_buffer_device(
+    analog_buffer: *mut c_float,
+    len: c_uint,
+    device_id: DeviceID,
+) -> c_int {
+    let analog = unsafe {
+        assert!(!analog_buffer.is_null());
+
+        slice::from_raw_parts_mut(analog_buffer, len as usize)
+    };
+
+    ANALOG_SDK
+        .read()
+        .unwrap()
+        .read_dense_buffer(analog, device_id)
+        .into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,7 +1616,8 @@ mod tests {
         ReadLockGuard, ReadLockable, SharedMem, SharedMemCast, WriteLockGuard, WriteLockable,
     };
 
-    use std::sync::{Arc, MutexGuard};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, RwLockWriteGuard};
     use std::time::Duration;
 
     struct SharedState {
@@ -382,8 +1642,8 @@ mod tests {
 
     unsafe impl SharedMemCast for SharedState {}
 
-    pub fn get_sdk() -> MutexGuard<'static, AnalogSDK> {
-        ANALOG_SDK.lock().unwrap()
+    pub fn get_sdk() -> RwLockWriteGuard<'static, AnalogSDK> {
+        ANALOG_SDK.write().unwrap()
     }
 
     lazy_static! {
@@ -395,6 +1655,15 @@ mod tests {
         *Arc::clone(&got_connected).lock().unwrap() = event == DeviceEventType::Connected;
     }
 
+    extern "C" fn counting_listener(
+        _event: DeviceEventType,
+        _device: *mut DeviceInfo_FFI,
+        user_data: *mut c_void,
+    ) {
+        let count = unsafe { &*(user_data as *const AtomicUsize) };
+        count.fetch_add(1, Ordering::SeqCst);
+    }
+
     fn wait_for_connected(attempts: u32, connected: bool) {
         let mut n = 0;
         while *Arc::clone(&got_connected).lock().unwrap() != connected {
@@ -470,6 +1739,14 @@ mod tests {
             }
         };
 
+        //Added before the callback, so it will have seen each event by the time the callback has
+        let listener_count = AtomicUsize::new(0);
+        let listener = wooting_analog_add_device_event_listener(
+            counting_listener,
+            &listener_count as *const AtomicUsize as *mut c_void,
+        );
+        assert!(listener > 0);
+
         wooting_analog_set_device_event_cb(connect_cb);
 
         //Check the connected cb is called
@@ -525,6 +1802,29 @@ mod tests {
             );
         }
 
+        //Check the listener was given its user data for both events
+        assert_eq!(listener_count.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            wooting_analog_remove_device_event_listener(listener as ListenerID),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_remove_device_event_listener(listener as ListenerID),
+            WootingAnalogResult::InvalidArgument
+        );
+
+        //Check the events can be polled in the order they happened
+        {
+            let mut event = DeviceEventType::Disconnected;
+            let mut device: *mut DeviceInfo_FFI = std::ptr::null_mut();
+            assert_eq!(wooting_analog_poll_device_event(&mut event, &mut device), 1);
+            assert_eq!(event, DeviceEventType::Connected);
+            assert!(!device.is_null());
+            assert_eq!(wooting_analog_poll_device_event(&mut event, &mut device), 1);
+            assert_eq!(event, DeviceEventType::Disconnected);
+            assert_eq!(wooting_analog_poll_device_event(&mut event, &mut device), 0);
+        }
+
         let analog_val = 0xF4;
         let f_analog_val = f32::from(analog_val) / 255_f32;
         let analog_key = 5;
@@ -538,6 +1838,46 @@ mod tests {
 
         wait_for_connected(5, true);
 
+        //Check the extended device info, along with only part of it being filled in for an older, smaller struct
+        {
+            let mut info = DeviceInfoEx_FFI::default();
+            assert_eq!(
+                wooting_analog_get_device_info_ex(device_id, &mut info),
+                WootingAnalogResult::Ok
+            );
+            assert_eq!(info.size as usize, std::mem::size_of::<DeviceInfoEx_FFI>());
+            let device = info.into_device_info();
+            assert_eq!(device.device_id, device_id);
+            assert_eq!(
+                device.details.analog_key_count,
+                Some(KeyboardLayout::ANSI.keys().len() as u32)
+            );
+            assert_eq!(device.details.value_resolution, Some(256));
+            assert_eq!(device.details.serial_number, None);
+            assert!(!device.details.digital);
+
+            let mut info = DeviceInfoEx_FFI::default();
+            let old_size = &info.serial_number as *const _ as usize - &info as *const _ as usize;
+            info.size = old_size as u32;
+            info.analog_key_count = 7;
+            assert_eq!(
+                wooting_analog_get_device_info_ex(0, &mut info),
+                WootingAnalogResult::Ok
+            );
+            assert_eq!(info.size as usize, old_size);
+            assert_eq!(info.device_id, device_id);
+            assert_eq!(info.analog_key_count, 7);
+
+            assert_eq!(
+                wooting_analog_get_device_info_ex(device_id, std::ptr::null_mut()),
+                WootingAnalogResult::InvalidArgument
+            );
+            assert_eq!(
+                wooting_analog_get_device_info_ex(device_id + 1, &mut info),
+                WootingAnalogResult::NoDevices
+            );
+        }
+
         //Check we get the val with no id specified
         assert_eq!(wooting_analog_read_analog(analog_key as u16), f_analog_val);
         //Check we get the val with the device_id we use
@@ -566,6 +1906,42 @@ mod tests {
             ),
             f_analog_val
         );
+        mode = KeycodeType::Evdev;
+        assert_eq!(
+            wooting_analog_set_keycode_mode(mode.clone() as u32),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_read_analog_device(
+                hid_to_code(analog_key as u16, &mode).unwrap(),
+                device_id
+            ),
+            f_analog_val
+        );
+        #[cfg(target_os = "linux")]
+        {
+            let _keymap_lock = crate::keycode::xkb::TEST_KEYMAP_LOCK.lock().unwrap();
+            //On AZERTY the key which is B on the standard layout still inputs b
+            let keymap = std::ffi::CString::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/test_data/xkb/fr.xkb"
+            ))
+            .unwrap();
+            assert_eq!(
+                wooting_analog_set_xkb_keymap_file(FfiStr::from_cstr(&keymap)),
+                WootingAnalogResult::Ok
+            );
+            mode = KeycodeType::XkbKeysym;
+            assert_eq!(
+                wooting_analog_set_keycode_mode(mode.clone() as u32),
+                WootingAnalogResult::Ok
+            );
+            assert_eq!(
+                wooting_analog_read_analog_device('b' as u16, device_id),
+                f_analog_val
+            );
+            crate::keycode::xkb::set_keymap(None);
+        }
         mode = KeycodeType::HID;
         wooting_analog_set_keycode_mode(mode.clone() as u32);
 
@@ -613,6 +1989,22 @@ mod tests {
         assert_eq!(code_buffer[0], 0);
         assert_eq!(analog_buffer[0], 0.0);
 
+        //Check the dense buffer is indexed by HID code
+        let mut dense_buffer = [0.0; DENSE_BUFFER_LEN];
+        assert_eq!(
+            wooting_analog_read_dense_buffer(dense_buffer.as_mut_ptr(), dense_buffer.len() as u32),
+            1
+        );
+        assert_eq!(dense_buffer[analog_key], f_analog_val);
+        assert_eq!(
+            wooting_analog_read_dense_buffer_device(
+                dense_buffer.as_mut_ptr(),
+                dense_buffer.len() as u32,
+                device_id + 1
+            ),
+            WootingAnalogResult::NoDevices.into()
+        );
+
         //Check that it does code mapping
         wooting_analog_set_keycode_mode(mode.clone() as u32);
         assert_eq!(
@@ -632,6 +2024,21 @@ mod tests {
         mode = KeycodeType::HID;
         wooting_analog_set_keycode_mode(mode.clone() as u32);
 
+        //Have a second reader see the key pressed in its own session
+        let session = wooting_analog_create_session();
+        assert!(session > 0);
+        let session = session as SessionID;
+        assert_eq!(
+            wooting_analog_read_full_buffer_session(
+                session,
+                code_buffer.as_mut_ptr(),
+                analog_buffer.as_mut_ptr(),
+                buffer_len as u32,
+                0
+            ),
+            1
+        );
+
         {
             let mut shared_state = get_wlock(&mut shmem);
             shared_state.analog_values[analog_key] = 0;
@@ -664,6 +2071,30 @@ mod tests {
             0
         );
 
+        //Check the second reader still gets the release
+        code_buffer[0] = 0;
+        analog_buffer[0] = 1.0;
+        assert_eq!(
+            wooting_analog_read_full_buffer_session(
+                session,
+                code_buffer.as_mut_ptr(),
+                analog_buffer.as_mut_ptr(),
+                buffer_len as u32,
+                0
+            ),
+            1
+        );
+        assert_eq!(code_buffer[0], analog_key as u16);
+        assert_eq!(analog_buffer[0], 0.0);
+        assert_eq!(
+            wooting_analog_destroy_session(session),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_destroy_session(session),
+            WootingAnalogResult::InvalidArgument
+        );
+
         assert_eq!(
             wooting_analog_clear_device_event_cb(),
             WootingAnalogResult::Ok
@@ -683,4 +2114,108 @@ mod tests {
         wooting_analog_initialise();
         assert_eq!(wooting_analog_uninitialise(), WootingAnalogResult::Ok);
     }
+
+    #[test]
+    fn test_key_names() {
+        use std::ffi::CStr;
+
+        let name = wooting_analog_key_name(0x10, KeycodeType::ScanCode1 as c_uint);
+        assert_eq!(unsafe { CStr::from_ptr(name) }.to_str(), Ok("Q"));
+        //The same key should always give the same pointer
+        assert_eq!(
+            wooting_analog_key_name(HIDCodes::Q as c_ushort, KeycodeType::HID as c_uint),
+            name
+        );
+        assert!(wooting_analog_key_name(0x10, 42).is_null());
+        assert!(wooting_analog_key_name(0x3, KeycodeType::HID as c_uint).is_null());
+
+        let name = CString::new("left shift").unwrap();
+        assert_eq!(
+            wooting_analog_key_from_name(FfiStr::from_cstr(&name), KeycodeType::Evdev as c_uint),
+            42
+        );
+        assert_eq!(
+            wooting_analog_key_from_name(FfiStr::from_cstr(&name), KeycodeType::HID as c_uint),
+            HIDCodes::LeftShift as c_int
+        );
+        assert_eq!(
+            wooting_analog_key_from_name(FfiStr::from_cstr(&name), 42),
+            WootingAnalogResult::InvalidArgument as c_int
+        );
+        let name = CString::new("NotAKey").unwrap();
+        assert_eq!(
+            wooting_analog_key_from_name(FfiStr::from_cstr(&name), KeycodeType::HID as c_uint),
+            WootingAnalogResult::NoMapping as c_int
+        );
+    }
+
+    #[test]
+    fn test_device_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().join("aliases.txt").to_str().unwrap()).unwrap();
+        assert_eq!(
+            wooting_analog_set_device_alias_file(FfiStr::from_cstr(&path)),
+            WootingAnalogResult::Ok
+        );
+
+        let alias = CString::new("Left Keypad").unwrap();
+        assert_eq!(
+            wooting_analog_set_device_alias(5, FfiStr::from_cstr(&alias)),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_set_device_alias(6, FfiStr::from_cstr(&alias)),
+            WootingAnalogResult::InvalidArgument
+        );
+
+        let mut buffer = [0 as c_char; 32];
+        assert_eq!(
+            wooting_analog_get_device_alias(5, buffer.as_mut_ptr(), 32),
+            11
+        );
+        assert_eq!(
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str(),
+            Ok("Left Keypad")
+        );
+        //A buffer which is too small gets as much as fits
+        assert_eq!(
+            wooting_analog_get_device_alias(5, buffer.as_mut_ptr(), 5),
+            11
+        );
+        assert_eq!(
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str(),
+            Ok("Left")
+        );
+        assert_eq!(
+            wooting_analog_get_device_alias(6, buffer.as_mut_ptr(), 32),
+            0
+        );
+        assert_eq!(buffer[0], 0);
+
+        let mut device_id: DeviceID = 0;
+        let search = CString::new("left keypad").unwrap();
+        assert_eq!(
+            wooting_analog_find_device_by_alias(FfiStr::from_cstr(&search), &mut device_id),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(device_id, 5);
+
+        //The alias should be loaded back from the file
+        assert_eq!(
+            wooting_analog_set_device_alias_file(FfiStr::from_cstr(&path)),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_get_device_alias(5, buffer.as_mut_ptr(), 32),
+            11
+        );
+        assert_eq!(
+            wooting_analog_set_device_alias(5, unsafe { FfiStr::from_raw(std::ptr::null()) }),
+            WootingAnalogResult::Ok
+        );
+        assert_eq!(
+            wooting_analog_find_device_by_alias(FfiStr::from_cstr(&search), &mut device_id),
+            WootingAnalogResult::NoDevices
+        );
+    }
 }
@@ -1,6 +1,9 @@
 //use scancode::Scancode;
 use bimap::BiMap;
-use wooting_analog_common::KeycodeType;
+use wooting_analog_common::{KeycodeType, CONSUMER_PAGE};
+
+#[cfg(target_os = "linux")]
+pub mod xkb;
 
 lazy_static! {
     //<HID code, Scancode>
@@ -59,7 +62,7 @@ lazy_static! {
         bimap.insert(0x30, 0x001b); //BRACKET_RIGHT
         bimap.insert(0x31, 0x002b); //BACKSLASH
 
-        //bimap.insert(0x32, 0x0000); //INTL_HASH
+        //INTL_HASH (0x32) gives the same scancode as BACKSLASH
 
         bimap.insert(0x33, 0x0027); //SEMICOLON
         bimap.insert(0x34, 0x0028); //QUOTE
@@ -135,18 +138,15 @@ lazy_static! {
         bimap.insert(0x72, 0x006e); //F23
 
         bimap.insert(0x73, 0x0076); //F24
-        bimap.insert(0x74, 0x0000); //OPEN
-
+        //OPEN (0x74) has no scancode
         bimap.insert(0x75, 0xe03b); //HELP
+        //MENU, SELECT, STOP & AGAIN (0x76-0x79) have no scancode
 
-        //bimap.insert(0x77, 0x0000); //SELECT
-
-        bimap.insert(0x79, 0x0000); //AGAIN
         bimap.insert(0x7a, 0xe008); //UNDO
         bimap.insert(0x7b, 0xe017); //CUT
         bimap.insert(0x7c, 0xe018); //COPY
         bimap.insert(0x7d, 0xe00a); //PASTE
-        bimap.insert(0x7e, 0x0000); //FIND
+        //FIND (0x7e) has no scancode
         bimap.insert(0x7f, 0xe020); //VOLUME_MUTE
 
         bimap.insert(0x80, 0xe030); //VOLUME_UP
@@ -158,10 +158,15 @@ lazy_static! {
         bimap.insert(0x89, 0x007d); //INTL_YEN
         bimap.insert(0x8a, 0x0079); //CONVERT
         bimap.insert(0x8b, 0x007b); //NON_CONVERT
+        bimap.insert(0x8c, 0x005c); //INTL_6
+
         bimap.insert(0x90, 0x0072); //LANG1
         bimap.insert(0x91, 0x0071); //LANG2
         bimap.insert(0x92, 0x0078); //LANG3
         bimap.insert(0x93, 0x0077); //LANG4
+        //LANG5 (0x94) gives the same scancode as F24
+
+        bimap.insert(0x9a, 0x0054); //SYS_REQ
 
         bimap.insert(0xe0, 0x001d); //CONTROL_LEFT
         bimap.insert(0xe1, 0x002a); //SHIFT_LEFT
@@ -213,7 +218,8 @@ pub fn vk_to_scancode(code: u16, translate: bool) -> Option<u16> {
             scancode = MapVirtualKeyExA(code.into(), 4, layout);
         //println!("Window handle: {:?}, thread: {:?}, layout: {:?}, code: {} scancode: {}", window_handle, thread, layout, code, scancode);
         } else {
-            scancode = MapVirtualKeyA(code.into(), 0);
+            //MAPVK_VK_TO_VSC_EX, so extended keys get their E0 prefix, same as when translating
+            scancode = MapVirtualKeyA(code.into(), 4);
         }
 
         if scancode == 0 {
@@ -284,46 +290,75 @@ pub fn scancode_to_hid(code: u16) -> Option<u16> {
     }*/
 }
 
+pub use wooting_analog_common::{evdev_to_hid, hid_to_evdev};
+
+#[allow(unused)]
+pub fn keysym_to_hid(code: u16) -> Option<u16> {
+    #[cfg(target_os = "linux")]
+    return xkb::keysym_to_hid(code);
+
+    #[cfg(not(target_os = "linux"))]
+    None
+}
+
+#[allow(unused)]
+pub fn hid_to_keysym(code: u16) -> Option<u16> {
+    #[cfg(target_os = "linux")]
+    return xkb::hid_to_keysym(code);
+
+    #[cfg(not(target_os = "linux"))]
+    None
+}
+
+/// Whether the code has one of the Scan code set 1 escape prefixes (0x1 being a shorthand for 0xE0), these are only valid in the
+/// ScanCode1 mode
+fn is_scancode_escaped(code: u16) -> bool {
+    matches!(code >> 8, 0x01 | 0xE0 | 0xE1)
+}
+
+/// Whether the code is on the HID Consumer page, see `CONSUMER_PAGE`
+fn is_consumer(code: u16) -> bool {
+    code & 0xF000 == CONSUMER_PAGE
+}
+
 pub fn code_to_hid(code: u16, mode: &KeycodeType) -> Option<u16> {
-    let prefix = (code & 0xFF00) >> 8;
-    //Check if the code is a custom key, if it is, just straight return it. Additionally checking it isn't prefixed with the ScanCode 1 escape code
-    if code >= 0x200 && prefix != 0xE0 {
+    //Keysyms go up to 0xFFFF, covering the range of custom keys (e.g. Cyrillic keysyms are 0x6xx), so custom keys can't be used in
+    //this mode as there'd be no telling them apart. There are no keysyms on the Consumer page though
+    if *mode == KeycodeType::XkbKeysym && !is_consumer(code) {
+        return keysym_to_hid(code);
+    }
+
+    //Check if the code is a custom key or on the Consumer page, if it is, just straight return it as those are the same in every mode
+    if code >= 0x200 && !is_scancode_escaped(code) {
         return Some(code);
-    } else if prefix != 0 {
-        return None;
     }
 
     match &mode {
-        KeycodeType::HID => {
-            if prefix == 0xE0 || prefix == 0x1 {
-                None
-            } else {
-                Some(code)
-            }
-        }
         KeycodeType::ScanCode1 => scancode_to_hid(code),
+        //Only Scan codes can have a prefix
+        _ if code > 0xFF => None,
+        KeycodeType::HID => Some(code),
         KeycodeType::VirtualKey => vk_to_scancode(code, false).and_then(scancode_to_hid),
         KeycodeType::VirtualKeyTranslate => vk_to_scancode(code, true).and_then(scancode_to_hid),
+        KeycodeType::Evdev => evdev_to_hid(code),
+        KeycodeType::XkbKeysym => keysym_to_hid(code),
     }
 }
 
 pub fn hid_to_code(code: u16, mode: &KeycodeType) -> Option<u16> {
-    let prefix = (code & 0xFF00) >> 8;
-    //Check if the code is a custom key, if it is, just straight return it. Additionally checking it isn't prefixed with the ScanCode 1 escape code
-    if code >= 0x200 && prefix != 0xE0 {
+    //Check if the code is a custom key or on the Consumer page, if it is, just straight return it as those are the same in every
+    //mode, except for keysyms which only leave the Consumer page
+    if code >= 0x200
+        && !is_scancode_escaped(code)
+        && (*mode != KeycodeType::XkbKeysym || is_consumer(code))
+    {
         return Some(code);
-    } else if prefix != 0 {
+    } else if code > 0xFF {
         return None;
     }
 
     match &mode {
-        KeycodeType::HID => {
-            if prefix == 0xE0 || prefix == 0x1 {
-                None
-            } else {
-                Some(code)
-            }
-        }
+        KeycodeType::HID => Some(code),
         KeycodeType::ScanCode1 => hid_to_scancode(code),
         KeycodeType::VirtualKey => {
             hid_to_scancode(code).and_then(|code| scancode_to_vk(code, false))
@@ -331,24 +366,47 @@ pub fn hid_to_code(code: u16, mode: &KeycodeType) -> Option<u16> {
         KeycodeType::VirtualKeyTranslate => {
             hid_to_scancode(code).and_then(|code| scancode_to_vk(code, true))
         }
+        KeycodeType::Evdev => hid_to_evdev(code),
+        KeycodeType::XkbKeysym => hid_to_keysym(code),
     }
 }
 
+/// Gives the canonical name of the key with the given `code` in the `mode`, e.g. "Q" for 0x10 in `ScanCode1`
+pub fn key_name(code: u16, mode: &KeycodeType) -> Option<String> {
+    code_to_hid(code, mode).and_then(wooting_analog_common::key_name)
+}
+
+/// Gives the code in the `mode` of the key with the given name or alias
+pub fn key_from_name(name: &str, mode: &KeycodeType) -> Option<u16> {
+    wooting_analog_common::key_from_name(name).and_then(|code| hid_to_code(code, mode))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wooting_analog_common::{ConsumerCodes, HIDCodes};
 
     #[test]
     fn t_code_to_hid() {
+        #[cfg(target_os = "linux")]
+        let _lock = xkb::TEST_KEYMAP_LOCK.lock().unwrap();
         #[cfg(windows)]
         let keycode_types = [
             KeycodeType::HID,
             KeycodeType::ScanCode1,
             KeycodeType::VirtualKey,
             KeycodeType::VirtualKeyTranslate,
+            KeycodeType::Evdev,
         ];
-        #[cfg(not(windows))]
-        let keycode_types = [KeycodeType::HID, KeycodeType::ScanCode1];
+        #[cfg(target_os = "linux")]
+        let keycode_types = [
+            KeycodeType::HID,
+            KeycodeType::ScanCode1,
+            KeycodeType::Evdev,
+            KeycodeType::XkbKeysym,
+        ];
+        #[cfg(not(any(windows, target_os = "linux")))]
+        let keycode_types = [KeycodeType::HID, KeycodeType::ScanCode1, KeycodeType::Evdev];
         for code in 0..0xFFFF {
             let prefix = (code & 0xFF00) >> 8;
             match prefix {
@@ -358,6 +416,9 @@ mod tests {
                         if *keycode == KeycodeType::ScanCode1 {
                             let val = hid_to_code(code, keycode).unwrap_or(0);
                             assert!(val < 0x100 || (val & 0xFF00) == 0xE000|| (val & 0xFF00) == 0xE100);
+                        } else if *keycode == KeycodeType::XkbKeysym {
+                            //Keysyms go up to 0xFFFF, which is checked in `keysyms`
+                            continue;
                         } else {
                             assert!(hid_to_code(code, keycode).unwrap_or(0) < 0x100);
                         }
@@ -365,7 +426,7 @@ mod tests {
                 }
 
                 //ScanCode protected
-                0x01 | 0xE0 => {
+                0x01 | 0xE0 | 0xE1 => {
                     for keycode in keycode_types.iter() {
                         if *keycode == KeycodeType::ScanCode1 {
                             continue;
@@ -380,6 +441,12 @@ mod tests {
                 _x => {
                     //Ensure custom codes are unchanged in all keycode types
                     for keycode in keycode_types.iter() {
+                        //Except for keysyms, which overlap them so custom keys aren't available
+                        if *keycode == KeycodeType::XkbKeysym && !is_consumer(code) {
+                            assert_eq!(code_to_hid(code, keycode), keysym_to_hid(code));
+                            assert_eq!(hid_to_code(code, keycode), None);
+                            continue;
+                        }
                         assert_eq!(code_to_hid(code, keycode).unwrap(), code);
                         assert_eq!(hid_to_code(code, keycode).unwrap(), code);
                     }
@@ -393,5 +460,151 @@ mod tests {
         assert_eq!(scancode_to_hid(0x0001).unwrap(), 0x29);
         //Test if the 0x1 is translated to the 0xE0 correctly
         assert_eq!(scancode_to_hid(0x152).unwrap(), 0x49);
+        //Extended scancodes should be usable in both forms
+        let mode = KeycodeType::ScanCode1;
+        assert_eq!(code_to_hid(0xE052, &mode), Some(HIDCodes::Insert as u16));
+        assert_eq!(code_to_hid(0x152, &mode), Some(HIDCodes::Insert as u16));
+        assert_eq!(hid_to_code(HIDCodes::Insert as u16, &mode), Some(0xE052));
+        assert_eq!(
+            code_to_hid(0xE11D, &mode),
+            Some(HIDCodes::PauseBreak as u16)
+        );
+        assert_eq!(
+            hid_to_code(HIDCodes::PauseBreak as u16, &mode),
+            Some(0xE11D)
+        );
+        //Keys without a scancode shouldn't end up on scancode 0
+        assert_eq!(code_to_hid(0x0000, &mode), None);
+        assert_eq!(hid_to_code(HIDCodes::Find as u16, &mode), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        #[cfg(target_os = "linux")]
+        let _lock = xkb::TEST_KEYMAP_LOCK.lock().unwrap();
+        let mut keycode_types = vec![KeycodeType::HID, KeycodeType::ScanCode1, KeycodeType::Evdev];
+        #[cfg(windows)]
+        keycode_types
+            .extend_from_slice(&[KeycodeType::VirtualKey, KeycodeType::VirtualKeyTranslate]);
+        #[cfg(target_os = "linux")]
+        keycode_types.push(KeycodeType::XkbKeysym);
+
+        for mode in keycode_types.iter() {
+            //Virtual keys & keysyms can be given by several keys (e.g. numpad keys without numlock), so there we can only check it gets
+            //back to the same code
+            let translated = matches!(
+                mode,
+                KeycodeType::VirtualKey | KeycodeType::VirtualKeyTranslate | KeycodeType::XkbKeysym
+            );
+            for hid in 0..0x100 {
+                if let Some(code) = hid_to_code(hid, mode) {
+                    let back = code_to_hid(code, mode);
+                    if translated {
+                        assert_eq!(back.and_then(|hid| hid_to_code(hid, mode)), Some(code));
+                    } else {
+                        assert_eq!(back, Some(hid), "{:?} {:#x} -> {:#x}", mode, hid, code);
+                    }
+                }
+            }
+
+            //Consumer page codes are the same in every mode
+            for code in [
+                ConsumerCodes::PlayPause,
+                ConsumerCodes::VolumeIncrement,
+                ConsumerCodes::ACBack,
+            ]
+            .iter()
+            .cloned()
+            {
+                let code = code as u16;
+                assert_eq!(hid_to_code(code, mode), Some(code));
+                assert_eq!(code_to_hid(code, mode), Some(code));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn keysyms() {
+        let _lock = xkb::TEST_KEYMAP_LOCK.lock().unwrap();
+        xkb::set_keymap(Some(xkb::bundled("us")));
+        let mode = KeycodeType::XkbKeysym;
+        //The keysyms of keys like Escape & the modifiers are above Latin-1
+        for (hid, keysym) in [
+            (HIDCodes::Q, 'q' as u16),
+            (HIDCodes::Escape, 0xFF1B),
+            (HIDCodes::F1, 0xFFBE),
+            (HIDCodes::LeftShift, 0xFFE1),
+            (HIDCodes::ArrowUp, 0xFF52),
+        ]
+        .iter()
+        .cloned()
+        {
+            let hid = hid as u16;
+            assert_eq!(hid_to_code(hid, &mode), Some(keysym));
+            assert_eq!(code_to_hid(keysym, &mode), Some(hid));
+        }
+        xkb::set_keymap(None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn keysyms_beyond_latin1() {
+        let _lock = xkb::TEST_KEYMAP_LOCK.lock().unwrap();
+        xkb::set_keymap(Some(xkb::bundled("ru")));
+        let mode = KeycodeType::XkbKeysym;
+        //Cyrillic_shorti & Cyrillic_SHORTI are in the range of custom keys, but are looked up as keysyms
+        assert_eq!(hid_to_code(HIDCodes::Q as u16, &mode), Some(0x6ca));
+        assert_eq!(code_to_hid(0x6ca, &mode), Some(HIDCodes::Q as u16));
+        assert_eq!(code_to_hid(0x6ea, &mode), Some(HIDCodes::Q as u16));
+        //While custom keys aren't given at all, as they'd be mistaken for keysyms
+        assert_eq!(hid_to_code(0x6ca, &mode), None);
+        assert_eq!(hid_to_code(0x6ca, &KeycodeType::HID), Some(0x6ca));
+        assert_eq!(code_to_hid(0x250, &mode), None);
+        xkb::set_keymap(None);
+    }
+
+    #[test]
+    fn evdev_test() {
+        //KEY_ESC
+        assert_eq!(evdev_to_hid(1).unwrap(), 0x29);
+        //KEY_LEFTCTRL
+        assert_eq!(hid_to_evdev(0xe0).unwrap(), 29);
+        //Keypad ( has no Set 1 scancode, but should still be there for evdev
+        assert_eq!(code_to_hid(179, &KeycodeType::Evdev).unwrap(), 0xb6);
+        assert!(hid_to_scancode(0xb6).is_none());
+
+        //Every mapped key should make it back to the same code
+        for hid in 0..0x100 {
+            if let Some(code) = hid_to_code(hid, &KeycodeType::Evdev) {
+                assert_eq!(code_to_hid(code, &KeycodeType::Evdev), Some(hid));
+            }
+        }
+        for code in 0..0x100 {
+            if let Some(hid) = code_to_hid(code, &KeycodeType::Evdev) {
+                assert_eq!(hid_to_code(hid, &KeycodeType::Evdev), Some(code));
+            }
+        }
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(
+            key_name(0x10, &KeycodeType::ScanCode1),
+            Some("Q".to_owned())
+        );
+        assert_eq!(
+            key_name(0xE01D, &KeycodeType::ScanCode1),
+            Some("RightCtrl".to_owned())
+        );
+        assert_eq!(key_name(1, &KeycodeType::Evdev), Some("Escape".to_owned()));
+        assert_eq!(key_from_name("q", &KeycodeType::ScanCode1), Some(0x10));
+        assert_eq!(key_from_name("Esc", &KeycodeType::Evdev), Some(1));
+        //Consumer page keys are the same in every mode
+        assert_eq!(
+            key_from_name("PlayPause", &KeycodeType::ScanCode1),
+            Some(0xC0CD)
+        );
+        assert_eq!(key_from_name("NotAKey", &KeycodeType::HID), None);
     }
 }
@@ -0,0 +1,299 @@
+//! Layout aware translation of keys on Linux, using an XKB keymap to find which key produces a given keysym.
+//!
+//! libxkbcommon is loaded at runtime, so the SDK still works on systems without it, just without this translation.
+use super::hid_to_evdev;
+use log::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::fs;
+use std::path::Path;
+use std::ptr;
+#[cfg(test)]
+use std::sync::Mutex;
+use std::sync::RwLock;
+use wooting_analog_common::*;
+use xkbcommon_dl::*;
+
+/// XKB keycodes are the evdev codes offset by 8, a leftover from X11
+const EVDEV_OFFSET: u32 = 8;
+
+lazy_static! {
+    //`None` until the default keymap is first needed, it's only tried once as compiling it can be slow
+    static ref KEYMAP: RwLock<Option<Result<XkbKeymap, WootingAnalogResult>>> = RwLock::new(None);
+}
+
+//The keymap is shared by the whole process, so tests which set it hold this until they're done with it
+#[cfg(test)]
+lazy_static! {
+    pub static ref TEST_KEYMAP_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// The keysyms produced by each key of an XKB keymap
+#[derive(Debug, Clone, PartialEq)]
+pub struct XkbKeymap {
+    //<HID code, keysym on the first shift level>
+    keysyms: HashMap<u16, u32>,
+    //<keysym, HID code>, including the keysyms of the second shift level, so e.g. 'Z' also finds the z key
+    hid_codes: HashMap<u32, u16>,
+}
+
+impl XkbKeymap {
+    /// Compiles the keymap for the given layout & variant (e.g. "fr" & "oss") using the XKB data installed on the system. Empty
+    /// names are filled in by libxkbcommon, from the `XKB_DEFAULT_*` environment variables or its own defaults
+    pub fn from_names(layout: &str, variant: &str) -> SDKResult<XkbKeymap> {
+        let layout = CString::new(layout).map_err(|_| WootingAnalogResult::InvalidArgument);
+        let variant = CString::new(variant).map_err(|_| WootingAnalogResult::InvalidArgument);
+        let (layout, variant) = match (layout, variant) {
+            (Ok(layout), Ok(variant)) => (layout, variant),
+            _ => return Err(WootingAnalogResult::InvalidArgument).into(),
+        };
+
+        let names = xkb_rule_names {
+            rules: ptr::null(),
+            model: ptr::null(),
+            layout: layout.as_ptr(),
+            variant: variant.as_ptr(),
+            options: ptr::null(),
+        };
+        Self::compile(|xkb, context| unsafe {
+            (xkb.xkb_keymap_new_from_names)(
+                context,
+                &names,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            )
+        })
+    }
+
+    /// Loads a keymap in the XKB text format, e.g. one written by `xkbcomp`
+    pub fn from_string(keymap: &str) -> SDKResult<XkbKeymap> {
+        let keymap = match CString::new(keymap) {
+            Ok(keymap) => keymap,
+            Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+        };
+        Self::compile(|xkb, context| unsafe {
+            (xkb.xkb_keymap_new_from_string)(
+                context,
+                keymap.as_ptr(),
+                xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            )
+        })
+    }
+
+    /// Loads a keymap in the XKB text format from the file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> SDKResult<XkbKeymap> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(keymap) => Self::from_string(&keymap),
+            Err(e) => {
+                error!(
+                    "Unable to read XKB keymap {}: {}",
+                    path.as_ref().display(),
+                    e
+                );
+                Err(WootingAnalogResult::InvalidArgument).into()
+            }
+        }
+    }
+
+    fn compile<F>(new_keymap: F) -> SDKResult<XkbKeymap>
+    where
+        F: FnOnce(&XkbCommon, *mut xkb_context) -> *mut xkb_keymap,
+    {
+        let xkb = match xkbcommon_option() {
+            Some(xkb) => xkb,
+            None => {
+                warn!("libxkbcommon could not be loaded, layout translation is unavailable");
+                return Err(WootingAnalogResult::NotAvailable).into();
+            }
+        };
+
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                error!("Unable to create XKB context");
+                return Err(WootingAnalogResult::Failure).into();
+            }
+            let keymap = new_keymap(xkb, context);
+            (xkb.xkb_context_unref)(context);
+            if keymap.is_null() {
+                error!("Unable to compile XKB keymap");
+                return Err(WootingAnalogResult::InvalidArgument).into();
+            }
+
+            let mut keysyms = HashMap::new();
+            let mut hid_codes = HashMap::new();
+            let mut shifted = vec![];
+            //Going through in order of HID code, so that when several keys produce the same keysym, it's always the same one found
+            for hid in 0..0x100 {
+                let keycode = match hid_to_evdev(hid) {
+                    Some(evdev) => u32::from(evdev) + EVDEV_OFFSET,
+                    None => continue,
+                };
+                if let Some(sym) = key_sym(xkb, keymap, keycode, 0) {
+                    keysyms.insert(hid, sym);
+                    hid_codes.entry(sym).or_insert(hid);
+                }
+                if let Some(sym) = key_sym(xkb, keymap, keycode, 1) {
+                    shifted.push((sym, hid));
+                }
+            }
+            //The unshifted keysyms take priority, as e.g. '1' is on the first level of the numpad, but the second on AZERTY
+            for (sym, hid) in shifted {
+                hid_codes.entry(sym).or_insert(hid);
+            }
+            (xkb.xkb_keymap_unref)(keymap);
+
+            Ok(XkbKeymap { keysyms, hid_codes }).into()
+        }
+    }
+
+    /// Gives the keysym the key produces without any modifiers
+    pub fn hid_to_keysym(&self, code: u16) -> Option<u32> {
+        self.keysyms.get(&code).copied()
+    }
+
+    /// Gives the key which produces the keysym, either on its own or with shift
+    pub fn keysym_to_hid(&self, keysym: u32) -> Option<u16> {
+        self.hid_codes.get(&keysym).copied()
+    }
+}
+
+/// Gives the only keysym the key produces on the `level` of the first layout group
+unsafe fn key_sym(
+    xkb: &XkbCommon,
+    keymap: *mut xkb_keymap,
+    keycode: u32,
+    level: u32,
+) -> Option<u32> {
+    let mut syms: *const xkb_keysym_t = ptr::null();
+    if (xkb.xkb_keymap_key_get_syms_by_level)(keymap, keycode, 0, level, &mut syms) == 1 {
+        Some(*syms)
+    } else {
+        None
+    }
+}
+
+/// Sets the keymap used by the `XkbKeysym` keycode mode, `None` goes back to the libxkbcommon default keymap
+pub fn set_keymap(keymap: Option<XkbKeymap>) {
+    *KEYMAP.write().unwrap() = keymap.map(Ok);
+}
+
+/// Whether libxkbcommon is present, which is needed for the `XkbKeysym` keycode mode
+pub fn is_available() -> bool {
+    xkbcommon_option().is_some()
+}
+
+fn with_keymap<T, F: FnOnce(&XkbKeymap) -> Option<T>>(f: F) -> Option<T> {
+    if let Some(keymap) = KEYMAP.read().unwrap().as_ref() {
+        return keymap.as_ref().ok().and_then(f);
+    }
+
+    let mut keymap = KEYMAP.write().unwrap();
+    let keymap = keymap.get_or_insert_with(|| {
+        let default = XkbKeymap::from_names("", "").0;
+        if let Err(e) = &default {
+            warn!("Unable to load the default XKB keymap: {:?}", e);
+        }
+        default
+    });
+    keymap.as_ref().ok().and_then(f)
+}
+
+/// Gives the keysym the key produces, unless it's one of the Unicode keysyms which don't fit in a code
+pub fn hid_to_keysym(code: u16) -> Option<u16> {
+    with_keymap(|keymap| keymap.hid_to_keysym(code)).and_then(|sym| u16::try_from(sym).ok())
+}
+
+pub fn keysym_to_hid(code: u16) -> Option<u16> {
+    with_keymap(|keymap| keymap.keysym_to_hid(u32::from(code)))
+}
+
+/// Loads the keymap called `name` from `test_data/xkb`
+#[cfg(test)]
+pub fn bundled(name: &str) -> XkbKeymap {
+    XkbKeymap::from_file(format!(
+        "{}/test_data/xkb/{}.xkb",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .0
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn us_layout() {
+        let keymap = bundled("us");
+        assert_eq!(keymap.hid_to_keysym(HIDCodes::Q as u16), Some('q' as u32));
+        assert_eq!(keymap.keysym_to_hid('z' as u32), Some(HIDCodes::Z as u16));
+        assert_eq!(keymap.keysym_to_hid('Z' as u32), Some(HIDCodes::Z as u16));
+        assert_eq!(keymap.keysym_to_hid('1' as u32), Some(HIDCodes::N1 as u16));
+        assert_eq!(keymap.keysym_to_hid('!' as u32), Some(HIDCodes::N1 as u16));
+        assert_eq!(
+            keymap.hid_to_keysym(HIDCodes::Escape as u16),
+            Some(keysyms::Escape)
+        );
+    }
+
+    #[test]
+    fn azerty_layout() {
+        let keymap = bundled("fr");
+        //The key right of tab inputs 'a' on AZERTY, while the key which inputs 'q' is right of caps lock
+        assert_eq!(keymap.hid_to_keysym(HIDCodes::Q as u16), Some('a' as u32));
+        assert_eq!(keymap.keysym_to_hid('q' as u32), Some(HIDCodes::A as u16));
+        assert_eq!(keymap.keysym_to_hid('Z' as u32), Some(HIDCodes::W as u16));
+        assert_eq!(
+            keymap.keysym_to_hid('m' as u32),
+            Some(HIDCodes::Semicolon as u16)
+        );
+        //The digits are on the second level of the number row
+        assert_eq!(keymap.keysym_to_hid('1' as u32), Some(HIDCodes::N1 as u16));
+        assert_eq!(
+            keymap.hid_to_keysym(HIDCodes::N1 as u16),
+            Some(keysyms::ampersand)
+        );
+    }
+
+    #[test]
+    fn cyrillic_layout() {
+        let keymap = bundled("ru");
+        //The keysyms of non-Latin layouts are above Latin-1, e.g. the key right of tab inputs Cyrillic_shorti
+        assert_eq!(
+            keymap.hid_to_keysym(HIDCodes::Q as u16),
+            Some(keysyms::Cyrillic_shorti)
+        );
+        assert_eq!(
+            keymap.keysym_to_hid(keysyms::Cyrillic_SHORTI),
+            Some(HIDCodes::Q as u16)
+        );
+        assert_eq!(keymap.keysym_to_hid('q' as u32), None);
+    }
+
+    #[test]
+    fn keysyms_round_trip() {
+        for name in ["us", "fr", "ru"].iter() {
+            let keymap = bundled(name);
+            //Some keysyms are produced by several keys (e.g. Return & KP_Enter can be), so check the key found produces it
+            for &sym in keymap.keysyms.values() {
+                let hid = keymap.keysym_to_hid(sym).unwrap();
+                assert_eq!(keymap.hid_to_keysym(hid), Some(sym), "{} {:x}", name, sym);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_keymap() {
+        assert_eq!(
+            XkbKeymap::from_string("xkb_keymap { nonsense }").0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(
+            XkbKeymap::from_file("does/not/exist.xkb").0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Works out how each key is moving from the reports of the devices, e.g. how fast a key went down for rhythm games, or how long
+//! it was held for typing analytics. This is fed every report as it's published by a plugin, so it doesn't depend on how often
+//! the SDK is read.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use wooting_analog_common::*;
+
+/// The keys of a single device, as reported by one plugin
+#[derive(Debug, Default)]
+struct DeviceKeys {
+    //By HID code
+    keys: HashMap<u16, KeyKinematics>,
+    last_report: Option<Instant>,
+}
+
+impl DeviceKeys {
+    fn report<K>(&mut self, pressed: K, time: u64, at: Instant)
+    where
+        K: Iterator<Item = (u16, f32)> + Clone,
+    {
+        let elapsed = self
+            .last_report
+            .map(|last| at.duration_since(last).as_secs_f32())
+            .filter(|&elapsed| elapsed > 0.0);
+        self.last_report = Some(at);
+
+        for (code, depth) in pressed.clone() {
+            update(self.keys.entry(code).or_default(), depth, elapsed, time);
+        }
+        for (code, key) in self.keys.iter_mut() {
+            //Keys which are at rest don't need updating
+            if !key.is_pressed() && key.velocity == 0.0 && key.acceleration == 0.0 {
+                continue;
+            }
+            if !pressed.clone().any(|(pressed, _)| pressed == *code) {
+                update(key, 0.0, elapsed, time);
+            }
+        }
+    }
+}
+
+/// Moves `key` to `depth`, `elapsed` seconds after the report before, which arrived at `time`
+fn update(key: &mut KeyKinematics, depth: f32, elapsed: Option<f32>, time: u64) {
+    let velocity = elapsed.map_or(0.0, |elapsed| (depth - key.depth) / elapsed);
+    key.acceleration = elapsed.map_or(0.0, |elapsed| (velocity - key.velocity) / elapsed);
+    key.velocity = velocity;
+    if depth > 0.0 && !key.is_pressed() {
+        key.pressed_at = time;
+        key.released_at = 0;
+        key.peak_depth = depth;
+    } else if depth == 0.0 && key.is_pressed() {
+        key.released_at = time;
+    }
+    key.peak_depth = key.peak_depth.max(depth);
+    key.depth = depth;
+}
+
+/// The kinematics of every key of every device, kept by the index of the plugin which reported the device & its device ID so
+/// that devices reported by more than one plugin don't get mixed up
+#[derive(Debug)]
+pub struct KinematicsTracker {
+    start: Instant,
+    devices: Mutex<HashMap<(usize, DeviceID), DeviceKeys>>,
+}
+
+impl Default for KinematicsTracker {
+    fn default() -> Self {
+        KinematicsTracker {
+            start: Instant::now(),
+            devices: Default::default(),
+        }
+    }
+}
+
+impl KinematicsTracker {
+    /// Gives the time of `at` in microseconds since the tracker was created, which is what the times of `KeyKinematics` are in
+    pub fn time(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.start).as_micros() as u64
+    }
+
+    /// Updates the keys of the device with `device_id` from the `plugin` at index `plugin` with a report which arrived `at`.
+    /// `pressed` gives the pressed keys (HID code, analog value), keys which aren't in it are released
+    pub fn report<K>(&self, plugin: usize, device_id: DeviceID, pressed: K, at: Instant)
+    where
+        K: Iterator<Item = (u16, f32)> + Clone,
+    {
+        let time = self.time(at);
+        self.devices
+            .lock()
+            .unwrap()
+            .entry((plugin, device_id))
+            .or_default()
+            .report(pressed, time, at);
+    }
+
+    /// Gives the kinematics of a key on the device with `device_id`, or on any device if it's 0, from those devices for which `owns`
+    /// gives true. `codes` gives the HID codes which make up the key on a device (more than one if keys are remapped onto it), the
+    /// one pressed furthest is given, or the one pressed last if they're pressed as far. This is `None` if no device matched
+    pub fn key<O, C>(&self, device_id: DeviceID, owns: O, codes: C) -> Option<KeyKinematics>
+    where
+        O: Fn(usize, DeviceID) -> bool,
+        C: Fn(DeviceID) -> Vec<u16>,
+    {
+        let devices = self.devices.lock().unwrap();
+        let mut found = None;
+        for (&(plugin, id), device) in devices.iter() {
+            if (device_id != 0 && id != device_id) || !owns(plugin, id) {
+                continue;
+            }
+
+            let best = found.get_or_insert_with(KeyKinematics::default);
+            for key in codes(id).iter().filter_map(|code| device.keys.get(code)) {
+                if (key.depth, key.pressed_at) > (best.depth, best.pressed_at) {
+                    *best = *key;
+                }
+            }
+        }
+        found
+    }
+
+    pub fn clear(&self) {
+        self.devices.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const A: u16 = 0x04;
+    const B: u16 = 0x05;
+
+    fn analog(keys: &[(u16, f32)]) -> impl Iterator<Item = (u16, f32)> + Clone + '_ {
+        keys.iter().copied()
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < expected.abs() * 1e-4,
+            "{} isn't {}",
+            value,
+            expected
+        );
+    }
+
+    fn key(tracker: &KinematicsTracker, device_id: DeviceID, code: u16) -> KeyKinematics {
+        tracker.key(device_id, |_, _| true, |_| vec![code]).unwrap()
+    }
+
+    #[test]
+    fn press_and_release() {
+        let tracker = KinematicsTracker::default();
+        let start = tracker.start;
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        tracker.report(0, 1, analog(&[]), at(0));
+        tracker.report(0, 1, analog(&[(A, 0.25)]), at(10));
+        let a = key(&tracker, 1, A);
+        assert_eq!(a.depth, 0.25);
+        assert_close(a.velocity, 25.0);
+        assert_close(a.acceleration, 2500.0);
+        assert_eq!(a.pressed_at, 10_000);
+        assert_eq!(a.released_at, 0);
+
+        tracker.report(0, 1, analog(&[(A, 0.75)]), at(20));
+        tracker.report(0, 1, analog(&[(A, 0.5)]), at(30));
+        let a = key(&tracker, 1, A);
+        assert_eq!(a.peak_depth, 0.75);
+        assert_close(a.velocity, -25.0);
+        assert_close(a.acceleration, -7500.0);
+
+        tracker.report(0, 1, analog(&[]), at(40));
+        let a = key(&tracker, 1, A);
+        assert!(!a.is_pressed());
+        assert_eq!(a.peak_depth, 0.75);
+        assert_eq!(a.released_at, 40_000);
+        assert_eq!(a.press_duration(), Some(Duration::from_millis(30)));
+
+        //Once the key comes to rest it stays as it was released
+        tracker.report(0, 1, analog(&[]), at(50));
+        tracker.report(0, 1, analog(&[]), at(60));
+        assert_eq!(key(&tracker, 1, A).velocity, 0.0);
+        assert_eq!(key(&tracker, 1, A).released_at, 40_000);
+
+        //A new press starts over
+        tracker.report(0, 1, analog(&[(A, 0.5)]), at(70));
+        let a = key(&tracker, 1, A);
+        assert_eq!(
+            (a.peak_depth, a.pressed_at, a.released_at),
+            (0.5, 70_000, 0)
+        );
+        assert_eq!(a.press_duration(), None);
+    }
+
+    #[test]
+    fn devices_are_combined() {
+        let tracker = KinematicsTracker::default();
+        let at = tracker.start + Duration::from_millis(10);
+        tracker.report(0, 1, analog(&[(A, 0.25)]), at);
+        tracker.report(1, 1, analog(&[(A, 0.75)]), at);
+        tracker.report(0, 2, analog(&[(A, 0.5), (B, 1.0)]), at);
+
+        assert_eq!(key(&tracker, 0, A).depth, 0.75);
+        assert_eq!(key(&tracker, 2, A).depth, 0.5);
+        //Devices which aren't owned are skipped
+        assert_eq!(
+            tracker
+                .key(1, |plugin, _| plugin == 0, |_| vec![A])
+                .map(|a| a.depth),
+            Some(0.25)
+        );
+        //Keys remapped onto the key are read as well
+        assert_eq!(
+            tracker.key(2, |_, _| true, |_| vec![A, B]).map(|a| a.depth),
+            Some(1.0)
+        );
+        assert_eq!(key(&tracker, 1, B), KeyKinematics::default());
+        assert_eq!(tracker.key(3, |_, _| true, |_| vec![A]), None);
+    }
+}
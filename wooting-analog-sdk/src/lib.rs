@@ -19,7 +19,17 @@ extern crate wooting_analog_plugin_dev;
 extern crate shared_memory;
 
 //library modules
+mod aliases;
+mod axis;
 mod cplugin;
+mod events;
+mod kinematics;
+mod mouse;
+mod owners;
+mod remap;
+mod rustplugin;
+mod watchdog;
 pub mod ffi;
 pub mod keycode;
 pub mod sdk;
+pub mod session;
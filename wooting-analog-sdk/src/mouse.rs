@@ -0,0 +1,371 @@
+//! Emulation of a mouse from analog keys, e.g. IJKL moving the pointer faster the further they're pressed, for people who can't
+//! use a mouse. The keys are read through the SDK like any other reader, and the mouse is a virtual device created through
+//! `/dev/uinput` on Linux.
+#[cfg(target_os = "linux")]
+pub mod uinput;
+
+use log::*;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use wooting_analog_common::*;
+
+/// How often the keys are read & the mouse is moved
+pub const UPDATE_INTERVAL: Duration = Duration::from_millis(8);
+
+/// How far a key bound to a button has to be pressed to hold the button down
+const CLICK_POINT: f32 = 0.5;
+/// How far a key has to come back up to let go of the button, which is above the click point so that holding a key around it
+/// doesn't click repeatedly
+const RELEASE_POINT: f32 = 0.4;
+
+const BUTTONS: [MouseAction; 3] = [
+    MouseAction::LeftButton,
+    MouseAction::RightButton,
+    MouseAction::MiddleButton,
+];
+
+/// The keys bound to mouse actions (by HID code) and how fast they move the mouse
+#[derive(Debug, Clone, PartialEq)]
+pub struct MouseConfig {
+    pub bindings: BTreeMap<u16, MouseAction>,
+    pub pointer: MouseCurve,
+    pub scroll: MouseCurve,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        MouseConfig {
+            bindings: BTreeMap::new(),
+            pointer: MouseCurve {
+                deadzone: 0.1,
+                exponent: 2.0,
+                max_speed: 1500.0,
+            },
+            scroll: MouseCurve {
+                deadzone: 0.1,
+                exponent: 1.5,
+                max_speed: 20.0,
+            },
+        }
+    }
+}
+
+/// What the mouse did during an update. Scrolling up & right are positive
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MouseReport {
+    pub x: i32,
+    pub y: i32,
+    pub vertical: i32,
+    pub horizontal: i32,
+    /// The buttons which were pressed (true) or released (false)
+    pub buttons: Vec<(MouseAction, bool)>,
+}
+
+impl MouseReport {
+    pub fn is_empty(&self) -> bool {
+        *self == MouseReport::default()
+    }
+}
+
+/// Where the reports of the emulated mouse go
+pub trait MouseOutput {
+    fn send(&mut self, report: &MouseReport) -> io::Result<()>;
+}
+
+/// Works out what the mouse does from the keys which are pressed
+#[derive(Debug, Default)]
+pub struct MouseEmulator {
+    //Movement which is too small to report yet, as x, y, vertical & horizontal
+    remainder: [f32; 4],
+    buttons: [bool; 3],
+}
+
+impl MouseEmulator {
+    /// Moves the mouse on by `elapsed` with the `analog` data (HID code -> analog value) which was read
+    pub fn update(
+        &mut self,
+        config: &MouseConfig,
+        analog: &HashMap<u16, f32>,
+        elapsed: Duration,
+    ) -> MouseReport {
+        //Each action is as far as the furthest pressed key bound to it
+        let mut depths: HashMap<MouseAction, f32> = HashMap::new();
+        for (code, &action) in config.bindings.iter() {
+            let depth = analog.get(code).copied().unwrap_or(0.0);
+            let total = depths.entry(action).or_insert(0.0);
+            *total = total.max(depth);
+        }
+        let depth = |action| depths.get(&action).copied().unwrap_or(0.0);
+        let speed = |curve: &MouseCurve, positive, negative| {
+            curve.speed(depth(positive)) - curve.speed(depth(negative))
+        };
+
+        let mut x = speed(
+            &config.pointer,
+            MouseAction::MoveRight,
+            MouseAction::MoveLeft,
+        );
+        let mut y = speed(&config.pointer, MouseAction::MoveDown, MouseAction::MoveUp);
+        //Moving diagonally isn't faster than moving straight
+        let length = (x * x + y * y).sqrt();
+        if length > config.pointer.max_speed {
+            x *= config.pointer.max_speed / length;
+            y *= config.pointer.max_speed / length;
+        }
+        let vertical = speed(
+            &config.scroll,
+            MouseAction::ScrollUp,
+            MouseAction::ScrollDown,
+        );
+        let horizontal = speed(
+            &config.scroll,
+            MouseAction::ScrollRight,
+            MouseAction::ScrollLeft,
+        );
+
+        let mut moved = [0; 4];
+        let elapsed = elapsed.as_secs_f32();
+        for (i, &speed) in [x, y, vertical, horizontal].iter().enumerate() {
+            //Leftover movement isn't carried over to the next time the mouse moves, or when it turns around
+            if speed == 0.0 || self.remainder[i] * speed < 0.0 {
+                self.remainder[i] = 0.0;
+            }
+            let distance = self.remainder[i] + speed * elapsed;
+            moved[i] = distance.trunc() as i32;
+            self.remainder[i] = distance.fract();
+        }
+
+        let mut buttons = vec![];
+        for (i, &button) in BUTTONS.iter().enumerate() {
+            let depth = depth(button);
+            let pressed = if self.buttons[i] {
+                depth >= RELEASE_POINT
+            } else {
+                depth >= CLICK_POINT
+            };
+            if pressed != self.buttons[i] {
+                self.buttons[i] = pressed;
+                buttons.push((button, pressed));
+            }
+        }
+
+        MouseReport {
+            x: moved[0],
+            y: moved[1],
+            vertical: moved[2],
+            horizontal: moved[3],
+            buttons,
+        }
+    }
+}
+
+/// Runs the mouse emulation on its own thread until it's stopped
+pub struct MouseEmulation {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MouseEmulation {
+    /// Starts moving the `output` every `UPDATE_INTERVAL`. `read` gives the analog data (HID code -> analog value) along with the
+    /// config to use, nothing is pressed while it gives `None`
+    pub fn start<R, O>(mut read: R, mut output: O) -> Self
+    where
+        R: FnMut() -> Option<(HashMap<u16, f32>, Arc<MouseConfig>)> + Send + 'static,
+        O: MouseOutput + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let t_running = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            let mut emulator = MouseEmulator::default();
+            let mut config = Arc::new(MouseConfig::default());
+            let mut last_update = Instant::now();
+            while t_running.load(Ordering::SeqCst) {
+                thread::sleep(UPDATE_INTERVAL);
+                let analog = match read() {
+                    Some((analog, current)) => {
+                        config = current;
+                        analog
+                    }
+                    None => HashMap::new(),
+                };
+                let now = Instant::now();
+                let report = emulator.update(&config, &analog, now - last_update);
+                last_update = now;
+                if !report.is_empty() {
+                    if let Err(e) = output.send(&report) {
+                        error!("Stopping mouse emulation, couldn't move the mouse: {}", e);
+                        break;
+                    }
+                }
+            }
+            t_running.store(false, Ordering::SeqCst);
+        });
+        MouseEmulation {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns false if the emulation stopped by itself because the mouse couldn't be moved
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map_or(false, |_| self.running.load(Ordering::SeqCst))
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Mouse emulation thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for MouseEmulation {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const I: u16 = 0x0c;
+    const J: u16 = 0x0d;
+    const K: u16 = 0x0e;
+    const L: u16 = 0x0f;
+    const SPACE: u16 = 0x2c;
+    const SEMICOLON: u16 = 0x33;
+
+    fn config() -> MouseConfig {
+        let mut config = MouseConfig {
+            pointer: MouseCurve {
+                deadzone: 0.0,
+                exponent: 1.0,
+                max_speed: 1000.0,
+            },
+            ..Default::default()
+        };
+        config.bindings.insert(I, MouseAction::MoveUp);
+        config.bindings.insert(J, MouseAction::MoveLeft);
+        config.bindings.insert(K, MouseAction::MoveDown);
+        config.bindings.insert(L, MouseAction::MoveRight);
+        config.bindings.insert(SEMICOLON, MouseAction::ScrollDown);
+        config.bindings.insert(SPACE, MouseAction::LeftButton);
+        config
+    }
+
+    fn analog(keys: &[(u16, f32)]) -> HashMap<u16, f32> {
+        keys.iter().copied().collect()
+    }
+
+    #[test]
+    fn curve() {
+        let curve = MouseCurve {
+            deadzone: 0.5,
+            exponent: 2.0,
+            max_speed: 100.0,
+        };
+        assert_eq!(curve.speed(0.4), 0.0);
+        assert_eq!(curve.speed(0.75), 25.0);
+        assert_eq!(curve.speed(1.0), 100.0);
+        assert!(curve.is_valid());
+        assert!(!MouseCurve {
+            deadzone: 1.0,
+            ..curve
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn moves_proportionally() {
+        let config = config();
+        let mut emulator = MouseEmulator::default();
+        let report = emulator.update(
+            &config,
+            &analog(&[(L, 0.5), (I, 0.25)]),
+            Duration::from_millis(100),
+        );
+        assert_eq!((report.x, report.y), (50, -25));
+
+        //Diagonals are kept to the max speed
+        let report = emulator.update(
+            &config,
+            &analog(&[(L, 1.0), (K, 1.0)]),
+            Duration::from_millis(100),
+        );
+        assert_eq!((report.x, report.y), (70, 70));
+
+        //Movement which is too small to report adds up
+        let slow = analog(&[(J, 0.004)]);
+        assert_eq!(
+            emulator
+                .update(&config, &slow, Duration::from_millis(100))
+                .x,
+            0
+        );
+        assert_eq!(
+            emulator
+                .update(&config, &slow, Duration::from_millis(200))
+                .x,
+            -1
+        );
+
+        let report = emulator.update(
+            &config,
+            &analog(&[(SEMICOLON, 1.0)]),
+            Duration::from_secs(1),
+        );
+        assert_eq!((report.x, report.vertical), (0, -20));
+    }
+
+    #[test]
+    fn buttons_click_past_halfway() {
+        let config = config();
+        let mut emulator = MouseEmulator::default();
+        let mut update = |depth| {
+            emulator
+                .update(&config, &analog(&[(SPACE, depth)]), UPDATE_INTERVAL)
+                .buttons
+        };
+        assert_eq!(update(0.3), vec![]);
+        assert_eq!(update(0.6), vec![(MouseAction::LeftButton, true)]);
+        //It isn't let go until the key is back above the release point
+        assert_eq!(update(0.45), vec![]);
+        assert_eq!(update(0.2), vec![(MouseAction::LeftButton, false)]);
+    }
+
+    struct Recorder(Arc<std::sync::Mutex<Vec<MouseReport>>>);
+
+    impl MouseOutput for Recorder {
+        fn send(&mut self, report: &MouseReport) -> io::Result<()> {
+            self.0.lock().unwrap().push(report.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn emulation_thread_moves_output() {
+        let reports = Arc::new(std::sync::Mutex::new(vec![]));
+        let config = Arc::new(config());
+        let mut emulation = MouseEmulation::start(
+            move || Some((analog(&[(L, 1.0), (SPACE, 1.0)]), Arc::clone(&config))),
+            Recorder(Arc::clone(&reports)),
+        );
+        thread::sleep(Duration::from_millis(200));
+        assert!(emulation.is_running());
+        emulation.stop();
+        assert!(!emulation.is_running());
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports[0].buttons, vec![(MouseAction::LeftButton, true)]);
+        assert!(reports.iter().all(|report| report.x > 0 && report.y == 0));
+    }
+}
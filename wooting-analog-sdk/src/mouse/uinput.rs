@@ -0,0 +1,107 @@
+//! A virtual mouse created through `/dev/uinput`, which the kernel treats like any other mouse, so it works under X11, Wayland
+//! and on the console alike.
+//!
+//! Creating it needs write access to `/dev/uinput`, which is usually given through a udev rule or the `input` group.
+use super::{MouseOutput, MouseReport};
+use std::io;
+use std::path::Path;
+use wooting_analog_common::MouseAction;
+use wooting_analog_plugin_dev::evdev::{InputId, EV_KEY, EV_REL};
+use wooting_analog_plugin_dev::uinput::*;
+
+const DEVICE_NAME: &str = "Wooting Analog Mouse";
+
+//From linux/input-event-codes.h
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+
+fn button_code(action: MouseAction) -> Option<u16> {
+    match action {
+        MouseAction::LeftButton => Some(BTN_LEFT),
+        MouseAction::RightButton => Some(BTN_RIGHT),
+        MouseAction::MiddleButton => Some(BTN_MIDDLE),
+        _ => None,
+    }
+}
+
+/// A mouse created through uinput, which is removed again when this is dropped
+pub struct UInputMouse {
+    device: UInputDevice,
+}
+
+impl UInputMouse {
+    pub fn new() -> io::Result<Self> {
+        UInputMouse::open(UINPUT_PATH)
+    }
+
+    /// Creates the mouse through the uinput device at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let setup = UInputSetup {
+            name: DEVICE_NAME.to_owned(),
+            id: InputId {
+                bustype: BUS_VIRTUAL,
+                vendor: 0x31e3,
+                product: 0xfe00,
+                version: 1,
+            },
+            keys: vec![BTN_LEFT, BTN_RIGHT, BTN_MIDDLE],
+            relative_axes: vec![REL_X, REL_Y, REL_WHEEL, REL_HWHEEL],
+            ..Default::default()
+        };
+        Ok(UInputMouse {
+            device: UInputDevice::create_at(path, &setup)?,
+        })
+    }
+}
+
+impl MouseOutput for UInputMouse {
+    fn send(&mut self, report: &MouseReport) -> io::Result<()> {
+        let axes = [
+            (REL_X, report.x),
+            (REL_Y, report.y),
+            (REL_WHEEL, report.vertical),
+            (REL_HWHEEL, report.horizontal),
+        ];
+        for &(code, value) in axes.iter().filter(|(_, value)| *value != 0) {
+            self.device.send(EV_REL, code, value)?;
+        }
+        for &(action, pressed) in report.buttons.iter() {
+            if let Some(code) = button_code(action) {
+                self.device.send(EV_KEY, code, i32::from(pressed))?;
+            }
+        }
+        self.device.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Needs write access to /dev/uinput, e.g. run as root on a headless box with the uinput module loaded
+    #[test]
+    #[ignore]
+    fn create_mouse() {
+        let mut mouse = UInputMouse::new().unwrap();
+        mouse
+            .send(&MouseReport {
+                x: 10,
+                y: -10,
+                vertical: 1,
+                horizontal: 0,
+                buttons: vec![(MouseAction::LeftButton, true)],
+            })
+            .unwrap();
+        mouse
+            .send(&MouseReport {
+                buttons: vec![(MouseAction::LeftButton, false)],
+                ..Default::default()
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+//! Works out which plugin owns each device when more than one plugin reports the same hardware, e.g. a plugin talking to a
+//! daemon next to the official plugin, or an old and a new version of a plugin in different plugin directories.
+//!
+//! Devices are the same if they have the same vendor ID, product ID and serial number (or the same device ID when there's no
+//! serial number to go by). The device is owned by the plugin with the highest priority, or the one loaded first if they're the
+//! same, and is only listed, read & reported in device events through that plugin.
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::slice;
+use wooting_analog_common::*;
+
+/// What a device is recognised by across plugins
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Identity {
+    Serial {
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: String,
+    },
+    Id(DeviceID),
+}
+
+impl Identity {
+    fn of(device: &DeviceInfo) -> Self {
+        match &device.details.serial_number {
+            Some(serial_number) if !serial_number.is_empty() => Identity::Serial {
+                vendor_id: device.vendor_id,
+                product_id: device.product_id,
+                serial_number: serial_number.clone(),
+            },
+            _ => Identity::Id(device.device_id),
+        }
+    }
+}
+
+/// The devices of a single plugin, split by whether it owns them
+#[derive(Debug, Default, PartialEq)]
+struct PluginDevices {
+    owned: Vec<DeviceID>,
+    //Devices which another plugin with a higher priority also reports
+    shadowed: Vec<DeviceID>,
+}
+
+#[derive(Debug, Default)]
+pub struct DeviceOwners {
+    //The `devices_changed` count of each plugin this was worked out for
+    changes: Vec<u32>,
+    plugins: Vec<PluginDevices>,
+}
+
+impl DeviceOwners {
+    /// Works out the owner of every device. `plugins` gives the priority and devices of each plugin in the order they were
+    /// loaded, along with a count which changes whenever its devices do
+    pub fn resolve<I>(plugins: I) -> Self
+    where
+        I: IntoIterator<Item = (i32, u32, Vec<DeviceInfo>)>,
+    {
+        let mut changes = vec![];
+        let mut devices = vec![];
+        for (priority, changed, plugin_devices) in plugins {
+            changes.push(changed);
+            devices.push((priority, plugin_devices));
+        }
+
+        //Stable, so plugins with the same priority stay in the order they were loaded
+        let mut order: Vec<usize> = (0..devices.len()).collect();
+        order.sort_by_key(|&i| Reverse(devices[i].0));
+
+        let mut owners: HashMap<Identity, usize> = HashMap::new();
+        let mut plugins: Vec<PluginDevices> = devices.iter().map(|_| Default::default()).collect();
+        for i in order {
+            for device in devices[i].1.iter() {
+                let owner = *owners.entry(Identity::of(device)).or_insert(i);
+                if owner == i {
+                    plugins[i].owned.push(device.device_id);
+                } else {
+                    plugins[i].shadowed.push(device.device_id);
+                }
+            }
+        }
+
+        DeviceOwners { changes, plugins }
+    }
+
+    /// Returns true if this was worked out for the devices the plugins currently have, given by the count of changes to the
+    /// devices of each plugin
+    pub fn is_current<I: ExactSizeIterator<Item = u32>>(&self, changes: I) -> bool {
+        changes.len() == self.changes.len()
+            && changes.zip(self.changes.iter()).all(|(a, b)| a == *b)
+    }
+
+    /// Returns true if the plugin at `plugin` owns the device with `device_id`, or doesn't know of it
+    pub fn owns(&self, plugin: usize, device_id: DeviceID) -> bool {
+        self.plugins
+            .get(plugin)
+            .map_or(true, |devices| !devices.shadowed.contains(&device_id))
+    }
+
+    /// Gives the devices to read from the plugin at `plugin` for a read of `device_id`, so that devices which are owned by another
+    /// plugin aren't read twice. A `device_id` of 0 is given back as is unless the plugin has devices it doesn't own, or
+    /// `split` is set to read each device on its own
+    pub fn read_targets<'a>(
+        &'a self,
+        plugin: usize,
+        device_id: &'a DeviceID,
+        split: bool,
+    ) -> &'a [DeviceID] {
+        match self.plugins.get(plugin) {
+            Some(devices)
+                if *device_id == 0
+                    && (!devices.shadowed.is_empty() || (split && !devices.owned.is_empty())) =>
+            {
+                &devices.owned
+            }
+            Some(devices) if devices.shadowed.contains(device_id) => &[],
+            _ => slice::from_ref(device_id),
+        }
+    }
+}
+
+/// The devices each plugin has connected, which device events are checked against so that listeners only see the events of the
+/// plugin owning the device. When the owner of a device changes, e.g. as the plugin which owned it was quarantined, listeners see
+/// the device disconnect from the old owner & connect through the new one
+#[derive(Debug, Default)]
+pub struct EventOwners {
+    //<priority, connected devices> of each plugin, in the order they were loaded
+    plugins: Vec<(i32, Vec<DeviceInfo>)>,
+}
+
+impl EventOwners {
+    fn plugin_mut(&mut self, plugin: usize) -> &mut (i32, Vec<DeviceInfo>) {
+        if self.plugins.len() <= plugin {
+            self.plugins.resize_with(plugin + 1, Default::default);
+        }
+        &mut self.plugins[plugin]
+    }
+
+    /// Sets the priority & the connected devices of the plugin at `plugin`, without giving any events
+    pub fn set_plugin(&mut self, plugin: usize, priority: i32, devices: Vec<DeviceInfo>) {
+        *self.plugin_mut(plugin) = (priority, devices);
+    }
+
+    /// Gives the plugin which owns the device and the ID it has there
+    fn owner(&self, identity: &Identity) -> Option<(usize, DeviceID)> {
+        self.plugins
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (priority, devices))| {
+                devices
+                    .iter()
+                    .find(|device| Identity::of(device) == *identity)
+                    .map(|device| (Reverse(*priority), i, device.device_id))
+            })
+            .min()
+            .map(|(_, i, device_id)| (i, device_id))
+    }
+
+    fn device(&self, (plugin, device_id): (usize, DeviceID)) -> Option<DeviceInfo> {
+        self.plugins[plugin]
+            .1
+            .iter()
+            .find(|device| device.device_id == device_id)
+            .cloned()
+    }
+
+    /// Takes the `event` the plugin at `plugin` gave for `device`, giving the events listeners should see for it
+    pub fn filter(
+        &mut self,
+        plugin: usize,
+        event: DeviceEventType,
+        device: &DeviceInfo,
+    ) -> Vec<(DeviceEventType, DeviceInfo)> {
+        let identity = Identity::of(device);
+        let before = self.owner(&identity);
+        //Taken before the device is gone from the plugin, in case it's disconnecting from the owner
+        let before_device = before.and_then(|owner| self.device(owner));
+
+        let devices = &mut self.plugin_mut(plugin).1;
+        devices.retain(|known| known.device_id != device.device_id);
+        if event == DeviceEventType::Connected {
+            devices.push(device.clone());
+        }
+        let after = self.owner(&identity);
+
+        if before == after {
+            return match after {
+                Some((owner, _)) if owner != plugin => vec![],
+                _ => vec![(event, device.clone())],
+            };
+        }
+        let mut events = vec![];
+        if let Some(device) = before_device {
+            events.push((DeviceEventType::Disconnected, device));
+        }
+        if let Some(device) = after.and_then(|owner| self.device(owner)) {
+            events.push((DeviceEventType::Connected, device));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(device_id: DeviceID, serial_number: Option<&str>) -> DeviceInfo {
+        let mut device = DeviceInfo::new_with_id(
+            0x03eb,
+            0xff01,
+            "Wooting".to_owned(),
+            "Wooting One".to_owned(),
+            device_id,
+            DeviceType::Keyboard,
+        );
+        device.details.serial_number = serial_number.map(str::to_owned);
+        device
+    }
+
+    #[test]
+    fn first_loaded_plugin_owns_duplicates() {
+        let owners = DeviceOwners::resolve(vec![
+            (0, 0, vec![device(1, Some("A")), device(2, None)]),
+            //The same board with a device ID made differently, and a board without a serial number which can't be matched
+            (0, 0, vec![device(3, Some("A")), device(4, None)]),
+        ]);
+
+        assert!(owners.owns(0, 1));
+        assert!(!owners.owns(1, 3));
+        assert!(owners.owns(1, 4));
+        assert_eq!(owners.read_targets(0, &0, false), &[0]);
+        assert_eq!(owners.read_targets(1, &0, false), &[4]);
+        assert_eq!(owners.read_targets(1, &3, false), &[] as &[DeviceID]);
+        assert_eq!(owners.read_targets(1, &4, false), &[4]);
+        assert_eq!(owners.read_targets(0, &0, true), &[1, 2]);
+    }
+
+    #[test]
+    fn priority_beats_load_order() {
+        let owners = DeviceOwners::resolve(vec![
+            (0, 0, vec![device(1, Some("A"))]),
+            (1, 0, vec![device(1, Some("A"))]),
+        ]);
+
+        assert!(!owners.owns(0, 1));
+        assert!(owners.owns(1, 1));
+        assert_eq!(owners.read_targets(0, &0, false), &[] as &[DeviceID]);
+        assert_eq!(owners.read_targets(1, &1, false), &[1]);
+        assert!(owners.is_current(vec![0, 0].into_iter()));
+        assert!(!owners.is_current(vec![0, 1].into_iter()));
+
+        let owners = DeviceOwners::resolve(vec![
+            (i32::MIN, 0, vec![device(1, Some("A"))]),
+            (0, 0, vec![device(1, Some("A"))]),
+        ]);
+        assert!(!owners.owns(0, 1));
+        assert!(owners.owns(1, 1));
+    }
+
+    fn ids(events: Vec<(DeviceEventType, DeviceInfo)>) -> Vec<(DeviceEventType, DeviceID)> {
+        events
+            .into_iter()
+            .map(|(event, device)| (event, device.device_id))
+            .collect()
+    }
+
+    #[test]
+    fn events_of_duplicates_are_given_once() {
+        use DeviceEventType::*;
+
+        let mut owners = EventOwners::default();
+        owners.set_plugin(0, 0, vec![]);
+        owners.set_plugin(1, 0, vec![]);
+        assert_eq!(
+            ids(owners.filter(0, Connected, &device(1, Some("A")))),
+            vec![(Connected, 1)]
+        );
+        //The same board connecting through the second plugin is already known
+        assert_eq!(
+            ids(owners.filter(1, Connected, &device(3, Some("A")))),
+            vec![]
+        );
+        //Devices only one plugin has are passed on as they are
+        assert_eq!(
+            ids(owners.filter(1, Connected, &device(4, None))),
+            vec![(Connected, 4)]
+        );
+
+        //When the owner loses the board, it's handed over to the second plugin
+        assert_eq!(
+            ids(owners.filter(0, Disconnected, &device(1, Some("A")))),
+            vec![(Disconnected, 1), (Connected, 3)]
+        );
+        assert_eq!(
+            ids(owners.filter(1, Disconnected, &device(3, Some("A")))),
+            vec![(Disconnected, 3)]
+        );
+
+        //A plugin with a higher priority takes over the board
+        owners.set_plugin(0, -1, vec![]);
+        owners.filter(1, Connected, &device(3, Some("A")));
+        assert_eq!(
+            ids(owners.filter(0, Connected, &device(1, Some("A")))),
+            vec![]
+        );
+        owners.set_plugin(0, 1, vec![]);
+        assert_eq!(
+            ids(owners.filter(0, Connected, &device(1, Some("A")))),
+            vec![(Disconnected, 3), (Connected, 1)]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Remapping of keys per device, e.g. to move a broken switch to another key, or to have one key act as several. Keys are given
+//! by HID code, and are remapped before they're translated to the current keycode mode.
+//!
+//! A remap file has one remap per line, as the device ID in hex (or `*` for every device), the key being remapped and then the
+//! keys it's reported as, separated by spaces. Keys are given by name (see `key_from_name`) or as a HID code in hex (e.g. `0x04`).
+//! A key which isn't reported as anything is disabled. Lines starting with '#' are ignored.
+use log::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use wooting_analog_common::*;
+
+/// The remaps for every device (by device ID, 0 being every device), from the key being remapped to the keys it's reported as
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RemapTable {
+    devices: BTreeMap<DeviceID, BTreeMap<u16, Vec<u16>>>,
+}
+
+impl RemapTable {
+    /// Loads the remaps in the file at `path`, lines which can't be read are skipped
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        fs::read_to_string(path).map(|content| parse(&content, path))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Returns true if there are remaps for specific devices, rather than only ones for every device
+    pub fn has_device_remaps(&self) -> bool {
+        self.devices.keys().any(|&device_id| device_id != 0)
+    }
+
+    /// Reports the key `from` as the keys in `to` on the device with `device_id` (or every device if it's 0), replacing any
+    /// earlier remap of the key on it. If `to` is empty the key is disabled
+    pub fn set(&mut self, device_id: DeviceID, from: u16, to: &[u16]) {
+        self.devices
+            .entry(device_id)
+            .or_default()
+            .insert(from, to.to_vec());
+    }
+
+    /// Removes the remap of the key `from` on the device with `device_id` (or the one for every device if it's 0), giving if
+    /// there was one
+    pub fn remove(&mut self, device_id: DeviceID, from: u16) -> bool {
+        let removed = match self.devices.get_mut(&device_id) {
+            Some(remaps) => remaps.remove(&from).is_some(),
+            None => false,
+        };
+        self.devices.retain(|_, remaps| !remaps.is_empty());
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+    }
+
+    /// Gives the keys `from` is reported as on the device with `device_id`. Remaps for the device take precedence over those
+    /// for every device
+    pub fn targets<'a>(&'a self, device_id: DeviceID, from: &'a u16) -> &'a [u16] {
+        let remap = |device_id| {
+            self.devices
+                .get(&device_id)
+                .and_then(|remaps| remaps.get(from))
+        };
+        match remap(device_id).or_else(|| remap(0)) {
+            Some(to) => to,
+            None => std::slice::from_ref(from),
+        }
+    }
+
+    /// Gives the keys which are reported as `to` on the device with `device_id`, i.e. those which need to be read to get its value
+    pub fn sources(&self, device_id: DeviceID, to: u16) -> Vec<u16> {
+        let mut candidates: Vec<u16> = self
+            .devices
+            .iter()
+            .filter(|(&id, _)| id == 0 || id == device_id)
+            .flat_map(|(_, remaps)| remaps.keys().copied())
+            .chain(std::iter::once(to))
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates.retain(|from| self.targets(device_id, from).contains(&to));
+        candidates
+    }
+
+    /// Remaps the `analog` data (HID code -> analog value) read from the device with `device_id`. If more than one key ends up
+    /// as the same key, the greater value is given
+    pub fn apply(&self, device_id: DeviceID, analog: HashMap<u16, f32>) -> HashMap<u16, f32> {
+        let mut remapped = HashMap::with_capacity(analog.len());
+        for (from, value) in analog {
+            for &to in self.targets(device_id, &from) {
+                let total = remapped.entry(to).or_insert(value);
+                *total = total.max(value);
+            }
+        }
+        remapped
+    }
+
+    /// Remaps the dense buffer `from` (indexed by HID code) read from the device with `device_id`, combining it into `to` by
+    /// taking the greater value. Keys which end up outside of `to` are skipped
+    pub fn apply_dense(&self, device_id: DeviceID, from: &[f32], to: &mut [f32]) {
+        for (code, &value) in from.iter().enumerate().filter(|(_, value)| **value > 0.0) {
+            for &target in self.targets(device_id, &(code as u16)) {
+                if let Some(total) = to.get_mut(target as usize) {
+                    *total = total.max(value);
+                }
+            }
+        }
+    }
+}
+
+fn parse_key(key: &str) -> Option<u16> {
+    match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => key_from_name(key),
+    }
+}
+
+fn parse(content: &str, path: &Path) -> RemapTable {
+    let mut table = RemapTable::default();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let device_id = match fields.next() {
+            Some("*") => Some(0),
+            Some(id) => DeviceID::from_str_radix(id, 16).ok(),
+            None => None,
+        };
+        let from = fields.next().and_then(parse_key);
+        let to: Option<Vec<u16>> = fields.map(parse_key).collect();
+        match (device_id, from, to) {
+            (Some(device_id), Some(from), Some(to)) => table.set(device_id, from, &to),
+            _ => warn!(
+                "Skipping invalid line {} in key remaps {}",
+                i + 1,
+                path.display()
+            ),
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u16 = 0x04;
+    const B: u16 = 0x05;
+    const C: u16 = 0x06;
+
+    fn analog(keys: &[(u16, f32)]) -> HashMap<u16, f32> {
+        keys.iter().copied().collect()
+    }
+
+    #[test]
+    fn remaps_and_mirrors_keys() {
+        let mut table = RemapTable::default();
+        table.set(0, A, &[B]);
+        table.set(7, C, &[A, C]);
+
+        assert_eq!(
+            table.apply(1, analog(&[(A, 0.5), (C, 0.25)])),
+            analog(&[(B, 0.5), (C, 0.25)])
+        );
+        //Device remaps are combined with those for every device
+        assert_eq!(
+            table.apply(7, analog(&[(A, 0.5), (B, 0.75), (C, 0.25)])),
+            analog(&[(A, 0.25), (B, 0.75), (C, 0.25)])
+        );
+        assert_eq!(table.sources(7, A), vec![C]);
+        assert_eq!(table.sources(7, B), vec![A, B]);
+        assert_eq!(table.sources(1, A), Vec::<u16>::new());
+
+        let mut dense = [0.0; 8];
+        table.apply_dense(7, &[0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.25, 0.0], &mut dense);
+        assert_eq!(dense[A as usize], 0.25);
+        assert_eq!(dense[B as usize], 0.5);
+        assert_eq!(dense[C as usize], 0.25);
+
+        assert!(table.remove(7, C));
+        assert!(!table.has_device_remaps());
+    }
+
+    #[test]
+    fn parses_remap_file() {
+        let table = parse(
+            "# Broken A switch\n* A B\n3f 0x06 A C\n3f Escape\nnot a remap\n",
+            Path::new("remap.txt"),
+        );
+
+        let mut expected = RemapTable::default();
+        expected.set(0, A, &[B]);
+        expected.set(0x3f, C, &[A, C]);
+        expected.set(0x3f, 0x29, &[]);
+        assert_eq!(table, expected);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::events::PluginEventCallback;
+use libloading::{Library, Symbol};
+use log::*;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::{c_float, c_int, c_uint, c_ushort, c_void};
+use std::ptr;
+use wooting_analog_common::*;
+use wooting_analog_plugin_dev::abi::*;
+use wooting_analog_plugin_dev::*;
+
+/// A Rust plugin loaded from a library, which is called through the `PluginVTable` exported by `declare_plugin!`
+pub struct RustPlugin {
+    vtable: PluginVTable,
+    handle: PluginHandle,
+    //The callback given to the plugin in `initialise`, which is freed once the plugin can no longer call it
+    callback: *mut PluginEventCallback,
+}
+
+impl RustPlugin {
+    /// Creates the plugin from the `_plugin_vtable` exported by `lib`, which needs to be kept loaded for as long as the plugin is
+    /// around
+    ///
+    /// # Expected Returns
+    /// * `FunctionNotFound`: `lib` isn't a Rust plugin built with an ABI stable `wooting-analog-plugin-dev`
+    /// * `IncompatibleVersion`: The plugin was built for a different version of the `PluginVTable`, or its table is missing
+    /// required entries
+    /// * `Failure`: The plugin couldn't be constructed
+    pub fn new(lib: &Library) -> SDKResult<RustPlugin> {
+        type GetVTable = unsafe extern "C" fn() -> *const PluginVTable;
+
+        unsafe {
+            let get_vtable: Symbol<GetVTable> = match lib.get(b"_plugin_vtable") {
+                Ok(f) => f,
+                Err(_) => return Err(WootingAnalogResult::FunctionNotFound).into(),
+            };
+            let vtable = get_vtable();
+            if vtable.is_null() {
+                return Err(WootingAnalogResult::Failure).into();
+            }
+
+            //Only the version is read until we know the rest of the table has the layout we expect
+            let abi_version = ptr::read(vtable as *const u32);
+            if abi_version != PLUGIN_ABI_VERSION {
+                error!(
+                    "Plugin ABI version does not match! Given: {}, Expected: {}",
+                    abi_version, PLUGIN_ABI_VERSION
+                );
+                return Err(WootingAnalogResult::IncompatibleVersion).into();
+            }
+
+            //Plugins built before entries were added to the end of the table don't have them, which are left as `None`
+            let vtable = match PluginVTable::read_sized(vtable) {
+                Some(vtable) => vtable,
+                None => {
+                    error!("Plugin's vtable is too small to have the required entries");
+                    return Err(WootingAnalogResult::IncompatibleVersion).into();
+                }
+            };
+            if !vtable.plugin_dev_version.is_null() {
+                debug!(
+                    "Plugin was built with plugin-dev {}. SDK: {}",
+                    CStr::from_ptr(vtable.plugin_dev_version).to_string_lossy(),
+                    ANALOG_SDK_PLUGIN_VERSION
+                );
+            }
+
+            let handle = (vtable.create)();
+            if handle.is_null() {
+                error!("Plugin failed to construct");
+                return Err(WootingAnalogResult::Failure).into();
+            }
+            Ok(RustPlugin {
+                vtable,
+                handle,
+                callback: ptr::null_mut(),
+            })
+            .into()
+        }
+    }
+
+    /// Gives what's needed to get the plugin's `SnapshotStore` once it's initialised, as the plugin itself is handed to the SDK as
+    /// a `dyn Plugin`
+    pub fn snapshot_source(&self) -> SnapshotSource {
+        SnapshotSource {
+            vtable: self.vtable,
+            handle: self.handle,
+        }
+    }
+
+    fn free_callback(&mut self) {
+        if !self.callback.is_null() {
+            drop(unsafe { Box::from_raw(self.callback) });
+            self.callback = ptr::null_mut();
+        }
+    }
+}
+
+impl Drop for RustPlugin {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.destroy)(self.handle) };
+        self.free_callback();
+    }
+}
+
+unsafe extern "C" fn call_callback(
+    user_data: *mut c_void,
+    event: c_uint,
+    device: *const DeviceInfoEx_FFI,
+) {
+    let callback = match (user_data as *const PluginEventCallback).as_ref() {
+        Some(callback) => callback,
+        None => {
+            error!("Got a device event without the callback!");
+            return;
+        }
+    };
+    match (
+        DeviceEventType::from_u32(event),
+        DeviceInfoEx_FFI::read_versioned(device),
+    ) {
+        (Some(event), Some(device)) => callback(event, &device.into_device_info()),
+        _ => warn!("Ignoring invalid device event {} from plugin", event),
+    }
+}
+
+impl Plugin for RustPlugin {
+    fn name(&mut self) -> SDKResult<&'static str> {
+        let name = unsafe { (self.vtable.name)(self.handle) };
+        if name.is_null() {
+            return Err(WootingAnalogResult::Failure).into();
+        }
+        //The name is kept by the plugin until it's destroyed, which is as long as the SDK has it around
+        match unsafe { CStr::from_ptr(name) }.to_str() {
+            Ok(name) => Ok(name).into(),
+            Err(_) => Err(WootingAnalogResult::Failure).into(),
+        }
+    }
+
+    fn initialise(&mut self, callback: PluginEventCallback) -> SDKResult<u32> {
+        //If the plugin is initialised again it could still be using the old callback, so that's only freed with the plugin
+        if !self.callback.is_null() {
+            unsafe { (self.vtable.unload)(self.handle) };
+            self.free_callback();
+        }
+        self.callback = Box::into_raw(Box::new(callback));
+        unsafe {
+            (self.vtable.initialise)(self.handle, call_callback, self.callback as *mut c_void)
+        }
+        .into()
+    }
+
+    fn is_initialised(&mut self) -> bool {
+        unsafe { (self.vtable.is_initialised)(self.handle) }
+    }
+
+    fn unload(&mut self) {
+        unsafe { (self.vtable.unload)(self.handle) }
+    }
+
+    fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+        let count: SDKResult<u32> =
+            unsafe { (self.vtable.device_info)(self.handle, ptr::null_mut(), 0) }.into();
+        let count = match count.0 {
+            Ok(count) => count,
+            Err(e) => return Err(e).into(),
+        };
+
+        let mut buffer: Vec<*const DeviceInfoEx_FFI> = vec![ptr::null(); count as usize];
+        let filled: SDKResult<u32> =
+            unsafe { (self.vtable.device_info)(self.handle, buffer.as_mut_ptr(), count) }.into();
+        filled
+            .0
+            .map(|filled| {
+                buffer
+                    .iter()
+                    .take(filled as usize)
+                    .filter_map(|&device| unsafe { DeviceInfoEx_FFI::read_versioned(device) })
+                    .map(|device| device.into_device_info())
+                    .collect()
+            })
+            .into()
+    }
+
+    fn read_analog(&mut self, code: u16, device: DeviceID) -> SDKResult<f32> {
+        unsafe { (self.vtable.read_analog)(self.handle, code, device) }.into()
+    }
+
+    fn read_full_buffer(
+        &mut self,
+        max_length: usize,
+        device: DeviceID,
+    ) -> SDKResult<HashMap<c_ushort, c_float>> {
+        let mut codes: Vec<c_ushort> = vec![0; max_length];
+        let mut analog: Vec<c_float> = vec![0.0; max_length];
+        let count: SDKResult<u32> = unsafe {
+            (self.vtable.read_full_buffer)(
+                self.handle,
+                codes.as_mut_ptr(),
+                analog.as_mut_ptr(),
+                max_length as c_uint,
+                device,
+            )
+        }
+        .into();
+        count
+            .0
+            .map(|count| codes.into_iter().zip(analog).take(count as usize).collect())
+            .into()
+    }
+
+    fn read_dense_buffer(&mut self, buffer: &mut [c_float], device: DeviceID) -> SDKResult<()> {
+        let ret: SDKResult<c_int> = unsafe {
+            (self.vtable.read_dense_buffer)(
+                self.handle,
+                buffer.as_mut_ptr(),
+                buffer.len() as c_uint,
+                device,
+            )
+        }
+        .into();
+        ret.0.map(|_| ()).into()
+    }
+
+    fn device_layout(&mut self, device: DeviceID) -> SDKResult<KeyboardLayout> {
+        let ret: SDKResult<c_int> =
+            unsafe { (self.vtable.device_layout)(self.handle, device) }.into();
+        ret.0
+            .map(|layout| KeyboardLayout::from_i32(layout).unwrap_or(KeyboardLayout::Unknown))
+            .into()
+    }
+
+    fn send_feature_report(&mut self, device: DeviceID, data: &[u8]) -> SDKResult<()> {
+        let send_feature_report = match self.vtable.send_feature_report {
+            Some(f) => f,
+            None => return Err(WootingAnalogResult::NotAvailable).into(),
+        };
+        let ret: SDKResult<c_int> = unsafe {
+            send_feature_report(self.handle, device, data.as_ptr(), data.len() as c_uint)
+        }
+        .into();
+        ret.0.map(|_| ()).into()
+    }
+
+    fn get_feature_report(&mut self, device: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+        let get_feature_report = match self.vtable.get_feature_report {
+            Some(f) => f,
+            None => return Err(WootingAnalogResult::NotAvailable).into(),
+        };
+        let ret: SDKResult<c_int> = unsafe {
+            get_feature_report(
+                self.handle,
+                device,
+                buffer.as_mut_ptr(),
+                buffer.len() as c_uint,
+            )
+        }
+        .into();
+        ret.0.map(|read| read as usize).into()
+    }
+}
+
+/// Gets the `SnapshotStore` of a `RustPlugin`
+pub struct SnapshotSource {
+    vtable: PluginVTable,
+    handle: PluginHandle,
+}
+
+//It's only used to get the store on the thread which calls the plugin
+unsafe impl Send for SnapshotSource {}
+
+impl SnapshotSource {
+    /// Gives the reader of the plugin's `SnapshotStore` if it has one
+    ///
+    /// # Safety
+    /// This has to be called after the plugin has been initialised, while nothing else is calling it, and before it's dropped
+    pub unsafe fn snapshots(&self) -> Option<PluginSnapshots> {
+        let store = (self.vtable.snapshot_store)(self.handle);
+        if store.is_null() {
+            None
+        } else {
+            Some(PluginSnapshots {
+                store,
+                read_analog: self.vtable.snapshot_read_analog,
+                read_dense_buffer: self.vtable.snapshot_read_dense_buffer,
+                set_listener: self.vtable.snapshot_set_listener,
+            })
+        }
+    }
+}
+
+/// Reads the `SnapshotStore` of a `RustPlugin`, which can be done from any thread without locking the plugin
+pub struct PluginSnapshots {
+    store: SnapshotHandle,
+    read_analog: unsafe extern "C" fn(SnapshotHandle, c_ushort, DeviceID) -> c_float,
+    read_dense_buffer:
+        unsafe extern "C" fn(SnapshotHandle, *mut c_float, c_uint, DeviceID) -> c_int,
+    set_listener: Option<
+        unsafe extern "C" fn(SnapshotHandle, Option<SnapshotReportFn>, *mut c_void) -> c_int,
+    >,
+}
+
+//The store is thread safe, and is kept alive by the plugin for as long as it's around
+unsafe impl Send for PluginSnapshots {}
+unsafe impl Sync for PluginSnapshots {}
+
+impl PluginSnapshots {
+    pub fn read_analog(&self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
+        unsafe { (self.read_analog)(self.store, code, device_id) }.into()
+    }
+
+    pub fn read_dense_buffer(&self, buffer: &mut [f32], device_id: DeviceID) -> SDKResult<()> {
+        let ret: SDKResult<c_int> = unsafe {
+            (self.read_dense_buffer)(
+                self.store,
+                buffer.as_mut_ptr(),
+                buffer.len() as c_uint,
+                device_id,
+            )
+        }
+        .into();
+        ret.0.map(|_| ()).into()
+    }
+
+    /// Has the plugin's `SnapshotStore` give every report it publishes to `listener` along with `user_data`, or stop if it's `None`.
+    /// Gives `NotAvailable` if the plugin was built before stores could have a listener
+    ///
+    /// # Safety
+    /// `user_data` has to stay valid for as long as `listener` can be called
+    pub unsafe fn set_listener(
+        &self,
+        listener: Option<SnapshotReportFn>,
+        user_data: *mut c_void,
+    ) -> SDKResult<()> {
+        let set_listener = match self.set_listener {
+            Some(f) => f,
+            None => return Err(WootingAnalogResult::NotAvailable).into(),
+        };
+        let ret: SDKResult<c_int> = set_listener(self.store, listener, user_data).into();
+        ret.0.map(|_| ()).into()
+    }
+}
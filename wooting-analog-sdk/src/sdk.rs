@@ -1,13 +1,28 @@
+use crate::aliases::*;
+use crate::axis::*;
 use crate::cplugin::*;
+use crate::events::*;
 use crate::keycode::*;
+use crate::kinematics::*;
+use crate::mouse::*;
+use crate::owners::*;
+use crate::remap::*;
+use crate::rustplugin::*;
+use crate::session::*;
+use crate::watchdog::*;
 use anyhow::{Context, Error, Result};
-use libloading::{Library, Symbol};
-use log::{error, info, warn};
+use libloading::Library;
+use log::{error, info, trace, warn};
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::fs;
+use std::os::raw::{c_float, c_uint, c_ushort, c_void};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::{fs, thread};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant};
 use wooting_analog_common::*;
 use wooting_analog_plugin_dev::*;
 
@@ -17,17 +32,218 @@ lazy_static! {
     pub static ref TEST_PLUGIN_LOCK: Mutex<()> = Mutex::new(());
 }
 
-unsafe impl Send for AnalogSDK {}
+/// Where the device data of a plugin is read from without locking it
+enum Snapshots {
+    Store(Arc<SnapshotStore>),
+    Library(PluginSnapshots),
+}
+
+/// Passes the reports of the devices of a plugin on to the `KinematicsTracker`
+struct ReportFeed {
+    tracker: Arc<KinematicsTracker>,
+    plugin: usize,
+}
+
+impl ReportFeed {
+    fn report<K>(&self, device_id: DeviceID, pressed: K)
+    where
+        K: Iterator<Item = (u16, f32)> + Clone,
+    {
+        self.tracker
+            .report(self.plugin, device_id, pressed, Instant::now());
+    }
+}
+
+unsafe extern "C" fn feed_report(
+    user_data: *mut c_void,
+    device: DeviceID,
+    _sequence: u64,
+    codes: *const c_ushort,
+    analog: *const c_float,
+    len: c_uint,
+) {
+    let feed = match (user_data as *const ReportFeed).as_ref() {
+        Some(feed) => feed,
+        None => return,
+    };
+    let (codes, analog): (&[c_ushort], &[c_float]) =
+        if len == 0 || codes.is_null() || analog.is_null() {
+            (&[], &[])
+        } else {
+            (
+                slice::from_raw_parts(codes, len as usize),
+                slice::from_raw_parts(analog, len as usize),
+            )
+        };
+    feed.report(device, codes.iter().copied().zip(analog.iter().copied()));
+}
+
+/// A loaded plugin, running on its own thread, along with the `SnapshotStore` it publishes its device data into (if it has one)
+struct LoadedPlugin {
+    worker: PluginWorker,
+    snapshots: Option<Snapshots>,
+    //Plugins loaded from a library can't hand over their `SnapshotStore` through the `Plugin` trait. It's only taken once the
+    //plugin is initialised, the Mutex is what lets the SDK be shared between threads while it's held
+    snapshot_source: Mutex<Option<SnapshotSource>>,
+    //Given to the plugin's `SnapshotStore` as the listener's user data, so it has to outlive the plugin
+    report_feed: Option<Arc<ReportFeed>>,
+    //The library the plugin was loaded from, if it was
+    path: Option<PathBuf>,
+}
+
+impl LoadedPlugin {
+    fn new(plugin: Box<dyn Plugin>) -> Self {
+        LoadedPlugin {
+            worker: PluginWorker::spawn(plugin, None),
+            snapshots: None,
+            snapshot_source: Mutex::new(None),
+            report_feed: None,
+            path: None,
+        }
+    }
+
+    /// Loads the Rust `plugin`, which is from `library`
+    fn from_library(plugin: RustPlugin, library: Library) -> Self {
+        let snapshot_source = plugin.snapshot_source();
+        LoadedPlugin {
+            worker: PluginWorker::spawn(Box::new(plugin), Some(library)),
+            snapshots: None,
+            snapshot_source: Mutex::new(Some(snapshot_source)),
+            report_feed: None,
+            path: None,
+        }
+    }
+
+    /// Gives what the plugin's priority is set by, which is the path it was loaded from so that different versions of the same
+    /// plugin can be told apart. Plugins which weren't loaded from a library go by their name
+    fn priority_key(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(self.worker.name()))
+    }
+
+    fn priority(&self, priorities: &HashMap<PathBuf, i32>) -> i32 {
+        priorities.get(&self.priority_key()).copied().unwrap_or(0)
+    }
+
+    /// Has the plugin's `SnapshotStore` give every report it publishes to the `tracker`, as the plugin at index `plugin`. Plugins
+    /// without one aren't tracked
+    fn track_reports(&mut self, plugin: usize, tracker: &Arc<KinematicsTracker>) {
+        let feed = Arc::new(ReportFeed {
+            tracker: Arc::clone(tracker),
+            plugin,
+        });
+        let result = match &self.snapshots {
+            Some(Snapshots::Store(store)) => {
+                let feed = Arc::clone(&feed);
+                store.set_listener(Some(Box::new(move |snapshot: &AnalogSnapshot| {
+                    let pressed = snapshot.analog().iter();
+                    feed.report(
+                        snapshot.device_id(),
+                        pressed.map(|(&code, &value)| (code, value)),
+                    )
+                })));
+                Ok(()).into()
+            }
+            //The feed is kept by the plugin until it's unloaded
+            Some(Snapshots::Library(snapshots)) => unsafe {
+                snapshots.set_listener(Some(feed_report), Arc::as_ptr(&feed) as *mut c_void)
+            },
+            None => return,
+        };
+        match result.0 {
+            Ok(()) => self.report_feed = Some(feed),
+            Err(e) => warn!(
+                "Couldn't track the reports of {:?}: {:?}",
+                self.worker.name(),
+                e
+            ),
+        }
+    }
+
+    fn stop_tracking_reports(&self) {
+        match &self.snapshots {
+            Some(Snapshots::Store(store)) => store.set_listener(None),
+            Some(Snapshots::Library(snapshots)) => {
+                let _ = unsafe { snapshots.set_listener(None, ptr::null_mut()) };
+            }
+            None => {}
+        }
+    }
+
+    fn read_analog(&self, code: u16, device_id: DeviceID, timeout: Duration) -> SDKResult<f32> {
+        match &self.snapshots {
+            _ if self.worker.is_quarantined() => Err(WootingAnalogResult::NoDevices).into(),
+            Some(Snapshots::Store(store)) => store.read_analog(code, device_id),
+            Some(Snapshots::Library(snapshots)) => snapshots.read_analog(code, device_id),
+            None => self
+                .worker
+                .call(timeout, move |plugin| plugin.read_analog(code, device_id)),
+        }
+    }
+
+    fn read_dense_buffer(
+        &self,
+        buffer: &mut [f32],
+        device_id: DeviceID,
+        timeout: Duration,
+    ) -> SDKResult<()> {
+        match &self.snapshots {
+            _ if self.worker.is_quarantined() => Err(WootingAnalogResult::NoDevices).into(),
+            Some(Snapshots::Store(store)) => store.read_dense_buffer(buffer, device_id),
+            Some(Snapshots::Library(snapshots)) => snapshots.read_dense_buffer(buffer, device_id),
+            None => {
+                //The plugin may still be writing to the buffer after the call has timed out, so it's given a copy
+                let mut copy = buffer.to_vec();
+                self.worker
+                    .call(timeout, move |plugin| {
+                        plugin
+                            .read_dense_buffer(&mut copy, device_id)
+                            .0
+                            .map(|_| copy)
+                            .into()
+                    })
+                    .0
+                    .map(|copy| buffer.copy_from_slice(&copy))
+                    .into()
+            }
+        }
+    }
+}
 
 pub struct AnalogSDK {
     pub initialised: bool,
     pub keycode_mode: KeycodeType,
 
-    plugins: Vec<Box<dyn Plugin>>,
-    loaded_libraries: Vec<Library>,
-    device_event_callback: Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, DeviceInfo) + Send>>>>,
+    plugins: Vec<LoadedPlugin>,
+    call_timeout: Duration,
+    raw_device_access: bool,
+    plugin_priorities: HashMap<PathBuf, i32>,
+    owners: RwLock<DeviceOwners>,
+    event_owners: Arc<Mutex<EventOwners>>,
+    remap: RemapTable,
+    axes: Axes,
+    kinematics: Arc<KinematicsTracker>,
+    //Swapped out rather than changed in place, so the mouse emulation can keep reading the one it has without holding the SDK
+    mouse_config: Arc<MouseConfig>,
+    listeners: DeviceEventListeners,
+    next_listener_id: AtomicU32,
+    events: Option<EventDispatcher>,
+    sessions: Mutex<HashMap<SessionID, Arc<Mutex<ReadSession>>>>,
+    next_session_id: AtomicU32,
+    aliases: DeviceAliases,
 }
 
+/// The session used by `read_full_buffer`, which is shared by everyone who doesn't create their own
+pub const DEFAULT_SESSION: SessionID = 0;
+
+/// The listener which is replaced by `set_device_event_cb`
+pub const DEFAULT_LISTENER: ListenerID = 0;
+
+/// How many keys are read from the plugins for a session, which is cut down to the length asked for afterwards. No device has
+/// this many keys pressed at once
+const SESSION_READ_LEN: usize = 0x200;
+
 pub fn print_error(err: Error) -> Error {
     error!("{:#}", err);
     err
@@ -49,10 +265,23 @@ impl AnalogSDK {
     pub fn new() -> AnalogSDK {
         AnalogSDK {
             plugins: Vec::new(),
-            loaded_libraries: Vec::new(),
+            call_timeout: DEFAULT_CALL_TIMEOUT,
+            raw_device_access: false,
+            plugin_priorities: HashMap::new(),
+            owners: Default::default(),
+            event_owners: Default::default(),
+            remap: Default::default(),
+            axes: Default::default(),
+            kinematics: Default::default(),
+            mouse_config: Default::default(),
             initialised: false,
             keycode_mode: KeycodeType::HID,
-            device_event_callback: Arc::new(Mutex::new(None)),
+            listeners: Default::default(),
+            next_listener_id: AtomicU32::new(DEFAULT_LISTENER + 1),
+            events: None,
+            sessions: Mutex::new(std::iter::once((DEFAULT_SESSION, Default::default())).collect()),
+            next_session_id: AtomicU32::new(DEFAULT_SESSION + 1),
+            aliases: DeviceAliases::load_default(),
         }
     }
 
@@ -128,32 +357,67 @@ impl AnalogSDK {
             }
         }
 
+        self.initialise_plugins()
+    }
+
+    /// Initialise the SDK with the given, already constructed, `plugins` rather than loading them from a directory.
+    /// This is useful for embedding plugins directly into an application (or testing) without going through dynamic libraries
+    pub fn initialise_with_plugins(&mut self, plugins: Vec<Box<dyn Plugin>>) -> SDKResult<u32> {
+        if self.initialised {
+            self.unload();
+        }
+
+        for plugin in plugins {
+            let plugin = LoadedPlugin::new(plugin);
+            //The name is only used for the status & priorities, so a plugin without one is still used
+            let _ = plugin.worker.load_name(self.call_timeout);
+            self.plugins.push(plugin);
+        }
+        self.initialise_plugins()
+    }
+
+    fn initialise_plugins(&mut self) -> SDKResult<u32> {
         let mut plugins_initialised = 0;
         let mut device_no: u32 = 0;
-        for p in self.plugins.iter_mut() {
-            let arc_cb = self.device_event_callback.clone();
-            let ret = p.initialise(Box::new(
-                move |event: DeviceEventType, device_ref: &DeviceInfo| {
-                    let opt_cb = arc_cb.clone();
-                    let device = device_ref.clone();
-                    thread::spawn(move || {
-                        debug!("device event cb thread running");
-
-                        opt_cb.lock().unwrap().as_ref().and_then(|cb| {
-                            debug!("calling og callback");
-                            cb(event, device);
-                            Some(0)
-                        });
-                    });
-                },
-            ));
+        //All device events go through the one dispatcher, so they reach the user in the order they happened
+        let events = EventDispatcher::start(Arc::clone(&self.listeners));
+        for (i, p) in self.plugins.iter_mut().enumerate() {
+            //A device which several plugins report is only given to listeners through the plugin which owns it
+            let callback = events.plugin_callback();
+            let owners = Arc::clone(&self.event_owners);
+            let ret = p.worker.initialise(
+                Box::new(move |event, device| {
+                    for (event, device) in owners.lock().unwrap().filter(i, event, device) {
+                        callback(event, &device);
+                    }
+                }),
+                INITIALISE_TIMEOUT,
+            );
             debug!("{:?}", ret);
             if let Ok(num) = ret.0 {
                 plugins_initialised += 1;
                 device_no += num;
+                let source = p.snapshot_source.get_mut().unwrap().take();
+                p.snapshots = p
+                    .worker
+                    .call(self.call_timeout, move |plugin| {
+                        let snapshots = match source {
+                            //This is run by the plugin's thread, so nothing else can be calling it
+                            Some(source) => unsafe { source.snapshots() }.map(Snapshots::Library),
+                            None => plugin.snapshot_store().map(Snapshots::Store),
+                        };
+                        Ok(snapshots).into()
+                    })
+                    .0
+                    .unwrap_or(None);
+                p.track_reports(i, &self.kinematics);
+                //Which plugin owns each device is worked out from the devices the plugins have reported
+                let _ = p.worker.device_info(self.call_timeout);
             }
         }
         info!("{} plugins successfully initialised", plugins_initialised);
+        self.events = Some(events);
+        self.reset_event_owners();
 
         self.initialised = plugins_initialised > 0;
         if !self.initialised {
@@ -198,79 +462,21 @@ impl AnalogSDK {
             bail!("Path is directory!");
         }
 
-        type PluginCreate = unsafe extern "C" fn() -> *mut dyn Plugin;
-        type PluginVersion = unsafe extern "C" fn() -> &'static str;
-
         let lib = Library::new(filename.as_os_str()).context("Unable to load the plugin")?;
 
-        // We need to keep the library around otherwise our plugin's vtable will
-        // point to garbage. We do this little dance to make sure the library
-        // doesn't end up getting moved.
-        self.loaded_libraries.push(lib);
-
-        let lib = self.loaded_libraries.last().unwrap();
-
-        let full_version: Option<Symbol<PluginVersion>> = lib.get(b"plugin_version").ok();
-        let mut got_ver = false;
-        if let Some(f_ver) = full_version {
-            got_ver = true;
-            let ver = f_ver();
-            debug!(
-                "Plugin got plugin-dev sem version: {}. SDK: {}",
-                ver, ANALOG_SDK_PLUGIN_VERSION
-            );
-
-            if let Some(major_ver) = ANALOG_SDK_PLUGIN_VERSION
-                .split('.')
-                .collect::<Vec<&str>>()
-                .first()
-            {
-                if let Some(plugin_major_ver) = ver.split('.').collect::<Vec<&str>>().first() {
-                    if major_ver.eq(plugin_major_ver) {
-                        info!("Plugin and SDK are compatible!");
-                    } else {
-                        bail!(
-                            "Plugin has major version {}, which is incompatible with the SDK's: {}",
-                            plugin_major_ver,
-                            ANALOG_SDK_PLUGIN_VERSION
-                        );
-                    }
-                } else {
-                    bail!(
-                        "Unable to get the Plugin's major version from SemVer {}",
-                        ver
-                    );
-                }
-            } else {
-                bail!(
-                    "Unable to get the SDK's Plugin major version from SemVer {}",
-                    ANALOG_SDK_PLUGIN_VERSION
-                );
-            }
-        } else {
-            warn!("Unable to determine the Plugin's SemVer!");
-        }
-
-        let constructor: Option<Symbol<PluginCreate>> = lib
-            .get(b"_plugin_create")
-            .context("Failed to find constructor (_plugin_create symbol)")
-            .map_err(print_warn)
-            .ok();
-
-        let mut plugin = match constructor {
-            Some(f) => {
-                if !got_ver {
-                    bail!("Unable to determine the Plugin's SemVer!");
+        let mut plugin = match RustPlugin::new(&lib).0 {
+            // The library is kept around with the plugin, otherwise our plugin's vtable will
+            // point to garbage
+            Ok(plugin) => LoadedPlugin::from_library(plugin, lib),
+            Err(WootingAnalogResult::FunctionNotFound) => {
+                //Plugins built with an older plugin-dev handed over a Rust trait object, which can't be used safely
+                if lib.get::<unsafe extern "C" fn()>(b"_plugin_create").is_ok() {
+                    bail!("Plugin was built with an old version of wooting-analog-plugin-dev, it needs to be rebuilt against {} to be loaded", ANALOG_SDK_PLUGIN_VERSION);
                 }
 
-                debug!("We got it and we're trying");
-                Box::from_raw(f())
-            }
-            None => {
-                info!("Didn't find _plugin_create, assuming it's a C plugin");
-                let lib = self.loaded_libraries.pop().unwrap();
+                info!("Didn't find _plugin_vtable, assuming it's a C plugin");
                 match CPlugin::new(lib).0 {
-                    Ok(cplugin) => Box::new(cplugin),
+                    Ok(cplugin) => LoadedPlugin::new(Box::new(cplugin)),
                     Err(WootingAnalogResult::IncompatibleVersion) => {
                         bail!(
                             "Plugin is a C plugin which is incompatible with this version of the SDK"
@@ -281,8 +487,15 @@ impl AnalogSDK {
                     }
                 }
             }
+            Err(WootingAnalogResult::IncompatibleVersion) => {
+                bail!("Plugin is a Rust plugin which is incompatible with this version of the SDK");
+            }
+            Err(e) => {
+                bail!("Plugin failed to load with error {:?}", e);
+            }
         };
-        let name = plugin.name();
+        plugin.path = Some(filename.to_owned());
+        let name = plugin.worker.load_name(self.call_timeout);
         match name.0 {
             Ok(name) => {
                 info!("Loaded plugin: {:?}", name);
@@ -300,6 +513,8 @@ impl AnalogSDK {
         Ok(())
     }
 
+    /// Sets the default device event listener, replacing the one which was set before. This is kept around for applications which only
+    /// need one listener, use `add_device_event_listener` to be able to have several
     pub fn set_device_event_cb(
         &mut self,
         cb: impl Fn(DeviceEventType, DeviceInfo) + 'static + Send,
@@ -307,49 +522,137 @@ impl AnalogSDK {
         if !self.initialised {
             return WootingAnalogResult::UnInitialized.into();
         }
-        self.device_event_callback
-            .lock()
-            .unwrap()
-            .replace(Box::new(cb));
+        let cb = DeviceEventListener::new(Box::new(cb));
+        let mut listeners = self.listeners.lock().unwrap();
+        let old = match listeners.iter_mut().find(|(id, _)| *id == DEFAULT_LISTENER) {
+            Some((_, listener)) => Some(std::mem::replace(listener, cb)),
+            None => {
+                listeners.push((DEFAULT_LISTENER, cb));
+                None
+            }
+        };
+        drop(listeners);
+        if let Some(old) = old {
+            old.stop();
+        }
 
         Ok(()).into()
     }
 
+    /// Removes the default device event listener which was set with `set_device_event_cb`
     pub fn clear_device_event_cb(&mut self) -> SDKResult<()> {
         if !self.initialised {
             return Err(WootingAnalogResult::UnInitialized).into();
         }
-        self.device_event_callback.lock().unwrap().take();
+        if let Some(listener) = self.take_listener(DEFAULT_LISTENER) {
+            listener.stop();
+        }
 
         Ok(()).into()
     }
 
-    pub fn get_device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+    /// Adds a listener which is called for every device event, alongside any other listeners. Returns the ID to remove it
+    /// with using `remove_device_event_listener`.
+    ///
+    /// # Notes
+    /// * Listeners are called one after the other from a single thread, in the order they were added
+    /// * Listeners can add or remove listeners (including themselves) from inside their callback
+    pub fn add_device_event_listener(
+        &self,
+        cb: impl Fn(DeviceEventType, DeviceInfo) + 'static + Send,
+    ) -> SDKResult<ListenerID> {
         if !self.initialised {
             return Err(WootingAnalogResult::UnInitialized).into();
         }
-        let mut devices: Vec<DeviceInfo> = vec![];
+
+        let id = self.next_listener_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners
+            .lock()
+            .unwrap()
+            .push((id, DeviceEventListener::new(Box::new(cb))));
+        Ok(id).into()
+    }
+
+    /// Removes the listener with the given `id` that was added with `add_device_event_listener`. It won't be called again, but it may
+    /// still be in the middle of being called, so the removed listener is given back to `wait` on once the SDK has been released
+    pub fn remove_device_event_listener(
+        &self,
+        id: ListenerID,
+    ) -> SDKResult<Arc<DeviceEventListener>> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if id == DEFAULT_LISTENER {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        match self.take_listener(id) {
+            Some(listener) => {
+                listener.stop();
+                Ok(listener).into()
+            }
+            None => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+
+    /// Takes the listener with the given `id` out of the list
+    fn take_listener(&self, id: ListenerID) -> Option<Arc<DeviceEventListener>> {
+        let mut listeners = self.listeners.lock().unwrap();
+        let index = listeners.iter().position(|(listener, _)| *listener == id)?;
+        Some(listeners.remove(index).1)
+    }
+
+    /// Takes the oldest device event which hasn't been polled yet. This is an alternative to `set_device_event_cb` for applications
+    /// which need to handle events on their own thread. Events are given in the order they happened, with the last 64
+    /// being kept around
+    pub fn poll_device_event(&self) -> SDKResult<Option<(DeviceEventType, DeviceInfo)>> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        Ok(self.events.as_ref().and_then(|events| events.poll())).into()
+    }
+
+    pub fn get_device_info(&self) -> SDKResult<Vec<DeviceInfo>> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        let mut plugin_devices = vec![];
         let mut error: WootingAnalogResult = WootingAnalogResult::Ok;
-        for p in self.plugins.iter_mut() {
-            if !p.is_initialised() {
+        for (i, p) in self.plugins.iter().enumerate() {
+            //A quarantined plugin has no devices anymore, so it isn't an error for it to give none
+            if p.worker.is_quarantined() {
                 continue;
             }
 
-            //Give a reference to the buffer at the point where there is free space
-            match p.device_info().0 {
-                Ok(mut p_devices) => {
-                    devices.append(&mut p_devices);
+            match p.worker.device_info(self.call_timeout).0 {
+                Ok(p_devices) => {
+                    plugin_devices.push((i, p.worker.name(), p_devices));
                 }
                 Err(e) => {
                     error!(
                         "Plugin {:?} failed to fetch devices with error {:?}",
-                        p.name(),
+                        p.worker.name(),
                         e
                     );
                     error = e;
                 }
             }
         }
+        //Devices which more than one plugin reports are only listed once, by the plugin which owns them
+        let owners = self.device_owners();
+        let mut devices: Vec<DeviceInfo> = vec![];
+        for (i, name, p_devices) in plugin_devices {
+            devices.extend(
+                p_devices
+                    .into_iter()
+                    .filter(|device| owners.owns(i, device.device_id))
+                    .map(|mut device| {
+                        device.details.plugin_name = Some(name.clone());
+                        device
+                    }),
+            );
+        }
         if devices.is_empty() && !error.is_ok() {
             Err(error).into()
         } else {
@@ -357,7 +660,9 @@ impl AnalogSDK {
         }
     }
 
-    pub fn read_analog(&mut self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
+    /// Reads the analog value of the key with identifier `code` (of the current `keycode_mode`). This only needs a shared reference, so
+    /// many threads can read at the same time. Reads from plugins which provide a `SnapshotStore` don't take any locks at all
+    pub fn read_analog(&self, code: u16, device_id: DeviceID) -> SDKResult<f32> {
         if !self.initialised {
             return Err(WootingAnalogResult::UnInitialized).into();
         }
@@ -366,21 +671,40 @@ impl AnalogSDK {
         let hid_code = code_to_hid(code, &self.keycode_mode);
         if let Some(hid_code) = hid_code {
             let mut value: f32 = -1.0;
-            let mut err = WootingAnalogResult::Ok;
-
-            for p in self.plugins.iter_mut() {
-                match p.read_analog(hid_code, device_id).into() {
-                    Ok(x) => {
-                        value = value.max(x);
-                        //If we were looking to read from a specific device, we've found that read, so no need to continue
-                        if device_id != 0 {
-                            break;
+            let mut err = WootingAnalogResult::NoDevices;
+
+            let owners = self.device_owners();
+            let split = self.remap.has_device_remaps();
+            for (i, p) in self.plugins.iter().enumerate() {
+                for &target in owners.read_targets(i, &device_id, split) {
+                    //Remapped keys are read from the keys which are reported as them
+                    let remapped;
+                    let sources = if self.remap.is_empty() {
+                        slice::from_ref(&hid_code)
+                    } else {
+                        remapped = self.remap.sources(target, hid_code);
+                        &remapped[..]
+                    };
+                    //If no key is reported as this one it's still read, to find out if the device is there
+                    let moved = sources.is_empty();
+                    let sources = if moved {
+                        slice::from_ref(&hid_code)
+                    } else {
+                        sources
+                    };
+                    for &source in sources {
+                        match p.read_analog(source, target, self.call_timeout).into() {
+                            Ok(x) => value = value.max(if moved { 0.0 } else { x }),
+                            Err(e) => {
+                                //TODO: Improve collating of multiple errors
+                                err = e
+                            }
                         }
                     }
-                    Err(e) => {
-                        //TODO: Improve collating of multiple errors
-                        err = e
-                    }
+                }
+                //If we were looking to read from a specific device, we've found that read, so no need to continue
+                if device_id != 0 && value >= 0.0 {
+                    break;
                 }
             }
 
@@ -394,8 +718,54 @@ impl AnalogSDK {
         }
     }
 
+    /// Reads the analog values of all pressed keys using the default session. When a key is released it will be returned with
+    /// an analog value of 0.0 in the first call after it has been released.
+    ///
+    /// # Notes
+    /// * The default session is shared by everyone who calls this, so if there are multiple readers they will steal each others released
+    /// keys. Readers which need to see every release should create their own session with `create_session` and use `read_full_buffer_session`
     pub fn read_full_buffer(
-        &mut self,
+        &self,
+        max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<u16, f32>> {
+        self.read_full_buffer_session(DEFAULT_SESSION, max_length, device_id)
+    }
+
+    /// Creates a new session for reading with `read_full_buffer_session`. Each session keeps track of released keys on its own,
+    /// so readers don't miss out on releases another reader was given. The session should be destroyed with `destroy_session` once
+    /// it is no longer needed
+    pub fn create_session(&self) -> SDKResult<SessionID> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        let id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
+        self.sessions.lock().unwrap().insert(id, Default::default());
+        Ok(id).into()
+    }
+
+    /// Destroys the `session` that was created by `create_session`. The default session can't be destroyed
+    pub fn destroy_session(&self, session: SessionID) -> SDKResult<()> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        if session == DEFAULT_SESSION {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        match self.sessions.lock().unwrap().remove(&session) {
+            Some(_) => Ok(()).into(),
+            None => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+
+    /// Reads the analog values of all pressed keys for the given `session`. When a key is released it will be returned with
+    /// an analog value of 0.0 in the first call with this `session` (and `device_id`) after it has been released
+    pub fn read_full_buffer_session(
+        &self,
+        session: SessionID,
         max_length: usize,
         device_id: DeviceID,
     ) -> SDKResult<HashMap<u16, f32>> {
@@ -403,43 +773,164 @@ impl AnalogSDK {
             return Err(WootingAnalogResult::UnInitialized).into();
         }
 
-        let mut analog_data: HashMap<u16, f32> = HashMap::with_capacity(max_length);
+        let mut hid_data = match self.read_hid_session(session, max_length, device_id).0 {
+            Ok(hid_data) => hid_data,
+            Err(e) => return Err(e).into(),
+        };
+
+        let mut analog_data: HashMap<u16, f32> = HashMap::with_capacity(hid_data.len());
+        for (hid_code, analog) in hid_data.drain() {
+            match hid_to_code(hid_code, &self.keycode_mode) {
+                Some(code) => {
+                    analog_data.insert(code, analog);
+                }
+                //Keys without a code in the mode are left out, which would otherwise be logged on every read
+                None => trace!("Couldn't map HID:{} to {:?}", hid_code, self.keycode_mode),
+            }
+        }
+
+        Ok(analog_data).into()
+    }
+
+    /// Reads the analog values of all pressed keys by HID code for the given `session`, which gives keys that were released since
+    /// its last read with an analog value of 0.0
+    fn read_hid_session(
+        &self,
+        session: SessionID,
+        max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<u16, f32>> {
+        let session = match self.sessions.lock().unwrap().get(&session) {
+            Some(session) => Arc::clone(session),
+            None => return Err(WootingAnalogResult::InvalidArgument).into(),
+        };
+
+        //Keys which would be cut off are still held, so the whole buffer is read and only cut short once releases are added
+        let read_length = max_length.max(SESSION_READ_LEN);
+        let mut hid_data = match self.read_hid_data(read_length, device_id).0 {
+            Ok(hid_data) => hid_data,
+            Err(e) => return Err(e).into(),
+        };
+        session
+            .lock()
+            .unwrap()
+            .track_releases(device_id, &mut hid_data, max_length);
+        Ok(hid_data).into()
+    }
+
+    /// Reads the analog values of all pressed keys by HID code, with the remaps applied, combining the values of all plugins
+    fn read_hid_data(
+        &self,
+        max_length: usize,
+        device_id: DeviceID,
+    ) -> SDKResult<HashMap<u16, f32>> {
+        let mut hid_data: HashMap<u16, f32> = HashMap::with_capacity(max_length);
 
-        let mut err = WootingAnalogResult::Ok;
+        let mut err = WootingAnalogResult::NoDevices;
         let mut any_success = false;
         //Read from all and add up
-        for p in self.plugins.iter_mut() {
-            let plugin_data = p
-                .read_full_buffer(max_length - analog_data.len(), device_id)
-                .into();
-            match plugin_data {
-                Ok(mut data) => {
-                    for (hid_code, analog) in data.drain() {
-                        let code = hid_to_code(hid_code, &self.keycode_mode);
-                        if let Some(code) = code {
-                            let mut total_analog = analog;
-
-                            //No point in checking if the value is already present if we are only looking for data from one device
-                            if device_id == 0 {
-                                if let Some(val) = analog_data.get(&code) {
-                                    total_analog = total_analog.max(*val);
-                                }
-                            }
-                            analog_data.insert(code, total_analog);
-                        } else {
-                            warn!("Couldn't map HID:{} to {:?}", hid_code, self.keycode_mode);
+        let owners = self.device_owners();
+        let split = self.remap.has_device_remaps();
+        for (i, p) in self.plugins.iter().enumerate() {
+            for &target in owners.read_targets(i, &device_id, split) {
+                //Remapping can disable keys or report a key as several, so the whole buffer is read and cut short afterwards
+                let remaining = if self.remap.is_empty() {
+                    max_length - hid_data.len().min(max_length)
+                } else {
+                    max_length
+                };
+                let plugin_data = p
+                    .worker
+                    .call(self.call_timeout, move |plugin| {
+                        plugin.read_full_buffer(remaining, target)
+                    })
+                    .into();
+                match plugin_data {
+                    Ok(mut data) => {
+                        if !self.remap.is_empty() {
+                            data = self.remap.apply(target, data);
+                        }
+                        //Released keys are tracked per session below, so anything the plugin reports as not pressed is left out
+                        for (hid_code, analog) in data.drain().filter(|(_, analog)| *analog > 0.0) {
+                            let total_analog = hid_data.entry(hid_code).or_insert(analog);
+                            *total_analog = total_analog.max(analog);
                         }
-                    }
 
-                    any_success = true;
+                        any_success = true;
+                    }
+                    Err(e) => {
+                        //TODO: Improve collating of multiple errors
+                        err = e
+                    }
                 }
-                Err(e) => {
-                    //TODO: Improve collating of multiple errors
-                    err = e
+            }
+            //If we are looking for a specific device, just break out when we find one that returns good
+            if device_id != 0 && any_success {
+                break;
+            }
+        }
+        if !any_success {
+            return Err(err).into();
+        }
+        keep_furthest(&mut hid_data, max_length);
+
+        Ok(hid_data).into()
+    }
+
+    /// Fills `buffer` with the analog value of every key, indexed by HID code, i.e. the value of A (HID 0x04) ends up in `buffer[4]`.
+    /// Returns the number of keys which are pressed.
+    ///
+    /// # Notes
+    /// * This doesn't allocate when reading from plugins which provide a `SnapshotStore`, so it's suitable for reading every frame
+    /// * The buffer is always indexed by HID code, regardless of the `keycode_mode`
+    /// * Keys which aren't pressed are 0.0, so unlike `read_full_buffer` there's no need to track releases
+    /// * Codes which don't fit in `buffer` are skipped, a buffer of `DENSE_BUFFER_LEN` covers all standard keys
+    /// * If two devices have the same key pressed, the greater value will be given
+    /// * Key remaps are applied to the buffer too, but need it to be allocated while there are any
+    pub fn read_dense_buffer(&self, buffer: &mut [f32], device_id: DeviceID) -> SDKResult<u32> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        for value in buffer.iter_mut() {
+            *value = 0.0;
+        }
+
+        let mut err = WootingAnalogResult::NoDevices;
+        let mut any_success = false;
+        let owners = self.device_owners();
+        let split = self.remap.has_device_remaps();
+        //Remaps are applied to each device on its own, so it's read into a buffer of its own first
+        let mut unmapped = if self.remap.is_empty() {
+            None
+        } else {
+            Some(vec![0.0; buffer.len()])
+        };
+        for (i, p) in self.plugins.iter().enumerate() {
+            for &target in owners.read_targets(i, &device_id, split) {
+                let result = match unmapped.as_mut() {
+                    None => p.read_dense_buffer(buffer, target, self.call_timeout),
+                    Some(unmapped) => {
+                        for value in unmapped.iter_mut() {
+                            *value = 0.0;
+                        }
+                        let result = p.read_dense_buffer(unmapped, target, self.call_timeout);
+                        if result.0.is_ok() {
+                            self.remap.apply_dense(target, unmapped, buffer);
+                        }
+                        result
+                    }
+                };
+                match result.0 {
+                    Ok(()) => any_success = true,
+                    Err(e) => {
+                        //TODO: Improve collating of multiple errors
+                        err = e
+                    }
                 }
             }
             //If we are looking for a specific device, just break out when we find one that returns good
-            if device_id != 0 {
+            if device_id != 0 && any_success {
                 break;
             }
         }
@@ -447,60 +938,585 @@ impl AnalogSDK {
             return Err(err).into();
         }
 
-        Ok(analog_data).into()
+        Ok(buffer.iter().filter(|value| **value > 0.0).count() as u32).into()
     }
 
-    /// Unload all plugins and loaded plugin libraries, making sure to fire
-    /// their `on_plugin_unload()` methods so they can do any necessary cleanup.
-    pub fn unload(&mut self) {
-        debug!("Unloading plugins");
-        for mut plugin in self.plugins.drain(..) {
-            let name = plugin.name().0;
-            trace!("Firing on_plugin_unload for {:?}", name);
-            plugin.unload();
-            debug!("Unload successful for {:?}", name);
+    /// Gives the physical layout of the device with ID `device_id`, as reported by the plugin it belongs to. If `device_id` is 0
+    /// the layout of the first connected device is given
+    pub fn get_device_layout(&self, device_id: DeviceID) -> SDKResult<KeyboardLayout> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
         }
 
-        debug!("Attempting to drop loaded libraries");
-        self.loaded_libraries.drain(..);
-        debug!("Succeeded dropping loaded libraries");
+        let owners = self.device_owners();
+        for (i, p) in self.plugins.iter().enumerate() {
+            if !owners.owns(i, device_id) {
+                continue;
+            }
 
-        self.device_event_callback.lock().unwrap().take();
-        debug!("Finished Analog SDK Uninit");
+            let layout = p.worker.call(self.call_timeout, move |plugin| {
+                if !plugin.is_initialised() {
+                    return Ok(None).into();
+                }
 
-        self.initialised = false;
+                let device = plugin.device_info().0.ok().and_then(|devices| {
+                    devices
+                        .into_iter()
+                        .find(|device| device_id == 0 || device.device_id == device_id)
+                });
+                match device {
+                    Some(device) => plugin.device_layout(device.device_id).0.map(Some).into(),
+                    None => Ok(None).into(),
+                }
+            });
+            match layout.0 {
+                Ok(Some(layout)) => return Ok(layout).into(),
+                Err(e) if e != WootingAnalogResult::NoDevices => return Err(e).into(),
+                _ => {}
+            }
+        }
+        Err(WootingAnalogResult::NoDevices).into()
     }
-}
 
-impl Drop for AnalogSDK {
-    fn drop(&mut self) {
-        self.unload();
+    /// Gives the position & size of every key of the device with ID `device_id`, with the codes of the current `keycode_mode`.
+    /// Keys which have no code in the `keycode_mode` are left out. If `device_id` is 0 the first connected device is used
+    pub fn get_device_geometry(&self, device_id: DeviceID) -> SDKResult<Vec<KeyGeometry>> {
+        match self.get_device_layout(device_id).0 {
+            Ok(layout) => Ok(layout
+                .keys()
+                .into_iter()
+                .filter_map(|key| {
+                    hid_to_code(key.code, &self.keycode_mode)
+                        .map(|code| KeyGeometry { code, ..key })
+                })
+                .collect())
+            .into(),
+            Err(e) => Err(e).into(),
+        }
     }
-}
 
-impl Default for AnalogSDK {
-    fn default() -> Self {
-        Self::new()
+    /// Allows `send_feature_report` to send data to devices, which is refused by default. Only enable this if you know what the
+    /// data does, as vendor commands can change the settings of a device or even the firmware on it
+    pub fn set_raw_device_access(&mut self, enabled: bool) {
+        self.raw_device_access = enabled;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use shared_memory::*;
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::{Arc, Mutex};
-    use std::time::Duration;
+    /// Sends the feature report `data` to the device with ID `device_id`, the first byte being the report ID (or 0 if the device
+    /// doesn't use report IDs). This needs raw device access to have been enabled with `set_raw_device_access`
+    ///
+    /// # Expected Returns
+    /// * `PermissionDenied`: Raw device access hasn't been enabled
+    /// * `InvalidArgument`: `device_id` is 0 or `data` is empty
+    /// * `NoDevices`: There's no device with ID `device_id`
+    /// * `NotAvailable`: The plugin of the device can't send feature reports
+    pub fn send_feature_report(&self, device_id: DeviceID, data: &[u8]) -> SDKResult<()> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if !self.raw_device_access {
+            return Err(WootingAnalogResult::PermissionDenied).into();
+        }
+        if data.is_empty() {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
 
-    struct SharedState {
-        pub vendor_id: u16,
-        /// Device Product ID `pid`
-        pub product_id: u16,
-        //TODO: Consider switching these to FFiStr
-        /// Device Manufacturer name
-        pub manufacturer_name: [u8; 20],
-        /// Device name
-        pub device_name: [u8; 20],
+        let data = data.to_vec();
+        self.call_device_plugin(device_id, move |plugin| {
+            plugin.send_feature_report(device_id, &data)
+        })
+    }
+
+    /// Reads the feature report with the ID in the first byte of `buffer` from the device with ID `device_id` into `buffer`, giving
+    /// its length including the report ID. Only the report ID is sent to the device, so this doesn't need raw device access
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: `device_id` is 0 or `buffer` is empty
+    /// * `NoDevices`: There's no device with ID `device_id`
+    /// * `NotAvailable`: The plugin of the device can't read feature reports
+    pub fn get_feature_report(&self, device_id: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if buffer.is_empty() {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        //The plugin may still be writing to the buffer after the call has timed out, so it's given a copy
+        let mut copy = buffer.to_vec();
+        self.call_device_plugin(device_id, move |plugin| {
+            plugin
+                .get_feature_report(device_id, &mut copy)
+                .0
+                .map(|len| (len.min(copy.len()), copy))
+                .into()
+        })
+        .0
+        .map(|(len, copy)| {
+            buffer.copy_from_slice(&copy);
+            len
+        })
+        .into()
+    }
+
+    /// Runs `f` on the plugin which owns the device with ID `device_id`
+    fn call_device_plugin<T, F>(&self, device_id: DeviceID, f: F) -> SDKResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn Plugin) -> SDKResult<T> + Send + 'static,
+    {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if device_id == 0 {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        let owners = self.device_owners();
+        let plugin = self.plugins.iter().enumerate().find(|(i, p)| {
+            owners.owns(*i, device_id)
+                && p.worker
+                    .devices()
+                    .iter()
+                    .any(|device| device.device_id == device_id)
+        });
+        match plugin {
+            Some((_, p)) => p.worker.call(self.call_timeout, f),
+            None => Err(WootingAnalogResult::NoDevices).into(),
+        }
+    }
+
+    /// Switches to keeping device aliases in the file at `path`, loading the aliases already in it
+    pub fn set_alias_file(&mut self, path: &Path) {
+        self.aliases = DeviceAliases::load(path);
+    }
+
+    /// Gives the device with ID `device_id` the given alias (or removes it if `alias` is `None`), which is saved so that it's
+    /// kept between runs. This doesn't need the device to be connected
+    pub fn set_device_alias(&mut self, device_id: DeviceID, alias: Option<&str>) -> SDKResult<()> {
+        self.aliases.set(device_id, alias)
+    }
+
+    pub fn get_device_alias(&self, device_id: DeviceID) -> Option<String> {
+        self.aliases.get(device_id).map(str::to_owned)
+    }
+
+    /// Finds the ID of the device with the given alias, ignoring case
+    pub fn find_device_by_alias(&self, alias: &str) -> Option<DeviceID> {
+        self.aliases.find(alias)
+    }
+
+    /// Gives the name & status of every loaded plugin, in the order they were loaded
+    pub fn get_plugin_status(&self) -> SDKResult<Vec<PluginInfo>> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        Ok(self
+            .plugins
+            .iter()
+            .map(|p| PluginInfo {
+                name: p.worker.name(),
+                path: p
+                    .path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned()),
+                status: p.worker.status(),
+            })
+            .collect())
+        .into()
+    }
+
+    /// Sets how long a call into a plugin may take before it's treated as hung. Plugins which keep going over it get quarantined
+    pub fn set_plugin_call_timeout(&mut self, timeout: Duration) -> SDKResult<()> {
+        if timeout == Duration::from_secs(0) {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        self.call_timeout = timeout;
+        Ok(()).into()
+    }
+
+    /// Sets the priority of the plugin loaded from the library at `path` (or with the given name, for plugins given to
+    /// `initialise_with_plugins`), which decides which plugin owns a device when more than one plugin reports it. The plugin with
+    /// the highest priority owns it, or the one loaded first if they're the same. Plugins have a priority of 0 unless they're
+    /// given one, and plugins in the plugin directory itself are loaded before those in its sub directories. This can be set
+    /// before the plugins are loaded
+    pub fn set_plugin_priority(&mut self, path: &Path, priority: i32) {
+        self.plugin_priorities.insert(path.to_owned(), priority);
+        *self.owners.get_mut().unwrap() = Default::default();
+        self.reset_event_owners();
+    }
+
+    /// Gives the filter of device events the priority & devices of every plugin, without giving any events
+    fn reset_event_owners(&self) {
+        let mut owners = self.event_owners.lock().unwrap();
+        for (i, p) in self.plugins.iter().enumerate() {
+            owners.set_plugin(i, p.priority(&self.plugin_priorities), p.worker.devices());
+        }
+    }
+
+    /// Gives the owner of every device, working it out again if the devices of any plugin have changed since it last was
+    fn device_owners(&self) -> RwLockReadGuard<DeviceOwners> {
+        let changes = || self.plugins.iter().map(|p| p.worker.devices_changed());
+        {
+            let owners = self.owners.read().unwrap();
+            if owners.is_current(changes()) {
+                return owners;
+            }
+        }
+
+        *self.owners.write().unwrap() = DeviceOwners::resolve(self.plugins.iter().map(|p| {
+            let priority = p.priority(&self.plugin_priorities);
+            //The count is taken first, so if the devices change in between this is worked out again next time
+            let changed = p.worker.devices_changed();
+            let devices = if p.worker.is_quarantined() {
+                vec![]
+            } else {
+                p.worker.devices()
+            };
+            (priority, changed, devices)
+        }));
+        self.owners.read().unwrap()
+    }
+
+    /// Reports the key `from` as the keys in `to` on the device with `device_id`, or on every device if it's 0, replacing any
+    /// earlier remap of the key. This is applied to the keys read from the device before they're given, e.g. to move the analog
+    /// travel of a broken switch to another key, or to report a key as several keys. If `to` is empty the key is disabled.
+    /// Remaps for a specific device take precedence over those for every device
+    ///
+    /// # Notes
+    /// * The codes are of the current `keycode_mode`, the remap itself is kept by HID code so it isn't affected by changing it
+    ///
+    /// # Expected Returns
+    /// * `NoMapping`: One of the codes doesn't have a HID code
+    pub fn set_key_remap(&mut self, device_id: DeviceID, from: u16, to: &[u16]) -> SDKResult<()> {
+        let hid = |code| code_to_hid(code, &self.keycode_mode);
+        let to: Option<Vec<u16>> = to.iter().copied().map(hid).collect();
+        match (hid(from), to) {
+            (Some(from), Some(to)) => {
+                self.remap.set(device_id, from, &to);
+                Ok(()).into()
+            }
+            _ => Err(WootingAnalogResult::NoMapping).into(),
+        }
+    }
+
+    /// Removes the remap of the key `from` (of the current `keycode_mode`) set for the device with `device_id`, or the one for
+    /// every device if it's 0
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: The key isn't remapped for the device
+    /// * `NoMapping`: `from` doesn't have a HID code
+    pub fn remove_key_remap(&mut self, device_id: DeviceID, from: u16) -> SDKResult<()> {
+        match code_to_hid(from, &self.keycode_mode) {
+            Some(from) if self.remap.remove(device_id, from) => Ok(()).into(),
+            Some(_) => Err(WootingAnalogResult::InvalidArgument).into(),
+            None => Err(WootingAnalogResult::NoMapping).into(),
+        }
+    }
+
+    pub fn clear_key_remaps(&mut self) {
+        self.remap.clear();
+    }
+
+    /// Replaces all key remaps with those in the file at `path`. See the `remap` module for the format of the file
+    ///
+    /// # Expected Returns
+    /// * `Failure`: The file couldn't be read, in which case the remaps are left unchanged
+    pub fn load_key_remap_file(&mut self, path: &Path) -> SDKResult<()> {
+        match RemapTable::load(path) {
+            Ok(remap) => {
+                self.remap = remap;
+                Ok(()).into()
+            }
+            Err(e) => {
+                error!("Unable to read key remaps {}: {}", path.display(), e);
+                Err(WootingAnalogResult::Failure).into()
+            }
+        }
+    }
+
+    /// Defines the virtual axis called `name`, replacing any axis with the same name. The keys of the `definition` are of the
+    /// current `keycode_mode`, the axis itself is kept by HID code so it isn't affected by changing it
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: `name` is empty or the deadzone isn't from 0 up to (but not including) 1
+    /// * `NoMapping`: One of the keys doesn't have a HID code
+    pub fn define_axis(&mut self, name: &str, definition: AxisDefinition) -> SDKResult<()> {
+        let hid = |codes: &[u16]| -> Option<Vec<u16>> {
+            codes
+                .iter()
+                .map(|&code| code_to_hid(code, &self.keycode_mode))
+                .collect()
+        };
+        match (hid(&definition.positive), hid(&definition.negative)) {
+            (Some(positive), Some(negative)) => self.axes.define(
+                name,
+                AxisDefinition {
+                    positive,
+                    negative,
+                    ..definition
+                },
+            ),
+            _ => Err(WootingAnalogResult::NoMapping).into(),
+        }
+    }
+
+    /// Removes the virtual axis called `name`
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `name`
+    pub fn remove_axis(&mut self, name: &str) -> SDKResult<()> {
+        self.axes.remove(name)
+    }
+
+    /// Pairs up the axes `x` & `y` into a stick, which normalises them together so that their combined length is at most 1.0,
+    /// e.g. so that moving diagonally with WASD isn't faster than moving straight
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: One of the axes doesn't exist, or they're the same axis
+    pub fn pair_axes(&mut self, x: &str, y: &str) -> SDKResult<()> {
+        self.axes.pair(x, y)
+    }
+
+    /// Reads the value of the virtual axis called `name` from all devices, which goes from -1.0 to 1.0
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `name`
+    /// * `UnInitialized`: The SDK hasn't been initialised
+    /// * `NoDevices`: There are no connected devices
+    pub fn read_axis(&self, name: &str) -> SDKResult<f32> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if !self.axes.contains(name) {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        match self.read_hid_data(DENSE_BUFFER_LEN, 0).0 {
+            Ok(hid_data) => self.axes.read(name, &hid_data),
+            Err(e) => Err(e).into(),
+        }
+    }
+
+    /// Reads the values of the virtual axes called `x` & `y` from all devices at once, e.g. the two axes of a stick, so that they're
+    /// both from the same read. Each goes from -1.0 to 1.0
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: There's no axis called `x` or `y`
+    /// * `UnInitialized`: The SDK hasn't been initialised
+    /// * `NoDevices`: There are no connected devices
+    pub fn read_axis_pair(&self, x: &str, y: &str) -> SDKResult<(f32, f32)> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        if !self.axes.contains(x) || !self.axes.contains(y) {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+
+        match self.read_hid_data(DENSE_BUFFER_LEN, 0).0 {
+            Ok(hid_data) => self.axes.read_pair(x, y, &hid_data),
+            Err(e) => Err(e).into(),
+        }
+    }
+
+    /// Gives how the key with `code` is moving on the device with `device_id`, or on the device where it's pressed furthest if it's 0.
+    /// This is worked out from every report the device sends, so it doesn't matter how often it's read
+    ///
+    /// # Notes
+    /// * The code is of the current `keycode_mode`. If keys are remapped onto it, the one pressed furthest is given
+    /// * Only devices of plugins which publish their reports into a `SnapshotStore` are tracked
+    ///
+    /// # Expected Returns
+    /// * `UnInitialized`: The SDK hasn't been initialised
+    /// * `NoMapping`: `code` doesn't have a HID code
+    /// * `NoDevices`: No reports have come in from the device with `device_id` (or from any device if it's 0)
+    pub fn read_key_kinematics(&self, code: u16, device_id: DeviceID) -> SDKResult<KeyKinematics> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        let hid_code = match code_to_hid(code, &self.keycode_mode) {
+            Some(hid_code) => hid_code,
+            None => return Err(WootingAnalogResult::NoMapping).into(),
+        };
+
+        let owners = self.device_owners();
+        let owns = |plugin: usize, id| {
+            owners.owns(plugin, id)
+                && !self
+                    .plugins
+                    .get(plugin)
+                    .map_or(true, |p| p.worker.is_quarantined())
+        };
+        match self
+            .kinematics
+            .key(device_id, owns, |id| self.remap.sources(id, hid_code))
+        {
+            Some(kinematics) => Ok(kinematics).into(),
+            None => Err(WootingAnalogResult::NoDevices).into(),
+        }
+    }
+
+    /// Gives the current time in microseconds on the clock of the times in `KeyKinematics`, e.g. to tell how long a key which is
+    /// still pressed has been held for
+    pub fn kinematics_time(&self) -> u64 {
+        self.kinematics.time(Instant::now())
+    }
+
+    /// Binds the key with `code` (of the current `keycode_mode`) to `action` of the emulated mouse, replacing what it was bound
+    /// to. Any number of keys can be bound to the same action
+    ///
+    /// # Expected Returns
+    /// * `NoMapping`: `code` doesn't have a HID code
+    pub fn bind_mouse_action(&mut self, code: u16, action: MouseAction) -> SDKResult<()> {
+        match code_to_hid(code, &self.keycode_mode) {
+            Some(hid_code) => {
+                Arc::make_mut(&mut self.mouse_config)
+                    .bindings
+                    .insert(hid_code, action);
+                Ok(()).into()
+            }
+            None => Err(WootingAnalogResult::NoMapping).into(),
+        }
+    }
+
+    /// Unbinds the key with `code` (of the current `keycode_mode`) from the emulated mouse
+    ///
+    /// # Expected Returns
+    /// * `NoMapping`: `code` doesn't have a HID code
+    /// * `InvalidArgument`: The key isn't bound
+    pub fn unbind_mouse_action(&mut self, code: u16) -> SDKResult<()> {
+        let hid_code = match code_to_hid(code, &self.keycode_mode) {
+            Some(hid_code) => hid_code,
+            None => return Err(WootingAnalogResult::NoMapping).into(),
+        };
+        match Arc::make_mut(&mut self.mouse_config)
+            .bindings
+            .remove(&hid_code)
+        {
+            Some(_) => Ok(()).into(),
+            None => Err(WootingAnalogResult::InvalidArgument).into(),
+        }
+    }
+
+    /// Sets how fast the emulated mouse pointer moves (in pixels per second) with how far the keys moving it are pressed
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: The curve isn't valid, see `MouseCurve::is_valid`
+    pub fn set_mouse_pointer_curve(&mut self, curve: MouseCurve) -> SDKResult<()> {
+        if !curve.is_valid() {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+        Arc::make_mut(&mut self.mouse_config).pointer = curve;
+        Ok(()).into()
+    }
+
+    /// Sets how fast the emulated mouse scrolls (in lines per second) with how far the keys scrolling it are pressed
+    ///
+    /// # Expected Returns
+    /// * `InvalidArgument`: The curve isn't valid, see `MouseCurve::is_valid`
+    pub fn set_mouse_scroll_curve(&mut self, curve: MouseCurve) -> SDKResult<()> {
+        if !curve.is_valid() {
+            return Err(WootingAnalogResult::InvalidArgument).into();
+        }
+        Arc::make_mut(&mut self.mouse_config).scroll = curve;
+        Ok(()).into()
+    }
+
+    /// Reads the full buffer of all devices by HID code, along with the current mouse config, for the mouse emulation. This is
+    /// read through the emulation's own `session` (made with `create_session`), so it doesn't affect anyone else reading the SDK
+    pub(crate) fn read_mouse_input(
+        &self,
+        session: SessionID,
+    ) -> SDKResult<(HashMap<u16, f32>, Arc<MouseConfig>)> {
+        if !self.initialised {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+        match self.read_hid_session(session, DENSE_BUFFER_LEN, 0).0 {
+            Ok(hid_data) => Ok((hid_data, Arc::clone(&self.mouse_config))).into(),
+            Err(e) => Err(e).into(),
+        }
+    }
+
+    /// Unload all plugins and loaded plugin libraries, making sure to fire
+    /// their `on_plugin_unload()` methods so they can do any necessary cleanup.
+    ///
+    /// # Notes
+    /// * This takes at most `UNLOAD_TIMEOUT`, plugins which haven't stopped by then are left behind on their threads
+    pub fn unload(&mut self) {
+        debug!("Unloading plugins");
+        //Snapshot readers point into the plugins, so they're gone before the plugins are
+        for p in self.plugins.iter_mut() {
+            p.stop_tracking_reports();
+            p.snapshots = None;
+            p.worker.shutdown();
+        }
+        self.kinematics.clear();
+
+        *self.owners.get_mut().unwrap() = Default::default();
+        *self.event_owners.lock().unwrap() = Default::default();
+
+        let deadline = Instant::now() + UNLOAD_TIMEOUT;
+        for p in self.plugins.drain(..) {
+            if p.worker.wait_for_exit(deadline) {
+                debug!("Unload successful for {:?}", p.worker.name());
+            } else {
+                warn!(
+                    "Plugin {:?} didn't unload in time, leaving it behind",
+                    p.worker.name()
+                );
+                //It could still be giving a report to its feed
+                std::mem::forget(p.report_feed);
+            }
+        }
+
+        if let Some(mut events) = self.events.take() {
+            events.stop();
+        }
+        //The listeners aren't waited on, as they could be waiting on the SDK which is held by the caller
+        for (_, listener) in self.listeners.lock().unwrap().drain(..) {
+            listener.stop();
+        }
+        //Sessions don't outlive the plugins they were reading from
+        let sessions = self.sessions.get_mut().unwrap();
+        sessions.clear();
+        sessions.insert(DEFAULT_SESSION, Default::default());
+        debug!("Finished Analog SDK Uninit");
+
+        self.initialised = false;
+    }
+}
+
+impl Drop for AnalogSDK {
+    fn drop(&mut self) {
+        self.unload();
+    }
+}
+
+impl Default for AnalogSDK {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_memory::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    struct SharedState {
+        pub vendor_id: u16,
+        /// Device Product ID `pid`
+        pub product_id: u16,
+        //TODO: Consider switching these to FFiStr
+        /// Device Manufacturer name
+        pub manufacturer_name: [u8; 20],
+        /// Device name
+        pub device_name: [u8; 20],
         /// Unique device ID, which should be generated using `generate_device_id`
         pub device_id: u64,
 
@@ -519,6 +1535,13 @@ mod tests {
             .map_err(|e| println!("ERROR: Could not initialise env_logger. '{:?}'", e));
     }
 
+    //The SDK is shared between threads behind a RwLock, so this stops building if a field which isn't thread safe is added
+    #[test]
+    fn sdk_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AnalogSDK>();
+    }
+
     #[test]
     fn initialise_no_plugins() {
         shared_init();
@@ -620,6 +1643,17 @@ mod tests {
             }
         };
 
+        //Listeners are called in the order they were added, so this one will have seen each event by the time the default one has
+        let listener_events: Arc<Mutex<Vec<DeviceEventType>>> = Arc::new(Mutex::new(vec![]));
+        let listener_events_borrow = listener_events.clone();
+        let listener = sdk()
+            .add_device_event_listener(move |event: DeviceEventType, _device: DeviceInfo| {
+                listener_events_borrow.lock().unwrap().push(event);
+            })
+            .0
+            .unwrap();
+        assert_ne!(listener, DEFAULT_LISTENER);
+
         sdk().set_device_event_cb(move |event: DeviceEventType, _device: DeviceInfo| {
             debug!("Got cb {:?}", event);
 
@@ -654,6 +1688,27 @@ mod tests {
             assert_eq!(sdk().get_device_info().0.map(|dev| dev.len()), Ok(1));
         }
 
+        //Check the layout reported by the test plugin comes through, with codes of the current keycode mode
+        {
+            let device_id = sdk().get_device_info().0.unwrap()[0].device_id;
+            assert_eq!(
+                sdk().get_device_layout(device_id).0,
+                Ok(KeyboardLayout::ANSI)
+            );
+            assert_eq!(sdk().get_device_layout(0).0, Ok(KeyboardLayout::ANSI));
+            assert_eq!(
+                sdk().get_device_layout(device_id + 1).0,
+                Err(WootingAnalogResult::NoDevices)
+            );
+
+            sdk().keycode_mode = KeycodeType::ScanCode1;
+            let keys = sdk().get_device_geometry(device_id).0.unwrap();
+            sdk().keycode_mode = KeycodeType::HID;
+            assert_eq!(keys.len(), KeyboardLayout::ANSI.keys().len());
+            let escape = keys[0];
+            assert_eq!((escape.code, escape.x, escape.y), (0x01, 0.0, 0.0));
+        }
+
         //Check the cb is called with disconnected
         {
             {
@@ -668,6 +1723,38 @@ mod tests {
             assert_eq!(sdk().get_device_info().0.map(|dev| dev.len()), Ok(0));
         }
 
+        //Check the other listener got the same events
+        assert_eq!(
+            *listener_events.lock().unwrap(),
+            vec![DeviceEventType::Connected, DeviceEventType::Disconnected]
+        );
+        assert!(sdk().remove_device_event_listener(listener).0.is_ok());
+        assert_eq!(
+            sdk().remove_device_event_listener(listener).0.err(),
+            Some(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(
+            sdk().remove_device_event_listener(DEFAULT_LISTENER).0.err(),
+            Some(WootingAnalogResult::InvalidArgument)
+        );
+
+        //Check the events can be polled in the order they happened
+        assert_eq!(
+            sdk()
+                .poll_device_event()
+                .0
+                .map(|event| event.map(|(event, _)| event)),
+            Ok(Some(DeviceEventType::Connected))
+        );
+        assert_eq!(
+            sdk()
+                .poll_device_event()
+                .0
+                .map(|event| event.map(|(event, _)| event)),
+            Ok(Some(DeviceEventType::Disconnected))
+        );
+        assert!(sdk().poll_device_event().0.unwrap().is_none());
+
         let analog_val = 0xF4;
         let f_analog_val = f32::from(analog_val) / 255_f32;
         let analog_key = 5;
@@ -735,6 +1822,23 @@ mod tests {
             Err(WootingAnalogResult::NoDevices)
         );
 
+        //Check the dense buffer has the value at the index of the code and clears out anything that was there before
+        let mut dense = [1.0; DENSE_BUFFER_LEN];
+        assert_eq!(sdk().read_dense_buffer(&mut dense, 0).0, Ok(1));
+        assert_eq!(dense[analog_key], f_analog_val);
+        assert_eq!(dense[analog_key + 1], 0.0);
+        assert_eq!(sdk().read_dense_buffer(&mut dense, device_id).0, Ok(1));
+        assert_eq!(dense[analog_key], f_analog_val);
+        assert_eq!(
+            sdk().read_dense_buffer(&mut dense, device_id + 1).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        //Codes which don't fit are skipped
+        assert_eq!(
+            sdk().read_dense_buffer(&mut dense[..analog_key], 0).0,
+            Ok(0)
+        );
+
         //Check that it does code mapping
         sdk().keycode_mode = KeycodeType::ScanCode1;
         let analog_data = sdk().read_full_buffer(buffer_len, device_id).0.unwrap();
@@ -748,6 +1852,15 @@ mod tests {
         );
         sdk().keycode_mode = KeycodeType::HID;
 
+        //Have a second reader see the key pressed in its own session
+        let session = sdk().create_session().0.unwrap();
+        assert_ne!(session, DEFAULT_SESSION);
+        let analog_data = sdk()
+            .read_full_buffer_session(session, buffer_len, device_id)
+            .0
+            .unwrap();
+        assert_eq!(analog_data.len(), 1);
+
         {
             let mut shared_state = get_wlock(&mut shmem);
             shared_state.analog_values[analog_key] = 0;
@@ -758,6 +1871,32 @@ mod tests {
         assert_eq!(analog_data.len(), 1);
         assert_eq!(analog_data[&(analog_key as u16)], 0.0);
 
+        //Check the release wasn't stolen from the second reader
+        let analog_data = sdk()
+            .read_full_buffer_session(session, buffer_len, device_id)
+            .0
+            .unwrap();
+        assert_eq!(analog_data.len(), 1);
+        assert_eq!(analog_data[&(analog_key as u16)], 0.0);
+        assert_eq!(
+            sdk()
+                .read_full_buffer_session(session, buffer_len, device_id)
+                .0
+                .map(|data| data.len()),
+            Ok(0)
+        );
+        assert_eq!(sdk().destroy_session(session).0, Ok(()));
+        assert_eq!(
+            sdk()
+                .read_full_buffer_session(session, buffer_len, device_id)
+                .0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(
+            sdk().destroy_session(DEFAULT_SESSION).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+
         assert_eq!(sdk().read_analog(analog_key as u16, 0).0, Ok(0.0));
 
         let analog_data = sdk().read_full_buffer(buffer_len, device_id).0;
@@ -775,6 +1914,150 @@ mod tests {
         sdk().unload();
     }
 
+    #[test]
+    fn test_plugin_key_kinematics() {
+        shared_init();
+        let _lock = TEST_PLUGIN_LOCK.lock().unwrap();
+
+        let mut sdk = AnalogSDK::new();
+        let dir = format!(
+            "../target/{}/test_plugin",
+            std::env::var("TEST_TARGET").unwrap_or("debug".to_owned())
+        );
+        assert_eq!(
+            sdk.read_key_kinematics(4, 0).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+        assert!(sdk
+            .initialise_with_plugin_path(dir.as_str(), !dir.ends_with("debug"))
+            .0
+            .is_ok());
+        ::std::thread::sleep(Duration::from_millis(500));
+
+        let mut shmem = SharedMem::open_linked(
+            std::env::temp_dir()
+                .join("wooting-test-plugin.link")
+                .as_os_str(),
+        )
+        .unwrap();
+        let set_key = |shmem: &mut SharedMem, key: usize, value: u8| {
+            get_wlock(shmem).analog_values[key] = value;
+        };
+        //Waits for the report of the test plugin with the key at `depth`, which it sends every 10ms
+        let wait_for_depth = |sdk: &AnalogSDK, key: usize, depth: f32| {
+            for _ in 0..200 {
+                match sdk.read_key_kinematics(key as u16, 0).0 {
+                    Ok(kinematics) if kinematics.depth == depth => return kinematics,
+                    _ => ::std::thread::sleep(Duration::from_millis(10)),
+                }
+            }
+            panic!("Key {} didn't get to {}", key, depth);
+        };
+        let device_id = {
+            let mut shared_state = get_wlock(&mut shmem);
+            shared_state.analog_values = [0; 0xFF];
+            shared_state.device_connected = true;
+            shared_state.device_id
+        };
+        ::std::thread::sleep(Duration::from_secs(1));
+
+        //How keys move is worked out in `kinematics`, this checks the reports of a plugin get there
+        let key = 5;
+        assert_eq!(
+            sdk.read_key_kinematics(key as u16, 0).0,
+            Ok(KeyKinematics::default())
+        );
+
+        set_key(&mut shmem, key, 0x80);
+        let pressed = wait_for_depth(&sdk, key, f32::from(0x80u8) / 255_f32);
+        assert!(pressed.pressed_at > 0);
+        assert_eq!(pressed.released_at, 0);
+        assert_eq!(
+            sdk.read_key_kinematics(key as u16, device_id)
+                .0
+                .map(|kinematics| kinematics.pressed_at),
+            Ok(pressed.pressed_at)
+        );
+        assert_eq!(
+            sdk.read_key_kinematics(key as u16, device_id + 1).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+
+        set_key(&mut shmem, key, 0xFF);
+        wait_for_depth(&sdk, key, 1.0);
+        set_key(&mut shmem, key, 0);
+        let released = wait_for_depth(&sdk, key, 0.0);
+        assert_eq!(released.peak_depth, 1.0);
+        assert_eq!(released.pressed_at, pressed.pressed_at);
+        assert!(released.released_at > released.pressed_at);
+        assert!(sdk.kinematics_time() >= released.released_at);
+
+        //A tap is seen even though the SDK wasn't read while the key was down
+        set_key(&mut shmem, key + 1, 0xFF);
+        ::std::thread::sleep(Duration::from_millis(200));
+        set_key(&mut shmem, key + 1, 0);
+        ::std::thread::sleep(Duration::from_millis(200));
+        let tap = sdk.read_key_kinematics((key + 1) as u16, 0).0.unwrap();
+        assert_eq!(tap.peak_depth, 1.0);
+        assert!(!tap.is_pressed());
+        assert!(tap.released_at > released.released_at);
+
+        get_wlock(&mut shmem).device_connected = false;
+        ::std::thread::sleep(Duration::from_secs(1));
+        sdk.unload();
+        assert_eq!(
+            sdk.read_key_kinematics(key as u16, 0).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+    }
+
+    #[test]
+    fn key_kinematics_from_reports() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![
+                FixedPlugin::boxed("Fixed", 1, 0.5),
+                FixedPlugin::boxed("Duplicate", 2, 0.5),
+            ])
+            .0,
+            Ok(2)
+        );
+
+        //Reports are fed in as the plugins' stores would, with their own timestamps
+        let tracker = Arc::clone(&sdk.kinematics);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        tracker.report(0, 1, std::iter::empty(), at(0));
+        tracker.report(0, 1, [(4, 0.5)].iter().copied(), at(10));
+        let a = sdk.read_key_kinematics(4, 0).0.unwrap();
+        assert_eq!(a.depth, 0.5);
+        assert!((a.velocity - 50.0).abs() < 1e-3);
+        assert!(a.is_pressed());
+
+        //Held still for a report
+        tracker.report(0, 1, [(4, 0.5)].iter().copied(), at(20));
+        let a = sdk.read_key_kinematics(4, 1).0.unwrap();
+        assert_eq!((a.velocity, a.depth), (0.0, 0.5));
+
+        //The second plugin reports the same board, which it doesn't own
+        tracker.report(1, 2, [(4, 1.0)].iter().copied(), at(20));
+        assert_eq!(sdk.read_key_kinematics(4, 0).0.map(|a| a.depth), Ok(0.5));
+        assert_eq!(
+            sdk.read_key_kinematics(4, 2).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+
+        //Codes are of the keycode mode
+        sdk.keycode_mode = KeycodeType::ScanCode1;
+        let code = hid_to_code(4, &KeycodeType::ScanCode1).unwrap();
+        tracker.report(0, 1, std::iter::empty(), at(30));
+        let a = sdk.read_key_kinematics(code, 0).0.unwrap();
+        assert!(!a.is_pressed());
+        assert_eq!(a.press_duration(), Some(Duration::from_millis(20)));
+        sdk.unload();
+    }
+
     #[test]
     fn unitialised_sdk_functions_new() {
         shared_init();
@@ -794,7 +2077,11 @@ mod tests {
         let dir = format!("./{}/build/", TEST_PLUGIN_DIR);
         info!("Loading plugins from: {:?}", dir);
         assert!(!sdk.initialised);
-        assert_eq!(sdk.initialise_with_plugin_path(dir.as_str(), true).0, Ok(1));
+        //The v0 plugin is in a sub directory, which is tested on its own
+        assert_eq!(
+            sdk.initialise_with_plugin_path(dir.as_str(), false).0,
+            Ok(1)
+        );
         assert!(sdk.initialised);
         let got_cb = Arc::new(AtomicBool::new(false));
         let got_cb_inner = got_cb.clone();
@@ -806,19 +2093,70 @@ mod tests {
             //A couple of basic checks to ensure the callback gets valid data
             assert_eq!(event, DeviceEventType::Connected);
             assert_eq!(device.device_id, 7);
+            assert_eq!(device.details.serial_number.as_deref(), Some("C0FFEE"));
         });
 
+        //The plugin doesn't have read_analog, so this goes through read_full_buffer
         assert_eq!(sdk.read_analog(30, 0).0, Ok(0.56));
-        //We told it to execute the callback when read_analog is called so let's just call it a second time to ensure it can be called multiple times without dying
-        assert_eq!(sdk.read_analog(30, 0).0, Ok(0.56));
+        assert_eq!(sdk.read_analog(31, 0).0, Ok(0.0));
         assert_eq!(sdk.read_full_buffer(30, 0).0.unwrap().get(&5), Some(&0.4));
+        //We told it to execute the callback when the device info is fetched so let's just call it a second time to ensure it can be called multiple times without dying
         let device = sdk.get_device_info().0.unwrap().first().unwrap().clone();
         assert_eq!(device.device_id, 7);
+        assert_eq!(device.details.serial_number.as_deref(), Some("C0FFEE"));
+        assert_eq!(device.device_name, "Yeet");
+        assert_eq!(sdk.get_device_info().0.map(|devices| devices.len()), Ok(1));
+        let plugins = sdk.get_plugin_status().0.unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].name, "C Test plugin");
+        assert_eq!(plugins[0].status, PluginStatus::Healthy);
+        assert!(plugins[0]
+            .path
+            .as_deref()
+            .map_or(false, |path| path.contains(TEST_PLUGIN_DIR)));
+        assert_eq!(
+            sdk.set_plugin_call_timeout(Duration::from_secs(0)).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
 
         //Wait a wee bit to ensure the callback has been executed
         ::std::thread::sleep(Duration::from_millis(500));
 
         assert!(got_cb.load(Ordering::Relaxed));
+        //The plugin doesn't have unload either
+        sdk.unload();
+    }
+
+    /// Ensures plugins built against the old plugin.h, which exported each function on its own, can still be loaded
+    #[test]
+    fn test_c_plugin_v0_interface() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+
+        let dir = format!("./{}/build/v0/", TEST_PLUGIN_DIR);
+        assert_eq!(
+            sdk.initialise_with_plugin_path(dir.as_str(), false).0,
+            Ok(1)
+        );
+        let got_cb = Arc::new(AtomicBool::new(false));
+        let got_cb_inner = got_cb.clone();
+        sdk.set_device_event_cb(move |event, device| {
+            got_cb_inner.store(true, Ordering::Relaxed);
+            assert_eq!(event, DeviceEventType::Connected);
+            assert_eq!(device.device_id, 7);
+        });
+
+        //The v0 plugin calls the callback from read_analog
+        assert_eq!(sdk.read_analog(30, 0).0, Ok(0.56));
+        assert_eq!(sdk.read_analog(30, 0).0, Ok(0.56));
+        assert_eq!(sdk.read_full_buffer(30, 0).0.unwrap().get(&5), Some(&0.4));
+        let device = sdk.get_device_info().0.unwrap().first().unwrap().clone();
+        assert_eq!(device.device_id, 7);
+        assert_eq!(device.manufacturer_name, "Yeet");
+
+        ::std::thread::sleep(Duration::from_millis(500));
+        assert!(got_cb.load(Ordering::Relaxed));
+        sdk.unload();
     }
 
     /*#[test]
@@ -861,5 +2199,402 @@ mod tests {
             sdk.read_full_buffer(0, 0).0,
             Err(WootingAnalogResult::UnInitialized)
         );
+
+        assert_eq!(
+            sdk.read_dense_buffer(&mut [0.0; DENSE_BUFFER_LEN], 0).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+
+        assert_eq!(
+            sdk.create_session().0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+
+        assert_eq!(
+            sdk.poll_device_event().0.err(),
+            Some(WootingAnalogResult::UnInitialized)
+        );
+
+        assert_eq!(
+            sdk.add_device_event_listener(cb).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+    }
+
+    /// A plugin with a single device, which has every key pressed to `value` and a single feature report
+    struct FixedPlugin {
+        name: &'static str,
+        device: DeviceInfo,
+        value: f32,
+        feature: Vec<u8>,
+        initialised: bool,
+    }
+
+    impl FixedPlugin {
+        fn boxed(name: &'static str, device_id: DeviceID, value: f32) -> Box<dyn Plugin> {
+            let mut device = DeviceInfo::new_with_id(
+                0x03eb,
+                0xff01,
+                "Wooting".to_owned(),
+                "Wooting One".to_owned(),
+                device_id,
+                DeviceType::Keyboard,
+            );
+            device.details.serial_number = Some("WOOT_001".to_owned());
+            Box::new(FixedPlugin {
+                name,
+                device,
+                value,
+                feature: vec![1, 0x10],
+                initialised: false,
+            })
+        }
+    }
+
+    impl Plugin for FixedPlugin {
+        fn name(&mut self) -> SDKResult<&'static str> {
+            Ok(self.name).into()
+        }
+
+        fn initialise(
+            &mut self,
+            _callback: Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>,
+        ) -> SDKResult<u32> {
+            self.initialised = true;
+            Ok(1).into()
+        }
+
+        fn is_initialised(&mut self) -> bool {
+            self.initialised
+        }
+
+        fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+            Ok(vec![self.device.clone()]).into()
+        }
+
+        fn read_analog(&mut self, _code: u16, device: DeviceID) -> SDKResult<f32> {
+            if device == 0 || device == self.device.device_id {
+                Ok(self.value).into()
+            } else {
+                Err(WootingAnalogResult::NoDevices).into()
+            }
+        }
+
+        fn read_full_buffer(
+            &mut self,
+            _max_length: usize,
+            device: DeviceID,
+        ) -> SDKResult<HashMap<u16, f32>> {
+            self.read_analog(4, device)
+                .0
+                .map(|value| std::iter::once((4, value)).collect())
+                .into()
+        }
+
+        fn send_feature_report(&mut self, _device: DeviceID, data: &[u8]) -> SDKResult<()> {
+            self.feature = data.to_vec();
+            Ok(()).into()
+        }
+
+        fn get_feature_report(&mut self, _device: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+            if buffer[0] != self.feature[0] {
+                return Err(WootingAnalogResult::Failure).into();
+            }
+            let len = self.feature.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&self.feature[..len]);
+            Ok(len).into()
+        }
+    }
+
+    #[test]
+    fn device_feature_reports() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        let mut buffer = [1, 0, 0];
+        assert_eq!(
+            sdk.get_feature_report(1, &mut buffer).0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![
+                FixedPlugin::boxed("Old", 1, 0.25),
+                FixedPlugin::boxed("New", 2, 0.75),
+            ])
+            .0,
+            Ok(2)
+        );
+
+        //Reports can be read, but nothing can be sent until raw access is enabled
+        assert_eq!(sdk.get_feature_report(1, &mut buffer).0, Ok(2));
+        assert_eq!(buffer, [1, 0x10, 0]);
+        assert_eq!(
+            sdk.send_feature_report(1, &[1, 0x20]).0,
+            Err(WootingAnalogResult::PermissionDenied)
+        );
+        sdk.set_raw_device_access(true);
+        assert_eq!(sdk.send_feature_report(1, &[1, 0x20, 0x30]).0, Ok(()));
+        assert_eq!(sdk.get_feature_report(1, &mut buffer).0, Ok(3));
+        assert_eq!(buffer, [1, 0x20, 0x30]);
+        let mut buffer = [2, 0];
+        assert_eq!(
+            sdk.get_feature_report(1, &mut buffer).0,
+            Err(WootingAnalogResult::Failure)
+        );
+
+        assert_eq!(
+            sdk.send_feature_report(0, &[1]).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(
+            sdk.send_feature_report(1, &[]).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        //The same board reported by a plugin which doesn't own it
+        assert_eq!(
+            sdk.send_feature_report(2, &[1]).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        assert_eq!(
+            sdk.send_feature_report(3, &[1]).0,
+            Err(WootingAnalogResult::NoDevices)
+        );
+        sdk.unload();
+    }
+
+    #[test]
+    fn duplicate_devices_are_owned_by_one_plugin() {
+        shared_init();
+        //Two versions of a plugin reporting the same board, which the second gives a different ID
+        let plugins = || {
+            vec![
+                FixedPlugin::boxed("Old", 1, 0.25),
+                FixedPlugin::boxed("New", 2, 0.75),
+            ]
+        };
+
+        let mut sdk = AnalogSDK::new();
+        assert_eq!(sdk.initialise_with_plugins(plugins()).0, Ok(2));
+        let devices = sdk.get_device_info().0.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].device_id, 1);
+        assert_eq!(devices[0].details.plugin_name.as_deref(), Some("Old"));
+        assert_eq!(sdk.read_analog(4, 0).0, Ok(0.25));
+        assert_eq!(sdk.read_full_buffer(10, 0).0.unwrap().get(&4), Some(&0.25));
+        //The ID given by the plugin which doesn't own the device isn't read
+        assert_eq!(sdk.read_analog(4, 2).0, Err(WootingAnalogResult::NoDevices));
+        sdk.unload();
+
+        sdk.set_plugin_priority(Path::new("New"), 1);
+        assert_eq!(sdk.initialise_with_plugins(plugins()).0, Ok(2));
+        let devices = sdk.get_device_info().0.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].details.plugin_name.as_deref(), Some("New"));
+        let mut buffer = [0.0; DENSE_BUFFER_LEN];
+        assert_eq!(sdk.read_dense_buffer(&mut buffer, 0).0, Ok(1));
+        assert_eq!(buffer[4], 0.75);
+        assert_eq!(sdk.read_analog(4, 2).0, Ok(0.75));
+        sdk.unload();
+    }
+
+    #[test]
+    fn remapped_keys() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![FixedPlugin::boxed("Fixed", 1, 0.5)])
+                .0,
+            Ok(1)
+        );
+
+        //A is mirrored to B & C
+        assert_eq!(sdk.set_key_remap(0, 4, &[5, 6]).0, Ok(()));
+        let data = sdk.read_full_buffer(10, 0).0.unwrap();
+        assert_eq!(data.get(&4), None);
+        assert_eq!(data.get(&5), Some(&0.5));
+        assert_eq!(data.get(&6), Some(&0.5));
+        //The plugin gives one key, which becomes two once it's remapped. A new session so no keys are given as released
+        let session = sdk.create_session().0.unwrap();
+        assert_eq!(
+            sdk.read_full_buffer_session(session, 1, 0)
+                .0
+                .map(|data| data.len()),
+            Ok(1)
+        );
+        assert_eq!(sdk.read_analog(4, 0).0, Ok(0.0));
+        assert_eq!(sdk.read_analog(5, 1).0, Ok(0.5));
+        let mut buffer = [0.0; DENSE_BUFFER_LEN];
+        assert_eq!(sdk.read_dense_buffer(&mut buffer, 0).0, Ok(2));
+        assert_eq!(buffer[6], 0.5);
+
+        //The remap for the device takes precedence
+        assert_eq!(sdk.set_key_remap(1, 4, &[7]).0, Ok(()));
+        let data = sdk.read_full_buffer(10, 0).0.unwrap();
+        assert_eq!(data.get(&5), Some(&0.0));
+        assert_eq!(data.get(&7), Some(&0.5));
+
+        assert_eq!(sdk.remove_key_remap(1, 4).0, Ok(()));
+        assert_eq!(
+            sdk.remove_key_remap(1, 4).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        sdk.clear_key_remaps();
+        assert_eq!(sdk.read_analog(4, 0).0, Ok(0.5));
+        sdk.unload();
+    }
+
+    #[test]
+    fn session_with_more_keys_than_max_length() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![FixedPlugin::boxed("Fixed", 1, 0.5)])
+                .0,
+            Ok(1)
+        );
+        //The one key the plugin gives is pressed as three
+        assert_eq!(sdk.set_key_remap(0, 4, &[5, 6, 7]).0, Ok(()));
+        let session = sdk.create_session().0.unwrap();
+        for _ in 0..3 {
+            //The keys which are cut off are still held, so they're never given as released
+            let data = sdk.read_full_buffer_session(session, 2, 0).0.unwrap();
+            assert_eq!(data.len(), 2);
+            assert!(data.values().all(|&analog| analog == 0.5));
+        }
+
+        //All three are released, which are given as they fit behind the pressed key
+        sdk.clear_key_remaps();
+        let mut released = HashSet::new();
+        for _ in 0..3 {
+            let data = sdk.read_full_buffer_session(session, 2, 0).0.unwrap();
+            assert_eq!(data.len(), 2);
+            assert_eq!(data.get(&4), Some(&0.5));
+            released.extend(
+                data.iter()
+                    .filter(|(_, &analog)| analog == 0.0)
+                    .map(|(&code, _)| code),
+            );
+        }
+        assert_eq!(released, [5, 6, 7].iter().copied().collect());
+        assert_eq!(
+            sdk.read_full_buffer_session(session, 2, 0).0.unwrap().len(),
+            1
+        );
+        sdk.unload();
+    }
+
+    #[test]
+    fn virtual_axes() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        let axis = |positive: u16, negative: u16| AxisDefinition {
+            positive: vec![positive],
+            negative: vec![negative],
+            combine: AxisCombine::Difference,
+            deadzone: 0.0,
+        };
+        assert_eq!(sdk.define_axis("x", axis(4, 7)).0, Ok(()));
+        assert_eq!(
+            sdk.read_axis("x").0,
+            Err(WootingAnalogResult::UnInitialized)
+        );
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![FixedPlugin::boxed("Fixed", 1, 0.5)])
+                .0,
+            Ok(1)
+        );
+
+        //Only A (4) is pressed
+        assert_eq!(sdk.read_axis("x").0, Ok(0.5));
+        assert_eq!(sdk.define_axis("y", axis(7, 4)).0, Ok(()));
+        assert_eq!(sdk.read_axis("y").0, Ok(-0.5));
+        assert_eq!(
+            sdk.read_axis("z").0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+
+        //Axes are read after remapping
+        assert_eq!(sdk.set_key_remap(0, 4, &[7]).0, Ok(()));
+        assert_eq!(sdk.read_axis("x").0, Ok(-0.5));
+        sdk.clear_key_remaps();
+
+        assert_eq!(sdk.pair_axes("x", "y").0, Ok(()));
+        assert_eq!(sdk.read_axis_pair("x", "y").0, Ok((0.5, -0.5)));
+        assert_eq!(
+            sdk.read_axis_pair("x", "z").0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        assert_eq!(sdk.remove_axis("y").0, Ok(()));
+        assert_eq!(
+            sdk.remove_axis("y").0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        sdk.unload();
+    }
+
+    #[test]
+    fn mouse_config() {
+        shared_init();
+        let mut sdk = AnalogSDK::new();
+        assert_eq!(sdk.bind_mouse_action(4, MouseAction::MoveLeft).0, Ok(()));
+        assert_eq!(
+            sdk.read_mouse_input(DEFAULT_SESSION)
+                .0
+                .map(|(analog, _)| analog),
+            Err(WootingAnalogResult::UnInitialized)
+        );
+        assert_eq!(
+            sdk.initialise_with_plugins(vec![FixedPlugin::boxed("Fixed", 1, 0.5)])
+                .0,
+            Ok(1)
+        );
+
+        //The emulation reads through a session of its own
+        let session = sdk.create_session().0.unwrap();
+        let (analog, config) = sdk.read_mouse_input(session).0.unwrap();
+        assert_eq!(analog.get(&4), Some(&0.5));
+        assert_eq!(config.bindings.get(&4), Some(&MouseAction::MoveLeft));
+
+        //Bindings are kept by HID code
+        sdk.keycode_mode = KeycodeType::ScanCode1;
+        assert_eq!(
+            sdk.bind_mouse_action(0x1f, MouseAction::LeftButton).0,
+            Ok(())
+        );
+        assert_eq!(sdk.unbind_mouse_action(0x1e).0, Ok(()));
+        assert_eq!(
+            sdk.unbind_mouse_action(0x1e).0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        //The emulation keeps reading the config it was given until it reads again
+        assert_eq!(config.bindings.len(), 1);
+        let (_, config) = sdk.read_mouse_input(session).0.unwrap();
+        assert_eq!(
+            config.bindings.iter().collect::<Vec<_>>(),
+            vec![(&0x16, &MouseAction::LeftButton)]
+        );
+
+        let curve = MouseCurve {
+            deadzone: 0.2,
+            exponent: 1.0,
+            max_speed: 100.0,
+        };
+        assert_eq!(sdk.set_mouse_pointer_curve(curve).0, Ok(()));
+        assert_eq!(
+            sdk.set_mouse_scroll_curve(MouseCurve {
+                exponent: 0.0,
+                ..curve
+            })
+            .0,
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        let (_, config) = sdk.read_mouse_input(session).0.unwrap();
+        assert_eq!(config.pointer, curve);
+        assert_eq!(config.scroll, MouseConfig::default().scroll);
+        assert_eq!(sdk.destroy_session(session).0, Ok(()));
+        assert_eq!(
+            sdk.read_mouse_input(session).0.map(|(analog, _)| analog),
+            Err(WootingAnalogResult::InvalidArgument)
+        );
+        sdk.unload();
     }
 }
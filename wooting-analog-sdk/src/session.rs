@@ -0,0 +1,128 @@
+use std::collections::{HashMap, HashSet};
+use std::os::raw::{c_float, c_ushort};
+use wooting_analog_common::DeviceID;
+
+/// Keeps track of which keys a single reader of `read_full_buffer` has been given, so that every reader gets told about
+/// released keys, regardless of how many other readers there are or which devices they read from.
+#[derive(Debug, Default)]
+pub struct ReadSession {
+    /// The HID codes which were pressed on the last read, for each device ID read from (0 being all devices)
+    pressed: HashMap<DeviceID, HashSet<c_ushort>>,
+}
+
+impl ReadSession {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Takes the `analog` data (HID code -> analog value) of a read from `device_id`, which should hold every pressed key, and
+    /// adds any keys which were pressed on the previous read from the same `device_id`, but no longer are, with a value of 0.0.
+    /// It's then cut down to `max_length` keys, giving the pressed keys (furthest first) ahead of the releases. Releases which
+    /// don't fit are given on a later read, while pressed keys which don't fit aren't counted as released
+    pub fn track_releases(
+        &mut self,
+        device_id: DeviceID,
+        analog: &mut HashMap<c_ushort, c_float>,
+        max_length: usize,
+    ) {
+        analog.retain(|_, analog| *analog > 0.0);
+        let pressed = self.pressed.entry(device_id).or_default();
+        let mut released: Vec<c_ushort> = pressed
+            .drain()
+            .filter(|code| !analog.contains_key(code))
+            .collect();
+        pressed.extend(analog.keys().copied());
+
+        keep_furthest(analog, max_length);
+        released.sort_unstable();
+        let room = max_length - analog.len();
+        for &code in released.iter().take(room) {
+            analog.insert(code, 0.0);
+        }
+        pressed.extend(released.iter().skip(room));
+    }
+}
+
+/// Cuts `analog` (HID code -> analog value) down to the `max_length` keys which are pressed the furthest
+pub fn keep_furthest(analog: &mut HashMap<c_ushort, c_float>, max_length: usize) {
+    if analog.len() > max_length {
+        let mut keys: Vec<(c_ushort, c_float)> = analog.drain().collect();
+        keys.sort_by(|a, b| b.1.total_cmp(&a.1));
+        keys.truncate(max_length);
+        analog.extend(keys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(keys: &[(c_ushort, c_float)]) -> HashMap<c_ushort, c_float> {
+        keys.iter().cloned().collect()
+    }
+
+    #[test]
+    fn release_reported_once() {
+        let mut session = ReadSession::new();
+
+        let mut analog = data(&[(4, 0.5), (5, 0.25)]);
+        session.track_releases(0, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.5), (5, 0.25)]));
+
+        let mut analog = data(&[(4, 0.6)]);
+        session.track_releases(0, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.6), (5, 0.0)]));
+
+        let mut analog = data(&[(4, 0.6)]);
+        session.track_releases(0, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.6)]));
+    }
+
+    #[test]
+    fn sessions_and_devices_are_independent() {
+        let mut first = ReadSession::new();
+        let mut second = ReadSession::new();
+
+        first.track_releases(0, &mut data(&[(4, 0.5)]), 16);
+        first.track_releases(1, &mut data(&[(4, 0.5)]), 16);
+        second.track_releases(0, &mut data(&[(4, 0.5)]), 16);
+
+        //Reading from one device shouldn't consume the release of another
+        let mut analog = data(&[]);
+        first.track_releases(1, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.0)]));
+        let mut analog = data(&[]);
+        first.track_releases(0, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.0)]));
+
+        //Nor should one session consume the release of another
+        let mut analog = data(&[]);
+        second.track_releases(0, &mut analog, 16);
+        assert_eq!(analog, data(&[(4, 0.0)]));
+    }
+
+    #[test]
+    fn cut_short_after_releases() {
+        let mut session = ReadSession::new();
+
+        let mut analog = data(&[(4, 0.5), (5, 0.25), (6, 0.75)]);
+        session.track_releases(0, &mut analog, 2);
+        assert_eq!(analog, data(&[(4, 0.5), (6, 0.75)]));
+
+        //5 was cut off but is still held, so it isn't released, while pressed keys go ahead of the release of 6
+        let mut analog = data(&[(4, 0.5), (5, 0.25)]);
+        session.track_releases(0, &mut analog, 2);
+        assert_eq!(analog, data(&[(4, 0.5), (5, 0.25)]));
+
+        //The release of 6 which didn't fit is given once there's room
+        let mut analog = data(&[(4, 0.5)]);
+        session.track_releases(0, &mut analog, 2);
+        assert_eq!(analog, data(&[(4, 0.5), (5, 0.0)]));
+        let mut analog = data(&[(4, 0.5)]);
+        session.track_releases(0, &mut analog, 2);
+        assert_eq!(analog, data(&[(4, 0.5), (6, 0.0)]));
+        let mut analog = data(&[(4, 0.5)]);
+        session.track_releases(0, &mut analog, 2);
+        assert_eq!(analog, data(&[(4, 0.5)]));
+    }
+}
@@ -0,0 +1,506 @@
+//! Runs the calls into each plugin on a thread of its own, so a plugin which hangs or panics can't take the app down with it.
+//! Callers only wait for the time budget of the call, and a plugin which keeps overrunning it or panicking is quarantined: it's
+//! no longer called and the devices it had are reported as disconnected.
+use crate::events::PluginEventCallback;
+use libloading::Library;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use wooting_analog_common::*;
+use wooting_analog_plugin_dev::*;
+
+/// The default time budget of a call into a plugin
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The time budget of `Plugin::initialise`, which has to look for the plugin's devices
+pub const INITIALISE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long unloading the SDK waits for all of the plugins to unload
+pub const UNLOAD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The number of calls in a row which can overrun or panic before the plugin is quarantined
+pub const QUARANTINE_AFTER: u32 = 3;
+
+type Job = Box<dyn FnOnce(&mut dyn Plugin) + Send>;
+
+/// The plugin along with the library its code is in, which are only dropped once the plugin is no longer running
+struct OwnedPlugin {
+    plugin: Box<dyn Plugin>,
+    _library: Option<Library>,
+}
+
+//The plugin is only ever used by its worker thread
+unsafe impl Send for OwnedPlugin {}
+
+#[derive(Default)]
+struct Health {
+    faults_in_a_row: u32,
+    quarantined: bool,
+}
+
+struct Shared {
+    name: Mutex<String>,
+    health: Mutex<Health>,
+    //Whether the last call which overran its budget has finished, so later calls don't queue up behind it while it hasn't
+    overrun: Mutex<Option<Arc<AtomicBool>>>,
+    //The devices the plugin has reported, which are disconnected when it's quarantined
+    devices: Mutex<Vec<DeviceInfo>>,
+    //Counts the changes to `devices`, so the SDK knows when to work out which plugin owns each device again
+    devices_changed: AtomicU32,
+    callback: Mutex<Option<PluginEventCallback>>,
+}
+
+impl Shared {
+    fn on_event(&self, event: DeviceEventType, device: &DeviceInfo) {
+        //Events from a quarantined plugin would contradict the disconnects which were reported for it
+        if self.health.lock().unwrap().quarantined {
+            return;
+        }
+        {
+            let mut devices = self.devices.lock().unwrap();
+            devices.retain(|known| known.device_id != device.device_id);
+            if event == DeviceEventType::Connected {
+                devices.push(device.clone());
+            }
+            self.devices_changed.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+            callback(event, device);
+        }
+    }
+}
+
+/// A plugin running on a thread of its own, see the module docs
+pub struct PluginWorker {
+    jobs: Mutex<Option<Sender<Job>>>,
+    exited: Mutex<Receiver<()>>,
+    shared: Arc<Shared>,
+}
+
+impl PluginWorker {
+    /// Starts the thread which `plugin` is run on. `library` is the library the plugin was loaded from (if it's separate from
+    /// the plugin), which is kept loaded for as long as the plugin is
+    pub fn spawn(plugin: Box<dyn Plugin>, library: Option<Library>) -> Self {
+        let (jobs, job_receiver) = channel::<Job>();
+        let (exit_sender, exited) = channel();
+        let mut owned = OwnedPlugin {
+            plugin,
+            _library: library,
+        };
+        thread::spawn(move || {
+            while let Ok(job) = job_receiver.recv() {
+                job(owned.plugin.as_mut());
+            }
+            drop(owned);
+            let _ = exit_sender.send(());
+        });
+
+        PluginWorker {
+            jobs: Mutex::new(Some(jobs)),
+            exited: Mutex::new(exited),
+            shared: Arc::new(Shared {
+                name: Mutex::new(String::new()),
+                health: Default::default(),
+                overrun: Mutex::new(None),
+                devices: Default::default(),
+                devices_changed: AtomicU32::new(0),
+                callback: Mutex::new(None),
+            }),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.shared.name.lock().unwrap().clone()
+    }
+
+    /// Fetches the name of the plugin, which is kept for the status & logs
+    pub fn load_name(&self, timeout: Duration) -> SDKResult<String> {
+        let result = self.call(timeout, |plugin| plugin.name().0.map(str::to_owned).into());
+        if let Ok(name) = &result.0 {
+            *self.shared.name.lock().unwrap() = name.clone();
+        }
+        result
+    }
+
+    pub fn status(&self) -> PluginStatus {
+        let health = self.shared.health.lock().unwrap();
+        if health.quarantined {
+            PluginStatus::Quarantined
+        } else if health.faults_in_a_row > 0 {
+            PluginStatus::Degraded
+        } else {
+            PluginStatus::Healthy
+        }
+    }
+
+    pub fn is_quarantined(&self) -> bool {
+        self.shared.health.lock().unwrap().quarantined
+    }
+
+    /// Initialises the plugin, with device events going to `callback` for as long as the plugin isn't quarantined
+    pub fn initialise(&self, callback: PluginEventCallback, timeout: Duration) -> SDKResult<u32> {
+        *self.shared.callback.lock().unwrap() = Some(callback);
+        let shared = Arc::clone(&self.shared);
+        self.call(timeout, move |plugin| {
+            plugin.initialise(Box::new(move |event, device| {
+                shared.on_event(event, device)
+            }))
+        })
+    }
+
+    /// Gives the devices of the plugin, which are remembered so they can be disconnected if it's quarantined
+    pub fn device_info(&self, timeout: Duration) -> SDKResult<Vec<DeviceInfo>> {
+        let result = self.call(timeout, |plugin| {
+            if plugin.is_initialised() {
+                plugin.device_info()
+            } else {
+                Ok(vec![]).into()
+            }
+        });
+        if let Ok(devices) = &result.0 {
+            let mut known = self.shared.devices.lock().unwrap();
+            if *known != *devices {
+                *known = devices.clone();
+                self.shared.devices_changed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        result
+    }
+
+    /// Gives the devices the plugin last reported, either through `device_info` or device events
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.shared.devices.lock().unwrap().clone()
+    }
+
+    /// Gives a count which changes whenever the devices given by `devices` do
+    pub fn devices_changed(&self) -> u32 {
+        self.shared.devices_changed.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f` on the plugin's thread, waiting at most `timeout` for it to finish
+    ///
+    /// # Expected Returns
+    /// * `NoDevices`: The plugin is quarantined, so it has no devices anymore
+    /// * `Timeout`: `f` didn't finish in time, or an earlier call still hasn't
+    /// * `Failure`: `f` panicked
+    pub fn call<T, F>(&self, timeout: Duration, f: F) -> SDKResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn Plugin) -> SDKResult<T> + Send + 'static,
+    {
+        if self.is_quarantined() {
+            return Err(WootingAnalogResult::NoDevices).into();
+        }
+        let still_running = self
+            .shared
+            .overrun
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |finished| !finished.load(Ordering::Acquire));
+        if still_running {
+            self.fault("is still running a call which overran");
+            return Err(WootingAnalogResult::Timeout).into();
+        }
+
+        let (sender, receiver) = sync_channel(1);
+        let finished = Arc::new(AtomicBool::new(false));
+        let job_finished = Arc::clone(&finished);
+        let job: Job = Box::new(move |plugin| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(plugin)));
+            job_finished.store(true, Ordering::Release);
+            //The caller may have given up on the result
+            let _ = sender.send(result);
+        });
+        let queued = match self.jobs.lock().unwrap().as_ref() {
+            Some(jobs) => jobs.send(job).is_ok(),
+            None => false,
+        };
+        if !queued {
+            return Err(WootingAnalogResult::UnInitialized).into();
+        }
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(result)) => {
+                self.shared.health.lock().unwrap().faults_in_a_row = 0;
+                result
+            }
+            Ok(Err(_)) => {
+                self.fault("panicked");
+                Err(WootingAnalogResult::Failure).into()
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                *self.shared.overrun.lock().unwrap() = Some(finished);
+                self.fault(&format!("didn't respond within {:?}", timeout));
+                Err(WootingAnalogResult::Timeout).into()
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(WootingAnalogResult::Failure).into(),
+        }
+    }
+
+    fn fault(&self, reason: &str) {
+        let quarantine = {
+            let mut health = self.shared.health.lock().unwrap();
+            health.faults_in_a_row += 1;
+            warn!(
+                "Plugin {:?} {} ({} in a row)",
+                self.name(),
+                reason,
+                health.faults_in_a_row
+            );
+            if health.faults_in_a_row >= QUARANTINE_AFTER && !health.quarantined {
+                health.quarantined = true;
+                true
+            } else {
+                false
+            }
+        };
+
+        if quarantine {
+            error!(
+                "Quarantining plugin {:?}, it will no longer be called",
+                self.name()
+            );
+            let devices: Vec<DeviceInfo> = self.shared.devices.lock().unwrap().drain(..).collect();
+            self.shared.devices_changed.fetch_add(1, Ordering::Relaxed);
+            if let Some(callback) = self.shared.callback.lock().unwrap().as_ref() {
+                for device in devices.iter() {
+                    callback(DeviceEventType::Disconnected, device);
+                }
+            }
+        }
+    }
+
+    /// Unloads the plugin and stops its thread once it's done with the calls which came before. Use `wait_for_exit` to wait for
+    /// it to finish
+    pub fn shutdown(&self) {
+        if let Some(jobs) = self.jobs.lock().unwrap().take() {
+            let unload: Job = Box::new(|plugin| {
+                if panic::catch_unwind(AssertUnwindSafe(|| plugin.unload())).is_err() {
+                    error!("Plugin panicked while unloading");
+                }
+            });
+            let _ = jobs.send(unload);
+        }
+        *self.shared.callback.lock().unwrap() = None;
+    }
+
+    /// Waits until `deadline` for the plugin to have been unloaded and dropped, giving if it has. If it hasn't the plugin is left
+    /// running, along with the library it's from
+    pub fn wait_for_exit(&self, deadline: Instant) -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.exited.lock().unwrap().recv_timeout(timeout) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => true,
+            Err(RecvTimeoutError::Timeout) => false,
+        }
+    }
+}
+
+impl Drop for PluginWorker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::os::raw::{c_float, c_ushort};
+    use std::sync::Condvar;
+
+    //Generous, as it's only reached by calls which are meant to finish in time if the machine is very slow
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    //Given to calls which are meant to overrun, these are stuck until the test lets them through so they always do
+    const OVERRUN_TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Holds up the calls into a plugin while it's closed
+    #[derive(Default)]
+    struct Gate {
+        //<closed, number of calls which reached the gate>
+        state: Mutex<(bool, u32)>,
+        changed: Condvar,
+    }
+
+    impl Gate {
+        fn set_closed(&self, closed: bool) {
+            self.state.lock().unwrap().0 = closed;
+            self.changed.notify_all();
+        }
+
+        fn pass(&self) {
+            let mut state = self.state.lock().unwrap();
+            state.1 += 1;
+            while state.0 {
+                state = self.changed.wait(state).unwrap();
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.state.lock().unwrap().1
+        }
+    }
+
+    /// A plugin which can be made to hang in every read & when it's unloaded
+    struct StuckPlugin {
+        gate: Arc<Gate>,
+        callback: Option<PluginEventCallback>,
+    }
+
+    impl Plugin for StuckPlugin {
+        fn name(&mut self) -> SDKResult<&'static str> {
+            Ok("Stuck Plugin").into()
+        }
+
+        fn initialise(&mut self, callback: PluginEventCallback) -> SDKResult<u32> {
+            callback(DeviceEventType::Connected, &device());
+            self.callback = Some(callback);
+            Ok(1).into()
+        }
+
+        fn is_initialised(&mut self) -> bool {
+            self.callback.is_some()
+        }
+
+        fn device_info(&mut self) -> SDKResult<Vec<DeviceInfo>> {
+            Ok(vec![device()]).into()
+        }
+
+        fn read_analog(&mut self, code: u16, _device: DeviceID) -> SDKResult<f32> {
+            if code == 0 {
+                panic!("Stuck plugin can't read code 0");
+            }
+            self.gate.pass();
+            Ok(0.5).into()
+        }
+
+        fn read_full_buffer(
+            &mut self,
+            _max_length: usize,
+            _device: DeviceID,
+        ) -> SDKResult<HashMap<c_ushort, c_float>> {
+            Ok(HashMap::new()).into()
+        }
+
+        fn unload(&mut self) {
+            self.gate.pass();
+        }
+    }
+
+    fn device() -> DeviceInfo {
+        DeviceInfo::new_with_id(
+            0x03eb,
+            0xff02,
+            "Wooting".to_owned(),
+            "Stuck Device".to_owned(),
+            1,
+            DeviceType::Keyboard,
+        )
+    }
+
+    fn stuck_worker(events: Arc<Mutex<Vec<DeviceEventType>>>) -> (PluginWorker, Arc<Gate>) {
+        let gate: Arc<Gate> = Default::default();
+        let worker = PluginWorker::spawn(
+            Box::new(StuckPlugin {
+                gate: Arc::clone(&gate),
+                callback: None,
+            }),
+            None,
+        );
+        assert_eq!(worker.load_name(TIMEOUT).0, Ok("Stuck Plugin".to_owned()));
+        let callback: PluginEventCallback =
+            Box::new(move |event, _device| events.lock().unwrap().push(event));
+        assert_eq!(worker.initialise(callback, TIMEOUT).0, Ok(1));
+        (worker, gate)
+    }
+
+    /// Waits for the call which overran to return, once it's been let through the gate
+    fn wait_for_overrun(worker: &PluginWorker) {
+        while worker
+            .shared
+            .overrun
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |finished| !finished.load(Ordering::Acquire))
+        {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn calls_which_overrun_quarantine_the_plugin() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let (worker, gate) = stuck_worker(Arc::clone(&events));
+        let read = |worker: &PluginWorker, timeout: Duration| {
+            worker.call(timeout, |plugin| plugin.read_analog(4, 0)).0
+        };
+
+        assert_eq!(read(&worker, TIMEOUT), Ok(0.5));
+        assert_eq!(worker.status(), PluginStatus::Healthy);
+
+        gate.set_closed(true);
+        assert_eq!(
+            read(&worker, OVERRUN_TIMEOUT),
+            Err(WootingAnalogResult::Timeout)
+        );
+        assert_eq!(worker.status(), PluginStatus::Degraded);
+        assert_eq!(gate.calls(), 2);
+
+        //Calls fail without reaching the plugin while it's still stuck in the call which overran
+        assert_eq!(read(&worker, TIMEOUT), Err(WootingAnalogResult::Timeout));
+        assert_eq!(worker.status(), PluginStatus::Degraded);
+        assert_eq!(gate.calls(), 2);
+
+        //A call which succeeds in time resets the count
+        gate.set_closed(false);
+        wait_for_overrun(&worker);
+        assert_eq!(read(&worker, TIMEOUT), Ok(0.5));
+        assert_eq!(worker.status(), PluginStatus::Healthy);
+
+        gate.set_closed(true);
+        for _ in 0..QUARANTINE_AFTER {
+            assert_eq!(
+                read(&worker, OVERRUN_TIMEOUT),
+                Err(WootingAnalogResult::Timeout)
+            );
+        }
+        assert_eq!(worker.status(), PluginStatus::Quarantined);
+        assert_eq!(read(&worker, TIMEOUT), Err(WootingAnalogResult::NoDevices));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![DeviceEventType::Connected, DeviceEventType::Disconnected]
+        );
+
+        //Shutdown is bounded while the plugin is stuck unloading
+        worker.shutdown();
+        assert!(!worker.wait_for_exit(Instant::now() + OVERRUN_TIMEOUT));
+        gate.set_closed(false);
+        assert!(worker.wait_for_exit(Instant::now() + TIMEOUT));
+    }
+
+    #[test]
+    fn panics_quarantine_the_plugin() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let (worker, _gate) = stuck_worker(Arc::clone(&events));
+        assert_eq!(worker.device_info(TIMEOUT).0.map(|d| d.len()), Ok(1));
+
+        for _ in 0..QUARANTINE_AFTER {
+            assert_eq!(
+                worker.call(TIMEOUT, |plugin| plugin.read_analog(0, 0)).0,
+                Err(WootingAnalogResult::Failure)
+            );
+        }
+        assert_eq!(worker.status(), PluginStatus::Quarantined);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![DeviceEventType::Connected, DeviceEventType::Disconnected]
+        );
+
+        worker.shutdown();
+        assert!(worker.wait_for_exit(Instant::now() + TIMEOUT));
+    }
+}
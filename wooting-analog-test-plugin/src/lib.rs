@@ -18,13 +18,15 @@ struct WootingAnalogTestPlugin {
     device_connected: Arc<Mutex<bool>>,
     device_event_cb: Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>>,
     device: Arc<Mutex<Option<DeviceInfo>>>,
-    buffer: Arc<Mutex<HashMap<u16, f32>>>,
+    snapshots: Arc<SnapshotStore>,
     device_id: Arc<Mutex<DeviceID>>,
-    pressed_keys: Vec<u16>,
     thread_running: Arc<AtomicBool>,
     worker_thread: Option<JoinHandle<()>>,
 }
 
+/// The physical layout of the test device, which is what `wooting-analog-virtual-control` draws
+pub const DEVICE_LAYOUT: KeyboardLayout = KeyboardLayout::ANSI;
+
 pub struct SharedState {
     pub vendor_id: u16,
     /// Device Product ID `pid`
@@ -34,6 +36,8 @@ pub struct SharedState {
     pub manufacturer_name: [u8; 20],
     /// Device name
     pub device_name: [u8; 20],
+    /// Unique device ID
+    pub device_id: u64,
 
     pub device_type: DeviceType,
 
@@ -52,15 +56,16 @@ impl WootingAnalogTestPlugin {
         }
 
         let device: Arc<Mutex<Option<DeviceInfo>>> = Arc::new(Mutex::new(None));
-        let buffer: Arc<Mutex<HashMap<u16, f32>>> = Arc::new(Mutex::new(HashMap::new()));
+        let snapshots: Arc<SnapshotStore> = Arc::new(SnapshotStore::new());
         let device_id: Arc<Mutex<DeviceID>> = Arc::new(Mutex::new(1));
         let device_event_cb: Arc<Mutex<Option<Box<dyn Fn(DeviceEventType, &DeviceInfo) + Send>>>> =
             Arc::new(Mutex::new(None));
         let device_connected: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
         let thread_running: Arc<AtomicBool> = Arc::new(AtomicBool::new(true));
 
-        let t_buffer = Arc::clone(&buffer);
+        let t_snapshots = Arc::clone(&snapshots);
         let t_device = Arc::clone(&device);
+        let t_device_id = Arc::clone(&device_id);
         let t_device_event_cb = Arc::clone(&device_event_cb);
         let t_device_connected = Arc::clone(&device_connected);
         let t_thread_running = Arc::clone(&thread_running);
@@ -105,6 +110,7 @@ impl WootingAnalogTestPlugin {
                 };
                 shared_state.vendor_id = 0x03eb;
                 shared_state.product_id = 0xFFFF;
+                shared_state.device_id = *t_device_id.lock().unwrap();
                 shared_state.device_type = DeviceType::Keyboard;
                 shared_state.device_connected = false;
                 shared_state.dirty_device_info = false;
@@ -115,6 +121,7 @@ impl WootingAnalogTestPlugin {
             }
 
             let mut vals = vec![0; 0xFF];
+            let mut device_id;
             loop {
                 if !t_thread_running.load(Ordering::SeqCst) {
                     break;
@@ -131,7 +138,7 @@ impl WootingAnalogTestPlugin {
 
                     if state.dirty_device_info || t_device.lock().unwrap().is_none() {
                         state.dirty_device_info = false;
-                        let dev = DeviceInfo::new_with_id(
+                        let mut dev = DeviceInfo::new_with_id(
                             state.vendor_id,
                             state.product_id,
                             from_ut8f_to_null(
@@ -141,13 +148,22 @@ impl WootingAnalogTestPlugin {
                             .to_string(),
                             from_ut8f_to_null(&state.device_name[..], state.device_name.len())
                                 .to_string(),
-                            1,
+                            state.device_id,
                             state.device_type.clone(),
                         );
+                        dev.details.analog_key_count = Some(DEVICE_LAYOUT.keys().len() as u32);
+                        //The virtual device's analog values are single bytes
+                        dev.details.value_resolution = Some(256);
+                        *t_device_id.lock().unwrap() = state.device_id;
                         t_device.lock().unwrap().replace(dev);
                     }
                     if *t_device_connected.lock().unwrap() != state.device_connected {
                         *t_device_connected.lock().unwrap() = state.device_connected;
+                        if state.device_connected {
+                            t_snapshots.add_device(state.device_id);
+                        } else {
+                            t_snapshots.remove_device(state.device_id);
+                        }
                         if let Some(device) = t_device.lock().unwrap().as_ref() {
                             t_device_event_cb.lock().unwrap().as_ref().and_then(|cb| {
                                 cb(
@@ -171,6 +187,7 @@ impl WootingAnalogTestPlugin {
                     }
 
                     vals.copy_from_slice(&state.analog_values[..]);
+                    device_id = state.device_id;
                 }
 
                 let analog: HashMap<u16, f32> = vals
@@ -184,12 +201,7 @@ impl WootingAnalogTestPlugin {
                         }
                     })
                     .collect();
-                {
-                    let mut m = t_buffer.lock().unwrap();
-                    m.clear();
-                    m.extend(analog);
-                }
-                //t_buffer.lock().unwrap().
+                t_snapshots.publish(device_id, analog);
                 thread::sleep(Duration::from_millis(10));
             }
         });
@@ -198,9 +210,8 @@ impl WootingAnalogTestPlugin {
             device_connected,
             device_event_cb,
             device,
-            buffer,
+            snapshots,
             device_id,
-            pressed_keys: vec![],
             thread_running: thread_running,
             worker_thread: Some(worker_thread),
         }
@@ -266,22 +277,21 @@ impl Plugin for WootingAnalogTestPlugin {
     }
 
     fn read_analog(&mut self, code: u16, device: u64) -> SDKResult<f32> {
-        if !*self.device_connected.lock().unwrap() {
-            return Err(WootingAnalogResult::NoDevices).into();
-        }
-
-        if device == 0 || device == *self.device_id.lock().unwrap() {
-            Ok(self
-                .buffer
-                .lock()
-                .unwrap()
-                .get(&code)
-                .cloned()
-                .or(Some(0.0))
-                .unwrap())
-            .into()
+        if device == 0 {
+            let mut analog: f32 = -1.0;
+            self.snapshots.for_each(|snapshot| {
+                analog = analog.max(snapshot.read_analog(code));
+            });
+            if analog < 0.0 {
+                Err(WootingAnalogResult::NoDevices).into()
+            } else {
+                Ok(analog).into()
+            }
         } else {
-            Err(WootingAnalogResult::NoDevices).into()
+            match self.snapshots.device_snapshot(device) {
+                Some(snapshot) => Ok(snapshot.read_analog(code)).into(),
+                None => Err(WootingAnalogResult::NoDevices).into(),
+            }
         }
     }
 
@@ -295,25 +305,25 @@ impl Plugin for WootingAnalogTestPlugin {
         }
 
         if device == 0 || device == *self.device_id.lock().unwrap() {
-            let mut buffer = self.buffer.lock().unwrap().clone();
-            //Collect the new pressed keys
-            let new_pressed_keys: Vec<u16> = buffer.keys().map(|x| *x).collect();
-
-            //Put the old pressed keys into the buffer
-            for key in self.pressed_keys.drain(..) {
-                if !buffer.contains_key(&key) {
-                    buffer.insert(key, 0.0);
-                }
+            match self
+                .snapshots
+                .device_snapshot(*self.device_id.lock().unwrap())
+            {
+                Some(snapshot) => Ok(snapshot.analog().clone()).into(),
+                None => Err(WootingAnalogResult::NoDevices).into(),
             }
-
-            //Store the newPressedKeys for the next call
-            self.pressed_keys = new_pressed_keys;
-
-            Ok(buffer).into()
         } else {
             Err(WootingAnalogResult::NoDevices).into()
         }
     }
+
+    fn snapshot_store(&mut self) -> Option<Arc<SnapshotStore>> {
+        Some(Arc::clone(&self.snapshots))
+    }
+
+    fn device_layout(&mut self, _device: DeviceID) -> SDKResult<KeyboardLayout> {
+        Ok(DEVICE_LAYOUT).into()
+    }
 }
 
 declare_plugin!(WootingAnalogTestPlugin, WootingAnalogTestPlugin::new);
@@ -1,143 +1,11 @@
 extern crate env_logger;
 extern crate log;
-#[macro_use]
-extern crate lazy_static;
 use log::{error, info};
 use shared_memory::*;
 
-use wooting_analog_test_plugin::SharedState;
-
-lazy_static! {
-    static ref KEYBOARD_LAYOUT: Vec<Vec<(&'static str, u16, u16, u16)>> = vec![
-        vec![
-            ("Esc", 41, 1, 1),
-            ("", 0, 1, 1),
-            ("F1", 58, 1, 1),
-            ("F2", 59, 1, 1),
-            ("F3", 60, 1, 1),
-            ("F4", 61, 1, 1),
-            ("F5", 62, 1, 1),
-            ("F6", 63, 1, 1),
-            ("F7", 64, 1, 1),
-            ("F8", 65, 1, 1),
-            ("F9", 66, 1, 1),
-            ("F10", 67, 1, 1),
-            ("F11", 68, 1, 1),
-            ("F12", 69, 1, 1),
-            ("Prnt", 70, 1, 1),
-            ("Pse", 72, 1, 1),
-            ("Scrl", 71, 1, 1),
-            ("A1", 0, 1, 1),
-            ("A2", 0, 1, 1),
-            ("A3", 0, 1, 1),
-            ("Mode", 0, 1, 1),
-        ],
-        vec![
-            ("`", 53, 1, 1),
-            ("1", 30, 1, 1),
-            ("2", 31, 1, 1),
-            ("3", 32, 1, 1),
-            ("4", 33, 1, 1),
-            ("5", 34, 1, 1),
-            ("6", 35, 1, 1),
-            ("7", 36, 1, 1),
-            ("8", 37, 1, 1),
-            ("9", 38, 1, 1),
-            ("0", 39, 1, 1),
-            ("-", 45, 1, 1),
-            ("=", 46, 1, 1),
-            ("<-", 42, 1, 1),
-            ("Ins", 73, 1, 1),
-            ("Hme", 74, 1, 1),
-            ("PgUp", 75, 1, 1),
-            ("NumLck", 83, 1, 1),
-            ("/", 84, 1, 1),
-            ("*", 85, 1, 1),
-            ("-", 86, 1, 1),
-        ],
-        vec![
-            ("Tab", 43, 1, 1),
-            ("Q", 20, 1, 1),
-            ("W", 26, 1, 1),
-            ("E", 8, 1, 1),
-            ("R", 21, 1, 1),
-            ("T", 23, 1, 1),
-            ("Y", 28, 1, 1),
-            ("U", 24, 1, 1),
-            ("I", 12, 1, 1),
-            ("O", 18, 1, 1),
-            ("P", 19, 1, 1),
-            ("[", 47, 1, 1),
-            ("]", 48, 1, 1),
-            ("#", 49, 1, 1),
-            ("Del", 76, 1, 1),
-            ("End", 77, 1, 1),
-            ("PgDn", 78, 1, 1),
-            ("7", 95, 1, 1),
-            ("8", 96, 1, 1),
-            ("9", 97, 1, 1),
-            ("+", 87, 1, 2),
-        ],
-        vec![
-            ("Caps", 57, 1, 1),
-            ("A", 4, 1, 1),
-            ("S", 22, 1, 1),
-            ("D", 7, 1, 1),
-            ("F", 9, 1, 1),
-            ("G", 10, 1, 1),
-            ("H", 11, 1, 1),
-            ("J", 13, 1, 1),
-            ("K", 14, 1, 1),
-            ("L", 15, 1, 1),
-            (";", 51, 1, 1),
-            ("'", 52, 1, 1),
-            ("Enter", 40, 2, 1),
-            ("", 0, 1, 1),
-            ("", 0, 1, 1),
-            ("", 0, 1, 1),
-            ("4", 92, 1, 1),
-            ("5", 93, 1, 1),
-            ("6", 94, 1, 1),
-        ],
-        vec![
-            ("Shift", 225, 1, 1),
-            ("Z", 29, 1, 1),
-            ("X", 27, 1, 1),
-            ("C", 6, 1, 1),
-            ("V", 25, 1, 1),
-            ("B", 5, 1, 1),
-            ("N", 17, 1, 1),
-            ("M", 16, 1, 1),
-            (",", 54, 1, 1),
-            (".", 55, 1, 1),
-            ("/", 56, 1, 1),
-            ("Shift", 229, 3, 1),
-            ("", 0, 1, 1),
-            ("^", 82, 1, 1),
-            ("", 0, 1, 1),
-            ("1", 89, 1, 1),
-            ("2", 90, 1, 1),
-            ("3", 91, 1, 1),
-            ("Enter", 88, 1, 2),
-        ],
-        vec![
-            ("Ctrl", 224, 1, 1),
-            ("Win", 227, 1, 1),
-            ("Alt", 226, 1, 1),
-            ("Space", 44, 7, 1),
-            ("Alt", 230, 1, 1),
-            ("Win", 231, 1, 1),
-            ("Fn", 0, 1, 1),
-            ("Ctrl", 228, 1, 1),
-            ("<", 80, 1, 1),
-            ("v", 81, 1, 1),
-            (">", 79, 1, 1),
-            ("0", 98, 2, 1),
-            (".", 99, 1, 1),
-            ("", 0, 1, 1),
-        ],
-    ];
-}
+use wooting_analog_common::{key_name, KeyGeometry};
+use wooting_analog_test_plugin::{SharedState, DEVICE_LAYOUT};
+
 // use iced::{slider,HorizontalAlignment, Length, Column, Container, Element, Row, Sandbox, Settings, Slider, Text};
 
 use env_logger::Env;
@@ -146,14 +14,13 @@ use iced::{
     alignment, container, slider, window, Alignment, Checkbox, Color, Column, Container, Element,
     Length, Row, Sandbox, Settings, Slider, Text,
 };
-use std::borrow::Borrow;
 
 struct Key {
     slider_state: slider::State,
     keycode: u16,
     label: String,
-    width: u16,
-    _height: u16,
+    width: f32,
+    _height: f32,
     value: f32,
     xy: (usize, usize),
 }
@@ -162,6 +29,23 @@ const KEY_WIDTH: u16 = 60;
 const KEY_SPACING: u16 = 10;
 const WIDGET_PADDING: u16 = 5;
 
+/// Gives the pixel width of something `units` keys wide, which includes the spacing between the keys it covers
+fn units_to_pixels(units: f32) -> f32 {
+    (units * f32::from(KEY_WIDTH + KEY_SPACING) - f32::from(KEY_SPACING)).max(0.0)
+}
+
+/// Splits the keys of the layout into its rows, ordered from left to right
+fn layout_rows() -> Vec<Vec<KeyGeometry>> {
+    let mut rows: Vec<Vec<KeyGeometry>> = vec![];
+    for key in DEVICE_LAYOUT.keys() {
+        match rows.last_mut() {
+            Some(row) if row[0].y == key.y => row.push(key),
+            _ => rows.push(vec![key]),
+        }
+    }
+    rows
+}
+
 struct KeyStyle;
 // impl From<KeyStyle> for Box<dyn container::StyleSheet> {
 //     fn from(_: KeyStyle) -> Self {
@@ -189,8 +73,8 @@ impl Key {
     fn new(
         keycode: u16,
         label: String,
-        width: u16,
-        height: u16,
+        width: f32,
+        height: f32,
         value: f32,
         xy: (usize, usize),
     ) -> Self {
@@ -206,7 +90,7 @@ impl Key {
     }
 
     fn width(&self) -> Length {
-        Length::Units(KEY_WIDTH * self.width + (KEY_SPACING * (self.width - 1)))
+        Length::Units(units_to_pixels(self.width) as u16)
     }
 
     fn height(&self) -> Length {
@@ -312,19 +196,30 @@ impl Sandbox for AppState {
                 Ok(v) => v,
                 Err(_) => panic!("Failed to acquire read lock !"),
             };
-            for (y, items) in KEYBOARD_LAYOUT.iter().enumerate() {
+            for (y, items) in layout_rows().iter().enumerate() {
                 let mut row: Vec<Key> = vec![];
-                for (x, &(name, code, width, height)) in items.iter().enumerate() {
-                    // if width == 0 {
-                    //     continue;
-                    // }
+                let mut x_units = 0.0;
+                for key in items.iter() {
+                    //Fill the space up to the key (e.g. between the key blocks, or below a tall key) with an empty key
+                    if key.x > x_units {
+                        row.push(Key::new(
+                            0,
+                            String::new(),
+                            key.x - x_units,
+                            1.0,
+                            0.0,
+                            (row.len(), y),
+                        ));
+                    }
+                    x_units = key.x + key.width;
+
                     row.push(Key::new(
-                        code,
-                        name.to_string(),
-                        width,
-                        height,
-                        state.analog_values[code as usize].into(),
-                        (x, y),
+                        key.code,
+                        key_name(key.code).unwrap_or_default(),
+                        key.width,
+                        key.height,
+                        state.analog_values[key.code as usize].into(),
+                        (row.len(), y),
                     ))
                 }
                 keys.push(row);
@@ -403,19 +298,13 @@ fn main() -> Result<(), iced::Error> {
     {
         error!("Failed to init env_logger: {}", e)
     }
-    let kb: &Vec<Vec<(&'static str, u16, u16, u16)>> = KEYBOARD_LAYOUT.borrow();
-    let max_key_width = kb.iter().fold(0, |current: u32, item| {
-        current.max(
-            item.iter()
-                .fold(0, |width: u32, key: &(&'static str, u16, u16, u16)| {
-                    width + key.2 as u32
-                }),
-        )
-    });
-    let width: u32 = max_key_width * KEY_WIDTH as u32
-        + ((max_key_width - 1) * KEY_SPACING as u32)
-        + WIDGET_PADDING as u32 * 2;
-    let rows = kb.len() as u32;
+    let rows = layout_rows();
+    let max_key_width = rows
+        .iter()
+        .flatten()
+        .fold(0.0, |width: f32, key| width.max(key.x + key.width));
+    let width: u32 = units_to_pixels(max_key_width) as u32 + WIDGET_PADDING as u32 * 2;
+    let rows = rows.len() as u32;
     // Add 1 to the number of rows for the Height for the extra row of controls
     let height =
         (rows + 1) * (KEY_WIDTH as u32) + (rows * KEY_SPACING as u32) + WIDGET_PADDING as u32 * 2;
@@ -1,9 +1,10 @@
 use crate::SDK_ABI_VERSION;
 use libloading as libl;
 use std::ops::Deref;
-use std::os::raw::{c_float, c_int, c_uint, c_ushort};
+use std::os::raw::{c_char, c_float, c_int, c_uint, c_ushort, c_void};
 pub use wooting_analog_common::{
-    DeviceEventType, DeviceID, DeviceInfo_FFI, KeycodeType, WootingAnalogResult,
+    DeviceEventType, DeviceID, DeviceInfoEx_FFI, DeviceInfo_FFI, KeyGeometry, KeyKinematics,
+    KeycodeType, ListenerID, MouseCurve, SessionID, WootingAnalogResult,
 };
 
 macro_rules! dynamic_extern {
@@ -107,8 +108,10 @@ dynamic_extern! {
         ///
         /// # Notes
         /// * `VirtualKey` and `VirtualKeyTranslate` are only available on Windows
-        /// * With all modes except `VirtualKeyTranslate`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
+        /// * `XkbKeysym` is only available on Linux, with libxkbcommon installed
+        /// * With all modes except `VirtualKeyTranslate` and `XkbKeysym`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
         /// * With `VirtualKeyTranslate`, if you request Q, it will be the key that inputs Q on the current layout, not the key that is Q on the standard layout.
+        /// * With `XkbKeysym`, the codes are X keysyms (e.g. 0x71 for q) and point to the key that inputs it on the layout set with `wooting_analog_set_xkb_keymap`. Custom keys can't be read in this mode, as they overlap the keysyms.
         ///
         /// # Expected Returns
         /// * `Ok`: The Keycode mode was changed successfully
@@ -117,6 +120,46 @@ dynamic_extern! {
         /// * `UnInitialized`: The SDK is not initialised
         fn wooting_analog_set_keycode_mode(mode: KeycodeType) -> WootingAnalogResult;
 
+        /// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, compiled from the system's XKB data for the given
+        /// `layout` and `variant` (e.g. "fr" and "oss"). `variant` may be null, and empty names are filled in by libxkbcommon from the
+        /// `XKB_DEFAULT_*` environment variables or its own defaults, which is also the keymap used until this is called
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The keymap was compiled and will be used from now on
+        /// * `InvalidArgument`: No keymap could be compiled for the given names
+        /// * `NotAvailable`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+        /// * `UnInitialized`: The SDK is not initialised
+        fn wooting_analog_set_xkb_keymap(layout: *const c_char, variant: *const c_char) -> WootingAnalogResult;
+
+        /// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, loaded from the keymap file at `path` (in the
+        /// format written by e.g. `xkbcomp $DISPLAY keymap.xkb`)
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The keymap was loaded and will be used from now on
+        /// * `InvalidArgument`: The file couldn't be read or isn't a valid keymap
+        /// * `NotAvailable`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+        /// * `UnInitialized`: The SDK is not initialised
+        fn wooting_analog_set_xkb_keymap_file(path: *const c_char) -> WootingAnalogResult;
+
+        /// Gives the canonical name of the key with identifier `code` in the given keycode `mode`, e.g. "Q" for 0x10 in `ScanCode1`. The
+        /// names don't depend on the layout, and are the names of the `HIDCodes`/`ConsumerCodes` variants. This can be used without
+        /// initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// A null terminated string which remains valid for the lifetime of the SDK and must not be freed, or null if the `mode` isn't
+        /// a valid `KeycodeType` or the `code` isn't a known key in it
+        fn wooting_analog_key_name(code: c_ushort, mode: KeycodeType) -> *const c_char;
+
+        /// Gives the identifier in the given keycode `mode` of the key with the given `name`. Names are case insensitive, ignore spaces,
+        /// underscores and dashes, and common aliases (e.g. "Esc", "LShift" or "KP_Enter") are accepted alongside the names given by
+        /// `wooting_analog_key_name`. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `ret>=0`: The identifier of the key in the given `mode`
+        /// * `InvalidArgument`: The `mode` isn't a valid `KeycodeType` or `name` is null
+        /// * `NoMapping`: There's no key with the given name, or it has no identifier in the given `mode`
+        fn wooting_analog_key_from_name(name: *const c_char, mode: KeycodeType) -> c_int;
+
         /// Reads the Analog value of the key with identifier `code` from any connected device. The set of key identifiers that is used
         /// depends on the Keycode mode set using `wooting_analog_set_mode`.
         ///
@@ -160,7 +203,10 @@ dynamic_extern! {
         ///
         /// # Notes
         /// * You must copy the DeviceInfo struct or its data if you wish to use it after the callback has completed, as the memory will be freed straight after
-        /// * The execution of the callback is performed in a separate thread so it is fine to put time consuming code and further SDK calls inside your callback
+        /// * The callback is called from a single SDK thread, in the order the events happened, so it is fine to put further SDK calls inside your callback.
+        /// Time consuming code will hold up the events which come after
+        /// * If events need to be handled on a specific thread, use `wooting_analog_poll_device_event` instead
+        /// * Setting the callback replaces the one which was set before, use `wooting_analog_add_device_event_listener` to have several listeners
         ///
         /// # Expected Returns
         /// * `Ok`: The callback was set successfully
@@ -174,6 +220,52 @@ dynamic_extern! {
         /// * `UnInitialized`: The SDK is not initialised
         fn wooting_analog_clear_device_event_cb() -> WootingAnalogResult;
 
+        /// Adds a listener which is called when there is a DeviceEvent, alongside any other listeners and the callback set with `wooting_analog_set_device_event_cb`.
+        /// The listener gets given the type of event `DeviceEventType`, a pointer to the DeviceInfo struct that the event applies to and the `user_data`
+        /// pointer that was given here, which the SDK doesn't touch.
+        ///
+        /// # Notes
+        /// * You must copy the DeviceInfo struct or its data if you wish to use it after the listener has completed, as the memory will be freed straight after
+        /// * Listeners are called one after the other from a single SDK thread, in the order they were added
+        /// * Listeners can add or remove listeners (including themselves) from inside the callback
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `>=0` The ID of the listener, which is used to remove it with `wooting_analog_remove_device_event_listener`
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        fn wooting_analog_add_device_event_listener(cb: extern "C" fn(DeviceEventType, *mut DeviceInfo_FFI, *mut c_void), user_data: *mut c_void) -> c_int;
+
+        /// Removes the listener with the given `id` that was added with `wooting_analog_add_device_event_listener`
+        ///
+        /// # Notes
+        /// * Once this returns the listener won't be called again, so its `user_data` can be freed. If the listener is being called on the
+        /// SDK thread right now, this waits for it to return first
+        /// * When a listener is removed from inside a listener this doesn't wait, as it can't be running at the same time
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The listener was removed
+        /// * `InvalidArgument`: There is no listener with the given ID
+        /// * `UnInitialized`: The SDK is not initialised
+        fn wooting_analog_remove_device_event_listener(id: ListenerID) -> WootingAnalogResult;
+
+        /// Takes the oldest device event which hasn't been polled yet, writing the type of event into `event` and a pointer to the DeviceInfo
+        /// struct the event applies to into `device`. This is an alternative to `wooting_analog_set_device_event_cb` for applications which need to
+        /// handle device events on their own thread, e.g. by calling this once a frame until it returns 0.
+        ///
+        /// # Notes
+        /// * Events are given in the order they happened. The SDK keeps the last 64 events, older ones are dropped if they aren't polled in time
+        /// * Events are queued regardless of whether a callback has been set, and the callback doesn't affect what this returns
+        /// * The memory of the DeviceInfo struct will only be kept until the next call of this function, so if you wish to use any data from it, please copy it
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `1`: An event was written into `event` & `device`
+        /// * `0`: There are no events waiting
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        fn wooting_analog_poll_device_event(event: *mut DeviceEventType, device: *mut *mut DeviceInfo_FFI) -> c_int;
+
         /// Fills up the given `buffer`(that has length `len`) with pointers to the DeviceInfo structs for all connected devices (as many that can fit in the buffer)
         ///
         /// # Notes
@@ -185,6 +277,390 @@ dynamic_extern! {
         /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
         fn wooting_analog_get_connected_devices_info(buffer: *mut *mut DeviceInfo_FFI, len: c_uint) -> c_int;
 
+        /// Gives the physical layout of the device with id `device_id` as a `KeyboardLayout`, if `device_id` is 0 the first connected
+        /// device is used. The geometry of the layout's keys can be fetched with `wooting_analog_get_device_geometry`
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The `KeyboardLayout` of the device, which is `Unknown` if the plugin of the device doesn't know its layout
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+        fn wooting_analog_get_device_layout(device_id: DeviceID) -> c_int;
+
+        /// Fills up the given `buffer` (that has length `len`) with the position & size of every key of the device with id `device_id`
+        /// (as many that can fit in the buffer), ordered by row and then from left to right. If `device_id` is 0 the first connected device
+        /// is used. Positions & sizes are in key units, where 1.0 is the size of a standard alphanumeric key, measured from the top left of
+        /// the keyboard.
+        ///
+        /// # Notes
+        /// * The codes that are filled into the `buffer` are of the KeycodeType set with wooting_analog_set_mode, keys which have no code in it are left out
+        /// * If `buffer` is null, nothing is filled in and the number of keys is given, so that the buffer can be allocated first
+        /// * Nothing is filled in if the layout of the device isn't known
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The number of keys that have been filled into the buffer
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+        fn wooting_analog_get_device_geometry(device_id: DeviceID, buffer: *mut KeyGeometry, len: c_uint) -> c_int;
+
+        /// Allows `wooting_analog_send_feature_report` to send data to devices, which is refused by default. Only enable this if you know
+        /// what the data does, as vendor commands can change the settings of a device or even the firmware on it. This can be used without
+        /// initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: Sending feature reports is now allowed, or refused if `enabled` is false
+        fn wooting_analog_set_raw_device_access(enabled: bool) -> WootingAnalogResult;
+
+        /// Sends the feature report `data` (that has length `len`) to the device with id `device_id`, which is how vendor commands are given
+        /// to a device. The first byte is the report ID, or 0 if the device doesn't use report IDs. The response to a command can be read
+        /// with `wooting_analog_get_feature_report`.
+        ///
+        /// # Notes
+        /// * This needs raw device access to have been enabled with `wooting_analog_set_raw_device_access`
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The report has been sent to the device
+        /// * `UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `PermissionDenied`: Raw device access hasn't been enabled
+        /// * `InvalidArgument`: `device_id` is 0, or `data` is null or `len` is 0
+        /// * `NoDevices`: Indicates the device with id `device_id` is not connected
+        /// * `NotAvailable`: The plugin of the device can't send feature reports
+        fn wooting_analog_send_feature_report(device_id: DeviceID, data: *const u8, len: c_uint) -> WootingAnalogResult;
+
+        /// Reads the feature report with the ID in the first byte of `buffer` (that has length `len`) from the device with id `device_id`
+        /// into `buffer`. This doesn't need raw device access, as only the report ID is sent to the device.
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The length of the report that has been read into the buffer, including the report ID
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::InvalidArgument`: `device_id` is 0, or `buffer` is null or `len` is 0
+        /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+        /// * `WootingAnalogResult::NotAvailable`: The plugin of the device can't read feature reports
+        fn wooting_analog_get_feature_report(device_id: DeviceID, buffer: *mut u8, len: c_uint) -> c_int;
+
+        /// Fills in `info` with the extended information of the device with id `device_id`, including the serial number, firmware version
+        /// and interface path. If `device_id` is 0 the first connected device is used.
+        ///
+        /// # Notes
+        /// * `info->size` must be set to `sizeof(WootingAnalog_DeviceInfoEx_FFI)` before the call. Only the fields which fit within it are
+        /// filled in, and it's set to the size which was filled in, so older and newer versions of the struct can be used with any SDK
+        /// * Information the plugin of the device doesn't know is given as null strings and 0 numbers
+        /// * The memory of the strings will only be kept until the next call of this function on the same thread, so if you wish to use
+        /// any data from them, please copy it
+        ///
+        /// # Expected Returns
+        /// * `Ok`: `info` has been filled in
+        /// * `InvalidArgument`: `info` is null or its `size` is too small to hold the `size` field
+        /// * `UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `NoDevices`: Indicates the device with id `device_id` is not connected
+        fn wooting_analog_get_device_info_ex(device_id: DeviceID, info: *mut DeviceInfoEx_FFI) -> WootingAnalogResult;
+
+        /// Gives the device with id `device_id` the given `alias` (e.g. "left keypad"), so that users can tell their devices apart. Aliases
+        /// are saved, so they're kept between runs and shared by every app using the SDK. A null `alias` removes the device's alias.
+        /// This can be used without initialising the SDK.
+        ///
+        /// # Notes
+        /// * The device doesn't need to be connected
+        /// * Leading & trailing whitespace is removed from the alias, and it can be at most 256 bytes long
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The alias has been set & saved
+        /// * `InvalidArgument`: `device_id` is 0, or the alias is empty, too long, has a line break or is already used by another device
+        /// * `Failure`: The alias couldn't be saved, in which case it's left unchanged
+        fn wooting_analog_set_device_alias(device_id: DeviceID, alias: *const c_char) -> WootingAnalogResult;
+
+        /// Fills `buffer` (that has length `len`) with the null terminated alias of the device with id `device_id`. If the alias doesn't
+        /// fit, it's cut short to fit. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The length of the alias in bytes, not including the null terminator. If this is `len` or more it has been cut short.
+        /// 0 means the device has no alias
+        /// * `InvalidArgument`: `buffer` is null or `len` is 0
+        fn wooting_analog_get_device_alias(device_id: DeviceID, buffer: *mut c_char, len: c_uint) -> c_int;
+
+        /// Finds the device with the given `alias`, ignoring case, and writes its id into `device_id`. This can be used without
+        /// initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The id of the device has been written into `device_id`. The device may not be connected
+        /// * `InvalidArgument`: `alias` or `device_id` is null
+        /// * `NoDevices`: No device has the given alias
+        fn wooting_analog_find_device_by_alias(alias: *const c_char, device_id: *mut DeviceID) -> WootingAnalogResult;
+
+        /// Sets the file device aliases are kept in, loading the aliases already in it. By default they're kept in
+        /// `wooting-analog-sdk/device-aliases.txt` in the user's config directory, or the file given by the `WOOTING_ANALOG_SDK_ALIASES`
+        /// environment variable. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The aliases in the file will be used from now on. If the file doesn't exist yet, it's created once an alias is set
+        /// * `InvalidArgument`: `path` is null
+        fn wooting_analog_set_device_alias_file(path: *const c_char) -> WootingAnalogResult;
+
+        /// Gives the number of loaded plugins, which can be looked at with `wooting_analog_get_plugin_status`,
+        /// `wooting_analog_get_plugin_name` & `wooting_analog_get_plugin_path`
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The number of loaded plugins
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        fn wooting_analog_get_plugin_count() -> c_int;
+
+        /// Gives the `PluginStatus` of the plugin at `index`, which is from 0 up to `wooting_analog_get_plugin_count`. A plugin becomes
+        /// `Degraded` when calls into it time out or fail, and `Quarantined` once that keeps happening, at which point its devices are
+        /// reported as disconnected and it isn't called anymore.
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The `PluginStatus` of the plugin
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::InvalidArgument`: There's no plugin at `index`
+        fn wooting_analog_get_plugin_status(index: c_uint) -> c_int;
+
+        /// Fills `buffer` (that has length `len`) with the null terminated name of the plugin at `index`, which is from 0 up to
+        /// `wooting_analog_get_plugin_count`. If the name doesn't fit, it's cut short to fit.
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The length of the name in bytes, not including the null terminator. If this is `len` or more it has been cut short
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::InvalidArgument`: `buffer` is null, `len` is 0 or there's no plugin at `index`
+        fn wooting_analog_get_plugin_name(index: c_uint, buffer: *mut c_char, len: c_uint) -> c_int;
+
+        /// Fills `buffer` (that has length `len`) with the null terminated path of the library the plugin at `index` was loaded from, which
+        /// is from 0 up to `wooting_analog_get_plugin_count`. If the path doesn't fit, it's cut short to fit. Plugins which weren't loaded
+        /// from a library of their own have an empty path.
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `ret>=0`: The length of the path in bytes, not including the null terminator. If this is `len` or more it has been cut short
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::InvalidArgument`: `buffer` is null, `len` is 0 or there's no plugin at `index`
+        fn wooting_analog_get_plugin_path(index: c_uint, buffer: *mut c_char, len: c_uint) -> c_int;
+
+        /// Sets how long (in milliseconds) a call into a plugin may take before the SDK stops waiting for it, 250ms by default. A plugin
+        /// whose calls keep going over it is quarantined. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The timeout will be used from now on
+        /// * `InvalidArgument`: `timeout_ms` is 0
+        fn wooting_analog_set_plugin_call_timeout(timeout_ms: c_uint) -> WootingAnalogResult;
+
+        /// Sets the `priority` of the plugin loaded from `path` (as given by `wooting_analog_get_plugin_path`), which decides which plugin
+        /// owns a device when more than one plugin reports it, e.g. an old and a new version of the same plugin, which can have the same
+        /// name. The device is only listed, read & reported in device events through the plugin with the highest priority, or the one loaded
+        /// first if they're the same. Plugins which weren't loaded from a library of their own are given by their name instead. Plugins have
+        /// a priority of 0 unless they're given one, and plugins in the plugin directory itself are loaded before those in its sub
+        /// directories. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The priority will be used from now on
+        /// * `InvalidArgument`: `path` is null
+        fn wooting_analog_set_plugin_priority(path: *const c_char, priority: c_int) -> WootingAnalogResult;
+
+        /// Reports the key `from` as the `len` keys in `to` on the device with id `device_id`, or on every device if it's 0, replacing any
+        /// earlier remap of the key. This is applied to the keys read from the device before they're given, e.g. to move the analog travel
+        /// of a broken switch to another key, or to report a key as several keys. If `len` is 0 the key is disabled. Remaps for a specific
+        /// device take precedence over those for every device. This can be used without initialising the SDK.
+        ///
+        /// # Notes
+        /// * The codes are of the KeycodeType set with wooting_analog_set_mode, the remap is kept by HID code so it isn't affected by
+        /// changing it
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The remap will be used from now on
+        /// * `InvalidArgument`: `to` is null while `len` isn't 0
+        /// * `NoMapping`: One of the codes doesn't have a HID code
+        fn wooting_analog_set_key_remap(device_id: DeviceID, from: c_ushort, to: *const c_ushort, len: c_uint) -> WootingAnalogResult;
+
+        /// Removes the remap of the key `from` set for the device with id `device_id`, or the one for every device if it's 0. This can be
+        /// used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The key is no longer remapped for the device
+        /// * `InvalidArgument`: The key isn't remapped for the device
+        /// * `NoMapping`: `from` doesn't have a HID code
+        fn wooting_analog_remove_key_remap(device_id: DeviceID, from: c_ushort) -> WootingAnalogResult;
+
+        /// Removes every key remap. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: No keys are remapped anymore
+        fn wooting_analog_clear_key_remaps() -> WootingAnalogResult;
+
+        /// Replaces every key remap with those in the file at `path`. The file has one remap per line, as the device id in hex (or `*` for
+        /// every device), the key being remapped and then the keys it's reported as, separated by spaces, e.g. `* CapsLock Escape`. Keys
+        /// are given by name (as accepted by `wooting_analog_key_from_name`) or as a HID code in hex (e.g. `0x04`), lines starting with `#`
+        /// are ignored. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The remaps in the file will be used from now on, lines which couldn't be read are skipped
+        /// * `InvalidArgument`: `path` is null
+        /// * `Failure`: The file couldn't be read, in which case the remaps are left unchanged
+        fn wooting_analog_load_key_remap_file(path: *const c_char) -> WootingAnalogResult;
+
+        /// Defines the virtual axis called `name`, replacing any axis with the same name. The axis goes from -1.0 to 1.0, made up of the
+        /// `positive_len` keys in `positive` and the `negative_len` keys in `negative`, where each side is as far as the furthest pressed
+        /// key of it. This can be used without initialising the SDK.
+        ///
+        /// # Notes
+        /// * `combine` is how the sides are combined when both are pressed, as one of the AxisCombine enum
+        /// * Values which are closer to 0 than `deadzone` are given as 0, the rest are scaled so that they still go up to 1
+        /// * The codes are of the KeycodeType set with wooting_analog_set_mode, the axis is kept by HID code so it isn't affected by
+        /// changing it
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The axis can be read with wooting_analog_read_axis
+        /// * `InvalidArgument`: `name` is null or empty, `combine` isn't one of the AxisCombine enum, `deadzone` isn't from 0 up to (but
+        /// not including) 1, or one of the key arrays is null while its length isn't 0
+        /// * `NoMapping`: One of the codes doesn't have a HID code
+        fn wooting_analog_define_axis(name: *const c_char, positive: *const c_ushort, positive_len: c_uint, negative: *const c_ushort, negative_len: c_uint, combine: c_uint, deadzone: f32) -> WootingAnalogResult;
+
+        /// Removes the virtual axis called `name`, unpairing it from the axis it was paired with. This can be used without initialising
+        /// the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The axis has been removed
+        /// * `InvalidArgument`: `name` is null or there's no axis called `name`
+        fn wooting_analog_remove_axis(name: *const c_char) -> WootingAnalogResult;
+
+        /// Pairs up the virtual axes called `x` & `y` into a stick, which normalises them together so that their combined length is at
+        /// most 1.0, e.g. so that moving diagonally with WASD isn't faster than moving straight. Either of them is unpaired from the axis
+        /// it was paired with before. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The axes will be normalised together from now on
+        /// * `InvalidArgument`: One of the names is null, one of the axes doesn't exist, or they're the same axis
+        fn wooting_analog_pair_axes(x: *const c_char, y: *const c_char) -> WootingAnalogResult;
+
+        /// Reads the value of the virtual axis called `name` (defined with wooting_analog_define_axis) from all connected devices
+        ///
+        /// # Expected Returns
+        /// The float return value can be either a -1->1 axis value, or (if < -1) is part of the WootingAnalogResult enum, which is how errors are given back on this call.
+        /// So if the value is below -1, you should cast it as WootingAnalogResult to see what the error is.
+        /// * `-1.0f - 1.0f`: The value of the axis called `name`
+        /// * `WootingAnalogResult::InvalidArgument`: `name` is null or there's no axis called `name`
+        /// * `WootingAnalogResult::UnInitialized`: The SDK is not initialised
+        /// * `WootingAnalogResult::NoDevices`: There are no connected devices
+        fn wooting_analog_read_axis(name: *const c_char) -> f32;
+
+        /// Reads the values of the virtual axes called `x` & `y` (defined with wooting_analog_define_axis) from all connected devices at once
+        /// into `x_value` & `y_value`, e.g. the two axes of a stick, so that they're both from the same read. Each goes from -1.0 to 1.0
+        ///
+        /// # Expected Returns
+        /// * `Ok`: `x_value` & `y_value` have been filled in
+        /// * `InvalidArgument`: One of the names or values is null, or there's no axis called `x` or `y`
+        /// * `UnInitialized`: The SDK is not initialised
+        /// * `NoDevices`: There are no connected devices
+        fn wooting_analog_read_axis_pair(x: *const c_char, y: *const c_char, x_value: *mut f32, y_value: *mut f32) -> WootingAnalogResult;
+
+        /// Fills in `kinematics` with how the key with identifier `code` is moving on the device with id `device_id`, or on the device
+        /// where it's pressed furthest if `device_id` is 0. This gives how far the key is pressed, how fast it's moving, its acceleration,
+        /// the furthest it got during its last press, and when that press started & ended. It's worked out from every report the device
+        /// sends rather than from the reads of the SDK, so it doesn't matter how often it's read.
+        ///
+        /// # Notes
+        /// * The code is of the KeycodeType set with wooting_analog_set_mode. If keys are remapped onto it, the one pressed furthest is given
+        /// * Times are in microseconds on the clock given by `wooting_analog_kinematics_time`, the press duration of a released key is
+        /// `released_at - pressed_at`
+        /// * Only devices of plugins which publish their reports into a `SnapshotStore` are tracked
+        ///
+        /// # Expected Returns
+        /// * `Ok`: `kinematics` has been filled in
+        /// * `InvalidArgument`: `kinematics` is null
+        /// * `UnInitialized`: The SDK is not initialised
+        /// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+        /// * `NoDevices`: No reports have come in from the device with id `device_id` (or from any device if it's 0)
+        fn wooting_analog_read_key_kinematics(code: c_ushort, device_id: DeviceID, kinematics: *mut KeyKinematics) -> WootingAnalogResult;
+
+        /// Gives the current time in microseconds on the clock which the times given by `wooting_analog_read_key_kinematics` are on, e.g.
+        /// to tell how long a key which is still pressed has been held for. This can be used without initialising the SDK.
+        fn wooting_analog_kinematics_time() -> u64;
+
+        /// Binds the key with identifier `code` to `action` of the emulated mouse, as one of the MouseAction enum, replacing what the key
+        /// was bound to. Any number of keys can be bound to the same action, e.g. both arrows & IJKL to move the pointer. This can be used
+        /// without initialising the SDK.
+        ///
+        /// # Notes
+        /// * The code is of the KeycodeType set with wooting_analog_set_mode, the binding is kept by HID code so it isn't affected by
+        /// changing it
+        /// * Keys which move the pointer or scroll go faster the further they're pressed, keys bound to buttons hold them down while
+        /// they're pressed past halfway
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The key is bound
+        /// * `InvalidArgument`: `action` isn't one of the MouseAction enum
+        /// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+        fn wooting_analog_bind_mouse_action(code: c_ushort, action: c_uint) -> WootingAnalogResult;
+
+        /// Unbinds the key with identifier `code` from the emulated mouse. This can be used without initialising the SDK.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The key isn't bound anymore
+        /// * `InvalidArgument`: The key isn't bound
+        /// * `NoMapping`: No keycode mapping was found from the selected mode (set by wooting_analog_set_mode) and HID.
+        fn wooting_analog_unbind_mouse_action(code: c_ushort) -> WootingAnalogResult;
+
+        /// Sets the acceleration curve of the emulated mouse pointer, which gives how fast it moves in pixels per second with how far
+        /// the keys moving it are pressed. This can be used without initialising the SDK.
+        ///
+        /// # Notes
+        /// * By default the pointer has a deadzone of 0.1, an exponent of 2 and a max speed of 1500 pixels per second
+        /// * Moving diagonally is kept to the max speed as well
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The curve is used from the next update of the mouse
+        /// * `InvalidArgument`: The deadzone isn't from 0 up to (but not including) 1, the exponent isn't above 0, or the max speed is
+        /// below 0
+        fn wooting_analog_set_mouse_pointer_curve(curve: MouseCurve) -> WootingAnalogResult;
+
+        /// Sets the acceleration curve of the emulated mouse wheel, which gives how fast it scrolls in lines per second with how
+        /// far the keys scrolling it are pressed. This can be used without initialising the SDK.
+        ///
+        /// # Notes
+        /// * By default scrolling has a deadzone of 0.1, an exponent of 1.5 and a max speed of 20 lines per second
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The curve is used from the next update of the mouse
+        /// * `InvalidArgument`: The deadzone isn't from 0 up to (but not including) 1, the exponent isn't above 0, or the max speed is
+        /// below 0
+        fn wooting_analog_set_mouse_scroll_curve(curve: MouseCurve) -> WootingAnalogResult;
+
+        /// Starts emulating a mouse with the keys bound by wooting_analog_bind_mouse_action, reading all devices every 8ms. The mouse is a
+        /// virtual device, so it moves the system pointer like any other mouse, e.g. to give people who can't use a mouse a cursor
+        /// controlled by how far they press keys.
+        ///
+        /// # Notes
+        /// * This is only available on Linux, where the mouse is created through `/dev/uinput`, which needs write access to it (usually
+        /// given through a udev rule or the `input` group)
+        /// * The bound keys are still read by everyone else reading the SDK
+        /// * The keys are read through a read session of its own, so it doesn't take released keys from anyone else reading the SDK
+        /// * The emulation is stopped by wooting_analog_stop_mouse_emulation or when the SDK is uninitialised
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The mouse is being emulated, or already was
+        /// * `UnInitialized`: The SDK is not initialised
+        /// * `NotAvailable`: Mouse emulation isn't available on this platform
+        /// * `Failure`: The virtual mouse couldn't be created, e.g. because `/dev/uinput` couldn't be opened
+        fn wooting_analog_start_mouse_emulation() -> WootingAnalogResult;
+
+        /// Stops emulating the mouse started by wooting_analog_start_mouse_emulation, letting go of any buttons it was holding. The key
+        /// bindings & curves are kept.
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The mouse isn't being emulated anymore
+        fn wooting_analog_stop_mouse_emulation() -> WootingAnalogResult;
+
         /// Reads all the analog values for pressed keys for all devices and combines their values, filling up `code_buffer` with the
         /// keycode identifying the pressed key and fills up `analog_buffer` with the corresponding float analog values. i.e. The analog
         /// value for they key at index 0 of code_buffer, is at index 0 of analog_buffer.
@@ -195,6 +671,7 @@ dynamic_extern! {
         /// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
         /// * If two devices have the same key pressed, the greater value will be given
         /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+        /// * Released keys are tracked in a session shared with every other caller, use `wooting_analog_read_full_buffer_session` if there are multiple readers
         ///
         /// # Expected Returns
         /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
@@ -213,6 +690,7 @@ dynamic_extern! {
         /// key & analog value pairs that can be filled in.
         /// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
         /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+        /// * Released keys are tracked in a session shared with every other caller, use `wooting_analog_read_full_buffer_session` if there are multiple readers
         ///
         /// # Expected Returns
         /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
@@ -221,5 +699,82 @@ dynamic_extern! {
         /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
         /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
         fn wooting_analog_read_full_buffer_device(code_buffer: *mut c_ushort, analog_buffer: *mut c_float, len: c_uint, device_id: DeviceID) -> c_int;
+
+        /// Creates a new session to read with using `wooting_analog_read_full_buffer_session`. Each session keeps track of released keys
+        /// on its own, so if there are multiple parts of your application reading the full buffer, they should each have their own session
+        /// to ensure none of them miss out on a key being released.
+        ///
+        /// # Notes
+        /// * The session should be destroyed with `wooting_analog_destroy_session` once it is no longer needed
+        /// * Sessions are destroyed when the SDK is uninitialised
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `>=0` The ID of the new session
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        fn wooting_analog_create_session() -> c_int;
+
+        /// Destroys the `session` that was created with `wooting_analog_create_session`
+        ///
+        /// # Expected Returns
+        /// * `Ok`: The session was destroyed
+        /// * `InvalidArgument`: There is no session with the given ID
+        /// * `UnInitialized`: The SDK is not initialised
+        fn wooting_analog_destroy_session(session: SessionID) -> WootingAnalogResult;
+
+        /// The same as `wooting_analog_read_full_buffer_device`, except released keys are tracked for the given `session` (created with
+        /// `wooting_analog_create_session`), rather than being shared with every other reader. Pass a `device_id` of 0 to read from all devices.
+        ///
+        /// # Notes
+        /// * `len` is the length of code_buffer & analog_buffer, if the buffers are of unequal length, then pass the lower of the two, as it is the max amount of
+        /// key & analog value pairs that can be filled in.
+        /// * The codes that are filled into the `code_buffer` are of the KeycodeType set with wooting_analog_set_mode
+        /// * When a key is released it will be returned with an analog value of 0.0f in the first call with this `session` after the key has been released
+        /// * If more keys are pressed than fit, the ones pressed furthest are given ahead of any releases. Releases which don't fit are given in a later call
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `>=0` means the value indicates how many keys & analog values have been read into the buffers
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::InvalidArgument`: There is no session with the given ID
+        /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+        fn wooting_analog_read_full_buffer_session(session: SessionID, code_buffer: *mut c_ushort, analog_buffer: *mut c_float, len: c_uint, device_id: DeviceID) -> c_int;
+
+        /// Fills up `analog_buffer` (that has length `len`) with the analog value of every key on all devices, indexed by HID code. i.e. the
+        /// analog value for A (HID 0x04) is at index 4 of `analog_buffer`. This doesn't allocate, so it is the recommended way to read every key
+        /// once per frame.
+        ///
+        /// # Notes
+        /// * The buffer is always indexed by HID code, regardless of the KeycodeType set with wooting_analog_set_mode
+        /// * Keys which aren't pressed are set to 0.0f, so there is no need to keep track of released keys
+        /// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+        /// * If two devices have the same key pressed, the greater value will be given
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `>=0` means the value indicates how many keys are pressed in the buffer
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::NoDevices`: Indicates no devices are connected
+        fn wooting_analog_read_dense_buffer(analog_buffer: *mut c_float, len: c_uint) -> c_int;
+
+        /// Fills up `analog_buffer` (that has length `len`) with the analog value of every key on the device with id `device_id`, indexed by HID code.
+        /// i.e. the analog value for A (HID 0x04) is at index 4 of `analog_buffer`. This doesn't allocate, so it is the recommended way to read every key
+        /// once per frame.
+        ///
+        /// # Notes
+        /// * The buffer is always indexed by HID code, regardless of the KeycodeType set with wooting_analog_set_mode
+        /// * Keys which aren't pressed are set to 0.0f, so there is no need to keep track of released keys
+        /// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+        ///
+        /// # Expected Returns
+        /// Similar to other functions like `wooting_analog_device_info`, the return value encodes both errors and the return value we want.
+        /// Where >=0 is the actual return, and <0 should be cast as WootingAnalogResult to find the error.
+        /// * `>=0` means the value indicates how many keys are pressed in the buffer
+        /// * `WootingAnalogResult::UnInitialized`: Indicates that the AnalogSDK hasn't been initialised
+        /// * `WootingAnalogResult::NoDevices`: Indicates the device with id `device_id` is not connected
+        fn wooting_analog_read_dense_buffer_device(analog_buffer: *mut c_float, len: c_uint, device_id: DeviceID) -> c_int;
     }
 }
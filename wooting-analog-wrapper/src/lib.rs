@@ -6,8 +6,11 @@ pub use wooting_analog_common::*;
 pub mod ffi;
 use ffi::*;
 use std::collections::HashMap;
-use std::os::raw::c_uint;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::path::Path;
 use std::ptr;
+use std::time::Duration;
 
 pub(crate) const SDK_ABI_VERSION: u32 = 0;
 
@@ -47,8 +50,10 @@ pub fn uninitialise() -> SDKResult<()> {
 ///
 /// # Notes
 /// * `VirtualKey` and `VirtualKeyTranslate` are only available on Windows
-/// * With all modes except `VirtualKeyTranslate`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
+/// * `XkbKeysym` is only available on Linux, with libxkbcommon installed
+/// * With all modes except `VirtualKeyTranslate` and `XkbKeysym`, the key identifier will point to the physical key on the standard layout. i.e. if you ask for the Q key, it will be the key right to tab regardless of the layout you have selected
 /// * With `VirtualKeyTranslate`, if you request Q, it will be the key that inputs Q on the current layout, not the key that is Q on the standard layout.
+/// * With `XkbKeysym`, the codes are X keysyms (e.g. 0x71 for q) and point to the key that inputs it on the layout set with `wooting_analog_set_xkb_keymap`. Custom keys can't be read in this mode, as they overlap the keysyms.
 ///
 /// # Expected Returns
 /// * `Ok(())`: The Keycode mode was changed successfully
@@ -59,6 +64,75 @@ pub fn set_keycode_mode(mode: KeycodeType) -> SDKResult<()> {
     return unsafe { wooting_analog_set_keycode_mode(mode).into() };
 }
 
+/// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, compiled from the system's XKB data for the given
+/// `layout` and `variant` (e.g. "fr" and "oss"). Empty names are filled in by libxkbcommon from the `XKB_DEFAULT_*` environment
+/// variables or its own defaults, which is also the keymap used until this is called
+///
+/// # Expected Returns
+/// * `Ok(())`: The keymap was compiled and will be used from now on
+/// * `Err(InvalidArgument)`: No keymap could be compiled for the given names
+/// * `Err(NotAvailable)`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn set_xkb_keymap(layout: &str, variant: &str) -> SDKResult<()> {
+    let (layout, variant) = match (CString::new(layout), CString::new(variant)) {
+        (Ok(layout), Ok(variant)) => (layout, variant),
+        _ => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    return unsafe { wooting_analog_set_xkb_keymap(layout.as_ptr(), variant.as_ptr()).into() };
+}
+
+/// Sets the XKB keymap used to translate keys in the `XkbKeysym` keycode mode, loaded from the keymap file at `path` (in the
+/// format written by e.g. `xkbcomp $DISPLAY keymap.xkb`)
+///
+/// # Expected Returns
+/// * `Ok(())`: The keymap was loaded and will be used from now on
+/// * `Err(InvalidArgument)`: The file couldn't be read or isn't a valid keymap
+/// * `Err(NotAvailable)`: XKB isn't supported on the current platform, or libxkbcommon isn't installed
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn set_xkb_keymap_file<P: AsRef<Path>>(path: P) -> SDKResult<()> {
+    let path = match path.as_ref().to_str().and_then(|p| CString::new(p).ok()) {
+        Some(path) => path,
+        None => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    return unsafe { wooting_analog_set_xkb_keymap_file(path.as_ptr()).into() };
+}
+
+/// Gives the canonical name of the key with identifier `code` in the given keycode `mode`, e.g. "Q" for 0x10 in `ScanCode1`. The
+/// names don't depend on the layout, and are the names of the `HIDCodes`/`ConsumerCodes` variants. This can be used without
+/// initialising the SDK.
+///
+/// # Expected Returns
+/// * `Some(name)`: The name of the key
+/// * `None`: The `code` isn't a known key in the given `mode`, or the SDK couldn't be loaded
+pub fn key_name(code: u16, mode: KeycodeType) -> Option<String> {
+    unsafe {
+        let name = wooting_analog_key_name(code, mode);
+        if name.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        }
+    }
+}
+
+/// Gives the identifier in the given keycode `mode` of the key with the given `name`. Names are case insensitive, ignore spaces,
+/// underscores and dashes, and common aliases (e.g. "Esc", "LShift" or "KP_Enter") are accepted alongside the names given by
+/// `key_name`. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(code)`: The identifier of the key in the given `mode`
+/// * `Err(InvalidArgument)`: The `name` contains a null character
+/// * `Err(NoMapping)`: There's no key with the given name, or it has no identifier in the given `mode`
+pub fn key_from_name(name: &str, mode: KeycodeType) -> SDKResult<u16> {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    let code: SDKResult<c_int> =
+        unsafe { wooting_analog_key_from_name(name.as_ptr(), mode).into() };
+    code.0.map(|code| code as u16).into()
+}
+
 /// Reads the Analog value of the key with identifier `code` from any connected device. The set of key identifiers that is used
 /// depends on the Keycode mode set using `wooting_analog_set_mode`.
 ///
@@ -102,7 +176,10 @@ pub fn read_analog_device(code: u16, device_id: DeviceID) -> SDKResult<f32> {
 ///
 /// # Notes
 /// * You must copy the DeviceInfo struct or its data if you wish to use it after the callback has completed, as the memory will be freed straight after
-/// * The execution of the callback is performed in a separate thread so it is fine to put time consuming code and further SDK calls inside your callback
+/// * The callback is called from a single SDK thread, in the order the events happened, so it is fine to put further SDK calls inside your callback.
+/// Time consuming code will hold up the events which come after
+/// * If events need to be handled on a specific thread, use `poll_device_event` instead
+/// * Setting the callback replaces the one which was set before, use `add_device_event_listener` to have several listeners
 ///
 /// # Expected Returns
 /// * `Ok(())`: The callback was set successfully
@@ -122,6 +199,67 @@ pub fn clear_device_event_cb() -> SDKResult<()> {
     return unsafe { wooting_analog_clear_device_event_cb().into() };
 }
 
+/// Adds a listener which is called when there is a DeviceEvent, alongside any other listeners and the callback set with `set_device_event_cb`.
+/// The listener gets given the type of event `DeviceEventType`, a pointer to the DeviceInfo struct that the event applies to and the `user_data`
+/// pointer that was given here, which the SDK doesn't touch.
+///
+/// # Notes
+/// * You must copy the DeviceInfo struct or its data if you wish to use it after the listener has completed, as the memory will be freed straight after
+/// * Listeners are called one after the other from a single SDK thread, in the order they were added
+///
+/// # Expected Returns
+/// * `Ok(ListenerID)`: The ID of the listener, which is used to remove it with `remove_device_event_listener`
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn add_device_event_listener(
+    cb: extern "C" fn(DeviceEventType, *mut DeviceInfo_FFI, *mut c_void),
+    user_data: *mut c_void,
+) -> SDKResult<ListenerID> {
+    return unsafe { wooting_analog_add_device_event_listener(cb, user_data).into() };
+}
+
+/// Removes the listener with the given `id` that was added with `add_device_event_listener`
+///
+/// # Notes
+/// * Once this returns the listener won't be called again, so whatever it uses can be freed. If the listener is being called on
+/// the SDK thread right now, this waits for it to return first
+///
+/// # Expected Returns
+/// * `Ok(())`: The listener was removed
+/// * `Err(InvalidArgument)`: There is no listener with the given ID
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn remove_device_event_listener(id: ListenerID) -> SDKResult<()> {
+    return unsafe { wooting_analog_remove_device_event_listener(id).into() };
+}
+
+/// Takes the oldest device event which hasn't been polled yet. This is an alternative to `set_device_event_cb` for applications which need to
+/// handle device events on their own thread, e.g. by calling this once a frame until it returns `Ok(None)`.
+///
+/// # Notes
+/// * Events are given in the order they happened. The SDK keeps the last 64 events, older ones are dropped if they aren't polled in time
+/// * Events are queued regardless of whether a callback has been set, and the callback doesn't affect what this returns
+///
+/// # Expected Returns
+/// * `Ok(Some((DeviceEventType, DeviceInfo)))`: The oldest event which hadn't been polled yet
+/// * `Ok(None)`: There are no events waiting
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn poll_device_event() -> SDKResult<Option<(DeviceEventType, DeviceInfo)>> {
+    let mut event = DeviceEventType::Connected;
+    let mut device: *mut DeviceInfo_FFI = ptr::null_mut();
+    let ret: SDKResult<u32> =
+        unsafe { wooting_analog_poll_device_event(&mut event, &mut device).into() };
+
+    ret.0
+        .map(|polled| {
+            if polled > 0 {
+                // The SDK keeps the memory of the device until the next poll, so we only copy it here
+                unsafe { device.as_ref() }.map(|device| (event, device.into_device_info()))
+            } else {
+                None
+            }
+        })
+        .into()
+}
+
 /// Returns all connected devices with a max Vector return length of `max_devices` (as many that can fit in the buffer)
 ///
 /// # Notes
@@ -158,12 +296,526 @@ pub fn get_connected_devices_info(max_devices: usize) -> SDKResult<Vec<DeviceInf
     }
 }
 
+/// Gives the physical layout of the device with id `device_id`, if `device_id` is 0 the first connected device is used
+///
+/// # Expected Returns
+/// * `Ok(layout)`: The layout of the device, which is `Unknown` if the plugin of the device doesn't know its layout
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+pub fn get_device_layout(device_id: DeviceID) -> SDKResult<KeyboardLayout> {
+    let ret: SDKResult<c_int> = unsafe { wooting_analog_get_device_layout(device_id).into() };
+    ret.0
+        .map(|layout| KeyboardLayout::from_i32(layout).unwrap_or(KeyboardLayout::Unknown))
+        .into()
+}
+
+/// Gives the position & size of every key of the device with id `device_id`, ordered by row and then from left to right. If
+/// `device_id` is 0 the first connected device is used. Positions & sizes are in key units, where 1.0 is the size of a standard
+/// alphanumeric key, measured from the top left of the keyboard.
+///
+/// # Notes
+/// * The codes of the keys are of the KeycodeType set with set_mode, keys which have no code in it are left out
+/// * This is empty if the layout of the device isn't known
+///
+/// # Expected Returns
+/// * `Ok(keys)`: The geometry of the device's keys
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+pub fn get_device_geometry(device_id: DeviceID) -> SDKResult<Vec<KeyGeometry>> {
+    unsafe {
+        let len: SDKResult<u32> =
+            wooting_analog_get_device_geometry(device_id, ptr::null_mut(), 0).into();
+        let len = match len.0 {
+            Ok(len) => len,
+            Err(e) => return Err(e).into(),
+        };
+
+        let mut buffer: Vec<KeyGeometry> = Vec::with_capacity(len as usize);
+        let ret: SDKResult<u32> =
+            wooting_analog_get_device_geometry(device_id, buffer.as_mut_ptr(), len).into();
+        ret.0
+            .map(|key_no| {
+                buffer.set_len(key_no as usize);
+                buffer
+            })
+            .into()
+    }
+}
+
+/// Allows `send_feature_report` to send data to devices, which is refused by default. Only enable this if you know what the data
+/// does, as vendor commands can change the settings of a device or even the firmware on it. This can be used without initialising
+/// the SDK.
+pub fn set_raw_device_access(enabled: bool) -> SDKResult<()> {
+    unsafe { wooting_analog_set_raw_device_access(enabled).into() }
+}
+
+/// Sends the feature report `data` to the device with id `device_id`, which is how vendor commands are given to a device. The
+/// first byte is the report ID, or 0 if the device doesn't use report IDs. The response to a command can be read with
+/// `get_feature_report`.
+///
+/// # Notes
+/// * This needs raw device access to have been enabled with `set_raw_device_access`
+///
+/// # Expected Returns
+/// * `Ok(())`: The report has been sent to the device
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(PermissionDenied)`: Raw device access hasn't been enabled
+/// * `Err(InvalidArgument)`: `device_id` is 0 or `data` is empty
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+/// * `Err(NotAvailable)`: The plugin of the device can't send feature reports
+pub fn send_feature_report(device_id: DeviceID, data: &[u8]) -> SDKResult<()> {
+    unsafe {
+        wooting_analog_send_feature_report(device_id, data.as_ptr(), data.len() as c_uint).into()
+    }
+}
+
+/// Reads the feature report with the ID in the first byte of `buffer` from the device with id `device_id` into `buffer`. This
+/// doesn't need raw device access, as only the report ID is sent to the device.
+///
+/// # Expected Returns
+/// * `Ok(len)`: The length of the report that has been read into the buffer, including the report ID
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(InvalidArgument)`: `device_id` is 0 or `buffer` is empty
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+/// * `Err(NotAvailable)`: The plugin of the device can't read feature reports
+pub fn get_feature_report(device_id: DeviceID, buffer: &mut [u8]) -> SDKResult<usize> {
+    let ret: SDKResult<u32> = unsafe {
+        wooting_analog_get_feature_report(device_id, buffer.as_mut_ptr(), buffer.len() as c_uint)
+            .into()
+    };
+    ret.0.map(|len| len as usize).into()
+}
+
+/// Gives the information of the device with id `device_id`, including the `DeviceDetails` like its serial number & firmware
+/// version. If `device_id` is 0 the first connected device is used
+///
+/// # Expected Returns
+/// * `Ok(device)`: The information of the device, with details the plugin of the device doesn't know left as `None`
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+pub fn get_device_info_ex(device_id: DeviceID) -> SDKResult<DeviceInfo> {
+    let mut info = DeviceInfoEx_FFI::default();
+    match unsafe { wooting_analog_get_device_info_ex(device_id, &mut info) } {
+        WootingAnalogResult::Ok => Ok(info.into_device_info()).into(),
+        e => Err(e).into(),
+    }
+}
+
+/// Gives the device with id `device_id` the given `alias` (e.g. "left keypad"), so that users can tell their devices apart. Aliases
+/// are saved, so they're kept between runs and shared by every app using the SDK. An `alias` of `None` removes the device's alias.
+/// This can be used without initialising the SDK.
+///
+/// # Notes
+/// * The device doesn't need to be connected
+/// * Leading & trailing whitespace is removed from the alias, and it can be at most 256 bytes long
+///
+/// # Expected Returns
+/// * `Ok(())`: The alias has been set & saved
+/// * `Err(InvalidArgument)`: `device_id` is 0, or the alias is empty, too long, has a line break or is already used by another device
+/// * `Err(Failure)`: The alias couldn't be saved, in which case it's left unchanged
+pub fn set_device_alias(device_id: DeviceID, alias: Option<&str>) -> SDKResult<()> {
+    let alias = match alias.map(CString::new) {
+        Some(Ok(alias)) => Some(alias),
+        Some(Err(_)) => return Err(WootingAnalogResult::InvalidArgument).into(),
+        None => None,
+    };
+    let alias_ptr = alias.as_ref().map_or(ptr::null(), |alias| alias.as_ptr());
+    unsafe { wooting_analog_set_device_alias(device_id, alias_ptr).into() }
+}
+
+/// Gives the alias of the device with id `device_id`. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(Some(alias))`: The alias of the device
+/// * `Ok(None)`: The device has no alias
+pub fn get_device_alias(device_id: DeviceID) -> SDKResult<Option<String>> {
+    //Aliases are at most 256 bytes, plus the null terminator
+    let mut buffer = [0 as c_char; 257];
+    let ret: SDKResult<u32> = unsafe {
+        wooting_analog_get_device_alias(device_id, buffer.as_mut_ptr(), buffer.len() as c_uint)
+            .into()
+    };
+    ret.0
+        .map(|len| {
+            if len == 0 {
+                None
+            } else {
+                let alias = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+                Some(alias.to_string_lossy().into_owned())
+            }
+        })
+        .into()
+}
+
+/// Finds the device with the given `alias`, ignoring case. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(device_id)`: The id of the device with the alias, which may not be connected
+/// * `Err(NoDevices)`: No device has the given alias
+pub fn find_device_by_alias(alias: &str) -> SDKResult<DeviceID> {
+    let alias = match CString::new(alias) {
+        Ok(alias) => alias,
+        Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    let mut device_id: DeviceID = 0;
+    match unsafe { wooting_analog_find_device_by_alias(alias.as_ptr(), &mut device_id) } {
+        WootingAnalogResult::Ok => Ok(device_id).into(),
+        e => Err(e).into(),
+    }
+}
+
+/// Sets the file device aliases are kept in, loading the aliases already in it. By default they're kept in
+/// `wooting-analog-sdk/device-aliases.txt` in the user's config directory, or the file given by the `WOOTING_ANALOG_SDK_ALIASES`
+/// environment variable. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The aliases in the file will be used from now on. If the file doesn't exist yet, it's created once an alias is set
+/// * `Err(InvalidArgument)`: The path isn't valid UTF-8
+pub fn set_device_alias_file<P: AsRef<Path>>(path: P) -> SDKResult<()> {
+    let path = match path.as_ref().to_str().and_then(|p| CString::new(p).ok()) {
+        Some(path) => path,
+        None => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe { wooting_analog_set_device_alias_file(path.as_ptr()).into() }
+}
+
+/// Gives the name, library path & `PluginStatus` of every plugin loaded by the SDK. A plugin becomes `Degraded` when calls into it time out or
+/// fail, and `Quarantined` once that keeps happening, at which point its devices are reported as disconnected and it isn't called
+/// anymore.
+///
+/// # Expected Returns
+/// * `Ok(plugins)`: The loaded plugins, in the order they were loaded
+/// * `Err(UnInitialized)`: The SDK hasn't been initialised
+pub fn get_plugin_status() -> SDKResult<Vec<PluginInfo>> {
+    let count: SDKResult<u32> = unsafe { wooting_analog_get_plugin_count().into() };
+    let count = match count.0 {
+        Ok(count) => count,
+        Err(e) => return Err(e).into(),
+    };
+
+    let mut plugins = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        let status: SDKResult<u32> = unsafe { wooting_analog_get_plugin_status(index).into() };
+        let status = match status.0 {
+            Ok(status) => PluginStatus::from_u32(status).unwrap_or(PluginStatus::Quarantined),
+            Err(e) => return Err(e).into(),
+        };
+        let name = match read_plugin_string(index, wooting_analog_get_plugin_name).0 {
+            Ok(name) => name,
+            Err(e) => return Err(e).into(),
+        };
+        let path = match read_plugin_string(index, wooting_analog_get_plugin_path).0 {
+            Ok(path) => path,
+            Err(e) => return Err(e).into(),
+        };
+        plugins.push(PluginInfo {
+            name,
+            path: if path.is_empty() { None } else { Some(path) },
+            status,
+        });
+    }
+    Ok(plugins).into()
+}
+
+/// Reads the name or path of the plugin at `index` with `read`, which is given a bigger buffer if it doesn't fit
+fn read_plugin_string(
+    index: c_uint,
+    read: unsafe extern "C" fn(c_uint, *mut c_char, c_uint) -> c_int,
+) -> SDKResult<String> {
+    let mut buffer = vec![0 as c_char; 256];
+    loop {
+        let ret: SDKResult<u32> =
+            unsafe { read(index, buffer.as_mut_ptr(), buffer.len() as c_uint).into() };
+        match ret.0 {
+            Ok(len) if (len as usize) < buffer.len() => break,
+            Ok(len) => buffer.resize(len as usize + 1, 0),
+            Err(e) => return Err(e).into(),
+        }
+    }
+    let value = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+    Ok(value.to_string_lossy().into_owned()).into()
+}
+
+/// Sets how long a call into a plugin may take before the SDK stops waiting for it, 250ms by default. A plugin whose calls keep
+/// going over it is quarantined. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The timeout will be used from now on
+/// * `Err(InvalidArgument)`: `timeout` is less than a millisecond
+pub fn set_plugin_call_timeout(timeout: Duration) -> SDKResult<()> {
+    let timeout_ms = timeout.as_millis().min(c_uint::max_value() as u128) as c_uint;
+    unsafe { wooting_analog_set_plugin_call_timeout(timeout_ms).into() }
+}
+
+/// Sets the `priority` of the plugin loaded from `path` (as given by `get_plugin_status`), which decides which plugin owns a device
+/// when more than one plugin reports it, e.g. an old and a new version of the same plugin, which can have the same name. The device
+/// is only listed, read & reported in device events through the plugin with the highest priority, or the one loaded first if they're
+/// the same. Plugins which weren't loaded from a library of their own are given by their name instead. Plugins have a priority of 0
+/// unless they're given one, and plugins in the plugin directory itself are loaded before those in its sub directories. This can be
+/// used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The priority will be used from now on
+/// * `Err(InvalidArgument)`: The path isn't valid UTF-8
+pub fn set_plugin_priority<P: AsRef<Path>>(path: P, priority: i32) -> SDKResult<()> {
+    let path = match path.as_ref().to_str().and_then(|p| CString::new(p).ok()) {
+        Some(path) => path,
+        None => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe { wooting_analog_set_plugin_priority(path.as_ptr(), priority).into() }
+}
+
+/// Reports the key `from` as the keys in `to` on the device with id `device_id`, or on every device if it's 0, replacing any
+/// earlier remap of the key. This is applied to the keys read from the device before they're given, e.g. to move the analog travel
+/// of a broken switch to another key, or to report a key as several keys. If `to` is empty the key is disabled. Remaps for a
+/// specific device take precedence over those for every device. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * The codes are of the KeycodeType set with `set_keycode_mode`, the remap is kept by HID code so it isn't affected by changing it
+///
+/// # Expected Returns
+/// * `Ok(())`: The remap will be used from now on
+/// * `Err(NoMapping)`: One of the codes doesn't have a HID code
+pub fn set_key_remap(device_id: DeviceID, from: u16, to: &[u16]) -> SDKResult<()> {
+    unsafe { wooting_analog_set_key_remap(device_id, from, to.as_ptr(), to.len() as c_uint).into() }
+}
+
+/// Removes the remap of the key `from` set for the device with id `device_id`, or the one for every device if it's 0. This can be
+/// used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The key is no longer remapped for the device
+/// * `Err(InvalidArgument)`: The key isn't remapped for the device
+/// * `Err(NoMapping)`: `from` doesn't have a HID code
+pub fn remove_key_remap(device_id: DeviceID, from: u16) -> SDKResult<()> {
+    unsafe { wooting_analog_remove_key_remap(device_id, from).into() }
+}
+
+/// Removes every key remap. This can be used without initialising the SDK.
+pub fn clear_key_remaps() -> SDKResult<()> {
+    unsafe { wooting_analog_clear_key_remaps().into() }
+}
+
+/// Replaces every key remap with those in the file at `path`. The file has one remap per line, as the device id in hex (or `*` for
+/// every device), the key being remapped and then the keys it's reported as, separated by spaces, e.g. `* CapsLock Escape`. Keys
+/// are given by name (as accepted by `key_from_name`) or as a HID code in hex (e.g. `0x04`), lines starting with `#` are ignored.
+/// This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The remaps in the file will be used from now on, lines which couldn't be read are skipped
+/// * `Err(InvalidArgument)`: The path isn't valid UTF-8
+/// * `Err(Failure)`: The file couldn't be read, in which case the remaps are left unchanged
+pub fn load_key_remap_file<P: AsRef<Path>>(path: P) -> SDKResult<()> {
+    let path = match path.as_ref().to_str().and_then(|p| CString::new(p).ok()) {
+        Some(path) => path,
+        None => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe { wooting_analog_load_key_remap_file(path.as_ptr()).into() }
+}
+
+/// Defines the virtual axis called `name`, replacing any axis with the same name. The axis goes from -1.0 to 1.0, made up of the
+/// keys of `definition`, where each side is as far as the furthest pressed key of it. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * Values which are closer to 0 than the deadzone are given as 0, the rest are scaled so that they still go up to 1
+/// * The codes are of the KeycodeType set with `set_keycode_mode`, the axis is kept by HID code so it isn't affected by changing it
+///
+/// # Expected Returns
+/// * `Ok(())`: The axis can be read with `read_axis`
+/// * `Err(InvalidArgument)`: `name` is empty or contains a nul byte, or the deadzone isn't from 0 up to (but not including) 1
+/// * `Err(NoMapping)`: One of the codes doesn't have a HID code
+pub fn define_axis(name: &str, definition: &AxisDefinition) -> SDKResult<()> {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe {
+        wooting_analog_define_axis(
+            name.as_ptr(),
+            definition.positive.as_ptr(),
+            definition.positive.len() as c_uint,
+            definition.negative.as_ptr(),
+            definition.negative.len() as c_uint,
+            definition.combine as c_uint,
+            definition.deadzone,
+        )
+        .into()
+    }
+}
+
+/// Removes the virtual axis called `name`, unpairing it from the axis it was paired with. This can be used without initialising
+/// the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The axis has been removed
+/// * `Err(InvalidArgument)`: There's no axis called `name`
+pub fn remove_axis(name: &str) -> SDKResult<()> {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe { wooting_analog_remove_axis(name.as_ptr()).into() }
+}
+
+/// Pairs up the virtual axes called `x` & `y` into a stick, which normalises them together so that their combined length is at
+/// most 1.0, e.g. so that moving diagonally with WASD isn't faster than moving straight. Either of them is unpaired from the axis
+/// it was paired with before. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The axes will be normalised together from now on
+/// * `Err(InvalidArgument)`: One of the axes doesn't exist, or they're the same axis
+pub fn pair_axes(x: &str, y: &str) -> SDKResult<()> {
+    let (x, y) = match (CString::new(x), CString::new(y)) {
+        (Ok(x), Ok(y)) => (x, y),
+        _ => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    unsafe { wooting_analog_pair_axes(x.as_ptr(), y.as_ptr()).into() }
+}
+
+/// Reads the value of the virtual axis called `name` (defined with `define_axis`) from all connected devices
+///
+/// # Expected Returns
+/// * `Ok(-1.0f - 1.0f)`: The value of the axis called `name`
+/// * `Err(InvalidArgument)`: There's no axis called `name`
+/// * `Err(UnInitialized)`: The SDK is not initialised
+/// * `Err(NoDevices)`: There are no connected devices
+pub fn read_axis(name: &str) -> SDKResult<f32> {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    let value = unsafe { wooting_analog_read_axis(name.as_ptr()) };
+    //Errors are below -1, as the axis itself goes down to -1
+    if value >= -1.0 {
+        Ok(value).into()
+    } else {
+        Err(WootingAnalogResult::from_f32(value).unwrap_or(WootingAnalogResult::Failure)).into()
+    }
+}
+
+/// Reads the values of the virtual axes called `x` & `y` (defined with `define_axis`) from all connected devices at once, e.g. the
+/// two axes of a stick, so that they're both from the same read
+///
+/// # Expected Returns
+/// * `Ok((-1.0f - 1.0f, -1.0f - 1.0f))`: The values of the axes called `x` & `y`
+/// * `Err(InvalidArgument)`: There's no axis called `x` or `y`
+/// * `Err(UnInitialized)`: The SDK is not initialised
+/// * `Err(NoDevices)`: There are no connected devices
+pub fn read_axis_pair(x: &str, y: &str) -> SDKResult<(f32, f32)> {
+    let (x, y) = match (CString::new(x), CString::new(y)) {
+        (Ok(x), Ok(y)) => (x, y),
+        _ => return Err(WootingAnalogResult::InvalidArgument).into(),
+    };
+    let mut values = (0.0, 0.0);
+    let result: SDKResult<()> = unsafe {
+        wooting_analog_read_axis_pair(x.as_ptr(), y.as_ptr(), &mut values.0, &mut values.1).into()
+    };
+    result.0.map(|_| values).into()
+}
+
+/// Gives how the key with identifier `code` is moving on the device with id `device_id`, or on the device where it's pressed
+/// furthest if `device_id` is 0. It's worked out from every report the device sends rather than from the reads of the SDK, so it
+/// doesn't matter how often it's read.
+///
+/// # Notes
+/// * The code is of the KeycodeType set with `set_keycode_mode`. If keys are remapped onto it, the one pressed furthest is given
+/// * Times are in microseconds on the clock given by `kinematics_time`
+/// * Only devices of plugins which publish their reports into a `SnapshotStore` are tracked
+///
+/// # Expected Returns
+/// * `Ok(KeyKinematics)`
+/// * `Err(UnInitialized)`: The SDK is not initialised
+/// * `Err(NoMapping)`: No keycode mapping was found from the selected mode (set by `set_keycode_mode`) and HID.
+/// * `Err(NoDevices)`: No reports have come in from the device with id `device_id` (or from any device if it's 0)
+pub fn read_key_kinematics(code: u16, device_id: DeviceID) -> SDKResult<KeyKinematics> {
+    let mut kinematics = KeyKinematics::default();
+    let result = unsafe { wooting_analog_read_key_kinematics(code, device_id, &mut kinematics) };
+    match result {
+        WootingAnalogResult::Ok => Ok(kinematics).into(),
+        e => Err(e).into(),
+    }
+}
+
+/// Gives the current time in microseconds on the clock which the times of `KeyKinematics` are on, e.g. to tell how long a key
+/// which is still pressed has been held for. This can be used without initialising the SDK.
+pub fn kinematics_time() -> u64 {
+    unsafe { wooting_analog_kinematics_time() }
+}
+
+/// Binds the key with identifier `code` to `action` of the emulated mouse, replacing what the key was bound to. Any number of keys
+/// can be bound to the same action, e.g. both arrows & IJKL to move the pointer. This can be used without initialising the SDK.
+///
+/// # Notes
+/// * The code is of the KeycodeType set with `set_keycode_mode`, the binding is kept by HID code so it isn't affected by changing it
+/// * Keys which move the pointer or scroll go faster the further they're pressed, keys bound to buttons hold them down while
+/// they're pressed past halfway
+///
+/// # Expected Returns
+/// * `Ok(())`: The key is bound
+/// * `Err(NoMapping)`: No keycode mapping was found from the selected mode (set by `set_keycode_mode`) and HID.
+pub fn bind_mouse_action(code: u16, action: MouseAction) -> SDKResult<()> {
+    unsafe { wooting_analog_bind_mouse_action(code, action as c_uint).into() }
+}
+
+/// Unbinds the key with identifier `code` from the emulated mouse. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The key isn't bound anymore
+/// * `Err(InvalidArgument)`: The key isn't bound
+/// * `Err(NoMapping)`: No keycode mapping was found from the selected mode (set by `set_keycode_mode`) and HID.
+pub fn unbind_mouse_action(code: u16) -> SDKResult<()> {
+    unsafe { wooting_analog_unbind_mouse_action(code).into() }
+}
+
+/// Sets the acceleration curve of the emulated mouse pointer, which gives how fast it moves in pixels per second with how far the
+/// keys moving it are pressed. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The curve is used from the next update of the mouse
+/// * `Err(InvalidArgument)`: The curve isn't valid, see `MouseCurve::is_valid`
+pub fn set_mouse_pointer_curve(curve: MouseCurve) -> SDKResult<()> {
+    unsafe { wooting_analog_set_mouse_pointer_curve(curve).into() }
+}
+
+/// Sets the acceleration curve of the emulated mouse wheel, which gives how fast it scrolls in lines per second with how
+/// far the keys scrolling it are pressed. This can be used without initialising the SDK.
+///
+/// # Expected Returns
+/// * `Ok(())`: The curve is used from the next update of the mouse
+/// * `Err(InvalidArgument)`: The curve isn't valid, see `MouseCurve::is_valid`
+pub fn set_mouse_scroll_curve(curve: MouseCurve) -> SDKResult<()> {
+    unsafe { wooting_analog_set_mouse_scroll_curve(curve).into() }
+}
+
+/// Starts emulating a mouse with the keys bound by `bind_mouse_action`, reading all devices every 8ms. The mouse is a virtual
+/// device, so it moves the system pointer like any other mouse.
+///
+/// # Notes
+/// * This is only available on Linux, where the mouse is created through `/dev/uinput`, which needs write access to it
+/// * The emulation is stopped by `stop_mouse_emulation` or when the SDK is uninitialised
+///
+/// # Expected Returns
+/// * `Ok(())`: The mouse is being emulated, or already was
+/// * `Err(UnInitialized)`: The SDK is not initialised
+/// * `Err(NotAvailable)`: Mouse emulation isn't available on this platform
+/// * `Err(Failure)`: The virtual mouse couldn't be created, e.g. because `/dev/uinput` couldn't be opened
+pub fn start_mouse_emulation() -> SDKResult<()> {
+    unsafe { wooting_analog_start_mouse_emulation().into() }
+}
+
+/// Stops emulating the mouse started by `start_mouse_emulation`, letting go of any buttons it was holding. The key bindings &
+/// curves are kept.
+pub fn stop_mouse_emulation() -> SDKResult<()> {
+    unsafe { wooting_analog_stop_mouse_emulation().into() }
+}
+
 /// Reads all the analog values for pressed keys for the device with id `device_id`,returning a HashMap of keycode -> analog value.
 ///
 /// # Notes
 /// * `max_items` is the maximum length of items that can be returned in the HashMap
 /// * The keycodes returned are of the KeycodeType set with `set_mode`
 /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+/// * Released keys are tracked in a session shared with every other caller, use `read_full_buffer_session` if there are multiple readers
 ///
 /// # Expected Returns
 /// * `Ok(HashMap)`
@@ -173,34 +825,96 @@ pub fn read_full_buffer_device(
     max_items: usize,
     device_id: DeviceID,
 ) -> SDKResult<HashMap<u16, f32>> {
-    unsafe {
-        let mut code_buffer: Vec<u16> = vec![0; max_items];
-        let mut analog_buffer: Vec<f32> = vec![0.0; max_items];
+    read_full_buffer_with(max_items, |code_buffer, analog_buffer| unsafe {
+        wooting_analog_read_full_buffer_device(
+            code_buffer,
+            analog_buffer,
+            max_items as u32,
+            device_id,
+        )
+    })
+}
+
+/// Creates a new session to read with using `read_full_buffer_session`. Each session keeps track of released keys
+/// on its own, so if there are multiple parts of your application reading the full buffer, they should each have their own session
+/// to ensure none of them miss out on a key being released.
+///
+/// # Notes
+/// * The session should be destroyed with `destroy_session` once it is no longer needed
+/// * Sessions are destroyed when the SDK is uninitialised
+///
+/// # Expected Returns
+/// * `Ok(SessionID)`: The ID of the new session
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+pub fn create_session() -> SDKResult<SessionID> {
+    return unsafe { wooting_analog_create_session().into() };
+}
 
-        let ret: SDKResult<u32> = wooting_analog_read_full_buffer_device(
-            code_buffer.as_mut_ptr(),
-            analog_buffer.as_mut_ptr(),
+/// Destroys the `session` that was created with `create_session`
+///
+/// # Expected Returns
+/// * `Ok(())`: The session was destroyed
+/// * `Err(InvalidArgument)`: There is no session with the given ID
+/// * `Err(UnInitialized)`: The SDK is not initialised
+pub fn destroy_session(session: SessionID) -> SDKResult<()> {
+    return unsafe { wooting_analog_destroy_session(session).into() };
+}
+
+/// The same as `read_full_buffer_device`, except released keys are tracked for the given `session` (created with `create_session`),
+/// rather than being shared with every other reader. Pass a `device_id` of 0 to read from all devices.
+///
+/// # Notes
+/// * `max_items` is the maximum length of items that can be returned in the HashMap
+/// * The keycodes returned are of the KeycodeType set with `set_mode`
+/// * When a key is released it will be returned with an analog value of 0.0f in the first call with this `session` after the key has been released
+/// * If more keys are pressed than fit, the ones pressed furthest are given ahead of any releases. Releases which don't fit are given in a later call
+///
+/// # Expected Returns
+/// * `Ok(HashMap)`
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(InvalidArgument)`: There is no session with the given ID
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+pub fn read_full_buffer_session(
+    session: SessionID,
+    max_items: usize,
+    device_id: DeviceID,
+) -> SDKResult<HashMap<u16, f32>> {
+    read_full_buffer_with(max_items, |code_buffer, analog_buffer| unsafe {
+        wooting_analog_read_full_buffer_session(
+            session,
+            code_buffer,
+            analog_buffer,
             max_items as u32,
             device_id,
         )
-        .into();
+    })
+}
 
-        return ret
-            .0
-            .clone()
-            .map(|read_num| {
-                let read_num: usize = read_num as usize;
-                code_buffer.truncate(read_num);
-                analog_buffer.truncate(read_num);
-                let mut data: HashMap<u16, f32> = HashMap::with_capacity(read_num);
-
-                for i in 0..read_num {
-                    data.insert(code_buffer[i], analog_buffer[i]);
-                }
-                data
-            })
-            .into();
-    }
+/// Gives `read` buffers which can hold `max_items` and collects what it filled into them
+fn read_full_buffer_with(
+    max_items: usize,
+    read: impl FnOnce(*mut u16, *mut f32) -> c_int,
+) -> SDKResult<HashMap<u16, f32>> {
+    let mut code_buffer: Vec<u16> = vec![0; max_items];
+    let mut analog_buffer: Vec<f32> = vec![0.0; max_items];
+
+    let ret: SDKResult<u32> = read(code_buffer.as_mut_ptr(), analog_buffer.as_mut_ptr()).into();
+
+    return ret
+        .0
+        .clone()
+        .map(|read_num| {
+            let read_num: usize = read_num as usize;
+            code_buffer.truncate(read_num);
+            analog_buffer.truncate(read_num);
+            let mut data: HashMap<u16, f32> = HashMap::with_capacity(read_num);
+
+            for i in 0..read_num {
+                data.insert(code_buffer[i], analog_buffer[i]);
+            }
+            data
+        })
+        .into();
 }
 
 /// Reads all the analog values for pressed keys for all devices and combines their values, returning a HashMap of keycode -> analog value.
@@ -210,6 +924,7 @@ pub fn read_full_buffer_device(
 /// * The keycodes returned are of the KeycodeType set with `set_mode`
 /// * If two devices have the same key pressed, the greater value will be given
 /// * When a key is released it will be returned with an analog value of 0.0f in the first read_full_buffer call after the key has been released
+/// * Released keys are tracked in a session shared with every other caller, use `read_full_buffer_session` if there are multiple readers
 ///
 /// # Expected Returns
 /// * `Ok(HashMap)`
@@ -218,3 +933,43 @@ pub fn read_full_buffer_device(
 pub fn read_full_buffer(max_items: usize) -> SDKResult<HashMap<u16, f32>> {
     return read_full_buffer_device(max_items, 0);
 }
+
+/// Fills `buffer` with the analog value of every key on the device with id `device_id`, indexed by HID code. i.e. the analog value
+/// for A (HID 0x04) ends up in `buffer[4]`. This doesn't allocate, so it is the recommended way to read every key once per frame.
+///
+/// # Notes
+/// * The buffer is always indexed by HID code, regardless of the KeycodeType set with `set_mode`
+/// * Keys which aren't pressed are set to 0.0, so there is no need to keep track of released keys
+/// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+///
+/// # Expected Returns
+/// * `Ok(>=0)`: The number of keys which are pressed in the buffer
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(NoDevices)`: Indicates the device with id `device_id` is not connected
+pub fn read_dense_buffer_device(buffer: &mut [f32], device_id: DeviceID) -> SDKResult<u32> {
+    return unsafe {
+        wooting_analog_read_dense_buffer_device(
+            buffer.as_mut_ptr(),
+            buffer.len() as c_uint,
+            device_id,
+        )
+        .into()
+    };
+}
+
+/// Fills `buffer` with the analog value of every key on all devices, indexed by HID code. i.e. the analog value
+/// for A (HID 0x04) ends up in `buffer[4]`. This doesn't allocate, so it is the recommended way to read every key once per frame.
+///
+/// # Notes
+/// * The buffer is always indexed by HID code, regardless of the KeycodeType set with `set_mode`
+/// * Keys which aren't pressed are set to 0.0, so there is no need to keep track of released keys
+/// * Codes which don't fit in the buffer are skipped, a buffer of length `DENSE_BUFFER_LEN` covers all standard keys
+/// * If two devices have the same key pressed, the greater value will be given
+///
+/// # Expected Returns
+/// * `Ok(>=0)`: The number of keys which are pressed in the buffer
+/// * `Err(UnInitialized)`: Indicates that the AnalogSDK hasn't been initialised
+/// * `Err(NoDevices)`: Indicates no devices are connected
+pub fn read_dense_buffer(buffer: &mut [f32]) -> SDKResult<u32> {
+    return read_dense_buffer_device(buffer, 0);
+}